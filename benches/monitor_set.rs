@@ -0,0 +1,72 @@
+//! Per-event cost of a MonitorSet as the number of watched monitors grows, run with `cargo bench --bench monitor_set`
+//!
+//! Every monitor waits on one end of a socket pair, and events are generated by writing to the other end, so no privileges or PSI are needed.
+//! The cost of each wait should only depend on how many monitors fired, not on how many are watched
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn main() {
+    use std::{
+        hint::black_box,
+        io::Write,
+        os::{fd::OwnedFd, unix::net::UnixStream},
+        time::Instant,
+    };
+
+    use pressure::{MonitorSet, PressureMonitor};
+
+    const ROUNDS: usize = 2000;
+    const FIRING: usize = 16;
+
+    println!(
+        "{:>8} {:>16} {:>16}",
+        "watched", "ns/event (16)", "ns/event (all)"
+    );
+    for watched in [16, 256, 1024, 4096] {
+        let mut set = MonitorSet::new().unwrap();
+        let mut peers = Vec::with_capacity(watched);
+        for _ in 0..watched {
+            let (monitored, peer) = UnixStream::pair().unwrap();
+            set.insert(PressureMonitor::from_fd(OwnedFd::from(monitored)).unwrap())
+                .unwrap();
+            peers.push(peer);
+        }
+        let mut outcomes = Vec::with_capacity(watched);
+        let mut fire = |set: &mut MonitorSet, peers: &mut [UnixStream]| {
+            for peer in peers.iter_mut() {
+                peer.write_all(&[1]).unwrap();
+            }
+            outcomes.clear();
+            let mut received = 0;
+            while received < peers.len() {
+                received += set.wait_into(&mut outcomes, None).unwrap();
+            }
+            black_box(&outcomes);
+        };
+
+        // A fixed number of monitors spread across the set fire each round
+        let stride = watched / FIRING;
+        let mut firing: Vec<UnixStream> = peers
+            .iter()
+            .step_by(stride)
+            .map(|peer| peer.try_clone().unwrap())
+            .collect();
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            fire(&mut set, &mut firing);
+        }
+        let some = start.elapsed().as_nanos() / (ROUNDS * FIRING) as u128;
+
+        // Every monitor fires each round, harvested in several batches
+        let rounds = (ROUNDS * FIRING / watched).max(1);
+        let start = Instant::now();
+        for _ in 0..rounds {
+            fire(&mut set, &mut peers);
+        }
+        let all = start.elapsed().as_nanos() / (rounds * watched) as u128;
+        println!("{watched:>8} {some:>16} {all:>16}");
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn main() {
+    eprintln!("MonitorSet is only available on Linux and Android");
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Malformed input should be rejected with an error, never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = pressure::parse_psi(data);
+});
@@ -0,0 +1,99 @@
+#ifndef PRESSURE_H
+#define PRESSURE_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit */
+
+#include <stdint.h>
+
+/**
+ * A resource tracked by pressure stall information
+ */
+typedef enum {
+  PRESSURE_RESOURCE_MEMORY,
+  PRESSURE_RESOURCE_CPU,
+  PRESSURE_RESOURCE_IO,
+  PRESSURE_RESOURCE_IRQ,
+} pressure_resource;
+
+/**
+ * A pressure monitor, created with `pressure_monitor_new` and freed with `pressure_monitor_free`
+ */
+typedef struct pressure_monitor pressure_monitor;
+
+/**
+ * A pressure event, describing the monitor it was received on
+ */
+typedef struct {
+  pressure_resource resource;
+  /**
+   * 0 for some, 1 for full
+   */
+  int kind;
+  /**
+   * 0 when pressure rose, 1 when it cleared, 2 when the monitor reconnected
+   */
+  int transition;
+  /**
+   * Threshold of the trigger that fired in microseconds, or 0 if there was none
+   */
+  uint64_t threshold_us;
+  /**
+   * Window of the trigger that fired in microseconds, or 0 if there was none
+   */
+  uint64_t window_us;
+  uint32_t coalesced;
+  uint32_t suppressed;
+} pressure_event;
+
+/**
+ * Invoked with every event received on the monitor, and the user data it was registered with
+ */
+typedef void (*pressure_callback)(const pressure_event *event, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a monitor for the given resource, honoring systemd's memory pressure interface if present
+ */
+pressure_monitor *pressure_monitor_new(pressure_resource resource);
+
+/**
+ * Wait for a pressure event, storing it in `event` if it isn't NULL. A negative `timeout_ms` waits forever.
+ * Returns 1 if an event was received, 0 if the timeout elapsed, -EINTR if a signal interrupted the wait and -EBUSY while a callback is registered
+ *
+ * # Safety
+ *
+ * `monitor` must have been returned by `pressure_monitor_new` and not freed, and `event` must be NULL or valid for writes
+ */
+int pressure_monitor_wait(pressure_monitor *monitor,
+                          int64_t timeout_ms,
+                          pressure_event *event);
+
+/**
+ * Invoke `callback` with every event the monitor receives, from a thread waiting on the monitor until another callback is set or the monitor is freed.
+ * A NULL `callback` stops invoking the previous one, after which `pressure_monitor_wait` can be used again. Returns 0 on success
+ *
+ * # Safety
+ *
+ * `monitor` must have been returned by `pressure_monitor_new` and not freed, and `user_data` must be safe to use from the callback on another thread
+ */
+int pressure_monitor_set_callback(pressure_monitor *monitor,
+                                  pressure_callback callback,
+                                  void *user_data);
+
+/**
+ * Free a monitor, stopping its callback if one is registered
+ *
+ * # Safety
+ *
+ * `monitor` must be NULL or have been returned by `pressure_monitor_new` and not freed yet
+ */
+void pressure_monitor_free(pressure_monitor *monitor);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PRESSURE_H */
@@ -0,0 +1,37 @@
+use std::os::fd::{BorrowedFd, OwnedFd, RawFd};
+
+use crate::Error;
+
+/// First file descriptor passed by the service manager, see sd_listen_fds(3)
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Find a file descriptor passed through socket activation by its name, as set by FileDescriptorName= in the socket unit
+///
+/// The descriptor is duplicated rather than taken, so the one passed by the service manager stays valid for other users
+pub(crate) fn listen_fd(name: &str) -> Result<Option<OwnedFd>, Error> {
+    // Environment variables are inherited by child processes, so descriptors are only meant for us if LISTEN_PID matches
+    let is_ours = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    if !is_ours {
+        return Ok(None);
+    }
+    let Some(count) = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<RawFd>().ok())
+    else {
+        return Ok(None);
+    };
+    let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+    let Some(index) = names
+        .split(':')
+        .take(count as usize)
+        .position(|fd_name| fd_name == name)
+    else {
+        return Ok(None);
+    };
+    // SAFETY: the service manager keeps descriptors it passes open, and they are only closed if the application does so explicitly
+    let fd = unsafe { BorrowedFd::borrow_raw(LISTEN_FDS_START + index as RawFd) };
+    Ok(Some(fd.try_clone_to_owned()?))
+}
@@ -0,0 +1,110 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{Error, MonitorType, trigger::Trigger};
+
+/// Re-arms a monitor's trigger, doubling the threshold on each event and halving it again once pressure has been quiet for a cooldown period
+pub(crate) struct Adaptive {
+    base: Trigger,
+    max_threshold: Duration,
+    cooldown: Duration,
+    armed: Trigger,
+    last_change: Instant,
+}
+
+impl Adaptive {
+    pub(crate) fn new(
+        base: Trigger,
+        max_threshold: Duration,
+        cooldown: Duration,
+    ) -> Result<Self, Error> {
+        // Validate the largest threshold up front. Thresholds in between are whole microseconds, as doubling keeps them so and halving rounds down,
+        // so neither escalating nor de-escalating can fail validation later on
+        Trigger::new(
+            base.kind(),
+            max_threshold.max(base.threshold()),
+            base.window(),
+        )?;
+        Ok(Self {
+            base,
+            max_threshold,
+            cooldown,
+            armed: base,
+            last_change: Instant::now(),
+        })
+    }
+
+    /// Start adapting from a new trigger, which the monitor has just been armed with
+    pub(crate) fn rebase(&mut self, base: Trigger) -> Result<(), Error> {
+        *self = Self::new(base, self.max_threshold, self.cooldown)?;
+        Ok(())
+    }
+
+    pub(crate) fn armed(&self) -> Trigger {
+        self.armed
+    }
+
+    /// When the trigger should next be de-escalated if no event occurs, or [None] if it is armed at the base threshold
+    pub(crate) fn cooldown_deadline(&self) -> Option<Instant> {
+        // Cooldowns too long to represent never de-escalate
+        (self.armed.threshold() > self.base.threshold())
+            .then(|| self.last_change.checked_add(self.cooldown))
+            .flatten()
+    }
+
+    /// Double the threshold after an event, returning a newly opened pressure file if it changed
+    pub(crate) fn escalate(&mut self, path: &Path) -> Result<Option<MonitorType>, Error> {
+        let threshold = (self.armed.threshold() * 2).min(self.max_threshold);
+        self.rearm(path, threshold.max(self.armed.threshold()))
+    }
+
+    /// Halve the threshold after the cooldown elapsed without an event, returning a newly opened pressure file if it changed
+    pub(crate) fn deescalate(&mut self, path: &Path) -> Result<Option<MonitorType>, Error> {
+        let threshold = Duration::from_micros((self.armed.threshold().as_micros() / 2) as u64);
+        let threshold = threshold.max(self.base.threshold());
+        self.rearm(path, threshold)
+    }
+
+    fn rearm(&mut self, path: &Path, threshold: Duration) -> Result<Option<MonitorType>, Error> {
+        self.last_change = Instant::now();
+        if threshold == self.armed.threshold() {
+            return Ok(None);
+        }
+        let trigger = Trigger::new(self.base.kind(), threshold, self.base.window())?;
+        // The kernel only allows one trigger per fd, so a new one has to be opened
+        let pressure_file = crate::open_monitor(path, Some(trigger))?;
+        self.armed = trigger;
+        Ok(Some(pressure_file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StallKind;
+
+    #[test]
+    fn deescalates_from_an_odd_max_threshold_back_to_the_base() {
+        // Any file accepts the triggers written to it
+        let path = std::env::temp_dir().join(format!("pressure-adaptive-{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let base = Trigger::new(
+            StallKind::Some,
+            Duration::from_millis(20),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        let mut adaptive =
+            Adaptive::new(base, Duration::from_millis(333), Duration::from_secs(1)).unwrap();
+        while adaptive.escalate(&path).unwrap().is_some() {}
+        assert_eq!(adaptive.armed().threshold(), Duration::from_millis(333));
+        let mut thresholds = Vec::new();
+        while adaptive.deescalate(&path).unwrap().is_some() {
+            thresholds.push(adaptive.armed().threshold().as_micros());
+        }
+        assert_eq!(thresholds, [166500, 83250, 41625, 20812, 20000]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
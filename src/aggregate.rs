@@ -0,0 +1,157 @@
+//! Primitives folding samples of pressure stall information into averages and percentiles over horizons of your choosing, beyond the kernel's fixed 10, 60 and 300 second windows
+//!
+//! ```no_run
+//! use pressure::{
+//!     PressureMonitor, Resource, StallKind,
+//!     aggregate::{Ewma, RollingPercentiles, StallRate},
+//! };
+//! use std::time::Duration;
+//!
+//! let mut rate = StallRate::new(StallKind::Some);
+//! let mut fifteen_minutes = Ewma::new(Duration::from_secs(15 * 60));
+//! let mut last_hour = RollingPercentiles::new(Duration::from_secs(60 * 60));
+//! for stats in PressureMonitor::sampler(Resource::Memory, Duration::from_secs(1))? {
+//!     let Some(percent) = rate.update(&stats?) else {
+//!         continue;
+//!     };
+//!     fifteen_minutes.update(percent);
+//!     last_hour.update(percent);
+//!     if last_hour.percentile(95.0).is_some_and(|p95| p95 > 20.0) {
+//!         // Pressure has been high for a significant part of the last hour
+//!     }
+//! }
+//! # Ok::<(), pressure::Error>(())
+//! ```
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{PsiStats, StallKind};
+
+/// Derives the percentage of time stalled between consecutive samples from their total stall time, which unlike the kernel's averages isn't smoothed
+#[derive(Debug, Clone)]
+pub struct StallRate {
+    kind: StallKind,
+    previous: Option<(Instant, PsiStats)>,
+}
+
+impl StallRate {
+    pub fn new(kind: StallKind) -> Self {
+        Self {
+            kind,
+            previous: None,
+        }
+    }
+
+    /// Record a sample taken now, returning the percentage of time stalled since the previous one, or [None] for the first sample
+    pub fn update(&mut self, stats: &PsiStats) -> Option<f32> {
+        self.update_at(stats, Instant::now())
+    }
+
+    /// Record a sample taken at `timestamp`, see [update](Self::update)
+    pub fn update_at(&mut self, stats: &PsiStats, timestamp: Instant) -> Option<f32> {
+        let (previous_timestamp, previous) = self.previous.replace((timestamp, *stats))?;
+        let elapsed = timestamp.saturating_duration_since(previous_timestamp);
+        if elapsed.is_zero() {
+            return None;
+        }
+        Some(stats.delta(&previous).percent(self.kind, elapsed))
+    }
+}
+
+/// Exponentially weighted moving average over a horizon, computed the same way as the kernel's averages so that samples taken at irregular intervals are weighted by the time they cover
+#[derive(Debug, Clone)]
+pub struct Ewma {
+    horizon: Duration,
+    value: Option<(Instant, f32)>,
+}
+
+impl Ewma {
+    pub fn new(horizon: Duration) -> Self {
+        Self {
+            horizon,
+            value: None,
+        }
+    }
+
+    pub fn horizon(&self) -> Duration {
+        self.horizon
+    }
+
+    /// Fold in a value observed now, returning the updated average
+    pub fn update(&mut self, value: f32) -> f32 {
+        self.update_at(value, Instant::now())
+    }
+
+    /// Fold in a value observed at `timestamp`, returning the updated average. The first value becomes the average as is
+    pub fn update_at(&mut self, value: f32, timestamp: Instant) -> f32 {
+        let average = match self.value {
+            Some((previous_timestamp, average)) if !self.horizon.is_zero() => {
+                let elapsed = timestamp.saturating_duration_since(previous_timestamp);
+                let decay = (-elapsed.as_secs_f32() / self.horizon.as_secs_f32()).exp();
+                average * decay + value * (1.0 - decay)
+            }
+            _ => value,
+        };
+        self.value = Some((timestamp, average));
+        average
+    }
+
+    /// The current average, or [None] before the first value
+    pub fn value(&self) -> Option<f32> {
+        self.value.map(|(_, average)| average)
+    }
+}
+
+/// Percentiles of the values observed within a rolling horizon
+#[derive(Debug, Clone)]
+pub struct RollingPercentiles {
+    horizon: Duration,
+    values: VecDeque<(Instant, f32)>,
+}
+
+impl RollingPercentiles {
+    pub fn new(horizon: Duration) -> Self {
+        Self {
+            horizon,
+            values: VecDeque::new(),
+        }
+    }
+
+    pub fn horizon(&self) -> Duration {
+        self.horizon
+    }
+
+    /// Record a value observed now
+    pub fn update(&mut self, value: f32) {
+        self.update_at(value, Instant::now());
+    }
+
+    /// Record a value observed at `timestamp`, forgetting values that fell out of the horizon
+    pub fn update_at(&mut self, value: f32, timestamp: Instant) {
+        while self.values.front().is_some_and(|(observed, _)| {
+            timestamp.saturating_duration_since(*observed) > self.horizon
+        }) {
+            self.values.pop_front();
+        }
+        self.values.push_back((timestamp, value));
+    }
+
+    /// The `percentile`th percentile (between 0 and 100) of the values within the horizon, using the nearest rank, or [None] if there are none
+    pub fn percentile(&self, percentile: f32) -> Option<f32> {
+        let mut values: Vec<f32> = self.values.iter().map(|(_, value)| *value).collect();
+        values.sort_by(f32::total_cmp);
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * values.len() as f32).ceil() as usize;
+        values.get(rank.saturating_sub(1)).copied()
+    }
+
+    /// Number of values within the horizon
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use crate::{ClockTimestamp, Severity, current_level};
+
+/// Number of trim hooks a [PressureAllocator] can hold
+const HOOK_SLOTS: usize = 8;
+
+thread_local! {
+    /// Set while this thread runs trim hooks, so that allocations made by the hooks don't run them again
+    static IN_HOOKS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A global allocator that degrades gracefully under memory pressure, delegating every allocation to an inner allocator
+///
+/// While [current_level] is [Severity::Critical], large allocations first run the registered trim hooks, such as returning freed memory to the
+/// operating system or dropping caches, at most once per cooldown. Hooks also run when the inner allocator fails a large allocation, after which it is retried once.
+/// Allocations above an optional limit can be failed right away under critical pressure, which [Vec::try_reserve] and similar report as an error
+/// but which aborts the process for infallible allocations.
+///
+/// The level is only read from [current_level], so it has to be kept up to date, for example by a [LevelTracker](crate::LevelTracker).
+/// Hooks run on the allocating thread and may allocate themselves, but must not block or take locks that could be held while allocating,
+/// which rules out [shrink_all](crate::shrink::shrink_all) unless shrinkables are only registered up front
+///
+/// ```no_run
+/// use pressure::{LevelTracker, PressureAllocator, PressureMonitor};
+/// use std::alloc::System;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: PressureAllocator = PressureAllocator::new(System).fail_above(256 << 20);
+///
+/// fn main() -> Result<(), pressure::Error> {
+///     ALLOCATOR.register_trim_hook(|| {
+///         pressure::malloc_trim_now();
+///     });
+///     let _tracker = LevelTracker::spawn(PressureMonitor::new()?)?;
+///     // ...
+///     Ok(())
+/// }
+/// ```
+pub struct PressureAllocator<A = System> {
+    inner: A,
+    large: usize,
+    fail_above: usize,
+    cooldown: Duration,
+    /// Hooks stored as function pointers cast to integers, zero for free slots
+    hooks: [AtomicUsize; HOOK_SLOTS],
+    /// When the hooks last ran in nanoseconds of [ClockTimestamp::monotonic], offset by one so that zero means never
+    last_run: AtomicU64,
+}
+
+impl<A> PressureAllocator<A> {
+    /// Wrap `inner`, treating allocations of at least 1MiB as large and running hooks at most once a second, without failing any allocations
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            large: 1 << 20,
+            fail_above: usize::MAX,
+            cooldown: Duration::from_secs(1),
+            hooks: [const { AtomicUsize::new(0) }; HOOK_SLOTS],
+            last_run: AtomicU64::new(0),
+        }
+    }
+
+    /// Size in bytes from which allocations count as large
+    pub const fn large_allocation(mut self, bytes: usize) -> Self {
+        self.large = bytes;
+        self
+    }
+
+    /// Fail allocations larger than `bytes` while pressure is critical
+    pub const fn fail_above(mut self, bytes: usize) -> Self {
+        self.fail_above = bytes;
+        self
+    }
+
+    /// Minimum time between two runs of the hooks
+    pub const fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Run `hook` before large allocations under critical pressure, returning false if all 8 slots are taken
+    pub fn register_trim_hook(&self, hook: fn()) -> bool {
+        self.hooks.iter().any(|slot| {
+            slot.compare_exchange(0, hook as usize, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        })
+    }
+
+    /// Run the hooks unless they ran within the cooldown or this thread is running them already
+    fn run_hooks(&self, force: bool) {
+        if IN_HOOKS.with(Cell::get) {
+            return;
+        }
+        let now = ClockTimestamp::now().monotonic.as_nanos() as u64 + 1;
+        let last_run = self.last_run.load(Ordering::Relaxed);
+        let cooling_down =
+            last_run != 0 && now.saturating_sub(last_run) < self.cooldown.as_nanos() as u64;
+        if (cooling_down && !force)
+            || self
+                .last_run
+                .compare_exchange(last_run, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        IN_HOOKS.with(|in_hooks| in_hooks.set(true));
+        for slot in &self.hooks {
+            let hook = slot.load(Ordering::Acquire);
+            if hook != 0 {
+                // SAFETY: non-zero slots only ever hold function pointers stored by register_trim_hook
+                let hook: fn() = unsafe { std::mem::transmute::<usize, fn()>(hook) };
+                hook();
+            }
+        }
+        IN_HOOKS.with(|in_hooks| in_hooks.set(false));
+    }
+
+    /// Decide whether a large allocation of `size` bytes may go ahead, running the hooks first under critical pressure
+    fn admit(&self, size: usize) -> bool {
+        if size < self.large || current_level() != Severity::Critical {
+            return true;
+        }
+        if size > self.fail_above {
+            return false;
+        }
+        self.run_hooks(false);
+        true
+    }
+}
+
+// SAFETY: every allocation is delegated to the inner allocator with the caller's layout, or fails by returning null
+unsafe impl<A: GlobalAlloc> GlobalAlloc for PressureAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if !self.admit(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        // SAFETY: forwarded from the caller
+        let ptr = unsafe { self.inner.alloc(layout) };
+        if ptr.is_null() && layout.size() >= self.large {
+            self.run_hooks(true);
+            // SAFETY: forwarded from the caller
+            return unsafe { self.inner.alloc(layout) };
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if !self.admit(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        // SAFETY: forwarded from the caller
+        let ptr = unsafe { self.inner.alloc_zeroed(layout) };
+        if ptr.is_null() && layout.size() >= self.large {
+            self.run_hooks(true);
+            // SAFETY: forwarded from the caller
+            return unsafe { self.inner.alloc_zeroed(layout) };
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded from the caller
+        unsafe { self.inner.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() && !self.admit(new_size) {
+            return std::ptr::null_mut();
+        }
+        // SAFETY: forwarded from the caller
+        let new = unsafe { self.inner.realloc(ptr, layout, new_size) };
+        if new.is_null() && new_size > layout.size() && new_size >= self.large {
+            self.run_hooks(true);
+            // SAFETY: forwarded from the caller, the original allocation is still valid after a failed realloc
+            return unsafe { self.inner.realloc(ptr, layout, new_size) };
+        }
+        new
+    }
+}
@@ -0,0 +1,55 @@
+//! Android specifics: sampling pressure instead of competing with lmkd for triggers, and mapping pressure onto trim levels
+
+use std::{path::Path, time::Duration};
+
+use crate::{PsiStats, Trigger};
+
+/// Control socket created by init for the low memory killer daemon
+const LMKD_SOCKET: &str = "/dev/socket/lmkd";
+
+/// Memory trim level modelled after the TRIM_MEMORY_RUNNING_* levels of Android's ComponentCallbacks2, for apps that already handle onTrimMemory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrimLevel {
+    /// TRIM_MEMORY_RUNNING_MODERATE, memory is getting low
+    RunningModerate,
+    /// TRIM_MEMORY_RUNNING_LOW, memory is low and unused resources should be released
+    RunningLow,
+    /// TRIM_MEMORY_RUNNING_CRITICAL, memory is so low that background processes are being killed
+    RunningCritical,
+}
+
+impl TrimLevel {
+    /// Map memory pressure onto a trim level, using the stalls lmkd registers its low, medium and critical triggers for by default
+    /// (70ms and 100ms of `some` stall and 70ms of `full` stall per second). Returns [None] if memory isn't under pressure
+    pub fn from_stats(stats: &PsiStats) -> Option<Self> {
+        if stats.full.avg10 >= 7.0 {
+            Some(TrimLevel::RunningCritical)
+        } else if stats.some.avg10 >= 10.0 {
+            Some(TrimLevel::RunningLow)
+        } else if stats.some.avg10 >= 7.0 {
+            Some(TrimLevel::RunningModerate)
+        } else {
+            None
+        }
+    }
+
+    /// Value of the corresponding ComponentCallbacks2 constant
+    pub fn value(self) -> i32 {
+        match self {
+            TrimLevel::RunningModerate => 5,
+            TrimLevel::RunningLow => 10,
+            TrimLevel::RunningCritical => 15,
+        }
+    }
+}
+
+/// Whether memory is managed by lmkd, which registers its own memory pressure triggers and kills processes based on them
+pub fn is_lmkd_managed() -> bool {
+    Path::new(LMKD_SOCKET).exists()
+}
+
+/// Sampling threshold and interval equivalent to a trigger, used where triggers can't or shouldn't be registered
+pub(crate) fn sampling(trigger: Trigger) -> (f32, Duration) {
+    let threshold = trigger.threshold().as_secs_f32() / trigger.window().as_secs_f32() * 100.0;
+    (threshold, trigger.window())
+}
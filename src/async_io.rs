@@ -0,0 +1,44 @@
+//! Asynchronous pressure monitoring using async-io, for use with smol, async-std and other runtimes built on it
+use std::{
+    io,
+    os::fd::{BorrowedFd, RawFd},
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use async_io::{Async, Timer};
+
+use crate::{AsyncPressureMonitor, AsyncPressureStream, Reactor};
+
+/// Asynchronous equivalent to [PressureMonitor](`super::PressureMonitor`)
+pub type PressureMonitor = AsyncPressureMonitor<AsyncIo>;
+
+/// Stream of pressure events produced by [PressureMonitor::into_stream]
+pub type PressureStream = AsyncPressureStream<AsyncIo>;
+
+/// [Reactor] waiting on async-io's event loop
+pub struct AsyncIo;
+
+impl Reactor for AsyncIo {
+    type Registration = Async<BorrowedFd<'static>>;
+    type Timer = Timer;
+
+    unsafe fn register(fd: RawFd) -> io::Result<Self::Registration> {
+        // SAFETY: the caller guarantees the fd stays open for as long as it is registered
+        Async::new_nonblocking(unsafe { BorrowedFd::borrow_raw(fd) })
+    }
+
+    fn poll_readable(
+        registration: &Self::Registration,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        registration.poll_readable(cx)
+    }
+
+    /// async-io already forgets readiness once it reported it
+    fn clear_readiness(_: &Self::Registration, _: &mut Context<'_>) {}
+
+    fn timer(deadline: Instant) -> Self::Timer {
+        Timer::at(deadline)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    os::fd::{AsFd, BorrowedFd},
+    time::Duration,
+};
+
+use nix::poll::PollFlags;
+
+use crate::{
+    Error, MonitorType, PollInterest, PsiStats, Resource, Severity, WaitOutcome, poll_set::PollSet,
+};
+
+/// A source of pressure events a [PressureMonitor](crate::PressureMonitor) can wait on
+///
+/// The crate provides backends for PSI triggers, notifications from a service manager and periodic sampling.
+/// Other sources can be plugged in with [PressureMonitor::from_backend](crate::PressureMonitor::from_backend).
+///
+/// Backends that signal events on their fd should report the readiness to wait for through [poll_interest](Self::poll_interest),
+/// which allows the monitor to be used with [MonitorSet](crate::MonitorSet) and external event loops
+///
+/// ```no_run
+/// use std::{io::Read, os::fd::{AsFd, BorrowedFd}, time::Duration};
+/// use pressure::{Error, PressureBackend, PressureMonitor, PsiStats, Resource, WaitOutcome};
+///
+/// /// Treats every byte written to a fifo by another process as a pressure event, ignoring timeouts and cancellation for brevity
+/// struct Notifier(std::fs::File);
+///
+/// impl AsFd for Notifier {
+///     fn as_fd(&self) -> BorrowedFd<'_> {
+///         self.0.as_fd()
+///     }
+/// }
+///
+/// impl PressureBackend for Notifier {
+///     fn wait(&mut self, _: Option<Duration>, _: Option<BorrowedFd<'_>>) -> Result<WaitOutcome<()>, Error> {
+///         self.0.read_exact(&mut [0])?;
+///         Ok(WaitOutcome::Triggered(()))
+///     }
+///
+///     fn stats(&self, resource: Resource) -> Result<PsiStats, Error> {
+///         PsiStats::read(resource)
+///     }
+/// }
+///
+/// let fifo = std::fs::File::open("/run/pressure-notify").unwrap();
+/// let mut monitor = PressureMonitor::from_backend(Notifier(fifo), Resource::Memory);
+/// monitor.wait().unwrap();
+/// ```
+pub trait PressureBackend: AsFd + Send {
+    /// Block until a pressure event occurs, `timeout` elapses or `cancellation` becomes readable.
+    /// Return [WaitOutcome::Hangup] or [WaitOutcome::Error] once the backend's source of events is gone
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error>;
+
+    /// Check whether a pressure event is pending without blocking, consuming it if so
+    fn try_wait(&mut self) -> Result<bool, Error> {
+        Ok(self.wait(Some(Duration::ZERO), None)? == WaitOutcome::Triggered(()))
+    }
+
+    /// Read the current pressure stall information, where `resource` is the resource the monitor reports events for
+    fn stats(&self, resource: Resource) -> Result<PsiStats, Error>;
+
+    /// Severity of the current pressure if the backend reports it itself, or [None] to classify [stats](Self::stats) with the monitor's [SeverityThresholds](crate::SeverityThresholds)
+    fn severity(&self) -> Option<Severity> {
+        None
+    }
+
+    /// Readiness the backend's fd signals events with, or [None] if it doesn't signal events on its fd
+    fn poll_interest(&self) -> Option<PollInterest> {
+        None
+    }
+
+    /// Consume an event after the backend's fd was reported ready by an event loop
+    fn acknowledge(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl PressureBackend for MonitorType {
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error> {
+        // Monitors poll their fd through their own poll set, this is only used when the fd is driven as a plain backend
+        let mut poll_set = PollSet::default();
+        let fds = std::iter::once((self.as_fd(), self.poll_flags()))
+            .chain(cancellation.map(|cancellation| (cancellation, PollFlags::POLLIN)));
+        if poll_set.poll(fds, timeout)? == 0 {
+            return Ok(WaitOutcome::TimedOut);
+        }
+        if poll_set.is_ready(1) {
+            return Ok(WaitOutcome::Cancelled);
+        }
+        if let Some(outcome) = crate::fd_died(poll_set.revents(0)) {
+            return Ok(outcome);
+        }
+        self.drain()?;
+        Ok(WaitOutcome::Triggered(()))
+    }
+
+    fn stats(&self, resource: Resource) -> Result<PsiStats, Error> {
+        MonitorType::stats(self, resource)
+    }
+
+    fn poll_interest(&self) -> Option<PollInterest> {
+        Some(MonitorType::poll_interest(self))
+    }
+
+    fn acknowledge(&mut self) -> Result<(), Error> {
+        self.drain().map(drop)
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use nix::{
+    errno::Errno,
+    fcntl::OFlag,
+    sys::{
+        mman::{MapFlags, ProtFlags, mmap_anonymous, munmap},
+        signal::{Signal, kill},
+        wait::waitpid,
+    },
+    unistd::{ForkResult, Pid, fork, pipe2},
+};
+
+use crate::{Error, Resource, cgroup};
+
+/// Stride at which memory is touched, which reaches every page whatever the page size
+const TOUCH_STRIDE: usize = 4096;
+
+/// Distinguishes the cgroups of balloons inflated by the same process
+static NEXT_BALLOON: AtomicU32 = AtomicU32::new(0);
+
+/// A child process allocating and touching memory over and over to generate real memory pressure, for integration tests of code reacting to it
+///
+/// The child frees and allocates its memory again in a loop, so with a limit set through [memory_high](MemoryBalloonBuilder::memory_high) it keeps being throttled,
+/// which the kernel reports as memory pressure of the balloon's cgroup. The child is killed when the balloon is dropped
+///
+/// ```no_run
+/// use pressure::{MemoryBalloon, PressureMonitor, Resource, WaitOutcome};
+///
+/// let balloon = MemoryBalloon::builder(256 << 20).memory_high(64 << 20).inflate()?;
+/// let mut monitor = PressureMonitor::for_cgroup(balloon.cgroup().unwrap(), Resource::Memory)?;
+/// assert!(matches!(monitor.wait()?, WaitOutcome::Triggered(_)));
+/// balloon.deflate()?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct MemoryBalloon {
+    child: Option<Pid>,
+    /// The cgroup created for the child, removed once it exited
+    cgroup: Option<PathBuf>,
+}
+
+/// Configures a [MemoryBalloon]
+#[derive(Debug, Clone)]
+pub struct MemoryBalloonBuilder {
+    size: usize,
+    memory_max: Option<u64>,
+    memory_high: Option<u64>,
+    parent: Option<PathBuf>,
+}
+
+impl MemoryBalloon {
+    /// Inflate a balloon of `size` bytes in the calling process' cgroup, which generates system-wide pressure once `size` approaches the memory available
+    pub fn inflate(size: usize) -> Result<Self, Error> {
+        Self::builder(size).inflate()
+    }
+
+    /// Configure a balloon of `size` bytes, see [MemoryBalloonBuilder]
+    pub fn builder(size: usize) -> MemoryBalloonBuilder {
+        MemoryBalloonBuilder {
+            size,
+            memory_max: None,
+            memory_high: None,
+            parent: None,
+        }
+    }
+
+    /// The child process allocating memory
+    pub fn pid(&self) -> Option<u32> {
+        self.child.map(|pid| pid.as_raw() as u32)
+    }
+
+    /// The cgroup created for the balloon, or [None] if it was inflated in the calling process' cgroup
+    pub fn cgroup(&self) -> Option<&Path> {
+        self.cgroup.as_deref()
+    }
+
+    /// Kill the child and remove its cgroup, reporting failures that dropping the balloon ignores
+    pub fn deflate(mut self) -> Result<(), Error> {
+        self.pop()
+    }
+
+    fn pop(&mut self) -> Result<(), Error> {
+        if let Some(child) = self.child.take() {
+            kill(child, Signal::SIGKILL)?;
+            waitpid(child, None)?;
+        }
+        if let Some(cgroup) = self.cgroup.take() {
+            std::fs::remove_dir(cgroup)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MemoryBalloon {
+    fn drop(&mut self) {
+        let _ = self.pop();
+    }
+}
+
+impl MemoryBalloonBuilder {
+    /// Limit the balloon's cgroup through memory.max, beyond which the child is reclaimed from and eventually OOM killed
+    pub fn memory_max(mut self, limit: u64) -> Self {
+        self.memory_max = Some(limit);
+        self
+    }
+
+    /// Limit the balloon's cgroup through memory.high, beyond which the child is throttled
+    pub fn memory_high(mut self, limit: u64) -> Self {
+        self.memory_high = Some(limit);
+        self
+    }
+
+    /// Create the balloon's cgroup below `cgroup` rather than at the root of the unified hierarchy.
+    /// A relative `cgroup` is resolved within the unified hierarchy
+    pub fn parent(mut self, cgroup: impl AsRef<Path>) -> Self {
+        self.parent = Some(cgroup.as_ref().to_path_buf());
+        self
+    }
+
+    /// Create the balloon's cgroup if it has limits or a parent, and start the child
+    pub fn inflate(self) -> Result<MemoryBalloon, Error> {
+        let size = NonZeroUsize::new(self.size).ok_or(Errno::EINVAL)?;
+        let mut balloon = MemoryBalloon {
+            child: None,
+            cgroup: None,
+        };
+        if self.memory_max.is_some() || self.memory_high.is_some() || self.parent.is_some() {
+            balloon.cgroup = Some(self.create_cgroup()?);
+        }
+        // The child waits for the write end to be closed, so that it only allocates once it was moved into its cgroup
+        let (moved, notify) = pipe2(OFlag::O_CLOEXEC)?;
+        // SAFETY: the child only makes async-signal-safe syscalls and never returns, so it doesn't touch state other threads may have left inconsistent
+        match unsafe { fork() }? {
+            ForkResult::Parent { child } => balloon.child = Some(child),
+            ForkResult::Child => {
+                drop(notify);
+                let _ = nix::unistd::read(&moved, &mut [0]);
+                inflate(size);
+            }
+        }
+        if let Some(cgroup) = &balloon.cgroup
+            && let Some(child) = balloon.child
+        {
+            std::fs::write(cgroup.join("cgroup.procs"), child.to_string())?;
+        }
+        drop(notify);
+        Ok(balloon)
+    }
+
+    fn create_cgroup(&self) -> Result<PathBuf, Error> {
+        let parent = match &self.parent {
+            Some(parent) => cgroup::resolve(parent, None)?,
+            None => cgroup::unified_root(None)?,
+        };
+        if !parent.is_dir() {
+            return Err(Error::CgroupNotFound(parent));
+        }
+        let limited = self.memory_max.is_some() || self.memory_high.is_some();
+        if limited {
+            enable_memory_controller(&parent)?;
+        }
+        let cgroup = parent.join(format!(
+            "pressure-balloon-{}-{}",
+            std::process::id(),
+            NEXT_BALLOON.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir(&cgroup)?;
+        let limits = [
+            ("memory.max", self.memory_max),
+            ("memory.high", self.memory_high),
+        ];
+        for (file, limit) in limits {
+            let Some(limit) = limit else {
+                continue;
+            };
+            if let Err(e) = std::fs::write(cgroup.join(file), limit.to_string()) {
+                let _ = std::fs::remove_dir(&cgroup);
+                return Err(e.into());
+            }
+        }
+        Ok(cgroup)
+    }
+}
+
+/// Make the memory controller available to the children of `parent`, so that limits can be set on them
+fn enable_memory_controller(parent: &Path) -> Result<(), Error> {
+    let enabled = std::fs::read_to_string(parent.join("cgroup.subtree_control"))?;
+    if enabled
+        .split_whitespace()
+        .any(|controller| controller == "memory")
+    {
+        return Ok(());
+    }
+    let available = std::fs::read_to_string(parent.join("cgroup.controllers"))?;
+    if !available
+        .split_whitespace()
+        .any(|controller| controller == "memory")
+    {
+        return Err(Error::ResourceUnavailable(Resource::Memory));
+    }
+    std::fs::write(parent.join("cgroup.subtree_control"), "+memory")?;
+    Ok(())
+}
+
+/// Allocate and touch `size` bytes, then free them and start over, until the child is killed
+fn inflate(size: NonZeroUsize) -> ! {
+    loop {
+        // SAFETY: an anonymous private mapping doesn't alias any memory of the process
+        let Ok(memory) = (unsafe {
+            mmap_anonymous(
+                None,
+                size,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_PRIVATE,
+            )
+        }) else {
+            // SAFETY: exiting without running destructors or atexit handlers, which are the parent's
+            unsafe { nix::libc::_exit(1) }
+        };
+        for offset in (0..size.get()).step_by(TOUCH_STRIDE) {
+            // SAFETY: the offset is within the mapping, which is writable
+            unsafe { memory.cast::<u8>().add(offset).write_volatile(1) };
+        }
+        // SAFETY: nothing refers to the mapping anymore
+        let _ = unsafe { munmap(memory, size.get()) };
+    }
+}
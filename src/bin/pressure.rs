@@ -0,0 +1,223 @@
+//! Command line tool for inspecting pressure stall information
+//!
+//! `pressure top` samples system-wide and per-cgroup pressure and shows the most pressured resources first, refreshing until interrupted
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use pressure::{PsiStats, Resource};
+
+const USAGE: &str = "usage: pressure top [--interval <seconds>] [--depth <levels>] [--cgroup <path>]... [--limit <rows>] [--iterations <count>] [--json]";
+
+const RESOURCES: [Resource; 4] = [Resource::Memory, Resource::Cpu, Resource::Io, Resource::Irq];
+
+struct Options {
+    interval: Duration,
+    /// How many levels of the cgroup hierarchy to sample below the root
+    depth: usize,
+    cgroups: Vec<PathBuf>,
+    limit: usize,
+    iterations: Option<usize>,
+    json: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            interval: Duration::from_secs(2),
+            depth: 1,
+            cgroups: Vec::new(),
+            limit: 20,
+            iterations: None,
+            json: false,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--interval" | "-i" => {
+                    let seconds = value()?.parse().map_err(|_| "invalid interval")?;
+                    options.interval =
+                        Duration::try_from_secs_f64(seconds).map_err(|_| "invalid interval")?;
+                }
+                "--depth" | "-d" => {
+                    options.depth = value()?.parse().map_err(|_| "invalid depth")?
+                }
+                "--cgroup" | "-c" => options.cgroups.push(value()?.into()),
+                "--limit" | "-l" => {
+                    options.limit = value()?.parse().map_err(|_| "invalid limit")?
+                }
+                "--iterations" | "-n" => {
+                    options.iterations = Some(value()?.parse().map_err(|_| "invalid iterations")?)
+                }
+                "--json" => options.json = true,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Pressure on a single resource, either system-wide or within a cgroup
+struct Row {
+    cgroup: Option<PathBuf>,
+    resource: Resource,
+    stats: PsiStats,
+}
+
+impl Row {
+    fn json(&self) -> serde_json::Value {
+        // Averages are reported with two decimals, which f32 doesn't represent exactly
+        let average = |average: f32| (f64::from(average) * 100.0).round() / 100.0;
+        let line = |line: &pressure::PsiLine| {
+            serde_json::json!({
+                "avg10": average(line.avg10),
+                "avg60": average(line.avg60),
+                "avg300": average(line.avg300),
+                "total_us": line.total.as_micros() as u64,
+            })
+        };
+        serde_json::json!({
+            "cgroup": self.cgroup,
+            "resource": self.resource.to_string(),
+            "some": line(&self.stats.some),
+            "full": line(&self.stats.full),
+        })
+    }
+}
+
+/// Find where the unified cgroup hierarchy is mounted, which is under /sys/fs/cgroup/unified on systems using the hybrid layout
+fn unified_root() -> Option<PathBuf> {
+    ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"]
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|path| path.join("cgroup.controllers").exists())
+}
+
+/// Collect the cgroups below `cgroup`, up to `depth` levels deep
+fn descendants(cgroup: &Path, depth: usize, cgroups: &mut Vec<PathBuf>) {
+    if depth == 0 {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(cgroup) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            let path = entry.path();
+            descendants(&path, depth - 1, cgroups);
+            cgroups.push(path);
+        }
+    }
+}
+
+fn sample(options: &Options) -> Vec<Row> {
+    let mut rows: Vec<Row> = RESOURCES
+        .into_iter()
+        .filter_map(|resource| {
+            Some(Row {
+                cgroup: None,
+                resource,
+                stats: PsiStats::read(resource).ok()?,
+            })
+        })
+        .collect();
+    let mut cgroups = options.cgroups.clone();
+    if let Some(root) = unified_root() {
+        descendants(&root, options.depth, &mut cgroups);
+    }
+    for cgroup in cgroups {
+        // Pressure files of controllers that aren't enabled for a cgroup don't exist, so only some resources may be available
+        rows.extend(
+            [Resource::Memory, Resource::Cpu, Resource::Io]
+                .into_iter()
+                .filter_map(|resource| {
+                    let file = format!("{resource}.pressure");
+                    Some(Row {
+                        stats: PsiStats::read_path(cgroup.join(file)).ok()?,
+                        cgroup: Some(cgroup.clone()),
+                        resource,
+                    })
+                }),
+        );
+    }
+    rows.sort_by(|a, b| {
+        let key = |row: &Row| row.stats.some.avg10.max(row.stats.full.avg10);
+        key(b).total_cmp(&key(a))
+    });
+    rows
+}
+
+fn render(rows: &[Row], options: &Options, out: &mut impl Write) -> std::io::Result<()> {
+    if options.json {
+        let rows: Vec<_> = rows.iter().take(options.limit).map(Row::json).collect();
+        return writeln!(out, "{}", serde_json::Value::Array(rows));
+    }
+    // Clear the screen and move the cursor to the top left, so the table refreshes in place
+    write!(out, "\x1b[2J\x1b[H")?;
+    writeln!(
+        out,
+        "{:<8} {:>7} {:>7} {:>7} {:>7} {:>7}  SCOPE",
+        "RESOURCE", "SOME10", "SOME60", "SOME300", "FULL10", "FULL60"
+    )?;
+    for row in rows.iter().take(options.limit) {
+        let scope = row
+            .cgroup
+            .as_deref()
+            .map_or("system".into(), Path::to_string_lossy);
+        writeln!(
+            out,
+            "{:<8} {:>7.2} {:>7.2} {:>7.2} {:>7.2} {:>7.2}  {scope}",
+            row.resource.to_string(),
+            row.stats.some.avg10,
+            row.stats.some.avg60,
+            row.stats.some.avg300,
+            row.stats.full.avg10,
+            row.stats.full.avg60,
+        )?;
+    }
+    out.flush()
+}
+
+fn top(options: Options) -> std::io::Result<()> {
+    let mut out = std::io::stdout().lock();
+    let mut iteration = 0;
+    loop {
+        render(&sample(&options), &options, &mut out)?;
+        iteration += 1;
+        if options
+            .iterations
+            .is_some_and(|iterations| iteration >= iterations)
+        {
+            return Ok(());
+        }
+        std::thread::sleep(options.interval);
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() != Some("top") {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("pressure: {e}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match top(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        // Stop quietly once the output is piped into a command that exited, such as head
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("pressure: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
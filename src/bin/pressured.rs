@@ -0,0 +1,284 @@
+//! Standalone daemon running the monitors and actions declared in a TOML configuration file, reloading it on SIGHUP
+//!
+//! With the systemd feature it reports readiness and the latest pressure to the service manager, and pings the watchdog from every monitor's thread,
+//! so it can run as a `Type=notify-reload` unit with `WatchdogSec=`
+//!
+//! ```toml
+//! [[monitor]]
+//! resource = "memory"
+//! cgroup = "/sys/fs/cgroup/system.slice"
+//! kind = "some"
+//! threshold_ms = 150
+//! window_ms = 2000
+//! min_severity = "medium"
+//! cooldown_secs = 30
+//! actions = [
+//!     { type = "log" },
+//!     { type = "exec", command = ["/usr/local/bin/shed-load"], timeout_secs = 10 },
+//!     { type = "notify" },
+//! ]
+//! ```
+
+#[cfg(not(unix))]
+compile_error!("pressured is only supported on Unix");
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+    time::Duration,
+};
+
+use nix::sys::signal::{SigSet, Signal};
+use pressure::{HookRegistry, HookRunner, PressureMonitorBuilder, Resource, Severity, StallKind};
+use serde::Deserialize;
+
+const DEFAULT_CONFIG: &str = "/etc/pressured.toml";
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default)]
+    monitor: Vec<MonitorConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MonitorConfig {
+    #[serde(default = "default_resource")]
+    resource: String,
+    /// Monitor a cgroup instead of system-wide pressure
+    cgroup: Option<PathBuf>,
+    kind: Option<String>,
+    threshold_ms: Option<u64>,
+    window_ms: Option<u64>,
+    /// Only run actions for events at which pressure is at least this severe
+    #[serde(default = "default_severity")]
+    min_severity: String,
+    /// Minimum time between two runs of each action
+    #[serde(default)]
+    cooldown_secs: u64,
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum Action {
+    /// Print the event to stderr
+    Log,
+    /// Run a command, with the event passed through environment variables
+    Exec {
+        command: Vec<String>,
+        timeout_secs: Option<u64>,
+    },
+    /// Send a LowMemoryWarning signal on the system bus, requires the dbus feature
+    Notify,
+}
+
+fn default_resource() -> String {
+    "memory".to_owned()
+}
+
+fn default_severity() -> String {
+    "low".to_owned()
+}
+
+fn parse_resource(name: &str) -> Result<Resource, Error> {
+    match name {
+        "memory" => Ok(Resource::Memory),
+        "cpu" => Ok(Resource::Cpu),
+        "io" => Ok(Resource::Io),
+        "irq" => Ok(Resource::Irq),
+        _ => Err(format!("unknown resource {name:?}, expected memory, cpu, io or irq").into()),
+    }
+}
+
+fn parse_severity(name: &str) -> Result<Severity, Error> {
+    match name {
+        "low" => Ok(Severity::Low),
+        "medium" => Ok(Severity::Medium),
+        "critical" => Ok(Severity::Critical),
+        _ => Err(format!("unknown severity {name:?}, expected low, medium or critical").into()),
+    }
+}
+
+/// State kept across reloads
+#[derive(Default)]
+struct Daemon {
+    /// Owns the low-memory-monitor name, which can't be taken again while the previous configuration's monitors are still running
+    #[cfg(feature = "dbus")]
+    emitter: Option<pressure::LowMemoryWarningEmitter>,
+    #[cfg(feature = "systemd")]
+    notifier: pressure::ServiceNotifier,
+}
+
+impl Daemon {
+    /// Open every configured monitor and start dispatching its events to its actions
+    fn start(&mut self, path: &Path) -> Result<Vec<HookRunner>, Error> {
+        let config: Config = toml::from_str(&std::fs::read_to_string(path)?)?;
+        // Open every monitor before starting any, so that a broken configuration doesn't leave some of them running
+        let mut monitors = Vec::new();
+        for monitor in &config.monitor {
+            let resource = parse_resource(&monitor.resource)?;
+            let mut builder = PressureMonitorBuilder::new().resource(resource);
+            if let Some(cgroup) = &monitor.cgroup {
+                builder = builder.cgroup(cgroup);
+            }
+            if let Some(kind) = &monitor.kind {
+                builder = builder.kind(kind.parse::<StallKind>()?);
+            }
+            if let Some(threshold) = monitor.threshold_ms {
+                builder = builder.threshold(Duration::from_millis(threshold));
+            }
+            if let Some(window) = monitor.window_ms {
+                builder = builder.window(Duration::from_millis(window));
+            }
+            let hooks = self.hooks(monitor)?;
+            monitors.push((builder.build()?, hooks));
+        }
+        monitors
+            .into_iter()
+            .map(|(monitor, hooks)| Ok(hooks.spawn(monitor)?))
+            .collect()
+    }
+
+    fn hooks(&mut self, monitor: &MonitorConfig) -> Result<HookRegistry, Error> {
+        let min = parse_severity(&monitor.min_severity)?;
+        let cooldown = Duration::from_secs(monitor.cooldown_secs);
+        let mut hooks = HookRegistry::new();
+        hooks.on_error(|e| eprintln!("pressured: action failed: {e}"));
+        #[cfg(feature = "systemd")]
+        hooks.notify_service(self.notifier.clone());
+        for action in &monitor.actions {
+            let hook = match action {
+                Action::Log => hooks.on_event(min, |event, severity| {
+                    match &event.cgroup {
+                        Some(cgroup) => eprintln!(
+                            "{} pressure ({severity}) in {}",
+                            event.resource,
+                            cgroup.display()
+                        ),
+                        None => eprintln!("{} pressure ({severity})", event.resource),
+                    };
+                }),
+                Action::Exec {
+                    command,
+                    timeout_secs,
+                } => {
+                    let (program, args) =
+                        command.split_first().ok_or("exec actions need a command")?;
+                    let mut command = Command::new(program);
+                    command.args(args);
+                    let hook = hooks.on_event_exec(min, command);
+                    if let Some(timeout) = timeout_secs {
+                        hook.timeout(Duration::from_secs(*timeout));
+                    }
+                    hook
+                }
+                #[cfg(feature = "dbus")]
+                Action::Notify => {
+                    let emitter = match &self.emitter {
+                        Some(emitter) => emitter.clone(),
+                        None => self
+                            .emitter
+                            .insert(pressure::LowMemoryWarningEmitter::new()?)
+                            .clone(),
+                    };
+                    hooks.on_event(min, move |_, severity| {
+                        if let Err(e) = emitter.emit(severity) {
+                            eprintln!("pressured: failed to send LowMemoryWarning: {e}");
+                        }
+                    })
+                }
+                #[cfg(not(feature = "dbus"))]
+                Action::Notify => return Err("notify actions require the dbus feature".into()),
+            };
+            hook.cooldown(cooldown);
+        }
+        Ok(hooks)
+    }
+}
+
+#[cfg(feature = "systemd")]
+impl Daemon {
+    /// Send a notification to the service manager, which only logs failures as supervision is best effort
+    fn notify(&self, send: impl FnOnce(&pressure::ServiceNotifier) -> Result<(), pressure::Error>) {
+        if let Err(e) = send(&self.notifier) {
+            eprintln!("pressured: failed to notify the service manager: {e}");
+        }
+    }
+}
+
+fn stop(runners: Vec<HookRunner>) {
+    for runner in runners {
+        if let Err(e) = runner.stop() {
+            eprintln!("pressured: monitor failed: {e}");
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let path = match (args.next().as_deref(), args.next()) {
+        (None, None) => PathBuf::from(DEFAULT_CONFIG),
+        (Some("--config" | "-c"), Some(path)) => PathBuf::from(path),
+        _ => {
+            eprintln!("usage: pressured [--config <path>]");
+            return ExitCode::FAILURE;
+        }
+    };
+    // Block the signals before any thread is spawned, so that they are only received through sigwait here
+    let mut signals = SigSet::empty();
+    for signal in [Signal::SIGHUP, Signal::SIGINT, Signal::SIGTERM] {
+        signals.add(signal);
+    }
+    if let Err(e) = signals.thread_block() {
+        eprintln!("pressured: failed to block signals: {e}");
+        return ExitCode::FAILURE;
+    }
+    let mut daemon = Daemon::default();
+    #[cfg(feature = "systemd")]
+    match pressure::ServiceNotifier::from_env() {
+        Ok(notifier) => daemon.notifier = notifier,
+        Err(e) => eprintln!("pressured: failed to connect to the service manager: {e}"),
+    }
+    let mut runners = match daemon.start(&path) {
+        Ok(runners) => runners,
+        Err(e) => {
+            eprintln!("pressured: {}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    #[cfg(feature = "systemd")]
+    daemon.notify(|notifier| notifier.ready());
+    loop {
+        match signals.wait() {
+            Ok(Signal::SIGHUP) => {
+                #[cfg(feature = "systemd")]
+                daemon.notify(|notifier| notifier.reloading());
+                match daemon.start(&path) {
+                    Ok(reloaded) => {
+                        stop(std::mem::replace(&mut runners, reloaded));
+                        eprintln!("pressured: reloaded {}", path.display());
+                    }
+                    Err(e) => eprintln!(
+                        "pressured: keeping the previous configuration, failed to reload {}: {e}",
+                        path.display()
+                    ),
+                }
+                #[cfg(feature = "systemd")]
+                daemon.notify(|notifier| notifier.ready());
+            }
+            Ok(_) => break,
+            Err(e) => {
+                eprintln!("pressured: failed to wait for signals: {e}");
+                break;
+            }
+        }
+    }
+    #[cfg(feature = "systemd")]
+    daemon.notify(|notifier| notifier.stopping());
+    stop(runners);
+    ExitCode::SUCCESS
+}
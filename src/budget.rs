@@ -0,0 +1,136 @@
+use crate::{Error, HookRegistry, HookRunner, MemoryContext, PressureMonitor, Severity};
+
+type Callback = dyn FnMut(u64) -> u64 + Send;
+
+struct Participant {
+    weight: u64,
+    callback: Box<Callback>,
+}
+
+/// Outcome of one reclaim cycle of a [ReclaimBudget]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BudgetReport {
+    pub severity: Severity,
+    /// Bytes the participants were asked to free in total
+    pub budget: u64,
+    /// Bytes the participants reported having freed
+    pub freed: u64,
+}
+
+/// Splits a byte budget to free between independent caches on each pressure cycle, so that they share the burden of reclaim
+///
+/// The budget grows with severity: it is at least a fixed amount per severity, and while [MemoryContext::mem_available] is below a goal
+/// also covers the shortfall, half of it under medium pressure and all of it once pressure is critical. Each participant is asked for a share
+/// of the budget proportional to its weight, such as its size or importance, and reports how many bytes it freed
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, ReclaimBudget, Severity};
+///
+/// let mut budget = ReclaimBudget::new().available_goal(1 << 30);
+/// // The image cache is three times as large as the query cache, so it frees three times as much
+/// budget.register(3, |bytes| { /* evict about `bytes` from the image cache */ bytes });
+/// budget.register(1, |bytes| { /* evict about `bytes` from the query cache */ bytes / 2 });
+/// let runner = budget.spawn(PressureMonitor::new()?, Severity::Medium, |report| {
+///     eprintln!("freed {} of {} bytes under {} pressure", report.freed, report.budget, report.severity);
+/// })?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct ReclaimBudget {
+    participants: Vec<Participant>,
+    medium: u64,
+    critical: u64,
+    available_goal: u64,
+}
+
+impl Default for ReclaimBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReclaimBudget {
+    /// A budget of at least 16MiB under medium and 64MiB under critical pressure, without an available memory goal
+    pub fn new() -> Self {
+        Self {
+            participants: Vec::new(),
+            medium: 16 << 20,
+            critical: 64 << 20,
+            available_goal: 0,
+        }
+    }
+
+    /// Least bytes to free per cycle under `severity`. Nothing is freed while pressure is [Severity::Low]
+    pub fn budget_at(mut self, severity: Severity, bytes: u64) -> Self {
+        match severity {
+            Severity::Low => {}
+            Severity::Medium => self.medium = bytes,
+            Severity::Critical => self.critical = bytes,
+        }
+        self
+    }
+
+    /// Bytes of memory that should be available, so that the budget covers the shortfall below it
+    pub fn available_goal(mut self, bytes: u64) -> Self {
+        self.available_goal = bytes;
+        self
+    }
+
+    /// Ask `callback` for a share of each budget proportional to `weight`, which returns how many bytes it freed
+    pub fn register(&mut self, weight: u64, callback: impl FnMut(u64) -> u64 + Send + 'static) {
+        self.participants.push(Participant {
+            weight,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Bytes to free under `severity` given `memory`, or only the least amount for the severity without a snapshot
+    pub fn budget(&self, severity: Severity, memory: Option<&MemoryContext>) -> u64 {
+        let shortfall = memory.map_or(0, |memory| {
+            self.available_goal.saturating_sub(memory.mem_available)
+        });
+        match severity {
+            Severity::Low => 0,
+            Severity::Medium => self.medium.max(shortfall / 2),
+            Severity::Critical => self.critical.max(shortfall),
+        }
+    }
+
+    /// Run one cycle, splitting the budget for `severity` and `memory` between the participants
+    pub fn reclaim(&mut self, severity: Severity, memory: Option<&MemoryContext>) -> BudgetReport {
+        let budget = self.budget(severity, memory);
+        let total_weight: u64 = self.participants.iter().map(|p| p.weight).sum();
+        let mut freed = 0;
+        if budget > 0 && total_weight > 0 {
+            for participant in &mut self.participants {
+                let share = (u128::from(budget) * u128::from(participant.weight)
+                    / u128::from(total_weight)) as u64;
+                if share > 0 {
+                    freed += (participant.callback)(share);
+                }
+            }
+        }
+        BudgetReport {
+            severity,
+            budget,
+            freed,
+        }
+    }
+
+    /// Run a cycle on each event of `monitor` at which pressure is at least `min` severe, on a worker thread. Events without an attached
+    /// [MemoryContext] read it from procfs, and only get the least amount for the severity where that fails.
+    /// `report` receives the outcome of each cycle
+    pub fn spawn(
+        mut self,
+        monitor: PressureMonitor,
+        min: Severity,
+        mut report: impl FnMut(&BudgetReport) + Send + 'static,
+    ) -> Result<HookRunner, Error> {
+        let mut hooks = HookRegistry::new();
+        hooks.on_event(min, move |event, severity| {
+            let memory = event.memory.or_else(|| MemoryContext::read().ok());
+            report(&self.reclaim(severity, memory.as_ref()))
+        });
+        hooks.spawn(monitor)
+    }
+}
@@ -0,0 +1,570 @@
+use std::{
+    os::fd::OwnedFd,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    CompressionContext, Error, MemoryContext, MonitorType, PressureMonitor, Resource,
+    SeverityThresholds, Source, StallKind,
+    adaptive::Adaptive,
+    cgroup,
+    clearing::Clearing,
+    debounce::Debounce,
+    env,
+    event::Target,
+    hysteresis::Hysteresis,
+    open_additional, open_monitor,
+    poll_set::PollSet,
+    polling::{self, Poller},
+    rate::EventCounter,
+    reconnect::Reconnect,
+    trigger::Trigger,
+};
+
+/// Builder used to configure a [PressureMonitor] before it is opened
+///
+/// If systemd's memory pressure interface is in use for system-wide memory pressure, the path and trigger it provides take precedence over the configured ones, see [prefer_environment](Self::prefer_environment) and [ignore_environment](Self::ignore_environment)
+///
+/// Holding back events with [sustained_firings](Self::sustained_firings), [sustained_avg10](Self::sustained_avg10) and [min_interval](Self::min_interval),
+/// [notify_cleared](Self::notify_cleared) and the `attach_*` options apply to the wait methods and streams of every [PressureMonitor],
+/// but not to [MonitorSet](crate::MonitorSet) or event loop integrations, which deliver every event as it is received
+///
+/// With the `serde` feature the configuration can be serialized and deserialized, with fields left out of it taking their default values
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PressureMonitorBuilder {
+    resource: Resource,
+    scope: Scope,
+    kind: Option<StallKind>,
+    threshold: Duration,
+    window: Duration,
+    fallback: Option<(f32, Duration)>,
+    severity: SeverityThresholds,
+    adaptive: Option<(Duration, Duration)>,
+    sustained_firings: Option<u32>,
+    sustained_avg10: Option<(f32, Duration)>,
+    min_interval: Option<Duration>,
+    reconnect: Option<(Duration, Duration)>,
+    cleared: Option<(f32, Duration)>,
+    attach_stats: bool,
+    attach_memory_context: bool,
+    attach_compression_context: bool,
+    additional: Vec<Trigger>,
+    procfs_root: Option<PathBuf>,
+    cgroup_root: Option<PathBuf>,
+    tolerate_invalid_write: bool,
+    interruptible: bool,
+    environment: Environment,
+}
+
+impl PressureMonitorBuilder {
+    pub fn new() -> Self {
+        let trigger = Trigger::default_for(Resource::Memory);
+        Self {
+            resource: Resource::Memory,
+            scope: Scope::System,
+            kind: None,
+            threshold: trigger.threshold(),
+            window: trigger.window(),
+            fallback: None,
+            severity: SeverityThresholds::default(),
+            adaptive: None,
+            sustained_firings: None,
+            sustained_avg10: None,
+            min_interval: None,
+            reconnect: None,
+            cleared: None,
+            attach_stats: false,
+            attach_memory_context: false,
+            attach_compression_context: false,
+            additional: Vec::new(),
+            procfs_root: None,
+            cgroup_root: None,
+            tolerate_invalid_write: false,
+            interruptible: false,
+            environment: Environment::SystemOnly,
+        }
+    }
+
+    /// Resource to monitor, defaults to [Resource::Memory]
+    pub fn resource(mut self, resource: Resource) -> Self {
+        self.resource = resource;
+        self
+    }
+
+    /// Monitor pressure within a cgroup v2 hierarchy instead of system-wide pressure, where relative paths such as `system.slice` are resolved within the unified hierarchy. This takes precedence over systemd's memory pressure interface unless [prefer_environment](Self::prefer_environment) is used
+    pub fn cgroup(mut self, cgroup: impl Into<PathBuf>) -> Self {
+        self.scope = Scope::Cgroup(cgroup.into());
+        self
+    }
+
+    /// Monitor pressure within the cgroup the calling process belongs to, which is resolved when the monitor is built
+    pub fn current_cgroup(mut self) -> Self {
+        self.scope = Scope::CurrentCgroup;
+        self
+    }
+
+    /// Monitor an explicit pressure file, fifo or socket instead of the one resolved for the resource, for example a pressure file bind-mounted into a chroot.
+    /// This takes precedence over systemd's memory pressure interface unless [prefer_environment](Self::prefer_environment) is used
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.scope = Scope::Path(path.into());
+        self
+    }
+
+    /// Locate system-wide pressure files under procfs mounted at `procfs_root` instead of `/proc`, for example in a container or to point tests at fixtures.
+    /// This takes precedence over the `PRESSURE_PROCFS_ROOT` environment variable
+    pub fn procfs_root(mut self, procfs_root: impl Into<PathBuf>) -> Self {
+        self.procfs_root = Some(procfs_root.into());
+        self
+    }
+
+    /// Look for the cgroup filesystem at `cgroup_root` instead of `/sys/fs/cgroup` when resolving [current_cgroup](Self::current_cgroup) and relative [cgroup](Self::cgroup) paths, for example in nested containers.
+    /// Its unified hierarchy is still detected within it on systems using the hybrid layout, and if neither layout is detected it's used as the unified hierarchy as is.
+    /// This takes precedence over the `PRESSURE_CGROUP_ROOT` environment variable
+    pub fn cgroup_root(mut self, cgroup_root: impl Into<PathBuf>) -> Self {
+        self.cgroup_root = Some(cgroup_root.into());
+        self
+    }
+
+    /// Set the kind, threshold and window at once from a [Trigger]
+    pub fn trigger(mut self, trigger: Trigger) -> Self {
+        self.kind = Some(trigger.kind());
+        self.threshold = trigger.threshold();
+        self.window = trigger.window();
+        self
+    }
+
+    /// Never use systemd's memory pressure interface, so that the monitor only depends on its explicit configuration
+    pub fn ignore_environment(mut self) -> Self {
+        self.environment = Environment::Ignore;
+        self
+    }
+
+    /// Use systemd's memory pressure interface whenever it is present for memory pressure, even if a cgroup or path was configured.
+    /// By default it is only used for system-wide memory pressure. Which configuration was used is reported by [PressureMonitor::config_source]
+    pub fn prefer_environment(mut self) -> Self {
+        self.environment = Environment::Prefer;
+        self
+    }
+
+    /// If systemd's MEMORY_PRESSURE_WRITE can't be decoded into a valid trigger, register the configured trigger instead of failing with [Error::InvalidWrite]
+    pub fn tolerate_invalid_write(mut self) -> Self {
+        self.tolerate_invalid_write = true;
+        self
+    }
+
+    /// Kind of stall to be notified about, defaults to [StallKind::Some], or [StallKind::Full] for [Resource::Irq]
+    pub fn kind(mut self, kind: StallKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Total stall time within a window that causes an event to be sent, defaults to 20ms
+    pub fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Length of the tracking window, which must be between 500ms and 10s. Defaults to 2s
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// If the kernel doesn't allow registering a trigger, for example because the process lacks write access to the pressure file,
+    /// fall back to reading pressure every `interval` and sending an event whenever avg10 is at least `avg10_threshold` percent
+    ///
+    /// On Android this defaults to sampling at the trigger's equivalent, and memory is always sampled on devices managed by lmkd, see `android::is_lmkd_managed`
+    pub fn fallback_to_polling(mut self, avg10_threshold: f32, interval: Duration) -> Self {
+        self.fallback = Some((avg10_threshold, interval));
+        self
+    }
+
+    /// Thresholds used to classify pressure, see [PressureMonitor::severity]
+    pub fn severity_thresholds(mut self, thresholds: SeverityThresholds) -> Self {
+        self.severity = thresholds;
+        self
+    }
+
+    /// Re-arm the trigger as pressure persists, doubling the threshold after each event up to `max_threshold`, and halving it again once no event occurred for `cooldown`.
+    /// The threshold the monitor is currently armed at is reported by [PressureMonitor::trigger].
+    ///
+    /// This only applies to the synchronous [PressureMonitor]'s wait methods, and is ignored if the trigger was provided by systemd or the monitor fell back to polling
+    pub fn adaptive(mut self, max_threshold: Duration, cooldown: Duration) -> Self {
+        self.adaptive = Some((max_threshold, cooldown));
+        self
+    }
+
+    /// Only surface events once the trigger fired `count` times in a row, so that a single transient spike doesn't cause one.
+    /// A streak ends once the trigger doesn't fire for two windows, or two sampling intervals if the monitor fell back to polling
+    pub fn sustained_firings(mut self, count: u32) -> Self {
+        self.sustained_firings = Some(count);
+        self
+    }
+
+    /// Only surface events once avg10 of the monitored kind of stall has stayed at least `avg10_threshold` percent for `duration`, as read whenever the trigger fires.
+    /// Combined with [sustained_firings](Self::sustained_firings), both conditions must hold
+    pub fn sustained_avg10(mut self, avg10_threshold: f32, duration: Duration) -> Self {
+        self.sustained_avg10 = Some((avg10_threshold, duration));
+        self
+    }
+
+    /// Deliver at most one event per `min_interval`. Events received within the interval are held back and coalesced into the latest one,
+    /// which is delivered once the interval has elapsed with the number of earlier events it replaces in [Event::suppressed](crate::Event::suppressed)
+    pub fn min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = Some(min_interval);
+        self
+    }
+
+    /// After an event, also deliver an event with [Transition::Cleared](crate::Transition::Cleared) once avg10 of the monitored kind of stall
+    /// has stayed below `avg10_threshold` percent for `duration`, for example to grow caches again. Pressure is read every second until it clears
+    pub fn notify_cleared(mut self, avg10_threshold: f32, duration: Duration) -> Self {
+        self.cleared = Some((avg10_threshold, duration));
+        self
+    }
+
+    /// Read the pressure file right after each event and attach the stall information to it as [Event::stats](crate::Event::stats),
+    /// sparing a separate [stats](PressureMonitor::stats) call that may already see different averages
+    pub fn attach_stats(mut self) -> Self {
+        self.attach_stats = true;
+        self
+    }
+
+    /// Read `/proc/meminfo` and `/proc/vmstat` right after each memory pressure event and attach a snapshot of them as [Event::memory](crate::Event::memory),
+    /// telling whether reclaim, swap or dirty writeback drives the pressure. Building fails if they can't be read, as on platforms other than Linux and Android
+    pub fn attach_memory_context(mut self) -> Self {
+        self.attach_memory_context = true;
+        self
+    }
+
+    /// Read zswap and zram statistics right after each memory pressure event and attach a snapshot of them as [Event::compression](crate::Event::compression),
+    /// telling whether pressure comes from compression churn or from compressed memory running out. Building fails if they can't be read, as on platforms other than Linux and Android
+    pub fn attach_compression_context(mut self) -> Self {
+        self.attach_compression_context = true;
+        self
+    }
+
+    /// Once the monitor's fd hangs up, for example because the service manager restarted or the fifo's writer went away, open its path again and resume waiting.
+    /// Failed attempts are retried after `initial_backoff`, doubling up to `max_backoff`. Once reconnected, waits return an event with [Transition::Reconnected](crate::Transition::Reconnected).
+    ///
+    /// This only applies to the synchronous [PressureMonitor]'s wait methods, others report the hangup
+    pub fn reconnect(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.reconnect = Some((initial_backoff, max_backoff));
+        self
+    }
+
+    /// End waits with [WaitOutcome::Interrupted](crate::WaitOutcome::Interrupted) when a signal is delivered to the waiting thread, so that signal handlers can request a shutdown.
+    /// By default waits resume after a signal. This only applies to the synchronous [PressureMonitor]
+    pub fn interruptible(mut self) -> Self {
+        self.interruptible = true;
+        self
+    }
+
+    /// Register another trigger on the same resource, for example a critical `full` trigger alongside an early warning `some` trigger.
+    /// Events report which trigger fired, and each additional trigger is registered with the kernel pressure file even if systemd provides the main one.
+    ///
+    /// Additional triggers are only waited on by the synchronous [PressureMonitor] and [MonitorSet](crate::MonitorSet), and are skipped if the monitor falls back to polling
+    pub fn additional_trigger(mut self, trigger: Trigger) -> Self {
+        self.additional.push(trigger);
+        self
+    }
+
+    pub fn build(self) -> Result<PressureMonitor, Error> {
+        let (source, target) = self.open()?;
+        let adaptive = match (&source, target.trigger, self.adaptive) {
+            // Only triggers configured here can be re-armed, and not ones provided by systemd
+            (
+                Source::Trigger(MonitorType::File(_)),
+                Some(trigger),
+                Some((max_threshold, cooldown)),
+            ) if target.config == ConfigSource::Explicit => {
+                Some(Adaptive::new(trigger, max_threshold, cooldown)?)
+            }
+            _ => None,
+        };
+        let pressure_path = match source {
+            Source::Trigger(_) => Some(self.pressure_path(target.cgroup())),
+            Source::Polling(_) | Source::Backend(_) | Source::Paused(_) => None,
+        };
+        let additional = match &pressure_path {
+            Some(path) => self.open_additional(path)?,
+            None => Vec::new(),
+        };
+        Ok(PressureMonitor {
+            source,
+            target,
+            adaptive,
+            reconnect: self
+                .reconnect
+                .map(|(initial_backoff, max_backoff)| Reconnect::new(initial_backoff, max_backoff)),
+            additional,
+            pressure_path,
+            cancellation: None,
+            interruptible: self.interruptible,
+            poll_set: PollSet::default(),
+        })
+    }
+
+    /// Build an [AsyncPressureMonitor](crate::AsyncPressureMonitor) waiting on the event loop of any [Reactor](crate::Reactor)
+    #[cfg(feature = "async")]
+    pub fn build_async<R: crate::Reactor>(self) -> Result<crate::AsyncPressureMonitor<R>, Error> {
+        let (source, target) = self.open()?;
+        crate::AsyncPressureMonitor::from_source(source, target)
+    }
+
+    /// Build an asynchronous [PressureMonitor](crate::tokio::PressureMonitor)
+    #[cfg(feature = "tokio")]
+    pub fn build_tokio(self) -> Result<crate::tokio::PressureMonitor, Error> {
+        self.build_async()
+    }
+
+    /// Build an asynchronous [PressureMonitor](crate::async_io::PressureMonitor) using async-io
+    #[cfg(feature = "async-io")]
+    pub fn build_async_io(self) -> Result<crate::async_io::PressureMonitor, Error> {
+        self.build_async()
+    }
+
+    fn configured_trigger(&self) -> Result<Trigger, Error> {
+        let kind = self
+            .kind
+            .unwrap_or_else(|| StallKind::default_for(self.resource));
+        if self.resource == Resource::Irq && kind == StallKind::Some {
+            return Err(Error::UnsupportedStallKind(self.resource, kind));
+        }
+        Trigger::new(kind, self.threshold, self.window)
+    }
+
+    /// Kernel pressure file for the configured resource and scope, ignoring systemd's memory pressure interface
+    fn pressure_path(&self, cgroup: Option<&Path>) -> PathBuf {
+        match (&self.scope, cgroup) {
+            (Scope::Path(path), _) => path.clone(),
+            (_, Some(cgroup)) => cgroup.join(self.resource.cgroup_file()),
+            (_, None) => match &self.procfs_root {
+                Some(procfs_root) => self.resource.proc_path_in(procfs_root),
+                None => self.resource.proc_path(),
+            },
+        }
+    }
+
+    /// Resolve which file to open and which trigger to register with it, if any
+    fn resolve(
+        &self,
+        trigger: Trigger,
+        cgroup: Option<&Path>,
+    ) -> Result<(PathBuf, Option<Trigger>, ConfigSource), Error> {
+        let use_environment = match self.environment {
+            Environment::SystemOnly => matches!(self.scope, Scope::System),
+            Environment::Prefer => true,
+            Environment::Ignore => false,
+        };
+        // MEMORY_PRESSURE_WATCH only describes memory pressure, other resources always use their own pressure file.
+        // If systemd disabled memory pressure monitoring for the unit, instead of disabling memory pressure handling entirely we instead use the kernel's pressure file
+        let watch = match self.resource {
+            Resource::Memory if use_environment => env::memory_pressure_watch()?,
+            _ => None,
+        };
+        if let Some(path) = watch {
+            let registered = match env::memory_pressure_write() {
+                Err(Error::InvalidWrite(_)) if self.tolerate_invalid_write => Some(trigger),
+                write => write?,
+            };
+            return Ok((path, registered, ConfigSource::Environment));
+        }
+        let path = self.pressure_path(cgroup);
+        let is_psi_file = !matches!(self.scope, Scope::Path(_));
+        if is_psi_file && !path.exists() {
+            match cgroup {
+                Some(cgroup) if !cgroup.exists() => {
+                    return Err(Error::CgroupNotFound(cgroup.to_path_buf()));
+                }
+                // The kernel doesn't create /proc/pressure at all when booted with psi=0
+                None if path.parent().is_some_and(|parent| !parent.exists()) => {
+                    return Err(Error::PsiDisabled);
+                }
+                // Nor pressure files for resources it doesn't account for, such as irq without CONFIG_IRQ_TIME_ACCOUNTING
+                _ => return Err(Error::ResourceUnavailable(self.resource)),
+            }
+        }
+        Ok((path, Some(trigger), ConfigSource::Explicit))
+    }
+
+    fn open_additional(&self, path: &Path) -> Result<Vec<(Trigger, OwnedFd)>, Error> {
+        self.additional
+            .iter()
+            .map(|&trigger| {
+                trigger.check_supported(self.resource)?;
+                Ok((trigger, open_additional(path, trigger)?))
+            })
+            .collect()
+    }
+
+    fn open(&self) -> Result<(Source, Target), Error> {
+        let opened = self.open_source();
+        #[cfg(feature = "tracing")]
+        match &opened {
+            Ok((source, target)) => tracing::info!(
+                resource = %target.resource,
+                kind = %target.kind,
+                path = ?target.path,
+                cgroup = ?target.cgroup,
+                trigger = ?target.trigger,
+                config = ?target.config,
+                polling = matches!(source, Source::Polling(_)),
+                "opened pressure monitor"
+            ),
+            Err(e) => {
+                tracing::warn!(resource = %self.resource, error = %e, "failed to open pressure monitor")
+            }
+        }
+        opened
+    }
+
+    fn open_source(&self) -> Result<(Source, Target), Error> {
+        // macOS has no pressure stall information, but reports system-wide memory pressure through a dispatch source
+        #[cfg(target_os = "macos")]
+        if let Scope::System = self.scope {
+            return crate::macos::open(self.resource);
+        }
+        // Neither does FreeBSD, where free memory is checked once per window instead
+        #[cfg(target_os = "freebsd")]
+        if let Scope::System = self.scope {
+            return crate::freebsd::open(self.resource, self.window);
+        }
+        let trigger = self.configured_trigger()?;
+        let kind = trigger.kind();
+        let cgroup = match &self.scope {
+            Scope::System | Scope::Path(_) => None,
+            Scope::Cgroup(path) => Some(cgroup::resolve(path, self.cgroup_root.as_deref())?),
+            Scope::CurrentCgroup => {
+                let procfs_root = self.procfs_root.clone().unwrap_or_else(crate::procfs_root);
+                Some(cgroup::current_cgroup_in(
+                    &procfs_root,
+                    self.cgroup_root.as_deref(),
+                )?)
+            }
+        };
+        let (path, registered, config) = self.resolve(trigger, cgroup.as_deref())?;
+        // A cgroup configured alongside the path provided by systemd isn't the one being monitored
+        let cgroup = cgroup.filter(|_| config == ConfigSource::Explicit);
+        // On Android triggers are usually restricted to system services, so sampling is the default fallback
+        #[cfg(target_os = "android")]
+        let fallback = self
+            .fallback
+            .or_else(|| registered.map(crate::android::sampling));
+        #[cfg(not(target_os = "android"))]
+        let fallback = self.fallback;
+        // lmkd registers its own memory triggers and acts on them, so sample rather than add triggers of our own
+        #[cfg(target_os = "android")]
+        let prefer_sampling =
+            self.resource == Resource::Memory && crate::android::is_lmkd_managed();
+        #[cfg(not(target_os = "android"))]
+        let prefer_sampling = false;
+        // The service manager may provide its own trigger, or none at all
+        let (source, trigger) = match fallback {
+            Some((threshold, interval)) if prefer_sampling => (
+                Source::Polling(Poller::new(&path, kind, threshold, interval)?),
+                None,
+            ),
+            _ => match (open_monitor(&path, registered), fallback) {
+                (Ok(pressure_file), _) => (Source::Trigger(pressure_file), registered),
+                (Err(e), Some((threshold, interval))) if polling::is_trigger_unsupported(&e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(error = %e, "trigger unsupported, falling back to polling");
+                    (
+                        Source::Polling(Poller::new(&path, kind, threshold, interval)?),
+                        None,
+                    )
+                }
+                (Err(e), _) => return Err(e),
+            },
+        };
+        // How often events arrive at most while pressure persists
+        let streak_interval = match &source {
+            Source::Polling(poller) => poller.interval(),
+            _ => trigger.map_or(self.window, Trigger::window),
+        };
+        let attach_memory = if self.attach_memory_context && self.resource == Resource::Memory {
+            let procfs_root = self.procfs_root.clone().unwrap_or_else(crate::procfs_root);
+            // Fail early rather than on the first event
+            MemoryContext::read_in(&procfs_root)?;
+            Some(procfs_root)
+        } else {
+            None
+        };
+        let attach_compression =
+            if self.attach_compression_context && self.resource == Resource::Memory {
+                let procfs_root = self.procfs_root.clone().unwrap_or_else(crate::procfs_root);
+                CompressionContext::read_in(&procfs_root, Path::new("/sys"))?;
+                Some(procfs_root)
+            } else {
+                None
+            };
+        let target = Target {
+            resource: self.resource,
+            kind: trigger.map_or(kind, Trigger::kind),
+            trigger,
+            cgroup,
+            path: Some(path),
+            severity: self.severity,
+            config,
+            hysteresis: Hysteresis::new(
+                self.sustained_firings
+                    .map(|count| (count, 2 * streak_interval)),
+                self.sustained_avg10,
+            ),
+            clearing: self
+                .cleared
+                .map(|(threshold, duration)| Clearing::new(threshold, duration)),
+            debounce: self.min_interval.map(Debounce::new),
+            attach_stats: self.attach_stats,
+            attach_memory,
+            attach_compression,
+            events: EventCounter::default(),
+        };
+        Ok((source, target))
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+enum Scope {
+    System,
+    Cgroup(PathBuf),
+    CurrentCgroup,
+    Path(PathBuf),
+}
+
+/// Where a monitor's path and trigger came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ConfigSource {
+    /// The builder's configuration
+    Explicit,
+    /// Systemd's memory pressure interface, through MEMORY_PRESSURE_WATCH and MEMORY_PRESSURE_WRITE
+    Environment,
+    /// A file descriptor the monitor was created from
+    FileDescriptor,
+    /// A source other than pressure stall information, such as the platform's memory pressure notifications or a custom backend passed to [PressureMonitor::from_backend](crate::PressureMonitor::from_backend)
+    Backend,
+}
+
+/// When systemd's memory pressure interface is used
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+enum Environment {
+    /// Only for system-wide memory pressure
+    SystemOnly,
+    Prefer,
+    Ignore,
+}
+
+impl Default for PressureMonitorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    sync::{
+        Arc, Mutex, MutexGuard,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::JoinHandle,
+};
+
+use crate::{CancellationToken, Error, Event, PressureMonitor, Severity, WaitOutcome};
+
+type Filter = dyn Fn(&Event, Severity) -> bool + Send;
+
+struct Subscriber {
+    filter: Box<Filter>,
+    sender: Sender<(Event, Severity)>,
+}
+
+/// Waits for events of one monitor on a worker thread and broadcasts them to any number of subscribers, each receiving them on its own channel
+///
+/// This lets different parts of a synchronous application react to pressure independently, without each owning a monitor.
+/// Subscribers are dropped from the bus once their receiver is dropped. If waiting for events fails the worker stops, disconnecting every receiver,
+/// and the error is returned by [stop](Self::stop)
+///
+/// ```no_run
+/// use pressure::{PressureBus, PressureMonitor, Severity};
+///
+/// let bus = PressureBus::spawn(PressureMonitor::new()?)?;
+/// let cache_events = bus.subscribe();
+/// let scheduler_events = bus.subscribe_min(Severity::Critical);
+/// std::thread::spawn(move || {
+///     for (event, severity) in cache_events {
+///         // Shrink caches
+///     }
+/// });
+/// // ...
+/// bus.stop()?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct PressureBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    cancellation: CancellationToken,
+    thread: JoinHandle<Result<(), Error>>,
+}
+
+impl PressureBus {
+    /// Wait for events on `monitor` on a worker thread
+    pub fn spawn(mut monitor: PressureMonitor) -> Result<Self, Error> {
+        let cancellation = monitor.cancellation_token()?;
+        let subscribers = Arc::new(Mutex::new(Vec::<Subscriber>::new()));
+        let shared = subscribers.clone();
+        let thread = std::thread::Builder::new()
+            .name("pressure-bus".into())
+            .spawn(move || {
+                let result = broadcast(&mut monitor, &shared);
+                // Disconnect the receivers, the bus may still hold the list
+                lock(&shared).clear();
+                result
+            })?;
+        Ok(Self {
+            subscribers,
+            cancellation,
+            thread,
+        })
+    }
+    /// Receive every event along with the severity of pressure at the time
+    pub fn subscribe(&self) -> Receiver<(Event, Severity)> {
+        self.subscribe_filtered(|_, _| true)
+    }
+    /// Receive events at which pressure is at least `min` severe
+    pub fn subscribe_min(&self, min: Severity) -> Receiver<(Event, Severity)> {
+        self.subscribe_filtered(move |_, severity| severity >= min)
+    }
+    /// Receive the events `filter` returns true for
+    pub fn subscribe_filtered(
+        &self,
+        filter: impl Fn(&Event, Severity) -> bool + Send + 'static,
+    ) -> Receiver<(Event, Severity)> {
+        let (sender, receiver) = mpsc::channel();
+        // Once the worker stopped the receiver is disconnected right away
+        if !self.thread.is_finished() {
+            lock(&self.subscribers).push(Subscriber {
+                filter: Box::new(filter),
+                sender,
+            });
+        }
+        receiver
+    }
+    /// Number of subscribers whose receiver hasn't been dropped as of the last event
+    pub fn subscribers(&self) -> usize {
+        lock(&self.subscribers).len()
+    }
+    /// Stop waiting for events, disconnecting every receiver, and return the error that stopped the worker early, if any
+    pub fn stop(self) -> Result<(), Error> {
+        self.cancellation.cancel();
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+    /// Whether the worker stopped because waiting for events failed
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
+
+fn broadcast(
+    monitor: &mut PressureMonitor,
+    subscribers: &Mutex<Vec<Subscriber>>,
+) -> Result<(), Error> {
+    loop {
+        match monitor.wait()? {
+            WaitOutcome::Triggered(event) => {
+                let severity = monitor.severity()?;
+                lock(subscribers).retain(|subscriber| {
+                    !(subscriber.filter)(&event, severity)
+                        || subscriber.sender.send((event.clone(), severity)).is_ok()
+                });
+            }
+            WaitOutcome::TimedOut | WaitOutcome::Cancelled => return Ok(()),
+            WaitOutcome::Interrupted => {}
+            WaitOutcome::Hangup => return Err(Error::Hangup),
+            WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+        }
+    }
+}
+
+fn lock(subscribers: &Mutex<Vec<Subscriber>>) -> MutexGuard<'_, Vec<Subscriber>> {
+    subscribers.lock().unwrap_or_else(|e| e.into_inner())
+}
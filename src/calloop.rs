@@ -0,0 +1,129 @@
+//! Pressure events as a [calloop] event source
+
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+
+use calloop::{
+    EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory, generic::Generic,
+};
+
+use crate::{
+    Error, Event, PressureMonitor, PsiStats, Resource, Source, event::Target,
+    readiness::ReadableMonitor,
+};
+
+/// Event source dispatching a callback on each pressure event
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, calloop::PressureSource};
+/// let mut event_loop = calloop::EventLoop::<()>::try_new().unwrap();
+/// let source = PressureSource::new(PressureMonitor::new().unwrap()).unwrap();
+/// event_loop
+///     .handle()
+///     .insert_source(source, |event, _, _| {
+///         // Drop caches
+///     })
+///     .unwrap();
+/// ```
+pub struct PressureSource {
+    inner: Generic<ReadableMonitor, Error>,
+    target: Target,
+}
+
+impl PressureSource {
+    /// Create an event source from a monitor. Monitors that fell back to polling or use a custom backend can't be used as an event source
+    pub fn new(monitor: PressureMonitor) -> Result<Self, Error> {
+        let pressure_file = match monitor.source {
+            Source::Trigger(pressure_file) => pressure_file,
+            Source::Polling(_) | Source::Backend(_) => return Err(Error::NoFileDescriptor),
+            Source::Paused(_) => return Err(Error::Paused),
+        };
+        Ok(Self {
+            inner: Generic::new_with_error(
+                ReadableMonitor::new(pressure_file)?,
+                Interest::READ,
+                Mode::Level,
+            ),
+            target: monitor.target,
+        })
+    }
+
+    pub fn resource(&self) -> Resource {
+        self.target.resource
+    }
+
+    /// Take ownership of the monitor's fd once the source has been removed from the event loop, see [PressureMonitor::into_fd]
+    pub fn into_fd(self) -> OwnedFd {
+        self.inner.unwrap().into_inner().into()
+    }
+
+    /// Read the current pressure stall information for the monitored resource
+    pub fn stats(&self) -> Result<PsiStats, Error> {
+        self.inner
+            .get_ref()
+            .pressure_file()
+            .stats(self.target.resource)
+    }
+}
+
+/// The fd pressure events are signalled on, see [PressureMonitor::poll_interest]
+impl AsFd for PressureSource {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.get_ref().pressure_file().as_fd()
+    }
+}
+
+impl AsRawFd for PressureSource {
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_fd().as_raw_fd()
+    }
+}
+
+impl EventSource for PressureSource {
+    type Event = Event;
+    type Metadata = ();
+    type Ret = ();
+    type Error = Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let target = &self.target;
+        self.inner.process_events(readiness, token, |_, monitor| {
+            let coalesced = monitor.clear()?;
+            callback(
+                Event {
+                    coalesced,
+                    ..target.event()
+                },
+                &mut (),
+            );
+            Ok(PostAction::Continue)
+        })
+    }
+
+    fn register(
+        &mut self,
+        poll: &mut Poll,
+        token_factory: &mut TokenFactory,
+    ) -> calloop::Result<()> {
+        self.inner.register(poll, token_factory)
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &mut Poll,
+        token_factory: &mut TokenFactory,
+    ) -> calloop::Result<()> {
+        self.inner.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.inner.unregister(poll)
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+use std::os::fd::OwnedFd;
+#[cfg(unix)]
+use std::os::fd::{AsFd, BorrowedFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, FromRawHandle, OwnedHandle};
+use std::sync::Arc;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use nix::sys::eventfd::{EfdFlags, EventFd};
+#[cfg(windows)]
+use windows_sys::Win32::{
+    Foundation::WAIT_OBJECT_0,
+    System::Threading::{CreateEventW, SetEvent, WaitForSingleObject},
+};
+
+use crate::Error;
+
+/// Handle used to interrupt a [PressureMonitor](crate::PressureMonitor) blocked in [wait](crate::PressureMonitor::wait) from another thread
+///
+/// Once cancelled, every wait on the monitor returns [WaitOutcome::Cancelled](crate::WaitOutcome::Cancelled) immediately, so the waiting thread can shut down and be joined
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    eventfd: Arc<EventFd>,
+    /// Read and write end of a pipe, used where eventfd isn't available
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    pipe: Arc<(OwnedFd, OwnedFd)>,
+    /// Manual-reset event object, which stays signalled once set
+    #[cfg(windows)]
+    event: Arc<OwnedHandle>,
+}
+
+impl CancellationToken {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn new() -> Result<Self, Error> {
+        let eventfd = EventFd::from_flags(EfdFlags::EFD_CLOEXEC | EfdFlags::EFD_NONBLOCK)?;
+        Ok(Self {
+            eventfd: Arc::new(eventfd),
+        })
+    }
+
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    pub(crate) fn new() -> Result<Self, Error> {
+        Ok(Self {
+            pipe: Arc::new(crate::nonblocking_pipe()?),
+        })
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn new() -> Result<Self, Error> {
+        // SAFETY: no security attributes or name are passed, and the returned handle is checked before taking ownership
+        let event = unsafe { CreateEventW(std::ptr::null(), 1, 0, std::ptr::null()) };
+        if event.is_null() {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            // SAFETY: the event was just created and is owned by nothing else
+            event: Arc::new(unsafe { OwnedHandle::from_raw_handle(event) }),
+        })
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn cancel(&self) {
+        // Writing only fails if the counter would overflow, in which case it is already non-zero and the token is cancelled
+        let _ = self.eventfd.write(1);
+    }
+
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    pub fn cancel(&self) {
+        // The pipe is never read, so a failed write means it is full and the token is already cancelled
+        let _ = nix::unistd::write(&self.pipe.1, &[1]);
+    }
+
+    #[cfg(windows)]
+    pub fn cancel(&self) {
+        // SAFETY: the handle is a valid event object for as long as the token exists
+        unsafe { SetEvent(self.event.as_raw_handle()) };
+    }
+
+    #[cfg(unix)]
+    pub fn is_cancelled(&self) -> bool {
+        let mut fds = [nix::poll::PollFd::new(
+            self.as_fd(),
+            nix::poll::PollFlags::POLLIN,
+        )];
+        nix::poll::poll(&mut fds, nix::poll::PollTimeout::ZERO).is_ok_and(|ready| ready > 0)
+    }
+
+    #[cfg(windows)]
+    pub fn is_cancelled(&self) -> bool {
+        // SAFETY: the handle is a valid event object for as long as the token exists
+        unsafe { WaitForSingleObject(self.event.as_raw_handle(), 0) == WAIT_OBJECT_0 }
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for CancellationToken {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        return self.eventfd.as_fd();
+        #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+        return self.pipe.0.as_fd();
+    }
+}
+
+/// The event object that becomes signalled once the token is cancelled
+#[cfg(windows)]
+impl AsHandle for CancellationToken {
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        self.event.as_handle()
+    }
+}
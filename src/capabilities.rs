@@ -0,0 +1,50 @@
+use crate::{PsiStats, Resource, Trigger, cgroup};
+
+/// Pressure stall information features supported by the running kernel, see [capabilities]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    /// Whether the kernel reports pressure, which needs CONFIG_PSI and isn't the case when booted with `psi=0`
+    pub psi: bool,
+    /// Whether the cgroup the calling process belongs to reports its own pressure
+    pub cgroup_pressure: bool,
+    /// Whether irq pressure is reported, see [Resource::Irq]
+    pub irq: bool,
+    /// Whether the calling process may register triggers on system-wide pressure files.
+    /// Kernels before 6.5 require CAP_SYS_RESOURCE, later ones allow unprivileged triggers with windows that are a multiple of 2s
+    pub triggers: bool,
+    /// Whether the unified cgroup v2 hierarchy is mounted
+    pub cgroup_v2: bool,
+}
+
+/// Detect which pressure stall information features the running kernel supports, so applications can choose between triggers and sampling up front
+///
+/// Trigger support is detected by registering [Trigger::default_for] on system-wide memory pressure and removing it again
+///
+/// ```no_run
+/// let capabilities = pressure::capabilities();
+/// if !capabilities.triggers {
+///     println!("triggers aren't allowed, falling back to sampling");
+/// }
+/// ```
+pub fn capabilities() -> Capabilities {
+    // Pressure files exist but fail to read on kernels with PSI compiled in but disabled at runtime
+    let psi = PsiStats::read(Resource::Memory).is_ok();
+    let cgroup_pressure = cgroup::current_cgroup().is_ok_and(|cgroup| {
+        PsiStats::read_path(cgroup.join(Resource::Memory.cgroup_file())).is_ok()
+    });
+    let irq = psi && PsiStats::read(Resource::Irq).is_ok();
+    let triggers = psi
+        && crate::open_monitor(
+            &Resource::Memory.proc_path(),
+            Some(Trigger::default_for(Resource::Memory)),
+        )
+        .is_ok();
+    Capabilities {
+        psi,
+        cgroup_pressure,
+        irq,
+        triggers,
+        cgroup_v2: cgroup::unified_root(None).is_ok(),
+    }
+}
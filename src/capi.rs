@@ -0,0 +1,235 @@
+//! C API, declared in include/pressure.h which is generated with `cbindgen --output include/pressure.h`
+//!
+//! Functions returning an int return a negative errno on failure, and those returning a pointer return NULL and set errno
+use std::{
+    ffi::{c_int, c_void},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use nix::errno::Errno;
+
+use crate::{CancellationToken, Error, Event, Resource, StallKind, Transition, WaitOutcome};
+
+/// A pressure monitor, created with `pressure_monitor_new` and freed with `pressure_monitor_free`
+pub struct FfiMonitor {
+    /// [None] while a thread waits on the monitor to invoke the callback
+    monitor: Option<crate::PressureMonitor>,
+    watcher: Option<(CancellationToken, JoinHandle<crate::PressureMonitor>)>,
+}
+
+/// A resource tracked by pressure stall information
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum FfiResource {
+    Memory,
+    Cpu,
+    Io,
+    Irq,
+}
+
+/// A pressure event, describing the monitor it was received on
+#[repr(C)]
+pub struct FfiEvent {
+    pub resource: FfiResource,
+    /// 0 for some, 1 for full
+    pub kind: c_int,
+    /// 0 when pressure rose, 1 when it cleared, 2 when the monitor reconnected
+    pub transition: c_int,
+    /// Threshold of the trigger that fired in microseconds, or 0 if there was none
+    pub threshold_us: u64,
+    /// Window of the trigger that fired in microseconds, or 0 if there was none
+    pub window_us: u64,
+    pub coalesced: u32,
+    pub suppressed: u32,
+}
+
+/// Invoked with every event received on the monitor, and the user data it was registered with
+pub type FfiCallback = Option<extern "C" fn(event: *const FfiEvent, user_data: *mut c_void)>;
+
+/// User data handed to the thread invoking a callback, which the caller of `pressure_monitor_set_callback` vouches is usable from it
+struct UserData(*mut c_void);
+
+// SAFETY: the caller of pressure_monitor_set_callback guarantees the user data can be used from another thread
+unsafe impl Send for UserData {}
+
+impl From<FfiResource> for Resource {
+    fn from(resource: FfiResource) -> Self {
+        match resource {
+            FfiResource::Memory => Resource::Memory,
+            FfiResource::Cpu => Resource::Cpu,
+            FfiResource::Io => Resource::Io,
+            FfiResource::Irq => Resource::Irq,
+        }
+    }
+}
+
+impl From<&Event> for FfiEvent {
+    fn from(event: &Event) -> Self {
+        Self {
+            resource: match event.resource {
+                Resource::Memory => FfiResource::Memory,
+                Resource::Cpu => FfiResource::Cpu,
+                Resource::Io => FfiResource::Io,
+                Resource::Irq => FfiResource::Irq,
+            },
+            kind: match event.kind {
+                StallKind::Some => 0,
+                StallKind::Full => 1,
+            },
+            transition: match event.transition {
+                Transition::Pressure => 0,
+                Transition::Cleared => 1,
+                Transition::Reconnected => 2,
+            },
+            threshold_us: event
+                .trigger
+                .map_or(0, |trigger| trigger.threshold().as_micros() as u64),
+            window_us: event
+                .trigger
+                .map_or(0, |trigger| trigger.window().as_micros() as u64),
+            coalesced: event.coalesced,
+            suppressed: event.suppressed,
+        }
+    }
+}
+
+/// Negative errno for an error, picking the closest one for errors that aren't a failed syscall
+fn to_errno(error: &Error) -> c_int {
+    let errno = crate::errno(error).unwrap_or(match error {
+        Error::PermissionDenied { .. } => Errno::EACCES,
+        Error::PsiDisabled | Error::ResourceUnavailable(_) => Errno::EOPNOTSUPP,
+        Error::CgroupNotFound(_) => Errno::ENOENT,
+        Error::TriggerRejected { .. } | Error::InvalidWindow(_) | Error::InvalidThreshold(_) => {
+            Errno::EINVAL
+        }
+        _ => Errno::EIO,
+    });
+    -(errno as c_int)
+}
+
+/// Create a monitor for the given resource, honoring systemd's memory pressure interface if present
+#[unsafe(no_mangle)]
+pub extern "C" fn pressure_monitor_new(resource: FfiResource) -> *mut FfiMonitor {
+    match crate::PressureMonitor::with_resource(resource.into()) {
+        Ok(monitor) => Box::into_raw(Box::new(FfiMonitor {
+            monitor: Some(monitor),
+            watcher: None,
+        })),
+        Err(e) => {
+            Errno::set_raw(-to_errno(&e));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Wait for a pressure event, storing it in `event` if it isn't NULL. A negative `timeout_ms` waits forever.
+/// Returns 1 if an event was received, 0 if the timeout elapsed, -EINTR if a signal interrupted the wait and -EBUSY while a callback is registered
+///
+/// # Safety
+///
+/// `monitor` must have been returned by `pressure_monitor_new` and not freed, and `event` must be NULL or valid for writes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pressure_monitor_wait(
+    monitor: *mut FfiMonitor,
+    timeout_ms: i64,
+    event: *mut FfiEvent,
+) -> c_int {
+    // SAFETY: the caller guarantees the monitor is valid and not used from another thread
+    let Some(monitor) = (unsafe { monitor.as_mut() }) else {
+        return -(Errno::EINVAL as c_int);
+    };
+    let Some(monitor) = &mut monitor.monitor else {
+        return -(Errno::EBUSY as c_int);
+    };
+    let outcome = match u64::try_from(timeout_ms) {
+        Ok(timeout) => monitor.wait_timeout(Duration::from_millis(timeout)),
+        Err(_) => monitor.wait(),
+    };
+    match outcome {
+        Ok(WaitOutcome::Triggered(received)) => {
+            if !event.is_null() {
+                // SAFETY: the caller guarantees a non-null event is valid for writes
+                unsafe { event.write(FfiEvent::from(&received)) };
+            }
+            1
+        }
+        Ok(WaitOutcome::TimedOut) => 0,
+        Ok(WaitOutcome::Cancelled | WaitOutcome::Interrupted) => -(Errno::EINTR as c_int),
+        Ok(WaitOutcome::Hangup) => to_errno(&Error::Hangup),
+        Ok(WaitOutcome::Error(revents)) => to_errno(&Error::PollError(revents)),
+        Err(e) => to_errno(&e),
+    }
+}
+
+/// Invoke `callback` with every event the monitor receives, from a thread waiting on the monitor until another callback is set or the monitor is freed.
+/// A NULL `callback` stops invoking the previous one, after which `pressure_monitor_wait` can be used again. Returns 0 on success
+///
+/// # Safety
+///
+/// `monitor` must have been returned by `pressure_monitor_new` and not freed, and `user_data` must be safe to use from the callback on another thread
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pressure_monitor_set_callback(
+    monitor: *mut FfiMonitor,
+    callback: FfiCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    // SAFETY: the caller guarantees the monitor is valid and not used from another thread
+    let Some(monitor) = (unsafe { monitor.as_mut() }) else {
+        return -(Errno::EINVAL as c_int);
+    };
+    if let Some((cancellation, watcher)) = monitor.watcher.take() {
+        cancellation.cancel();
+        let Ok(mut stopped) = watcher.join() else {
+            return -(Errno::EIO as c_int);
+        };
+        // Tokens stay cancelled, so waits get a fresh one
+        stopped.cancellation = None;
+        monitor.monitor = Some(stopped);
+    }
+    let Some(callback) = callback else {
+        return 0;
+    };
+    let Some(mut waiting) = monitor.monitor.take() else {
+        return -(Errno::EIO as c_int);
+    };
+    let cancellation = match waiting.cancellation_token() {
+        Ok(cancellation) => cancellation,
+        Err(e) => {
+            monitor.monitor = Some(waiting);
+            return to_errno(&e);
+        }
+    };
+    let user_data = UserData(user_data);
+    let watcher = std::thread::spawn(move || {
+        let user_data = user_data;
+        loop {
+            match waiting.wait() {
+                Ok(WaitOutcome::Triggered(event)) => callback(&FfiEvent::from(&event), user_data.0),
+                Ok(WaitOutcome::Interrupted | WaitOutcome::TimedOut) => continue,
+                // Waiting again after the fd died or failed would spin, so the callback stops until it is set again
+                Ok(_) | Err(_) => return waiting,
+            }
+        }
+    });
+    monitor.watcher = Some((cancellation, watcher));
+    0
+}
+
+/// Free a monitor, stopping its callback if one is registered
+///
+/// # Safety
+///
+/// `monitor` must be NULL or have been returned by `pressure_monitor_new` and not freed yet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pressure_monitor_free(monitor: *mut FfiMonitor) {
+    if monitor.is_null() {
+        return;
+    }
+    // SAFETY: the caller guarantees the monitor was allocated by pressure_monitor_new and is freed only once
+    let monitor = unsafe { Box::from_raw(monitor) };
+    if let Some((cancellation, watcher)) = monitor.watcher {
+        cancellation.cancel();
+        let _ = watcher.join();
+    }
+}
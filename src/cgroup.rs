@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Resolve the cgroup v2 directory the calling process belongs to
+pub(crate) fn current_cgroup() -> Result<PathBuf, Error> {
+    current_cgroup_in(&crate::procfs_root(), None)
+}
+
+/// Resolve the cgroup v2 directory the calling process belongs to, reading its membership from procfs mounted at `procfs_root` and resolving it within `cgroup_root`, see [unified_root]
+pub(crate) fn current_cgroup_in(
+    procfs_root: &Path,
+    cgroup_root: Option<&Path>,
+) -> Result<PathBuf, Error> {
+    let cgroups = std::fs::read_to_string(procfs_root.join("self/cgroup"))?;
+    // Entries are formatted as hierarchy-ID:controller-list:cgroup-path, the unified hierarchy always has the ID 0 and no controllers
+    let path = cgroups
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or(Error::NoUnifiedCgroup)?;
+    Ok(unified_root(cgroup_root)?.join(path.trim_start_matches('/')))
+}
+
+/// Resolve a cgroup given to a constructor, where relative paths are within the unified hierarchy
+pub(crate) fn resolve(cgroup: &Path, cgroup_root: Option<&Path>) -> Result<PathBuf, Error> {
+    if cgroup.is_absolute() {
+        return Ok(cgroup.to_path_buf());
+    }
+    Ok(unified_root(cgroup_root)?.join(cgroup))
+}
+
+/// Find where the unified hierarchy is mounted, which is under /sys/fs/cgroup/unified on systems using the hybrid layout.
+/// The cgroup filesystem is looked for at `root`, or where `PRESSURE_CGROUP_ROOT` points, before /sys/fs/cgroup. Such a root is trusted to be the unified hierarchy if neither layout is detected within it
+pub(crate) fn unified_root(root: Option<&Path>) -> Result<PathBuf, Error> {
+    let configured = root.map(Path::to_path_buf).or_else(|| {
+        std::env::var_os("PRESSURE_CGROUP_ROOT")
+            .filter(|root| !root.is_empty())
+            .map(PathBuf::from)
+    });
+    let root = configured.clone().unwrap_or_else(|| CGROUP_ROOT.into());
+    [root.clone(), root.join("unified")]
+        .into_iter()
+        .find(|path| path.join("cgroup.controllers").exists())
+        .or(configured)
+        .ok_or(Error::NoUnifiedCgroup)
+}
+
+/// Reject pod and container IDs that could name a path outside the cgroup being looked in
+#[cfg(all(any(feature = "k8s", feature = "containers"), target_os = "linux"))]
+pub(crate) fn check_id(id: &str) -> Result<(), Error> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::InvalidId(id.to_string()));
+    }
+    Ok(())
+}
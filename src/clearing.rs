@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use crate::PsiLine;
+
+/// How often pressure is read while waiting for it to clear
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Notices pressure clearing after an event, once avg10 stays below a threshold for a duration
+#[derive(Debug, Clone)]
+pub(crate) struct Clearing {
+    threshold: f32,
+    duration: Duration,
+    /// Whether an event was surfaced since pressure last cleared
+    pressured: bool,
+    last_check: Instant,
+    below_since: Option<Instant>,
+}
+
+impl Clearing {
+    pub(crate) fn new(threshold: f32, duration: Duration) -> Self {
+        Self {
+            threshold,
+            duration,
+            pressured: false,
+            last_check: Instant::now(),
+            below_since: None,
+        }
+    }
+
+    /// Record a firing, which means pressure hasn't cleared yet. Only surfaced events make the monitor wait for pressure to clear
+    pub(crate) fn fired(&mut self, surfaced: bool) {
+        self.pressured |= surfaced;
+        self.last_check = Instant::now();
+        self.below_since = None;
+    }
+
+    /// When pressure should next be read, if the monitor is waiting for it to clear
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        let next = self.last_check + CHECK_INTERVAL;
+        // Check again as soon as pressure has been low long enough
+        let cleared = self
+            .below_since
+            .and_then(|since| since.checked_add(self.duration));
+        self.pressured
+            .then(|| cleared.map_or(next, |cleared| next.min(cleared)))
+    }
+
+    /// Check the current pressure, returning whether it just cleared
+    pub(crate) fn check(&mut self, pressure: &PsiLine) -> bool {
+        let now = Instant::now();
+        self.last_check = now;
+        if pressure.avg10 >= self.threshold {
+            self.below_since = None;
+            return false;
+        }
+        let since = *self.below_since.get_or_insert(now);
+        if now - since < self.duration {
+            return false;
+        }
+        self.pressured = false;
+        self.below_since = None;
+        true
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+/// Raw readings of the system's clocks, taken right after the syscall that received an event or sample.
+/// Unlike an [Instant](std::time::Instant) they can be compared with timestamps from the kernel, tracing tools or other processes,
+/// for example to measure the latency between pressure being signalled and the application reacting to it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockTimestamp {
+    /// `CLOCK_MONOTONIC`, which doesn't advance while the system is suspended. On Windows this is the performance counter
+    pub monotonic: Duration,
+    /// `CLOCK_BOOTTIME`, which also advances while the system is suspended, or [None] on platforms other than Linux and Android
+    pub boottime: Option<Duration>,
+}
+
+impl ClockTimestamp {
+    /// Read the clocks now
+    pub fn now() -> Self {
+        Self {
+            monotonic: monotonic(),
+            boottime: boottime(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn read_clock(clock: nix::time::ClockId) -> Duration {
+    // Reading these clocks only fails for clock ids the kernel doesn't know
+    nix::time::clock_gettime(clock).map_or(Duration::ZERO, Duration::from)
+}
+
+#[cfg(unix)]
+fn monotonic() -> Duration {
+    read_clock(nix::time::ClockId::CLOCK_MONOTONIC)
+}
+
+#[cfg(windows)]
+fn monotonic() -> Duration {
+    use windows_sys::Win32::System::Performance::{
+        QueryPerformanceCounter, QueryPerformanceFrequency,
+    };
+
+    let (mut counter, mut frequency) = (0, 0);
+    // Both always succeed since Windows XP
+    unsafe {
+        QueryPerformanceCounter(&mut counter);
+        QueryPerformanceFrequency(&mut frequency);
+    }
+    let (counter, frequency) = (counter as u128, frequency.max(1) as u128);
+    Duration::from_nanos((counter * 1_000_000_000 / frequency) as u64)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn boottime() -> Option<Duration> {
+    Some(read_clock(nix::time::ClockId::CLOCK_BOOTTIME))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn boottime() -> Option<Duration> {
+    None
+}
@@ -0,0 +1,124 @@
+use std::{
+    ffi::c_void,
+    ptr::NonNull,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use nix::{
+    sys::mman::MmapAdvise,
+    unistd::{SysconfVar, sysconf},
+};
+
+use crate::{Severity, Shrinkable};
+
+/// How regions are advised, see [ColdRegions::advise]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Advice {
+    /// `MADV_COLD`, making the pages the first to be reclaimed once the kernel needs memory
+    Cold,
+    /// `MADV_PAGEOUT`, reclaiming the pages immediately
+    PageOut,
+}
+
+/// Identifies a region registered with [ColdRegions], to unregister it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegionId(u64);
+
+#[derive(Default)]
+struct Regions {
+    next_id: u64,
+    /// Start address and length of each region, as addresses aren't Send
+    regions: Vec<(RegionId, usize, usize)>,
+}
+
+/// Large, long-lived allocations whose pages are pushed out of memory on pressure, before the kernel has to pick pages to reclaim itself
+///
+/// Regions are added to the [shrink](crate::shrink) registry along with this, so they are advised by [shrink::spawn](crate::shrink::spawn).
+/// They are marked with `MADV_COLD` for [Severity::Low] and [Severity::Medium], which makes their pages the first to be reclaimed,
+/// and with `MADV_PAGEOUT` once pressure is critical, which reclaims them immediately. Their contents are kept, and paged back in on the next access
+///
+/// ```no_run
+/// use pressure::{ColdRegions, PressureMonitor, Severity};
+///
+/// let index = vec![0u8; 256 << 20].into_boxed_slice();
+/// let regions = ColdRegions::new();
+/// // SAFETY: the index outlives the registration
+/// let id = unsafe { regions.register(index.as_ptr(), index.len()) };
+/// let _runner = pressure::shrink::spawn(PressureMonitor::new()?, Severity::Low, |_, _| {})?;
+/// // ...
+/// regions.unregister(id);
+/// drop(index);
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct ColdRegions {
+    regions: Mutex<Regions>,
+}
+
+impl ColdRegions {
+    pub fn new() -> Arc<Self> {
+        let regions = Arc::new(Self {
+            regions: Mutex::default(),
+        });
+        crate::shrink::register(&regions);
+        regions
+    }
+
+    /// Register the `len` bytes starting at `addr`, such as a large buffer or a memory map. Only the pages entirely within the region are advised
+    ///
+    /// # Safety
+    ///
+    /// The region must stay mapped until it is unregistered or this is dropped
+    pub unsafe fn register(&self, addr: *const u8, len: usize) -> RegionId {
+        let mut regions = self.lock();
+        let id = RegionId(regions.next_id);
+        regions.next_id += 1;
+        regions.regions.push((id, addr as usize, len));
+        id
+    }
+
+    /// Stop advising a region, returning whether it was registered
+    pub fn unregister(&self, id: RegionId) -> bool {
+        let mut regions = self.lock();
+        let len = regions.regions.len();
+        regions.regions.retain(|(region, ..)| *region != id);
+        regions.regions.len() != len
+    }
+
+    /// Advise every region, returning how many bytes were advised. Regions the kernel rejects the advice for, such as on kernels older than 5.4, are skipped
+    pub fn advise(&self, advice: Advice) -> usize {
+        let advice = match advice {
+            Advice::Cold => MmapAdvise::MADV_COLD,
+            Advice::PageOut => MmapAdvise::MADV_PAGEOUT,
+        };
+        let page_size = sysconf(SysconfVar::PAGE_SIZE)
+            .ok()
+            .flatten()
+            .map_or(4096, |page_size| page_size as usize);
+        self.lock()
+            .regions
+            .iter()
+            .filter_map(|&(_, addr, len)| {
+                let start = addr.next_multiple_of(page_size);
+                let end = (addr + len) / page_size * page_size;
+                let addr = NonNull::new(start as *mut c_void)?;
+                let len = end.checked_sub(start).filter(|len| *len > 0)?;
+                // SAFETY: registering the region guarantees it's still mapped, and cold and pageout advice don't discard its contents
+                unsafe { nix::sys::mman::madvise(addr, len, advice) }.ok()?;
+                Some(len)
+            })
+            .sum()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Regions> {
+        self.regions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Shrinkable for ColdRegions {
+    fn shrink(&self, level: Severity) -> usize {
+        self.advise(match level {
+            Severity::Low | Severity::Medium => Advice::Cold,
+            Severity::Critical => Advice::PageOut,
+        })
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use crate::Error;
+
+/// Share of a compression pool's limit in use at which it counts as full
+const FULL: f64 = 0.9;
+
+/// Statistics of zswap, the compressed cache in front of swap devices
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZswapStats {
+    pub enabled: bool,
+    /// Largest share of RAM the pool may take, in percent
+    pub max_pool_percent: u64,
+    /// Bytes of RAM taken by the compressed pool
+    pub pool_size: u64,
+    /// Bytes stored in the pool before compression
+    pub stored: u64,
+    /// Pages loaded from the pool since boot
+    pub loads: u64,
+    /// Pages stored in the pool since boot
+    pub stores: u64,
+    /// Pages written back from the pool to the swap device since boot, because it was full or shrunk under pressure
+    pub written_back: u64,
+}
+
+/// Statistics of an initialized zram device, usually used as compressed swap in RAM
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZramStats {
+    /// Name of the device, such as `zram0`
+    pub device: String,
+    /// Bytes the device can store before compression
+    pub disk_size: u64,
+    /// Bytes stored on the device before compression
+    pub orig_data_size: u64,
+    /// Bytes stored on the device after compression
+    pub compr_data_size: u64,
+    /// Bytes of RAM taken by the device, including fragmentation and metadata
+    pub mem_used_total: u64,
+    /// Most bytes of RAM the device may take, or 0 if unlimited
+    pub mem_limit: u64,
+}
+
+/// What memory pressure means while memory is compressed, see [CompressionContext::classify]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CompressionPressure {
+    /// Neither zswap nor zram is in use
+    Inactive,
+    /// The pools have room, so pressure comes from compressing and decompressing pages, and compressing more is still possible
+    Churn,
+    /// A pool is nearly full or zswap is writing pages back to disk, so memory has to be freed
+    Exhausted,
+}
+
+impl std::fmt::Display for CompressionPressure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CompressionPressure::Inactive => "inactive",
+            CompressionPressure::Churn => "churn",
+            CompressionPressure::Exhausted => "exhausted",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A snapshot of zswap and zram statistics, telling whether memory pressure comes from compression churn or from compressed memory running out
+///
+/// Attached to memory pressure events with [PressureMonitorBuilder::attach_compression_context](crate::PressureMonitorBuilder::attach_compression_context)
+///
+/// ```no_run
+/// use pressure::{CompressionContext, CompressionPressure};
+///
+/// let context = CompressionContext::read()?;
+/// match context.classify(None) {
+///     CompressionPressure::Exhausted => println!("compressed memory is full, free memory"),
+///     CompressionPressure::Churn => println!("compression ratio {:.1}", context.ratio().unwrap_or(1.0)),
+///     CompressionPressure::Inactive => {}
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressionContext {
+    pub mem_total: u64,
+    /// zswap's statistics, or [None] if the kernel was built without it
+    pub zswap: Option<ZswapStats>,
+    pub zram: Vec<ZramStats>,
+}
+
+impl CompressionContext {
+    /// Read the statistics from procfs and sysfs, mounted at `/proc` unless overridden with the `PRESSURE_PROCFS_ROOT` environment variable and at `/sys`
+    pub fn read() -> Result<Self, Error> {
+        Self::read_in(&crate::procfs_root(), Path::new("/sys"))
+    }
+
+    /// Read the statistics from procfs mounted at `procfs_root` and sysfs mounted at `sysfs_root`
+    pub fn read_in(procfs_root: &Path, sysfs_root: &Path) -> Result<Self, Error> {
+        let mut context = Self::default();
+        let mut zswap = ZswapStats::default();
+        let meminfo = std::fs::read_to_string(procfs_root.join("meminfo"))?;
+        for line in meminfo.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let field = match key {
+                "MemTotal" => &mut context.mem_total,
+                // Reported since Linux 5.19
+                "Zswap" => &mut zswap.pool_size,
+                "Zswapped" => &mut zswap.stored,
+                _ => continue,
+            };
+            let kib = value.trim().trim_end_matches("kB").trim_end();
+            *field = kib.parse::<u64>().map_err(|_| Error::MalformedMemoryInfo)? * 1024;
+        }
+        if context.mem_total == 0 {
+            return Err(Error::MalformedMemoryInfo);
+        }
+        let parameters = sysfs_root.join("module/zswap/parameters");
+        if let Ok(enabled) = std::fs::read_to_string(parameters.join("enabled")) {
+            zswap.enabled = enabled.trim() == "Y";
+            zswap.max_pool_percent = std::fs::read_to_string(parameters.join("max_pool_percent"))?
+                .trim()
+                .parse()
+                .map_err(|_| Error::MalformedMemoryInfo)?;
+            let vmstat = std::fs::read_to_string(procfs_root.join("vmstat"))?;
+            for line in vmstat.lines() {
+                let Some((key, value)) = line.split_once(' ') else {
+                    continue;
+                };
+                // Reported since Linux 6.1, written back pages since Linux 6.8
+                let field = match key {
+                    "zswpin" => &mut zswap.loads,
+                    "zswpout" => &mut zswap.stores,
+                    "zswpwb" => &mut zswap.written_back,
+                    _ => continue,
+                };
+                *field = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::MalformedMemoryInfo)?;
+            }
+            context.zswap = Some(zswap);
+        }
+        let Ok(devices) = std::fs::read_dir(sysfs_root.join("block")) else {
+            return Ok(context);
+        };
+        for device in devices {
+            let device = device?;
+            let name = device.file_name().to_string_lossy().into_owned();
+            if !name.starts_with("zram") {
+                continue;
+            }
+            let path = device.path();
+            let disk_size = std::fs::read_to_string(path.join("disksize"))?
+                .trim()
+                .parse()
+                .map_err(|_| Error::MalformedMemoryInfo)?;
+            // Devices that weren't initialized hold nothing
+            if disk_size == 0 {
+                continue;
+            }
+            let mm_stat = std::fs::read_to_string(path.join("mm_stat"))?;
+            let mut mm_stat = mm_stat.split_whitespace().map(str::parse::<u64>);
+            let mut next = || {
+                mm_stat
+                    .next()
+                    .and_then(Result::ok)
+                    .ok_or(Error::MalformedMemoryInfo)
+            };
+            context.zram.push(ZramStats {
+                device: name,
+                disk_size,
+                orig_data_size: next()?,
+                compr_data_size: next()?,
+                mem_used_total: next()?,
+                mem_limit: next()?,
+            });
+        }
+        context
+            .zram
+            .sort_unstable_by(|a, b| a.device.cmp(&b.device));
+        Ok(context)
+    }
+
+    /// Whether zswap is enabled or a zram device is initialized
+    pub fn is_active(&self) -> bool {
+        self.zswap.is_some_and(|zswap| zswap.enabled) || !self.zram.is_empty()
+    }
+
+    /// Bytes stored before compression divided by the bytes of RAM they take, or [None] if nothing is compressed
+    pub fn ratio(&self) -> Option<f64> {
+        let (stored, used) = self.zram.iter().fold(
+            self.zswap
+                .map_or((0, 0), |zswap| (zswap.stored, zswap.pool_size)),
+            |(stored, used), zram| (stored + zram.orig_data_size, used + zram.mem_used_total),
+        );
+        (used > 0).then(|| stored as f64 / used as f64)
+    }
+
+    /// Share of its limit the fullest pool uses, between 0 and 1, or [None] if none is active.
+    /// zswap is limited to its share of RAM, and zram devices to their memory limit, or their size if they have none
+    pub fn pool_usage(&self) -> Option<f64> {
+        let zswap = self.zswap.filter(|zswap| zswap.enabled).map(|zswap| {
+            let limit = self.mem_total / 100 * zswap.max_pool_percent;
+            zswap.pool_size as f64 / limit.max(1) as f64
+        });
+        let zram = self.zram.iter().map(|zram| match zram.mem_limit {
+            0 => zram.orig_data_size as f64 / zram.disk_size as f64,
+            limit => zram.mem_used_total as f64 / limit as f64,
+        });
+        zswap.into_iter().chain(zram).reduce(f64::max)
+    }
+
+    /// Tell whether pressure calls for compressing more or freeing memory. Pools count as exhausted once they use 90% of their limit,
+    /// or if zswap wrote pages back to disk since `previous` was read
+    pub fn classify(&self, previous: Option<&Self>) -> CompressionPressure {
+        let Some(usage) = self.pool_usage() else {
+            return CompressionPressure::Inactive;
+        };
+        let written_back = match (self.zswap, previous.and_then(|previous| previous.zswap)) {
+            (Some(zswap), Some(previous)) => zswap.written_back > previous.written_back,
+            _ => false,
+        };
+        if usage >= FULL || written_back {
+            CompressionPressure::Exhausted
+        } else {
+            CompressionPressure::Churn
+        }
+    }
+}
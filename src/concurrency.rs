@@ -0,0 +1,182 @@
+use std::{
+    fs::File,
+    num::NonZeroUsize,
+    os::fd::AsFd,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use nix::poll::{PollFd, PollFlags};
+
+use crate::{CancellationToken, Error, PsiStats, Resource, StallKind, cgroup, poll_timeout};
+
+/// The concurrency target of a [ConcurrencyController], which can be read from other threads while the controller adjusts it
+#[derive(Debug, Clone)]
+pub struct ConcurrencyTarget(Arc<AtomicUsize>);
+
+impl ConcurrencyTarget {
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Adjusts a concurrency target to CPU pressure, raising it one step at a time while CPU stalls stay near zero and cutting it back as soon as they appear
+///
+/// The target is meant to bound the parallelism of work the application controls, such as the number of batches in flight, tasks spawned at once or
+/// rayon jobs started. By default it starts at the number of available CPUs, stays between 1 and that number, and is adjusted every second following
+/// `full` CPU stalls, where every runnable task of the system or cgroup was waiting for a CPU
+///
+/// ```no_run
+/// use pressure::ConcurrencyController;
+///
+/// let mut controller = ConcurrencyController::new()?.bounds(2, 64);
+/// let target = controller.target_handle();
+/// std::thread::spawn(move || controller.run());
+/// // In the pipeline
+/// let batches_in_flight = target.get();
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct ConcurrencyController {
+    pressure: File,
+    kind: StallKind,
+    interval: Duration,
+    tolerance: Duration,
+    step: usize,
+    backoff: f64,
+    min: usize,
+    max: usize,
+    target: ConcurrencyTarget,
+    last: PsiStats,
+    cancellation: Option<CancellationToken>,
+}
+
+impl ConcurrencyController {
+    /// Follow system-wide CPU pressure
+    pub fn new() -> Result<Self, Error> {
+        if !Resource::Cpu.is_available() {
+            return Err(Error::ResourceUnavailable(Resource::Cpu));
+        }
+        Self::from_path(Resource::Cpu.proc_path())
+    }
+    /// Follow CPU pressure within a cgroup v2 hierarchy
+    pub fn for_cgroup(cgroup: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_path(cgroup.as_ref().join(Resource::Cpu.cgroup_file()))
+    }
+    /// Follow CPU pressure within the cgroup the calling process belongs to
+    pub fn for_current_cgroup() -> Result<Self, Error> {
+        Self::for_cgroup(cgroup::current_cgroup()?)
+    }
+
+    fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let pressure = File::open(path)?;
+        let last = PsiStats::read_fd(&pressure)?;
+        let max = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        Ok(Self {
+            pressure,
+            kind: StallKind::Full,
+            interval: Duration::from_secs(1),
+            tolerance: Duration::from_millis(10),
+            step: 1,
+            backoff: 0.25,
+            min: 1,
+            max,
+            target: ConcurrencyTarget(Arc::new(AtomicUsize::new(max))),
+            last,
+            cancellation: None,
+        })
+    }
+
+    /// Kind of CPU stall to follow, defaults to [StallKind::Full]. `full` CPU stalls are only reported at the system level since Linux 5.13
+    pub fn kind(mut self, kind: StallKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    /// How often [run](Self::run) adjusts the target
+    pub fn adjust_every(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// Stall time per interval that still counts as no pressure, defaults to 10ms
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+    /// How much the target is raised by per interval without pressure, defaults to 1
+    pub fn step(mut self, step: usize) -> Self {
+        self.step = step;
+        self
+    }
+    /// Fraction the target is cut by per interval with pressure, defaults to a quarter
+    pub fn backoff(mut self, fraction: f64) -> Self {
+        self.backoff = fraction;
+        self
+    }
+    /// Bounds the target is kept within, which also moves the target into them
+    pub fn bounds(mut self, min: usize, max: usize) -> Self {
+        self.min = min;
+        self.max = max.max(min);
+        self.set_target(self.target().clamp(self.min, self.max));
+        self
+    }
+    /// Interval between adjustments, see [adjust_every](Self::adjust_every)
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+    /// The current concurrency target
+    pub fn target(&self) -> usize {
+        self.target.get()
+    }
+    /// Get a handle to read the target from other threads
+    pub fn target_handle(&self) -> ConcurrencyTarget {
+        self.target.clone()
+    }
+    /// Get a token that can be used to stop [run](Self::run) from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Adjust the target based on the pressure since the last adjustment, returning the new target.
+    /// This should be called once per interval, as the tolerance is the stall time per interval
+    pub fn adjust(&mut self) -> Result<usize, Error> {
+        let stats = PsiStats::read_fd(&self.pressure)?;
+        let stalled = stats.delta(&self.last).stall(self.kind);
+        self.last = stats;
+        let target = self.target();
+        let target = if stalled <= self.tolerance {
+            target.saturating_add(self.step)
+        } else {
+            // Always back off by at least one, so small targets still shrink
+            let cut = ((target as f64 * self.backoff) as usize).max(1);
+            target.saturating_sub(cut)
+        };
+        let target = target.clamp(self.min, self.max);
+        self.set_target(target);
+        Ok(target)
+    }
+    /// Adjust the target once per interval, until cancelled through [cancellation_token](Self::cancellation_token)
+    pub fn run(&mut self) -> Result<(), Error> {
+        loop {
+            if let Some(cancellation) = &self.cancellation {
+                let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+                if nix::poll::poll(&mut fds, poll_timeout(self.interval))? > 0 {
+                    return Ok(());
+                }
+            } else {
+                std::thread::sleep(self.interval);
+            }
+            self.adjust()?;
+        }
+    }
+
+    fn set_target(&self, target: usize) {
+        self.target.0.store(target, Ordering::Relaxed);
+    }
+}
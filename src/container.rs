@@ -0,0 +1,111 @@
+//! Resolving the cgroups Docker and Podman place containers in, with either the systemd or cgroupfs cgroup driver, including rootless containers
+//!
+//! ```no_run
+//! use pressure::{MonitorSet, Resource, container};
+//!
+//! let mut set = MonitorSet::new()?;
+//! for id in ["3f1c2a9b8d7e", "b62e0f4d5a91"] {
+//!     set.insert(container::monitor(id, Resource::Memory)?)?;
+//! }
+//! loop {
+//!     for event in set.wait()? {
+//!         println!("memory pressure in {:?}", event.cgroup);
+//!     }
+//! }
+//! # Ok::<(), pressure::Error>(())
+//! ```
+use std::path::{Path, PathBuf};
+
+use crate::{Error, PressureMonitor, Resource, cgroup};
+
+/// Where containers are placed with the systemd driver, and the affixes of their scopes
+const SCOPES: [(&str, &str, &str); 2] = [
+    ("system.slice", "docker-", ".scope"),
+    ("machine.slice", "libpod-", ".scope"),
+];
+
+/// Where containers are placed with the cgroupfs driver, and the prefixes of their cgroups
+const CGROUPFS: [(&str, &str); 2] = [("docker", ""), ("libpod_parent", "libpod-")];
+
+/// Resolve the cgroup of a container from its ID, or a prefix of it that matches a single container such as the short ID `docker ps` shows
+pub fn cgroup(id: &str) -> Result<PathBuf, Error> {
+    cgroup::check_id(id)?;
+    let root = cgroup::unified_root(None)?;
+    let systemd = SCOPES
+        .iter()
+        .map(|(slice, prefix, suffix)| (root.join(slice), *prefix, *suffix));
+    let cgroupfs = CGROUPFS
+        .iter()
+        .map(|(parent, prefix)| (root.join(parent), *prefix, ""));
+    // Rootless containers are placed below the service manager of the user running them
+    let rootless = user_managers(&root)?.into_iter().flat_map(|manager| {
+        [("docker-", ".scope"), ("libpod-", ".scope")]
+            .map(|(prefix, suffix)| (manager.join("user.slice"), prefix, suffix))
+    });
+    let mut matches = Vec::new();
+    for (parent, prefix, suffix) in systemd.chain(cgroupfs).chain(rootless) {
+        let exact = parent.join(format!("{prefix}{id}{suffix}"));
+        if exact.is_dir() {
+            return Ok(exact);
+        }
+        matches.extend(matching(&parent, prefix, suffix, id)?);
+    }
+    match matches.len() {
+        0 => Err(Error::CgroupNotFound(id.into())),
+        1 => Ok(matches.remove(0)),
+        _ => Err(Error::AmbiguousId(id.to_string())),
+    }
+}
+
+/// Create a monitor for a resource within a container's cgroup, see [cgroup]
+pub fn monitor(id: &str, resource: Resource) -> Result<PressureMonitor, Error> {
+    PressureMonitor::for_cgroup(cgroup(id)?, resource)
+}
+
+/// Cgroups in `parent` named after a container whose ID starts with `id`
+fn matching(parent: &Path, prefix: &str, suffix: &str, id: &str) -> Result<Vec<PathBuf>, Error> {
+    Ok(children(parent)?
+        .into_iter()
+        .filter(|(name, _)| {
+            name.strip_prefix(prefix)
+                .and_then(|name| name.strip_suffix(suffix))
+                .is_some_and(|name| name.starts_with(id))
+        })
+        .map(|(_, path)| path)
+        .collect())
+}
+
+/// The user@UID.service cgroups of every user's service manager
+fn user_managers(root: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut managers = Vec::new();
+    for (name, slice) in children(&root.join("user.slice"))? {
+        if name.starts_with("user-") && name.ends_with(".slice") {
+            managers.extend(
+                children(&slice)?
+                    .into_iter()
+                    .filter(|(name, _)| name.starts_with("user@") && name.ends_with(".service"))
+                    .map(|(_, path)| path),
+            );
+        }
+    }
+    Ok(managers)
+}
+
+/// Names and paths of the cgroups directly below `parent`, which are none if it doesn't exist
+fn children(parent: &Path) -> Result<Vec<(String, PathBuf)>, Error> {
+    let entries = match std::fs::read_dir(parent) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut children = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir()
+            && let Ok(name) = entry.file_name().into_string()
+        {
+            children.push((name, entry.path()));
+        }
+    }
+    Ok(children)
+}
@@ -0,0 +1,237 @@
+use std::{
+    fmt::Write as _,
+    fs::Permissions,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Error, SharedPressureMonitor, Trigger,
+    export::{json_line, json_string},
+};
+
+/// Longest command a client can send, beyond which it is disconnected rather than buffering an unbounded line
+const MAX_COMMAND: usize = 4096;
+
+/// Serves a [SharedPressureMonitor] on a unix socket, so that a running service can be inspected and retuned without restarting it
+///
+/// Clients send one command per line and receive one JSON object per line, with `"ok":true` and the command's fields or `"ok":false` and an `"error"`:
+///
+/// - `stats`: the current pressure stall information and severity
+/// - `triggers`: the triggers the monitor is armed with, formatted like `some 150000 1000000`
+/// - `events`: how many events the monitor delivered in total, in the last minute and hour, how many were published,
+///   the seconds since the last one and of the longest quiet period, and whether the monitor is paused
+/// - `set-trigger <some|full> <threshold in us> <window in us>`: replace the monitor's trigger
+/// - `pause` and `resume`: stop and resume publishing events
+///
+/// Commands aren't authenticated, so access is limited to the user the service runs as: the socket is only accessible to its owner,
+/// and where the platform reports the credentials of clients, those running as another user than the service or root are disconnected.
+/// Commands longer than 4KiB end the connection
+///
+/// ```no_run
+/// use pressure::{ControlSocket, PressureMonitor, SharedPressureMonitor};
+///
+/// let monitor = SharedPressureMonitor::new(PressureMonitor::new()?)?;
+/// let control = ControlSocket::bind("/run/myservice/pressure.sock", monitor.clone())?;
+/// std::thread::spawn(move || control.run());
+/// // Then, for example: echo stats | socat - UNIX-CONNECT:/run/myservice/pressure.sock
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct ControlSocket {
+    listener: UnixListener,
+    path: PathBuf,
+    monitor: SharedPressureMonitor,
+}
+
+impl ControlSocket {
+    /// Listen on `path`, replacing a socket left behind by a previous process, and make it accessible only to its owner. The socket is removed once this is dropped
+    pub fn bind(path: impl Into<PathBuf>, monitor: SharedPressureMonitor) -> Result<Self, Error> {
+        let path = path.into();
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(&path)?,
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        let listener = UnixListener::bind(&path)?;
+        // Clients that connected before this are turned away by their credentials where the platform reports them
+        std::fs::set_permissions(&path, Permissions::from_mode(0o600))?;
+        Ok(Self {
+            listener,
+            path,
+            monitor,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Accept clients, serving each on its own thread. Only returns if accepting a client fails
+    pub fn run(&self) -> Result<(), Error> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            if !authorized(&stream) {
+                continue;
+            }
+            let monitor = self.monitor.clone();
+            std::thread::Builder::new()
+                .name("pressure-control".into())
+                .spawn(move || {
+                    // The client hanging up or a failed write only ends this connection
+                    let _ = serve(stream, &monitor);
+                })?;
+        }
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Whether a client runs as the same user as the service or as root. Clients are let through on platforms that don't report their credentials,
+/// where only the socket's permissions keep other users out
+fn authorized(stream: &UnixStream) -> bool {
+    match peer_uid(stream) {
+        Some(uid) => uid.is_ok_and(|uid| uid == 0 || uid == nix::unistd::geteuid().as_raw()),
+        None => true,
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> Option<nix::Result<u32>> {
+    use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+    Some(getsockopt(stream, PeerCredentials).map(|credentials| credentials.uid()))
+}
+
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn peer_uid(stream: &UnixStream) -> Option<nix::Result<u32>> {
+    Some(nix::unistd::getpeereid(stream).map(|(uid, _)| uid.as_raw()))
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+fn peer_uid(_: &UnixStream) -> Option<nix::Result<u32>> {
+    None
+}
+
+/// Answer the commands of one client until it hangs up
+fn serve(stream: UnixStream, monitor: &SharedPressureMonitor) -> Result<(), Error> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        // Reading one byte past the limit leaves room for the newline ending a command of exactly the limit
+        (&mut reader)
+            .take(MAX_COMMAND as u64 + 1)
+            .read_line(&mut line)?;
+        if line.is_empty() {
+            return Ok(());
+        }
+        if line.len() > MAX_COMMAND && !line.ends_with('\n') {
+            writeln!(writer, r#"{{"ok":false,"error":"command too long"}}"#)?;
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match execute(line.trim(), monitor) {
+            Ok(fields) => format!(r#"{{"ok":true{fields}}}"#),
+            Err(e) => format!(r#"{{"ok":false,"error":{}}}"#, json_string(&e.to_string())),
+        };
+        writeln!(writer, "{reply}")?;
+    }
+}
+
+/// Run a command, returning the fields of its reply, each preceded by a comma
+fn execute(command: &str, monitor: &SharedPressureMonitor) -> Result<String, Error> {
+    let (name, arguments) = command.split_once(' ').unwrap_or((command, ""));
+    let mut fields = String::new();
+    match name {
+        "stats" => {
+            let stats = monitor.stats()?;
+            let _ = write!(
+                fields,
+                r#","some":{},"full":{},"severity":"{}""#,
+                json_line(&stats.some),
+                json_line(&stats.full),
+                monitor.severity()?
+            );
+        }
+        "triggers" => {
+            let triggers: Vec<_> = monitor
+                .triggers()?
+                .iter()
+                .map(|trigger| format!(r#""{trigger}""#))
+                .collect();
+            let _ = write!(fields, r#","triggers":[{}]"#, triggers.join(","));
+        }
+        "events" => {
+            let rate = monitor.event_rate()?;
+            let since_last = rate.since_last.map_or("null".into(), |since_last| {
+                format!("{:.3}", since_last.as_secs_f64())
+            });
+            let _ = write!(
+                fields,
+                r#","events":{},"published":{},"last_minute":{},"last_hour":{},"since_last":{since_last},"longest_quiet":{:.3},"paused":{}"#,
+                rate.total,
+                monitor.event_count(),
+                rate.last_minute,
+                rate.last_hour,
+                rate.longest_quiet.as_secs_f64(),
+                monitor.is_paused()
+            );
+        }
+        "set-trigger" => monitor.set_trigger(arguments.parse::<Trigger>()?)?,
+        "pause" => monitor.pause(),
+        "resume" => monitor.resume(),
+        _ => return Err(Error::UnknownCommand(name.into())),
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PressureMonitor;
+
+    #[test]
+    fn socket_is_private_and_long_commands_disconnect() {
+        let (fd, _peer) = UnixStream::pair().unwrap();
+        let monitor =
+            SharedPressureMonitor::new(PressureMonitor::from_fd(fd.into()).unwrap()).unwrap();
+        let path = std::env::temp_dir().join(format!("pressure-control-{}", std::process::id()));
+        let control = ControlSocket::bind(&path, monitor).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        std::thread::spawn(move || control.run());
+        let command = format!("{}\n", "x".repeat(MAX_COMMAND + 1));
+        // The connection may be closed before the whole command was written
+        let _ = client.write_all(command.as_bytes());
+        let mut reply = String::new();
+        BufReader::new(&client).read_line(&mut reply).unwrap();
+        assert_eq!(reply, "{\"ok\":false,\"error\":\"command too long\"}\n");
+        std::fs::remove_file(path).unwrap();
+    }
+}
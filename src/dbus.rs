@@ -0,0 +1,210 @@
+//! Memory pressure reported by low-memory-monitor over D-Bus, for processes that can't read or register triggers on pressure files themselves
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::net::UnixStream,
+    },
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+    time::Duration,
+};
+
+use nix::poll::{PollFd, PollFlags, PollTimeout};
+use zbus::blocking::{Connection, Proxy};
+
+use crate::{
+    Error, PollInterest, PressureBackend, PressureMonitor, PsiStats, Resource, Severity,
+    WaitOutcome, poll_timeout,
+};
+
+pub(crate) const DESTINATION: &str = "org.freedesktop.LowMemoryMonitor";
+pub(crate) const PATH: &str = "/org/freedesktop/LowMemoryMonitor";
+pub(crate) const INTERFACE: &str = "org.freedesktop.LowMemoryMonitor";
+pub(crate) const SIGNAL: &str = "LowMemoryWarning";
+
+/// Warning levels sent with LowMemoryWarning, see the org.freedesktop.LowMemoryMonitor interface
+pub(crate) const LEVEL_LOW: u8 = 50;
+pub(crate) const LEVEL_MEDIUM: u8 = 100;
+pub(crate) const LEVEL_CRITICAL: u8 = 255;
+
+/// Backend receiving LowMemoryWarning signals from low-memory-monitor on the system bus
+///
+/// Signals are received on a separate thread and forwarded through a socket pair, so the backend has an fd that can be waited on like a pressure file
+pub(crate) struct LowMemoryMonitorBackend {
+    level: Arc<AtomicU8>,
+    notifications: UnixStream,
+}
+
+impl LowMemoryMonitorBackend {
+    pub(crate) fn new() -> Result<Self, Error> {
+        let connection = Connection::system()?;
+        let proxy = Proxy::new(&connection, DESTINATION, PATH, INTERFACE)?;
+        // Subscribe before returning, so that signals sent after the monitor was created aren't missed
+        let signals = proxy.receive_signal(SIGNAL)?;
+        let (notifications, mut notify) = UnixStream::pair()?;
+        notifications.set_nonblocking(true)?;
+        notify.set_nonblocking(true)?;
+        let level = Arc::new(AtomicU8::new(0));
+        let shared = level.clone();
+        std::thread::Builder::new()
+            .name("low-memory-monitor".into())
+            .spawn(move || {
+                for message in signals {
+                    let Ok(received) = message.body().deserialize::<u8>() else {
+                        continue;
+                    };
+                    shared.store(received, Ordering::Relaxed);
+                    // The thread exits once the backend is dropped and the other end of the socket is closed, a full buffer already has an event pending
+                    if let Err(e) = notify.write(&[received])
+                        && e.kind() != ErrorKind::WouldBlock
+                    {
+                        return;
+                    }
+                }
+            })?;
+        Ok(Self {
+            level,
+            notifications,
+        })
+    }
+
+    /// Drain pending notifications from the signal thread, returning whether there were any
+    fn read_notifications(&mut self) -> Result<bool, Error> {
+        let mut buf = [0; 64];
+        let mut received = false;
+        loop {
+            match self.notifications.read(&mut buf) {
+                Ok(0) => return Ok(received),
+                Ok(_) => received = true,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(received),
+                Err(e) => return Err(e)?,
+            }
+        }
+    }
+}
+
+impl PressureBackend for LowMemoryMonitorBackend {
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error> {
+        let mut fds = vec![PollFd::new(self.notifications.as_fd(), PollFlags::POLLIN)];
+        if let Some(cancellation) = cancellation {
+            fds.push(PollFd::new(cancellation, PollFlags::POLLIN));
+        }
+        let ready = nix::poll::poll(&mut fds, timeout.map_or(PollTimeout::NONE, poll_timeout))?;
+        if ready == 0 {
+            return Ok(WaitOutcome::TimedOut);
+        }
+        if fds.get(1).is_some_and(|fd| fd.any() == Some(true)) {
+            return Ok(WaitOutcome::Cancelled);
+        }
+        // The socket only becomes readable without data once the signal thread lost its connection to the bus
+        if !self.read_notifications()? {
+            return Err(std::io::Error::from(ErrorKind::ConnectionAborted))?;
+        }
+        Ok(WaitOutcome::Triggered(()))
+    }
+
+    fn try_wait(&mut self) -> Result<bool, Error> {
+        self.read_notifications()
+    }
+
+    /// low-memory-monitor only sends warning levels, use [PressureMonitor::severity](crate::PressureMonitor::severity) instead
+    fn stats(&self, resource: Resource) -> Result<PsiStats, Error> {
+        Err(Error::ResourceUnavailable(resource))
+    }
+
+    /// Severity of the last warning, which is [Severity::Medium] from the medium level and [Severity::Critical] at the critical level
+    fn severity(&self) -> Option<Severity> {
+        Some(level_severity(self.level.load(Ordering::Relaxed)))
+    }
+
+    fn poll_interest(&self) -> Option<PollInterest> {
+        Some(PollInterest::Readable)
+    }
+
+    fn acknowledge(&mut self) -> Result<(), Error> {
+        self.read_notifications().map(drop)
+    }
+}
+
+impl AsFd for LowMemoryMonitorBackend {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.notifications.as_fd()
+    }
+}
+
+/// Classify a LowMemoryWarning level, where the low level only asks to free caches and doesn't indicate pressure yet
+pub(crate) fn level_severity(level: u8) -> Severity {
+    match level {
+        LEVEL_CRITICAL => Severity::Critical,
+        LEVEL_MEDIUM.. => Severity::Medium,
+        _ => Severity::Low,
+    }
+}
+
+/// The LowMemoryWarning level corresponding to a severity
+pub(crate) fn severity_level(severity: Severity) -> u8 {
+    match severity {
+        Severity::Low => LEVEL_LOW,
+        Severity::Medium => LEVEL_MEDIUM,
+        Severity::Critical => LEVEL_CRITICAL,
+    }
+}
+
+/// Sends LowMemoryWarning signals compatible with low-memory-monitor, so applications listening on the org.freedesktop.LowMemoryMonitor interface
+/// (such as those using GLib's GMemoryMonitor) react to the triggers configured here
+///
+/// ```no_run
+/// use pressure::{LowMemoryWarningEmitter, PressureMonitor};
+///
+/// let emitter = LowMemoryWarningEmitter::new()?;
+/// let mut monitor = PressureMonitor::new()?;
+/// emitter.forward(&mut monitor)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct LowMemoryWarningEmitter {
+    connection: Connection,
+}
+
+impl LowMemoryWarningEmitter {
+    /// Connect to the system bus and take the org.freedesktop.LowMemoryMonitor name, which fails if low-memory-monitor itself is running
+    pub fn new() -> Result<Self, Error> {
+        let connection = Connection::system()?;
+        connection.request_name(DESTINATION)?;
+        Ok(Self { connection })
+    }
+    /// Send signals on an existing connection, for example to the session bus or from a process that already owns the name
+    pub fn from_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+    /// Send a LowMemoryWarning with the level corresponding to `severity`, which is 50 for [Severity::Low], 100 for [Severity::Medium] and 255 for [Severity::Critical]
+    pub fn emit(&self, severity: Severity) -> Result<(), Error> {
+        self.emit_level(severity_level(severity))
+    }
+    /// Send a LowMemoryWarning with a raw level
+    pub fn emit_level(&self, level: u8) -> Result<(), Error> {
+        self.connection
+            .emit_signal(None::<&str>, PATH, INTERFACE, SIGNAL, &level)?;
+        Ok(())
+    }
+    /// Send a warning for every event on `monitor`, at the severity of pressure when it occurred, until the wait is cancelled
+    pub fn forward(&self, monitor: &mut PressureMonitor) -> Result<(), Error> {
+        loop {
+            match monitor.wait()? {
+                WaitOutcome::Triggered(_) => self.emit(monitor.severity()?)?,
+                WaitOutcome::TimedOut | WaitOutcome::Cancelled => return Ok(()),
+                WaitOutcome::Interrupted => {}
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            }
+        }
+    }
+}
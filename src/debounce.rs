@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use crate::Event;
+
+/// Delivers at most one event per interval, coalescing the events received in between into one delivered once the interval has elapsed
+#[derive(Debug, Clone)]
+pub(crate) struct Debounce {
+    min_interval: Duration,
+    last_delivered: Option<Instant>,
+    /// The latest event received during the interval
+    pending: Option<Event>,
+    suppressed: u32,
+}
+
+impl Debounce {
+    pub(crate) fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_delivered: None,
+            pending: None,
+            suppressed: 0,
+        }
+    }
+
+    /// Pass on an event received from the monitor, or hold it back if an event was delivered less than the interval ago
+    pub(crate) fn filter(&mut self, event: Event) -> Option<Event> {
+        let now = Instant::now();
+        if self
+            .last_delivered
+            .is_some_and(|last| now.saturating_duration_since(last) < self.min_interval)
+        {
+            self.suppressed += 1;
+            self.pending = Some(event);
+            return None;
+        }
+        Some(self.deliver(event, now))
+    }
+
+    /// When the events held back should be delivered, if there are any
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref()?;
+        // Intervals too long to represent hold events back for good
+        self.last_delivered?.checked_add(self.min_interval)
+    }
+
+    /// Deliver the latest event held back once the interval has elapsed
+    pub(crate) fn flush(&mut self) -> Option<Event> {
+        let now = Instant::now();
+        if self.deadline().is_none_or(|deadline| now < deadline) {
+            return None;
+        }
+        // The latest event stands for the ones before it, which are counted as suppressed
+        self.suppressed -= 1;
+        let event = self.pending.take()?;
+        Some(self.deliver(event, now))
+    }
+
+    fn deliver(&mut self, mut event: Event, now: Instant) -> Event {
+        event.suppressed = std::mem::take(&mut self.suppressed);
+        self.pending = None;
+        self.last_delivered = Some(now);
+        event
+    }
+}
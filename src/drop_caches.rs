@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+
+use crate::{Error, HookRegistry, HookRunner, PressureMonitor, Severity};
+
+/// Which caches the kernel drops, see the kernel's documentation of `/proc/sys/vm/drop_caches`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Caches {
+    /// The page cache
+    PageCache,
+    /// Reclaimable slab objects, such as dentries and inodes
+    Slab,
+    /// Both the page cache and reclaimable slab objects
+    All,
+}
+
+impl Caches {
+    fn value(self) -> &'static str {
+        match self {
+            Caches::PageCache => "1",
+            Caches::Slab => "2",
+            Caches::All => "3",
+        }
+    }
+}
+
+/// Writes to `/proc/sys/vm/drop_caches` when memory pressure is critical, as a last resort before the OOM killer for hosts whose caches are known to be cheap to refill
+///
+/// Dropping caches affects every process on the host and throws away clean pages that would otherwise be read again, so it is disabled until
+/// [enabled](Self::enable), requires running as root, only happens at [Severity::Critical] by default, and at most once every 5 minutes.
+/// Dirty pages are not written back first, since syncing under pressure would only add IO stalls
+///
+/// ```no_run
+/// use pressure::{Caches, DropCaches, PressureMonitor};
+///
+/// let runner = DropCaches::new()
+///     .enable(true)
+///     .caches(Caches::PageCache)
+///     .spawn_on(PressureMonitor::new()?)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DropCaches {
+    enabled: bool,
+    caches: Caches,
+    min_severity: Severity,
+    cooldown: Duration,
+    last_drop: Option<Instant>,
+}
+
+impl DropCaches {
+    /// A disabled helper dropping all caches at [Severity::Critical]
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            caches: Caches::All,
+            min_severity: Severity::Critical,
+            cooldown: Duration::from_secs(300),
+            last_drop: None,
+        }
+    }
+    /// Allow dropping caches, which fails with [Error::DropCachesDisabled] otherwise
+    pub fn enable(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+    pub fn caches(mut self, caches: Caches) -> Self {
+        self.caches = caches;
+        self
+    }
+    /// Only drop caches for events at which pressure is at least this severe, defaults to [Severity::Critical]
+    pub fn min_severity(mut self, min: Severity) -> Self {
+        self.min_severity = min;
+        self
+    }
+    /// Minimum time between two drops, defaults to 5 minutes. Refilling the caches takes a while, during which dropping them again frees little
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Fail unless dropping caches was enabled and the process runs as root
+    fn check_allowed(&self) -> Result<(), Error> {
+        if !self.enabled {
+            return Err(Error::DropCachesDisabled);
+        }
+        if !nix::unistd::geteuid().is_root() {
+            return Err(Error::NotRoot);
+        }
+        Ok(())
+    }
+
+    /// Drop caches if pressure is at least the minimum severity and the cooldown elapsed, returning whether they were dropped
+    pub fn drop_caches(&mut self, severity: Severity) -> Result<bool, Error> {
+        self.check_allowed()?;
+        let now = Instant::now();
+        let cooling_down = self
+            .last_drop
+            .is_some_and(|last_drop| now.saturating_duration_since(last_drop) < self.cooldown);
+        if severity < self.min_severity || cooling_down {
+            return Ok(false);
+        }
+        std::fs::write("/proc/sys/vm/drop_caches", self.caches.value())?;
+        self.last_drop = Some(now);
+        Ok(true)
+    }
+
+    /// Drop caches on the events of `monitor`, failing right away unless dropping caches is allowed
+    pub fn spawn_on(mut self, monitor: PressureMonitor) -> Result<HookRunner, Error> {
+        self.check_allowed()?;
+        let mut hooks = HookRegistry::new();
+        hooks.on_event(self.min_severity, move |_, severity| {
+            // Failed writes are retried on the next event
+            let _ = self.drop_caches(severity);
+        });
+        hooks.spawn(monitor)
+    }
+}
+
+impl Default for DropCaches {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,131 @@
+//! Base64 for MEMORY_PRESSURE_WRITE, hand-rolled unless the `base64` feature is enabled
+
+#[cfg(not(feature = "base64"))]
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode `bytes` with the standard alphabet and padding
+#[cfg(feature = "base64")]
+pub(crate) fn encode_base64(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::prelude::BASE64_STANDARD.encode(bytes)
+}
+
+/// Decode `input` with the standard alphabet and padding, returning why it is malformed otherwise
+#[cfg(feature = "base64")]
+pub(crate) fn decode_base64(input: &str) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    base64::prelude::BASE64_STANDARD
+        .decode(input)
+        .map_err(|e| e.to_string())
+}
+
+/// Encode `bytes` with the standard alphabet and padding
+#[cfg(not(feature = "base64"))]
+pub(crate) fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode `input` with the standard alphabet and padding, returning why it is malformed otherwise
+#[cfg(not(feature = "base64"))]
+pub(crate) fn decode_base64(input: &str) -> Result<Vec<u8>, String> {
+    let input = input.as_bytes();
+    if !input.len().is_multiple_of(4) {
+        return Err(format!("invalid length {}", input.len()));
+    }
+    let mut decoded = Vec::with_capacity(input.len() / 4 * 3);
+    for (i, chunk) in input.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        // Padding is only allowed at the end
+        if padding > 2 || (padding > 0 && (i + 1) * 4 != input.len()) {
+            return Err("invalid padding".into());
+        }
+        let mut group = 0;
+        for &c in &chunk[..4 - padding] {
+            let Some(sextet) = ALPHABET.iter().position(|&a| a == c) else {
+                return Err(format!("invalid byte {c:#x}"));
+            };
+            group = group << 6 | sextet as u32;
+        }
+        group <<= 6 * padding;
+        let bytes = group.to_be_bytes();
+        // Bits left over before the padding must be zero, so that every input decodes from exactly one encoding
+        if bytes[4 - padding..].iter().any(|&byte| byte != 0) {
+            return Err("invalid trailing bits".into());
+        }
+        decoded.extend_from_slice(&bytes[1..4 - padding]);
+    }
+    Ok(decoded)
+}
+
+#[cfg(all(test, not(feature = "base64")))]
+mod tests {
+    use super::*;
+
+    /// Output of the `base64` crate's standard engine
+    const VECTORS: &[(&[u8], &str)] = &[
+        (b"", ""),
+        (b"f", "Zg=="),
+        (b"fo", "Zm8="),
+        (b"foo", "Zm9v"),
+        (b"foob", "Zm9vYg=="),
+        (b"fooba", "Zm9vYmE="),
+        (b"foobar", "Zm9vYmFy"),
+        (b"some 150000 1000000\0", "c29tZSAxNTAwMDAgMTAwMDAwMAA="),
+    ];
+
+    /// Every byte value, as encoded by the `base64` crate
+    const ALL_BYTES: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4OTo7PD0+P0BBQkNERUZHSElKS0xNTk9QUVJTVFVWV1hZWltcXV5fYGFiY2RlZmdoaWprbG1ub3BxcnN0dXZ3eHl6e3x9fn+AgYKDhIWGh4iJiouMjY6PkJGSk5SVlpeYmZqbnJ2en6ChoqOkpaanqKmqq6ytrq+wsbKztLW2t7i5uru8vb6/wMHCw8TFxsfIycrLzM3Oz9DR0tPU1dbX2Nna29zd3t/g4eLj5OXm5+jp6uvs7e7v8PHy8/T19vf4+fr7/P3+/w==";
+
+    #[test]
+    fn round_trips_like_the_base64_crate() {
+        for &(bytes, encoded) in VECTORS {
+            assert_eq!(encode_base64(bytes), encoded);
+            assert_eq!(decode_base64(encoded).unwrap(), bytes);
+        }
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(encode_base64(&all_bytes), ALL_BYTES);
+        assert_eq!(decode_base64(ALL_BYTES).unwrap(), all_bytes);
+    }
+
+    #[test]
+    fn rejects_bad_length() {
+        for input in ["Z", "Zg", "Zg=", "Zm9vY", "Zm9vYmFy="] {
+            assert!(decode_base64(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_misplaced_padding() {
+        for input in ["Zg==Zm9v", "Z===", "====", "=Zm9", "Zm=v", "Zg=A"] {
+            assert!(decode_base64(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_bytes_outside_the_alphabet() {
+        for input in ["Zm9-", "Zm9_", "Zm 9", "Zm9\n", "Z\0m9", "Z\u{e9}m"] {
+            assert!(decode_base64(input).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_bits() {
+        // The base64 crate rejects these too, as "Zg==" and "Zm8=" are the only encodings of their bytes
+        for input in ["Zh==", "Zm9="] {
+            assert!(decode_base64(input).is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::{env::VarError, path::PathBuf};
+
+use crate::{Error, Trigger};
+
+/// Path systemd wants memory pressure to be watched on, or [None] if it isn't set or memory pressure monitoring was disabled for the unit
+pub(crate) fn memory_pressure_watch() -> Result<Option<PathBuf>, Error> {
+    match var("MEMORY_PRESSURE_WATCH")?.as_deref() {
+        // Systemd sets MEMORY_PRESSURE_WATCH to /dev/null to indicate memory pressure monitoring is disabled for this service/unit
+        None | Some("/dev/null") => Ok(None),
+        Some(path) if path.starts_with('/') => Ok(Some(path.into())),
+        Some(path) => Err(Error::InvalidPath(path.into())),
+    }
+}
+
+/// Trigger systemd wants written to the watched path, or [None] if nothing should be written
+pub(crate) fn memory_pressure_write() -> Result<Option<Trigger>, Error> {
+    let Some(write) = var("MEMORY_PRESSURE_WRITE")? else {
+        return Ok(None);
+    };
+    let write = crate::encoding::decode_base64(&write).map_err(Error::InvalidWrite)?;
+    Trigger::from_bytes(&write)
+        .map(Some)
+        .map_err(|e| Error::InvalidWrite(e.to_string()))
+}
+
+fn var(name: &'static str) -> Result<Option<String>, Error> {
+    match std::env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(VarError::NotUnicode(_)) => Err(Error::NonUtf8Env(name)),
+    }
+}
@@ -0,0 +1,477 @@
+#[cfg(unix)]
+use std::path::Path;
+use std::{
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ClockTimestamp, CompressionContext, MemoryContext, PsiLine, PsiStats, Resource, StallKind,
+    Trigger,
+};
+#[cfg(unix)]
+use crate::{
+    ConfigSource, Error, MonitorType, Severity, SeverityThresholds, clearing::Clearing,
+    debounce::Debounce, hysteresis::Hysteresis, rate::EventCounter,
+};
+
+/// A pressure event, describing the monitor it was received on
+///
+/// Events are compared and hashed with the bits of the averages in their [stats](Self::stats), so events carrying NaN averages still equal themselves
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event {
+    pub resource: Resource,
+    pub kind: StallKind,
+    /// The trigger that fired, or [None] if the monitor fell back to polling or didn't register a trigger itself, such as when systemd doesn't provide one
+    pub trigger: Option<Trigger>,
+    /// The cgroup being monitored, or [None] for system-wide pressure
+    pub cgroup: Option<PathBuf>,
+    /// When the event was received. Instants can't be serialized, so deserialized events are timestamped when they are deserialized
+    #[cfg_attr(feature = "serde", serde(skip, default = "Instant::now"))]
+    pub timestamp: Instant,
+    /// The system's clocks read when the event was received, which unlike [timestamp](Self::timestamp) can be compared across processes
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub clock: ClockTimestamp,
+    /// Whether pressure rose or cleared, or the monitor reconnected, see [PressureMonitorBuilder::notify_cleared](crate::PressureMonitorBuilder::notify_cleared)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transition: Transition,
+    /// Number of notifications queued on a service manager's socket or fifo that were read at once and coalesced into this event, which is always 1 for other monitors
+    #[cfg_attr(feature = "serde", serde(default = "one"))]
+    pub coalesced: u32,
+    /// Number of events held back since the previous one was delivered and coalesced into this one, see [PressureMonitorBuilder::min_interval](crate::PressureMonitorBuilder::min_interval)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub suppressed: u32,
+    /// Pressure stall information read right after the event was received, or [None] unless [PressureMonitorBuilder::attach_stats](crate::PressureMonitorBuilder::attach_stats) was set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stats: Option<PsiStats>,
+    /// System-wide memory statistics read right after a memory pressure event was received,
+    /// or [None] unless [PressureMonitorBuilder::attach_memory_context](crate::PressureMonitorBuilder::attach_memory_context) was set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub memory: Option<MemoryContext>,
+    /// zswap and zram statistics read right after a memory pressure event was received,
+    /// or [None] unless [PressureMonitorBuilder::attach_compression_context](crate::PressureMonitorBuilder::attach_compression_context) was set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compression: Option<CompressionContext>,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.resource == other.resource
+            && self.kind == other.kind
+            && self.trigger == other.trigger
+            && self.cgroup == other.cgroup
+            && self.timestamp == other.timestamp
+            && self.clock == other.clock
+            && self.transition == other.transition
+            && self.coalesced == other.coalesced
+            && self.suppressed == other.suppressed
+            && self.stats_bits() == other.stats_bits()
+            && self.memory == other.memory
+            && self.compression == other.compression
+    }
+}
+
+impl Eq for Event {}
+
+impl Hash for Event {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (
+            self.resource,
+            self.kind,
+            self.trigger,
+            &self.cgroup,
+            self.timestamp,
+            self.clock,
+            self.transition,
+            self.coalesced,
+            self.suppressed,
+            self.stats_bits(),
+            self.memory,
+            &self.compression,
+        )
+            .hash(state);
+    }
+}
+
+impl Event {
+    /// Attached stats with the averages as their bits, which unlike floats are equal to themselves and can be hashed
+    fn stats_bits(&self) -> Option<[(u32, u32, u32, Duration); 2]> {
+        let bits = |line: PsiLine| {
+            (
+                line.avg10.to_bits(),
+                line.avg60.to_bits(),
+                line.avg300.to_bits(),
+                line.total,
+            )
+        };
+        self.stats.map(|stats| [bits(stats.some), bits(stats.full)])
+    }
+}
+
+#[cfg(feature = "serde")]
+fn one() -> u32 {
+    1
+}
+
+/// What an [Event] reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Transition {
+    /// A trigger fired or pressure crossed the polling threshold
+    #[default]
+    Pressure,
+    /// Pressure stayed low for a while after an event, for example so that caches can grow again
+    Cleared,
+    /// The monitor's fd hung up and its path was opened again, see [PressureMonitorBuilder::reconnect](crate::PressureMonitorBuilder::reconnect)
+    Reconnected,
+}
+
+/// What a monitor is watching, used to describe the events it receives
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub(crate) struct Target {
+    pub(crate) resource: Resource,
+    pub(crate) kind: StallKind,
+    pub(crate) trigger: Option<Trigger>,
+    pub(crate) cgroup: Option<PathBuf>,
+    /// Pressure file, fifo or socket the monitor was opened from, or [None] if it was created from a file descriptor
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) severity: SeverityThresholds,
+    pub(crate) config: ConfigSource,
+    pub(crate) hysteresis: Option<Hysteresis>,
+    pub(crate) clearing: Option<Clearing>,
+    pub(crate) debounce: Option<Debounce>,
+    /// Whether events carry the stall information read right after they were received
+    pub(crate) attach_stats: bool,
+    /// Procfs to read the memory context attached to memory pressure events from, if any
+    pub(crate) attach_memory: Option<PathBuf>,
+    /// Procfs to read the compression context attached to memory pressure events from, if any
+    pub(crate) attach_compression: Option<PathBuf>,
+    /// Events delivered so far
+    pub(crate) events: EventCounter,
+}
+
+#[cfg(unix)]
+impl Target {
+    pub(crate) fn cgroup(&self) -> Option<&Path> {
+        self.cgroup.as_deref()
+    }
+
+    /// Describe a monitor created from a file descriptor, of which nothing is known besides it being memory pressure
+    pub(crate) fn for_fd() -> Self {
+        Self {
+            resource: Resource::Memory,
+            kind: StallKind::default_for(Resource::Memory),
+            trigger: None,
+            cgroup: None,
+            path: None,
+            severity: SeverityThresholds::default(),
+            config: ConfigSource::FileDescriptor,
+            hysteresis: None,
+            clearing: None,
+            debounce: None,
+            attach_stats: false,
+            attach_memory: None,
+            attach_compression: None,
+            events: EventCounter::default(),
+        }
+    }
+
+    /// Describe a monitor waiting on a custom backend, which has no trigger or path
+    pub(crate) fn for_backend(resource: Resource) -> Self {
+        Self {
+            resource,
+            kind: StallKind::default_for(resource),
+            config: ConfigSource::Backend,
+            ..Self::for_fd()
+        }
+    }
+
+    pub(crate) fn path(&self) -> Result<&Path, Error> {
+        self.path.as_deref().ok_or(Error::NoPath)
+    }
+
+    /// Open the monitor's path again with its current trigger, after the service manager closed the socket or fifo
+    pub(crate) fn reconnect(&self) -> Result<MonitorType, Error> {
+        crate::open_monitor(self.path()?, self.trigger)
+    }
+
+    /// Open the monitor's path again with a new trigger, as the kernel only allows one trigger per fd
+    pub(crate) fn reopen(&mut self, trigger: Trigger) -> Result<MonitorType, Error> {
+        trigger.check_supported(self.resource)?;
+        let pressure_file = crate::open_monitor(self.path()?, Some(trigger))?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(path = ?self.path, trigger = ?trigger, "reopened pressure monitor");
+        self.kind = trigger.kind();
+        self.trigger = Some(trigger);
+        Ok(pressure_file)
+    }
+
+    /// Classify the monitored kind of stall
+    pub(crate) fn classify(&self, stats: &PsiStats) -> Severity {
+        self.severity.classify(stats.line(self.kind))
+    }
+
+    pub(crate) fn event(&self) -> Event {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            resource = %self.resource,
+            kind = %self.kind,
+            trigger = ?self.trigger,
+            cgroup = ?self.cgroup,
+            "pressure event"
+        );
+        Event {
+            resource: self.resource,
+            kind: self.kind,
+            trigger: self.trigger,
+            cgroup: self.cgroup.clone(),
+            timestamp: Instant::now(),
+            clock: ClockTimestamp::now(),
+            transition: Transition::Pressure,
+            coalesced: 1,
+            suppressed: 0,
+            stats: None,
+            memory: None,
+            compression: None,
+        }
+    }
+
+    /// Attach the memory and compression contexts to an event about to be delivered, if they were asked for
+    pub(crate) fn enrich(&self, mut event: Event) -> Result<Event, Error> {
+        if event.resource != Resource::Memory {
+            return Ok(event);
+        }
+        if let Some(procfs_root) = &self.attach_memory {
+            event.memory = Some(MemoryContext::read_in(procfs_root)?);
+        }
+        if let Some(procfs_root) = &self.attach_compression {
+            event.compression = Some(CompressionContext::read_in(procfs_root, Path::new("/sys"))?);
+        }
+        Ok(event)
+    }
+
+    /// Whether [fired](Self::fired) needs the current pressure
+    pub(crate) fn reads_pressure(&self) -> bool {
+        self.attach_stats
+            || self
+                .hysteresis
+                .as_ref()
+                .is_some_and(Hysteresis::reads_pressure)
+    }
+
+    /// Handle an event received from the monitor, returning it if it should be delivered now.
+    /// Events are held back until pressure is sustained, see [PressureMonitorBuilder::sustained_firings](crate::PressureMonitorBuilder::sustained_firings),
+    /// and to deliver at most one per [minimum interval](crate::PressureMonitorBuilder::min_interval)
+    pub(crate) fn fired(&mut self, event: Event, stats: Option<&PsiStats>) -> Option<Event> {
+        let kind = self.kind;
+        let sustained = self
+            .hysteresis
+            .as_mut()
+            .is_none_or(|hysteresis| hysteresis.sustained(stats.map(|stats| stats.line(kind))));
+        if let Some(clearing) = &mut self.clearing {
+            clearing.fired(sustained);
+        }
+        if !sustained {
+            return None;
+        }
+        let event = Event {
+            stats: stats.copied().filter(|_| self.attach_stats),
+            ..event
+        };
+        let event = match &mut self.debounce {
+            Some(debounce) => debounce.filter(event),
+            None => Some(event),
+        };
+        self.delivered(event)
+    }
+
+    /// Count an event about to be delivered
+    pub(crate) fn delivered(&mut self, event: Option<Event>) -> Option<Event> {
+        if event.is_some() {
+            self.events.record();
+        }
+        event
+    }
+
+    /// When the monitor should wake up without an event, to deliver events held back or check whether pressure cleared
+    pub(crate) fn wake_deadline(&self) -> Option<Instant> {
+        let flush = self.debounce.as_ref().and_then(Debounce::deadline);
+        let clear = self.clearing.as_ref().and_then(Clearing::deadline);
+        flush.into_iter().chain(clear).min()
+    }
+
+    /// Whether [woken](Self::woken) needs the current pressure
+    pub(crate) fn clear_due(&self) -> bool {
+        self.clearing
+            .as_ref()
+            .and_then(Clearing::deadline)
+            .is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    /// Handle the monitor waking up without an event, returning events held back once the minimum interval elapsed,
+    /// or a [Transition::Cleared] event if pressure just cleared
+    pub(crate) fn woken(&mut self, stats: Option<&PsiStats>) -> Option<Event> {
+        if let Some(event) = self.debounce.as_mut().and_then(Debounce::flush) {
+            return self.delivered(Some(event));
+        }
+        let (clearing, stats) = (self.clearing.as_mut()?, stats?);
+        let event = clearing.check(stats.line(self.kind)).then(|| Event {
+            transition: Transition::Cleared,
+            stats: Some(*stats).filter(|_| self.attach_stats),
+            ..self.event()
+        });
+        self.delivered(event)
+    }
+
+    /// Describe an event from one of the monitor's additional triggers
+    pub(crate) fn event_for(&self, trigger: Trigger) -> Event {
+        Self {
+            kind: trigger.kind(),
+            trigger: Some(trigger),
+            ..self.clone()
+        }
+        .event()
+    }
+}
+
+#[cfg(all(test, feature = "test-util", unix))]
+mod tests {
+    use super::*;
+    use crate::{MockHandle, MockPressureMonitor, PressureMonitor, PsiLine, WaitOutcome};
+
+    fn monitor() -> (PressureMonitor, MockHandle) {
+        let mock = MockPressureMonitor::new(Resource::Memory).unwrap();
+        let handle = mock.handle();
+        (
+            PressureMonitor::from_backend(mock, Resource::Memory),
+            handle,
+        )
+    }
+
+    fn stats(avg10: f32) -> PsiStats {
+        PsiStats {
+            some: PsiLine {
+                avg10,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn events_with_nan_stats_equal_themselves() {
+        let (mut monitor, handle) = monitor();
+        handle.fire_pressure().unwrap();
+        let mut event = monitor.try_wait().unwrap().unwrap();
+        event.stats = Some(stats(f32::NAN));
+        assert_eq!(event, event.clone());
+        let events = std::collections::HashSet::from([event.clone(), event.clone()]);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn sustained_streak_resets_after_a_gap() {
+        let (mut monitor, handle) = monitor();
+        monitor.target.hysteresis = Hysteresis::new(Some((3, Duration::from_millis(100))), None);
+        for _ in 0..2 {
+            handle.fire_pressure().unwrap();
+            assert_eq!(monitor.try_wait().unwrap(), None);
+        }
+        std::thread::sleep(Duration::from_millis(150));
+        for _ in 0..2 {
+            handle.fire_pressure().unwrap();
+            assert_eq!(monitor.try_wait().unwrap(), None);
+        }
+        handle.fire_pressure().unwrap();
+        assert!(monitor.try_wait().unwrap().is_some());
+        assert_eq!(monitor.event_rate().total, 1);
+    }
+
+    #[test]
+    fn sustained_avg10_holds_back_events_until_held() {
+        let (mut monitor, handle) = monitor();
+        monitor.target.hysteresis = Hysteresis::new(None, Some((20.0, Duration::from_millis(50))));
+        handle.set_stats(stats(30.0));
+        handle.fire_pressure().unwrap();
+        assert_eq!(monitor.try_wait().unwrap(), None);
+        std::thread::sleep(Duration::from_millis(60));
+        handle.fire_pressure().unwrap();
+        assert!(monitor.try_wait().unwrap().is_some());
+        // Dropping below the threshold starts over
+        handle.set_stats(stats(10.0));
+        handle.fire_pressure().unwrap();
+        assert_eq!(monitor.try_wait().unwrap(), None);
+        handle.set_stats(stats(30.0));
+        handle.fire_pressure().unwrap();
+        assert_eq!(monitor.try_wait().unwrap(), None);
+    }
+
+    #[test]
+    fn debounced_events_count_the_ones_they_replace() {
+        let (mut monitor, handle) = monitor();
+        monitor.target.debounce = Some(Debounce::new(Duration::from_millis(100)));
+        handle.fire_pressure().unwrap();
+        assert_eq!(monitor.try_wait().unwrap().unwrap().suppressed, 0);
+        for _ in 0..3 {
+            handle.fire_pressure().unwrap();
+            assert_eq!(monitor.try_wait().unwrap(), None);
+        }
+        let started = Instant::now();
+        let WaitOutcome::Triggered(event) = monitor.wait_timeout(Duration::from_secs(1)).unwrap()
+        else {
+            panic!("held back event wasn't delivered");
+        };
+        assert_eq!(event.suppressed, 2);
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(
+            monitor.wait_timeout(Duration::from_millis(150)).unwrap(),
+            WaitOutcome::TimedOut
+        );
+    }
+
+    #[test]
+    fn unbounded_intervals_hold_events_back_for_good() {
+        let (mut monitor, handle) = monitor();
+        monitor.target.debounce = Some(Debounce::new(Duration::MAX));
+        monitor.target.clearing = Some(Clearing::new(10.0, Duration::MAX));
+        handle.set_stats(stats(5.0));
+        for _ in 0..2 {
+            handle.fire_pressure().unwrap();
+        }
+        assert!(monitor.try_wait().unwrap().is_some());
+        assert_eq!(
+            monitor.wait_timeout(Duration::from_millis(50)).unwrap(),
+            WaitOutcome::TimedOut
+        );
+    }
+
+    #[test]
+    fn cleared_after_pressure_stays_low() {
+        let (mut monitor, handle) = monitor();
+        monitor.target.clearing = Some(Clearing::new(10.0, Duration::from_millis(50)));
+        handle.set_stats(stats(50.0));
+        handle.fire_pressure().unwrap();
+        let event = monitor.try_wait().unwrap().unwrap();
+        assert_eq!(event.transition, Transition::Pressure);
+        // Pressure that stays high doesn't clear
+        assert_eq!(
+            monitor.wait_timeout(Duration::from_millis(1200)).unwrap(),
+            WaitOutcome::TimedOut
+        );
+        handle.set_stats(stats(1.0));
+        let WaitOutcome::Triggered(event) = monitor.wait_timeout(Duration::from_secs(3)).unwrap()
+        else {
+            panic!("pressure didn't clear");
+        };
+        assert_eq!(event.transition, Transition::Cleared);
+        // Clearing is only reported once per event
+        assert_eq!(
+            monitor.wait_timeout(Duration::from_millis(1200)).unwrap(),
+            WaitOutcome::TimedOut
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    Error, Event, PressureMonitor, Transition, WaitOutcome,
+    export::{json_line, json_string},
+    rotation::Output,
+};
+
+/// Appends every pressure event to a JSON lines file, so that incidents can be reconstructed after the fact without a metrics backend
+///
+/// Each line holds the Unix timestamp in seconds, the resource, kind of stall, trigger and cgroup (null if there is none),
+/// the transition, how many notifications and held back events were coalesced into the event, the raw clock readings in nanoseconds,
+/// and the stall information, memory context and compression pressure if they were attached, see [attach_stats](crate::PressureMonitorBuilder::attach_stats),
+/// [attach_memory_context](crate::PressureMonitorBuilder::attach_memory_context) and [attach_compression_context](crate::PressureMonitorBuilder::attach_compression_context)
+///
+/// ```no_run
+/// use pressure::{EventLogger, PressureMonitor};
+/// use std::time::Duration;
+///
+/// let mut monitor = PressureMonitor::builder().attach_stats().build()?;
+/// EventLogger::to_file("/var/log/pressure-events.jsonl")?
+///     // Start a new file every day or once it reaches 10MiB, keeping a week of files
+///     .rotate(10 << 20, 7)
+///     .rotate_every(Duration::from_secs(24 * 60 * 60), 7)
+///     .run(&mut monitor)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct EventLogger {
+    output: Output,
+}
+
+impl EventLogger {
+    /// Write events to any writer, which is flushed after each event. Writers can't be rotated
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            output: Output::writer(writer),
+        }
+    }
+
+    /// Append events to a file, creating it if it doesn't exist
+    pub fn to_file(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        Ok(Self {
+            output: Output::open(path.into())?,
+        })
+    }
+
+    /// Once the file would grow beyond `max_bytes`, rename it by appending `.1`, shifting older files up to `.<keep>` and deleting the oldest.
+    /// Has no effect on loggers created with [new](Self::new)
+    pub fn rotate(mut self, max_bytes: u64, keep: usize) -> Self {
+        self.output.rotate_at_size(max_bytes, keep);
+        self
+    }
+
+    /// Rotate the file like [rotate](Self::rotate) once it is older than `max_age`, checked whenever an event is logged.
+    /// Both limits apply if both are set, keeping the number of files last given
+    pub fn rotate_every(mut self, max_age: Duration, keep: usize) -> Self {
+        self.output.rotate_at_age(max_age, keep);
+        self
+    }
+
+    /// Append an event
+    pub fn write(&mut self, event: &Event) -> Result<(), Error> {
+        let mut row = event_json(event);
+        row.push('\n');
+        self.output.rotate_if_due(row.len() as u64)?;
+        self.output.write(row.as_bytes())
+    }
+
+    /// Append every event `monitor` receives. Only returns once a wait is cancelled or interrupted, or if waiting or writing fails
+    pub fn run(&mut self, monitor: &mut PressureMonitor) -> Result<(), Error> {
+        loop {
+            match monitor.wait()? {
+                WaitOutcome::Triggered(event) => self.write(&event)?,
+                WaitOutcome::TimedOut => {}
+                WaitOutcome::Cancelled | WaitOutcome::Interrupted => return Ok(()),
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            }
+        }
+    }
+}
+
+/// An event as the JSON object [EventLogger] writes on each line
+pub(crate) fn event_json(event: &Event) -> String {
+    let timestamp = (SystemTime::now() - event.timestamp.elapsed())
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let transition = match event.transition {
+        Transition::Pressure => "pressure",
+        Transition::Cleared => "cleared",
+        Transition::Reconnected => "reconnected",
+    };
+    let trigger = event
+        .trigger
+        .map_or("null".into(), |trigger| format!(r#""{trigger}""#));
+    let cgroup = event.cgroup.as_deref().map_or("null".into(), |cgroup| {
+        json_string(&cgroup.to_string_lossy())
+    });
+    let boottime = event
+        .clock
+        .boottime
+        .map_or("null".into(), |boottime| boottime.as_nanos().to_string());
+    let stats = event.stats.map_or("null".into(), |stats| {
+        format!(
+            r#"{{"some":{},"full":{}}}"#,
+            json_line(&stats.some),
+            json_line(&stats.full)
+        )
+    });
+    let memory = event.memory.map_or("null".into(), |memory| {
+        format!(
+            r#"{{"mem_available":{},"swap_free":{},"dirty":{},"writeback":{},"pgscan":{},"pgsteal":{},"pswpin":{},"pswpout":{},"refaults":{}}}"#,
+            memory.mem_available,
+            memory.swap_free,
+            memory.dirty,
+            memory.writeback,
+            memory.pgscan,
+            memory.pgsteal,
+            memory.pswpin,
+            memory.pswpout,
+            memory.refaults
+        )
+    });
+    let optional = |value: Option<f64>| value.map_or("null".into(), |value| format!("{value:.3}"));
+    let compression = event
+        .compression
+        .as_ref()
+        .map_or("null".into(), |compression| {
+            format!(
+                r#"{{"pressure":"{}","pool_usage":{},"ratio":{}}}"#,
+                compression.classify(None),
+                optional(compression.pool_usage()),
+                optional(compression.ratio())
+            )
+        });
+    format!(
+        r#"{{"timestamp":{timestamp:.3},"resource":"{}","kind":"{}","trigger":{trigger},"cgroup":{cgroup},"transition":"{transition}","coalesced":{},"suppressed":{},"monotonic_ns":{},"boottime_ns":{boottime},"stats":{stats},"memory":{memory},"compression":{compression}}}"#,
+        event.resource,
+        event.kind,
+        event.coalesced,
+        event.suppressed,
+        event.clock.monotonic.as_nanos(),
+    )
+}
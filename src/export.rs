@@ -0,0 +1,177 @@
+use std::{
+    fmt::Write as _,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Error, PsiLine, PsiStats, Resource, Sampler, rotation::Output};
+
+const CSV_HEADER: &str = "timestamp,resource,cgroup,some_avg10,some_avg60,some_avg300,some_total_us,full_avg10,full_avg60,full_avg300,full_total_us\n";
+
+/// Format of the rows written by a [SampleExporter]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    /// Comma-separated values, starting with a header row
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Appends samples of pressure stall information to a CSV or JSON lines file, for analysing pressure incidents offline without a metrics stack
+///
+/// Each row holds the Unix timestamp in seconds, the resource, the cgroup (empty or null for system-wide pressure) and the averages and total stall time in microseconds of both kinds of stall
+///
+/// ```no_run
+/// use pressure::{ExportFormat, PressureMonitor, Resource, SampleExporter};
+/// use std::time::Duration;
+///
+/// let mut exporter = SampleExporter::to_file("/var/log/pressure.csv", ExportFormat::Csv)?
+///     // Keep up to 5 files of 10MiB, pressure.csv.1 being the most recent
+///     .rotate(10 << 20, 5);
+/// exporter.run(PressureMonitor::sampler(Resource::Memory, Duration::from_secs(1))?, Resource::Memory, None)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct SampleExporter {
+    format: ExportFormat,
+    output: Output,
+    header_pending: bool,
+}
+
+impl SampleExporter {
+    /// Write rows to any writer, which is flushed after each row. Writers can't be rotated
+    pub fn new(writer: impl Write + Send + 'static, format: ExportFormat) -> Self {
+        Self {
+            format,
+            output: Output::writer(writer),
+            header_pending: true,
+        }
+    }
+
+    /// Append rows to a file, creating it if it doesn't exist. A CSV header is only written to empty files
+    pub fn to_file(path: impl Into<PathBuf>, format: ExportFormat) -> Result<Self, Error> {
+        let output = Output::open(path.into())?;
+        Ok(Self {
+            format,
+            header_pending: output.is_empty(),
+            output,
+        })
+    }
+
+    /// Once the file would grow beyond `max_bytes`, rename it by appending `.1`, shifting older files up to `.<keep>` and deleting the oldest.
+    /// Has no effect on exporters created with [new](Self::new)
+    pub fn rotate(mut self, max_bytes: u64, keep: usize) -> Self {
+        self.output.rotate_at_size(max_bytes, keep);
+        self
+    }
+
+    /// Append a sample of pressure on a resource, system-wide or within a cgroup
+    pub fn write(
+        &mut self,
+        resource: Resource,
+        cgroup: Option<&Path>,
+        stats: &PsiStats,
+    ) -> Result<(), Error> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let cgroup = cgroup.map(Path::to_string_lossy);
+        let mut row = String::new();
+        match self.format {
+            ExportFormat::Csv => {
+                let _ = write!(
+                    row,
+                    "{timestamp:.3},{resource},{}",
+                    csv_field(cgroup.as_deref().unwrap_or_default())
+                );
+                for line in [&stats.some, &stats.full] {
+                    let _ = write!(
+                        row,
+                        ",{},{},{},{}",
+                        line.avg10,
+                        line.avg60,
+                        line.avg300,
+                        line.total.as_micros()
+                    );
+                }
+                row.push('\n');
+            }
+            ExportFormat::JsonLines => {
+                row = sample_json(timestamp, resource, cgroup.as_deref(), stats);
+                row.push('\n');
+            }
+        }
+        if self.output.rotate_if_due(row.len() as u64)? {
+            self.header_pending = true;
+        }
+        if self.header_pending && self.format == ExportFormat::Csv {
+            row.insert_str(0, CSV_HEADER);
+        }
+        self.header_pending = false;
+        self.output.write(row.as_bytes())
+    }
+
+    /// Append every sample taken by `sampler`, which samples `resource` system-wide or within `cgroup`. Only returns if sampling or writing fails
+    pub fn run(
+        &mut self,
+        sampler: Sampler,
+        resource: Resource,
+        cgroup: Option<&Path>,
+    ) -> Result<(), Error> {
+        for stats in sampler {
+            self.write(resource, cgroup, &stats?)?;
+        }
+        Ok(())
+    }
+}
+
+/// A sample as the JSON object a [SampleExporter] writes on each line, taken at `timestamp` seconds since the Unix epoch
+pub(crate) fn sample_json(
+    timestamp: f64,
+    resource: Resource,
+    cgroup: Option<&str>,
+    stats: &PsiStats,
+) -> String {
+    format!(
+        r#"{{"timestamp":{timestamp:.3},"resource":"{resource}","cgroup":{},"some":{},"full":{}}}"#,
+        cgroup.map_or("null".into(), json_string),
+        json_line(&stats.some),
+        json_line(&stats.full)
+    )
+}
+
+/// Quote a CSV field if it contains characters that would otherwise break the row
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub(crate) fn json_line(line: &PsiLine) -> String {
+    format!(
+        r#"{{"avg10":{},"avg60":{},"avg300":{},"total_us":{}}}"#,
+        line.avg10,
+        line.avg60,
+        line.avg300,
+        line.total.as_micros()
+    )
+}
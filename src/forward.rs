@@ -0,0 +1,368 @@
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    Error, Event, PressureMonitor, PsiStats, Resource, Sampler, WaitOutcome,
+    event_log::event_json,
+    export::{json_string, sample_json},
+    reconnect::Reconnect,
+};
+
+/// Most bytes written to a TCP collector at once
+const TCP_BATCH: usize = 64 << 10;
+/// Most records sent in one gRPC request, keeping requests well below tonic's default 4MiB limit
+#[cfg(feature = "tonic")]
+const GRPC_BATCH: usize = 1000;
+
+/// The messages of the collector service declared in `proto/collector.proto`
+#[cfg(feature = "tonic")]
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct ReportRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub(super) records: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct ReportResponse {}
+}
+
+enum Transport {
+    Tcp {
+        addr: String,
+        stream: Option<TcpStream>,
+    },
+    #[cfg(feature = "tonic")]
+    Grpc {
+        runtime: tokio::runtime::Runtime,
+        channel: tonic::transport::Channel,
+    },
+}
+
+/// Ships pressure events and samples to a remote collector, so that fleet operators can centralize pressure telemetry from many hosts
+///
+/// Each event or sample becomes a JSON object like those of [EventLogger](crate::EventLogger) and [SampleExporter](crate::SampleExporter),
+/// with the `host` it came from and its `type`, `event` or `sample`, added. Over TCP records are sent one per line. With the `tonic` feature they can be
+/// sent in batches to the `pressure.v1.Collector/Report` gRPC method declared in `proto/collector.proto` instead.
+///
+/// Records are buffered while the collector can't be reached, dropping the oldest ones once the buffer is full, and sent once it can be reached again.
+/// Connecting is retried with exponential backoff. Records written to a connection the collector closed without being noticed yet can be lost.
+/// A record cut off by a failed write leaves a truncated line at the end of that connection, and is sent again in full over the next one.
+/// Over gRPC, a batch whose response didn't arrive in time is sent again, so the collector can receive its records twice
+///
+/// ```no_run
+/// use pressure::{PressureForwarder, PressureMonitor};
+///
+/// let mut forwarder = PressureForwarder::tcp("collector.example.com:9400").capacity(100_000);
+/// forwarder.run(&mut PressureMonitor::builder().attach_stats().build()?)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct PressureForwarder {
+    transport: Transport,
+    host: String,
+    buffer: VecDeque<String>,
+    capacity: usize,
+    dropped: u64,
+    timeout: Duration,
+    reconnect: Reconnect,
+}
+
+impl PressureForwarder {
+    /// Send records as JSON lines to a TCP collector at `addr`, such as `collector.example.com:9400`, which is resolved again on every connection attempt
+    pub fn tcp(addr: impl Into<String>) -> Self {
+        Self::with_transport(Transport::Tcp {
+            addr: addr.into(),
+            stream: None,
+        })
+    }
+
+    /// Send records to a gRPC collector at `endpoint`, such as `http://collector.example.com:50051`, through a runtime owned by the forwarder
+    #[cfg(feature = "tonic")]
+    pub fn grpc(endpoint: impl Into<String>) -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let endpoint = tonic::transport::Endpoint::from_shared(endpoint.into())?;
+        // Lazy channels spawn their connection onto the current runtime
+        let channel = {
+            let _guard = runtime.enter();
+            endpoint.connect_lazy()
+        };
+        Ok(Self::with_transport(Transport::Grpc { runtime, channel }))
+    }
+
+    fn with_transport(transport: Transport) -> Self {
+        Self {
+            transport,
+            host: nix::unistd::gethostname()
+                .map(|host| host.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            buffer: VecDeque::new(),
+            capacity: 10_000,
+            dropped: 0,
+            timeout: Duration::from_secs(5),
+            reconnect: Reconnect::new(Duration::from_secs(1), Duration::from_secs(60)),
+        }
+    }
+    /// Name records are tagged with, defaults to the hostname
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+    /// Most records to buffer while the collector can't be reached, defaults to 10000
+    pub fn capacity(mut self, records: usize) -> Self {
+        self.capacity = records;
+        self
+    }
+    /// Wait `initial` before retrying after a failed attempt, doubling up to `max`. Defaults to 1s and 60s
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.reconnect = Reconnect::new(initial, max);
+        self
+    }
+    /// Time to wait for connecting and sending before giving up on an attempt, defaults to 5s
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Number of records buffered until the collector can be reached
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+    /// Number of records dropped because the buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Forward an event, returning the number of buffered records that were sent
+    pub fn send_event(&mut self, event: &Event) -> usize {
+        self.push("event", &event_json(event));
+        self.flush()
+    }
+
+    /// Forward a sample of pressure on a resource, system-wide or within a cgroup, returning the number of buffered records that were sent
+    pub fn send_sample(
+        &mut self,
+        resource: Resource,
+        cgroup: Option<&Path>,
+        stats: &PsiStats,
+    ) -> usize {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let cgroup = cgroup.map(Path::to_string_lossy);
+        self.push(
+            "sample",
+            &sample_json(timestamp, resource, cgroup.as_deref(), stats),
+        );
+        self.flush()
+    }
+
+    /// Buffer a record, tagging the JSON object with the host and type
+    fn push(&mut self, kind: &str, json: &str) {
+        if self.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.buffer.len() >= self.capacity {
+            self.buffer.pop_front();
+            self.dropped += 1;
+        }
+        self.buffer.push_back(format!(
+            r#"{{"host":{},"type":"{kind}",{}"#,
+            json_string(&self.host),
+            &json[1..]
+        ));
+    }
+
+    /// Send the buffered records unless waiting to retry after a failed attempt, returning how many were sent
+    pub fn flush(&mut self) -> usize {
+        if self.buffer.is_empty()
+            || self
+                .reconnect
+                .retry_at()
+                .is_some_and(|retry_at| retry_at > Instant::now())
+        {
+            return 0;
+        }
+        let pending = self.buffer.len();
+        if self.transport.send(&mut self.buffer, self.timeout) {
+            self.reconnect.succeeded();
+        } else {
+            self.reconnect.failed();
+        }
+        pending - self.buffer.len()
+    }
+
+    /// Forward every event `monitor` receives, retrying buffered records while waiting. Only returns once a wait is cancelled or interrupted, or if waiting fails
+    pub fn run(&mut self, monitor: &mut PressureMonitor) -> Result<(), Error> {
+        loop {
+            let retry_at = self
+                .reconnect
+                .retry_at()
+                .filter(|_| !self.buffer.is_empty());
+            let outcome = match retry_at {
+                Some(retry_at) => {
+                    monitor.wait_timeout(retry_at.saturating_duration_since(Instant::now()))?
+                }
+                None => monitor.wait()?,
+            };
+            match outcome {
+                WaitOutcome::Triggered(event) => {
+                    self.send_event(&event);
+                }
+                WaitOutcome::TimedOut => {
+                    self.flush();
+                }
+                WaitOutcome::Cancelled | WaitOutcome::Interrupted => return Ok(()),
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            }
+        }
+    }
+
+    /// Forward every sample taken by `sampler`, which samples `resource` system-wide or within `cgroup`. Only returns if sampling fails
+    pub fn run_sampler(
+        &mut self,
+        sampler: Sampler,
+        resource: Resource,
+        cgroup: Option<&Path>,
+    ) -> Result<(), Error> {
+        for stats in sampler {
+            self.send_sample(resource, cgroup, &stats?);
+        }
+        Ok(())
+    }
+}
+
+impl Transport {
+    /// Send records from the front of `records`, removing those that were sent. Returns false if the collector couldn't be reached
+    fn send(&mut self, records: &mut VecDeque<String>, timeout: Duration) -> bool {
+        match self {
+            Transport::Tcp {
+                addr,
+                stream: stream_slot,
+            } => {
+                let connected = match stream_slot.take() {
+                    Some(connected) if !closed(&connected) => connected,
+                    _ => match connect(addr, timeout) {
+                        Ok(connected) => connected,
+                        Err(_) => return false,
+                    },
+                };
+                let stream = stream_slot.insert(connected);
+                let mut batch = String::new();
+                while !records.is_empty() {
+                    batch.clear();
+                    let mut count = 0;
+                    for record in records.iter() {
+                        if batch.len() >= TCP_BATCH {
+                            break;
+                        }
+                        batch.push_str(record);
+                        batch.push('\n');
+                        count += 1;
+                    }
+                    let written = write_prefix(stream, batch.as_bytes());
+                    if written < batch.len() {
+                        // Records written in full aren't sent again, while one cut off is sent again in full over the next connection
+                        let sent = records
+                            .iter()
+                            .scan(0, |end, record| {
+                                *end += record.len() + 1;
+                                Some(*end)
+                            })
+                            .take_while(|&end| end <= written)
+                            .count();
+                        records.drain(..sent);
+                        *stream_slot = None;
+                        return false;
+                    }
+                    records.drain(..count);
+                }
+                true
+            }
+            #[cfg(feature = "tonic")]
+            Transport::Grpc { runtime, channel } => {
+                use tonic::codegen::http::uri::PathAndQuery;
+                while !records.is_empty() {
+                    let count = records.len().min(GRPC_BATCH);
+                    let request = proto::ReportRequest {
+                        records: records.range(..count).cloned().collect(),
+                    };
+                    let mut client = tonic::client::Grpc::new(channel.clone());
+                    let report = async {
+                        client
+                            .ready()
+                            .await
+                            .map_err(|e| tonic::Status::unavailable(e.to_string()))?;
+                        client
+                            .unary::<_, proto::ReportResponse, _>(
+                                tonic::Request::new(request),
+                                PathAndQuery::from_static("/pressure.v1.Collector/Report"),
+                                tonic_prost::ProstCodec::default(),
+                            )
+                            .await
+                    };
+                    let sent = runtime.block_on(async {
+                        tokio::time::timeout(timeout, report)
+                            .await
+                            .is_ok_and(|response| response.is_ok())
+                    });
+                    if !sent {
+                        return false;
+                    }
+                    records.drain(..count);
+                }
+                true
+            }
+        }
+    }
+}
+
+/// Write as much of `bytes` as the stream accepts before failing, returning how many bytes were written
+fn write_prefix(stream: &mut TcpStream, bytes: &[u8]) -> usize {
+    let mut written = 0;
+    while written < bytes.len() {
+        match stream.write(&bytes[written..]) {
+            Ok(0) => break,
+            Ok(n) => written += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+    written
+}
+
+/// Connect to the first address `addr` resolves to that accepts a connection within `timeout`
+fn connect(addr: &str, timeout: Duration) -> std::io::Result<TcpStream> {
+    let mut last_error = ErrorKind::NotFound.into();
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                stream.set_write_timeout(Some(timeout))?;
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Whether the collector closed the connection. Collectors never send anything, so anything but an empty read means it is still open
+fn closed(mut stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let closed = match stream.read(&mut [0; 64]) {
+        Ok(read) => read == 0,
+        Err(e) => e.kind() != ErrorKind::WouldBlock,
+    };
+    closed || stream.set_nonblocking(false).is_err()
+}
@@ -0,0 +1,152 @@
+//! Memory pressure on FreeBSD, which has no pressure stall information, by comparing free memory against the page daemon's thresholds
+//!
+//! The kernel's vm_lowmem event is only delivered to in-kernel handlers, so the free page count is sampled on a kqueue timer instead
+
+use std::{
+    ffi::CStr,
+    os::fd::{AsFd, BorrowedFd},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    libc,
+    poll::{PollFd, PollFlags, PollTimeout},
+    sys::event::{EvFlags, EventFilter, FilterFlag, KEvent, Kqueue},
+};
+
+use crate::{
+    Error, PressureBackend, PsiStats, Resource, Severity, Source, WaitOutcome, event::Target,
+    poll_timeout,
+};
+
+/// Backend reporting an event whenever free memory is below vm.v_free_min, the level at which the kernel considers memory low and runs its vm_lowmem handlers
+///
+/// Free memory is checked each time a kqueue timer fires, so like a monitor that fell back to polling its fd doesn't signal events
+pub(crate) struct LowMemoryBackend {
+    kqueue: Kqueue,
+}
+
+impl LowMemoryBackend {
+    pub(crate) fn new(interval: Duration) -> Result<Self, Error> {
+        let kqueue = Kqueue::new()?;
+        let timer = KEvent::new(
+            0,
+            EventFilter::EVFILT_TIMER,
+            EvFlags::EV_ADD,
+            FilterFlag::empty(),
+            interval.as_millis() as isize,
+            0,
+        );
+        kqueue.kevent(&[timer], &mut [], None)?;
+        Ok(Self { kqueue })
+    }
+
+    /// Consume a pending timer expiration, returning whether there was one
+    fn tick(&self) -> Result<bool, Error> {
+        let mut events = [KEvent::new(
+            0,
+            EventFilter::EVFILT_TIMER,
+            EvFlags::empty(),
+            FilterFlag::empty(),
+            0,
+            0,
+        )];
+        let timeout = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        let received = self.kqueue.kevent(&[], &mut events, Some(timeout))?;
+        Ok(received > 0)
+    }
+}
+
+impl PressureBackend for LowMemoryBackend {
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let mut fds = vec![PollFd::new(self.kqueue.as_fd(), PollFlags::POLLIN)];
+            if let Some(cancellation) = cancellation {
+                fds.push(PollFd::new(cancellation, PollFlags::POLLIN));
+            }
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let ready =
+                nix::poll::poll(&mut fds, remaining.map_or(PollTimeout::NONE, poll_timeout))?;
+            if ready == 0 {
+                return Ok(WaitOutcome::TimedOut);
+            }
+            if fds.get(1).is_some_and(|fd| fd.any() == Some(true)) {
+                return Ok(WaitOutcome::Cancelled);
+            }
+            if self.tick()? && severity()? == Severity::Critical {
+                return Ok(WaitOutcome::Triggered(()));
+            }
+        }
+    }
+
+    /// Check free memory if the timer fired since the last check, so that persisting pressure doesn't produce an event on every call
+    fn try_wait(&mut self) -> Result<bool, Error> {
+        Ok(self.tick()? && severity()? == Severity::Critical)
+    }
+
+    /// FreeBSD doesn't expose stall times, use [PressureMonitor::severity](crate::PressureMonitor::severity) instead
+    fn stats(&self, resource: Resource) -> Result<PsiStats, Error> {
+        Err(Error::ResourceUnavailable(resource))
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        severity().ok()
+    }
+}
+
+impl AsFd for LowMemoryBackend {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.kqueue.as_fd()
+    }
+}
+
+/// Classify free memory against the page daemon's thresholds, which is [Severity::Critical] below vm.v_free_min and [Severity::Medium] below vm.v_free_target, where the page daemon starts reclaiming
+fn severity() -> Result<Severity, Error> {
+    let free = sysctl(c"vm.stats.vm.v_free_count")?;
+    if free < sysctl(c"vm.v_free_min")? {
+        Ok(Severity::Critical)
+    } else if free < sysctl(c"vm.v_free_target")? {
+        Ok(Severity::Medium)
+    } else {
+        Ok(Severity::Low)
+    }
+}
+
+/// Read an unsigned integer sysctl, which all page counts are
+fn sysctl(name: &CStr) -> Result<u32, Error> {
+    let mut value = 0u32;
+    let mut len = size_of::<u32>();
+    // SAFETY: the buffer is a u32 whose size is passed along, and no new value is set
+    let res = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            (&raw mut value).cast(),
+            &mut len,
+            std::ptr::null(),
+            0,
+        )
+    };
+    Errno::result(res)?;
+    Ok(value)
+}
+
+/// Open system-wide low memory notifications, the only resource FreeBSD reports pressure for, checking free memory once per `interval`
+pub(crate) fn open(resource: Resource, interval: Duration) -> Result<(Source, Target), Error> {
+    if resource != Resource::Memory {
+        return Err(Error::ResourceUnavailable(resource));
+    }
+    Ok((
+        Source::Backend(Box::new(LowMemoryBackend::new(interval)?)),
+        Target::for_backend(resource),
+    ))
+}
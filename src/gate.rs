@@ -0,0 +1,225 @@
+use std::{
+    future::Future,
+    os::fd::AsFd,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use nix::poll::{PollFd, PollFlags};
+
+use crate::{
+    CancellationToken, Error, PsiLine, Sampler, Severity, SeverityThresholds, StallKind,
+    poll_timeout,
+};
+
+struct State {
+    close_at: Severity,
+    severity: Severity,
+    thresholds: SeverityThresholds,
+    /// Tasks waiting in [PressureGate::wait_open], woken once the gate opens
+    wakers: Vec<Waker>,
+}
+
+impl State {
+    fn is_open(&self) -> bool {
+        self.severity < self.close_at
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    opened: Condvar,
+    /// Stops the threads started by [PressureGate::watch] once the gate is dropped
+    cancellation: Mutex<Option<CancellationToken>>,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if let Some(cancellation) = self
+            .cancellation
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+        {
+            cancellation.cancel();
+        }
+    }
+}
+
+/// A gate that is open while pressure is below a severity and closed while it is at least that severe, to suspend background work such as prefetching
+/// or indexing under stall and resume it automatically once pressure subsides
+///
+/// Tasks await [wait_open](Self::wait_open) before each unit of work, which completes right away while the gate is open. It works with any async runtime,
+/// and threads can use [wait_open_blocking](Self::wait_open_blocking) instead. Work already started isn't interrupted when the gate closes. Clones share the same gate
+///
+/// ```no_run
+/// use pressure::{PressureGate, Resource, Sampler, Severity, StallKind};
+/// use std::time::Duration;
+///
+/// # async fn prefetch(_: u32) {}
+/// # async fn run() -> Result<(), pressure::Error> {
+/// let gate = PressureGate::new(Severity::Medium);
+/// gate.watch([Sampler::new(Resource::Memory, Duration::from_secs(1))?], StallKind::Some)?;
+/// for page in 0..1000 {
+///     gate.wait_open().await;
+///     prefetch(page).await;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PressureGate {
+    shared: Arc<Shared>,
+}
+
+impl PressureGate {
+    /// A gate that closes once pressure is at least `close_at`, and is open until the first update
+    pub fn new(close_at: Severity) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    close_at,
+                    severity: Severity::Low,
+                    thresholds: SeverityThresholds::default(),
+                    wakers: Vec::new(),
+                }),
+                opened: Condvar::new(),
+                cancellation: Mutex::new(None),
+            }),
+        }
+    }
+    /// Thresholds at which pressure is medium or critical
+    pub fn thresholds(self, thresholds: SeverityThresholds) -> Self {
+        self.lock().thresholds = thresholds;
+        self
+    }
+
+    /// Open and close the gate on a worker thread for as long as it exists, following the most severe pressure across `samplers`.
+    /// Samples are taken at the shortest interval of the samplers, and failed samples are skipped
+    pub fn watch(
+        &self,
+        samplers: impl IntoIterator<Item = Sampler>,
+        kind: StallKind,
+    ) -> Result<(), Error> {
+        let samplers: Vec<Sampler> = samplers.into_iter().collect();
+        let Some(interval) = samplers.iter().map(Sampler::interval).min() else {
+            return Ok(());
+        };
+        let cancellation = {
+            let mut cancellation = self
+                .shared
+                .cancellation
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            match &*cancellation {
+                Some(token) => token.clone(),
+                None => cancellation.insert(CancellationToken::new()?).clone(),
+            }
+        };
+        let shared = Arc::downgrade(&self.shared);
+        std::thread::Builder::new()
+            .name("pressure-gate".into())
+            .spawn(move || watch(shared, samplers, kind, interval, cancellation))?;
+        Ok(())
+    }
+
+    /// Open or close the gate according to the most severe of `pressure`, such as the lines of the resources the background work competes for
+    pub fn update(&self, pressure: &[PsiLine]) {
+        let severity = {
+            let state = self.lock();
+            pressure
+                .iter()
+                .map(|line| state.thresholds.classify(line))
+                .max()
+        };
+        if let Some(severity) = severity {
+            self.set_severity(severity);
+        }
+    }
+
+    /// Open or close the gate according to a severity classified elsewhere, such as that of a monitor's events
+    pub fn set_severity(&self, severity: Severity) {
+        let mut state = self.lock();
+        let was_open = state.is_open();
+        state.severity = severity;
+        if state.is_open() && !was_open {
+            let wakers = std::mem::take(&mut state.wakers);
+            drop(state);
+            wakers.into_iter().for_each(Waker::wake);
+            self.shared.opened.notify_all();
+        }
+    }
+
+    /// Severity of the last update
+    pub fn severity(&self) -> Severity {
+        self.lock().severity
+    }
+    pub fn is_open(&self) -> bool {
+        self.lock().is_open()
+    }
+
+    /// Wait until the gate is open, completing right away if it already is
+    pub fn wait_open(&self) -> WaitOpen<'_> {
+        WaitOpen { gate: self }
+    }
+    /// Block until the gate is open
+    pub fn wait_open_blocking(&self) {
+        let mut state = self.lock();
+        while !state.is_open() {
+            state = self
+                .shared
+                .opened
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Future returned by [PressureGate::wait_open]
+pub struct WaitOpen<'a> {
+    gate: &'a PressureGate,
+}
+
+impl Future for WaitOpen<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.gate.lock();
+        if state.is_open() {
+            return Poll::Ready(());
+        }
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+fn watch(
+    shared: Weak<Shared>,
+    samplers: Vec<Sampler>,
+    kind: StallKind,
+    interval: Duration,
+    cancellation: CancellationToken,
+) {
+    loop {
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        let pressure: Vec<PsiLine> = samplers
+            .iter()
+            .filter_map(|sampler| sampler.sample().ok())
+            .map(|stats| *stats.line(kind))
+            .collect();
+        PressureGate { shared }.update(&pressure);
+        let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+        if !matches!(nix::poll::poll(&mut fds, poll_timeout(interval)), Ok(0)) {
+            return;
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Pressure events on the GLib main loop
+
+use std::os::fd::AsRawFd;
+
+use glib::{ControlFlow, IOCondition, SourceId};
+
+use crate::{Error, Event, PollInterest, PressureMonitor};
+
+/// Attach a monitor to the default [MainContext](glib::MainContext), calling `callback` with each pressure event until it returns [ControlFlow::Break]
+///
+/// The monitor is dropped once the source is removed. Like [glib::unix_fd_add_local], this panics if the default main context is owned by another thread.
+/// Monitors that fell back to polling, or whose backend doesn't signal events on its fd, have no fd to attach
+///
+/// ```no_run
+/// use pressure::PressureMonitor;
+/// pressure::glib::attach(PressureMonitor::new().unwrap(), |_, _| {
+///     // Drop caches
+///     glib::ControlFlow::Continue
+/// })
+/// .unwrap();
+/// ```
+pub fn attach<F>(mut monitor: PressureMonitor, mut callback: F) -> Result<SourceId, Error>
+where
+    F: FnMut(&mut PressureMonitor, Event) -> ControlFlow + 'static,
+{
+    let condition = match monitor.poll_interest() {
+        Some(PollInterest::Priority) => IOCondition::PRI,
+        Some(PollInterest::Readable) => IOCondition::IN,
+        None => return Err(Error::NoFileDescriptor),
+    };
+    let fd = monitor.as_raw_fd();
+    Ok(glib::unix_fd_add_local(fd, condition, move |_, _| {
+        // A failed read is harmless here, as the notification will be reported again
+        let coalesced = monitor.acknowledge().unwrap_or(1);
+        let event = Event {
+            coalesced,
+            ..monitor.target.event()
+        };
+        callback(&mut monitor, event)
+    }))
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::{Event, PsiStats, Resource, StallKind};
+
+/// Retains the most recent samples and events of each resource, to base decisions on recent pressure trends rather than single events
+///
+/// ```no_run
+/// use pressure::{History, PressureMonitor, Resource, StallKind, WaitOutcome};
+/// use std::time::Duration;
+///
+/// let mut monitor = PressureMonitor::new()?;
+/// let mut history = History::new(600);
+/// loop {
+///     history.record_sample(Resource::Memory, monitor.stats()?);
+///     if let WaitOutcome::Triggered(event) = monitor.wait_timeout(Duration::from_secs(1))? {
+///         history.record_event(event);
+///     }
+///     let window = Duration::from_secs(300);
+///     if history.time_above(Resource::Memory, StallKind::Some, 10.0, window) > Duration::from_secs(60)
+///         && history.event_count(Resource::Memory, window) >= 5
+///     {
+///         // Pressure has been sustained for a while, shed load
+///     }
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    samples: HashMap<Resource, VecDeque<(Instant, PsiStats)>>,
+    events: HashMap<Resource, VecDeque<Event>>,
+}
+
+impl History {
+    /// Retain up to `capacity` samples and `capacity` events per resource, dropping the oldest first
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: HashMap::new(),
+            events: HashMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Record a sample of pressure on a resource taken now
+    pub fn record_sample(&mut self, resource: Resource, stats: PsiStats) {
+        self.record_sample_at(resource, stats, Instant::now());
+    }
+
+    /// Record a sample of pressure on a resource taken at `timestamp`, which must not be earlier than the previous sample's
+    pub fn record_sample_at(&mut self, resource: Resource, stats: PsiStats, timestamp: Instant) {
+        push_bounded(
+            self.samples.entry(resource).or_default(),
+            (timestamp, stats),
+            self.capacity,
+        );
+    }
+
+    /// Record a pressure event
+    pub fn record_event(&mut self, event: Event) {
+        push_bounded(
+            self.events.entry(event.resource).or_default(),
+            event,
+            self.capacity,
+        );
+    }
+
+    /// Retained samples of a resource, oldest first
+    pub fn samples(&self, resource: Resource) -> impl Iterator<Item = (Instant, &PsiStats)> {
+        self.samples
+            .get(&resource)
+            .into_iter()
+            .flatten()
+            .map(|(timestamp, stats)| (*timestamp, stats))
+    }
+
+    /// Retained events of a resource, oldest first
+    pub fn events(&self, resource: Resource) -> impl Iterator<Item = &Event> {
+        self.events.get(&resource).into_iter().flatten()
+    }
+
+    /// Most recent sample of a resource
+    pub fn latest(&self, resource: Resource) -> Option<&PsiStats> {
+        self.samples
+            .get(&resource)
+            .and_then(VecDeque::back)
+            .map(|(_, stats)| stats)
+    }
+
+    /// Highest avg10 of a kind of stall among the samples taken within the last `window`, or [None] if there are none
+    pub fn max_avg10(&self, resource: Resource, kind: StallKind, window: Duration) -> Option<f32> {
+        self.recent_samples(resource, window)
+            .map(|(_, stats)| stats.line(kind).avg10)
+            .reduce(f32::max)
+    }
+
+    /// How long avg10 of a kind of stall was at or above `threshold` percent within the last `window`.
+    /// Each sample is assumed to hold until the next one was taken, and the latest one until now
+    pub fn time_above(
+        &self,
+        resource: Resource,
+        kind: StallKind,
+        threshold: f32,
+        window: Duration,
+    ) -> Duration {
+        let now = Instant::now();
+        let start = now.checked_sub(window);
+        let samples: Vec<_> = self.samples(resource).collect();
+        let mut above = Duration::ZERO;
+        for (i, (timestamp, stats)) in samples.iter().enumerate() {
+            let until = samples.get(i + 1).map_or(now, |(next, _)| *next);
+            let from = start.map_or(*timestamp, |start| (*timestamp).max(start));
+            if stats.line(kind).avg10 >= threshold {
+                above += until.saturating_duration_since(from);
+            }
+        }
+        above
+    }
+
+    /// Number of events of a resource received within the last `window`
+    pub fn event_count(&self, resource: Resource, window: Duration) -> usize {
+        let now = Instant::now();
+        self.events(resource)
+            .filter(|event| now.saturating_duration_since(event.timestamp) <= window)
+            .count()
+    }
+
+    /// Forget every sample and event
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.events.clear();
+    }
+
+    fn recent_samples(
+        &self,
+        resource: Resource,
+        window: Duration,
+    ) -> impl Iterator<Item = (Instant, &PsiStats)> {
+        let now = Instant::now();
+        self.samples(resource)
+            .filter(move |(timestamp, _)| now.saturating_duration_since(*timestamp) <= window)
+    }
+}
+
+fn push_bounded<T>(buffer: &mut VecDeque<T>, item: T, capacity: usize) {
+    if capacity == 0 {
+        return;
+    }
+    if buffer.len() == capacity {
+        buffer.pop_front();
+    }
+    buffer.push_back(item);
+}
@@ -0,0 +1,322 @@
+use std::{
+    ops::{Bound, RangeBounds},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{Connection, params};
+
+use crate::{Error, PsiStats, Resource, Sampler, StallKind};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS buckets (
+    resource TEXT NOT NULL,
+    cgroup TEXT NOT NULL,
+    start_ms INTEGER NOT NULL,
+    samples INTEGER NOT NULL,
+    some_avg10_sum REAL NOT NULL,
+    some_avg10_max REAL NOT NULL,
+    some_total_us INTEGER NOT NULL,
+    full_avg10_sum REAL NOT NULL,
+    full_avg10_max REAL NOT NULL,
+    full_total_us INTEGER NOT NULL,
+    PRIMARY KEY (resource, cgroup, start_ms)
+) WITHOUT ROWID;
+";
+
+/// Records pressure stall information in a SQLite database, downsampled into buckets of a fixed resolution, so that pressure can be looked back on across restarts
+///
+/// Each bucket keeps the mean and maximum avg10 and the latest total stall time of both kinds of stall among the samples taken within it.
+/// Buckets older than the retention period are deleted whenever a new bucket is started
+///
+/// ```
+/// use pressure::{HistoryStore, PsiLine, PsiStats, Resource, StallKind};
+/// use std::time::{Duration, SystemTime};
+///
+/// let mut store = HistoryStore::open_in_memory()?.resolution(Duration::from_secs(60));
+/// let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000);
+/// for (i, avg10) in [10.0, 30.0, 20.0].into_iter().enumerate() {
+///     let some = PsiLine { avg10, total: Duration::from_secs(i as u64), ..Default::default() };
+///     let stats = PsiStats { some, ..Default::default() };
+///     store.record_at(Resource::Memory, None, &stats, start + Duration::from_secs(30 * i as u64))?;
+/// }
+/// assert_eq!(store.range(Resource::Memory, None, ..)?.len(), 2);
+/// let summary = store.summary(Resource::Memory, None, start..)?.unwrap();
+/// assert_eq!(summary.samples, 3);
+/// assert_eq!(summary.line(StallKind::Some).avg10_max, 30.0);
+/// assert_eq!(summary.line(StallKind::Some).stalled, Duration::from_secs(1));
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct HistoryStore {
+    connection: Connection,
+    resolution: Duration,
+    retention: Duration,
+    /// Start of the bucket last recorded into, in milliseconds since the Unix epoch
+    last_bucket: Option<i64>,
+}
+
+/// Stall information of one kind of stall, downsampled into a [Bucket]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BucketLine {
+    pub avg10_mean: f32,
+    pub avg10_max: f32,
+    /// Total stall time since boot, as of the latest sample in the bucket
+    pub total: Duration,
+}
+
+/// The samples recorded within one period of a [HistoryStore]'s resolution
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bucket {
+    pub start: SystemTime,
+    pub samples: u32,
+    pub some: BucketLine,
+    pub full: BucketLine,
+}
+
+impl Bucket {
+    pub fn line(&self, kind: StallKind) -> &BucketLine {
+        match kind {
+            StallKind::Some => &self.some,
+            StallKind::Full => &self.full,
+        }
+    }
+}
+
+/// Stall information of one kind of stall, aggregated over a range of a [HistoryStore]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SummaryLine {
+    pub avg10_mean: f32,
+    pub avg10_max: f32,
+    /// Stall time accumulated between the latest samples of the first and last buckets, not counting totals that went backwards after a reboot
+    pub stalled: Duration,
+}
+
+/// Pressure aggregated over a range of a [HistoryStore], see [HistoryStore::summary]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Summary {
+    pub samples: u64,
+    pub some: SummaryLine,
+    pub full: SummaryLine,
+}
+
+impl Summary {
+    pub fn line(&self, kind: StallKind) -> &SummaryLine {
+        match kind {
+            StallKind::Some => &self.some,
+            StallKind::Full => &self.full,
+        }
+    }
+}
+
+impl HistoryStore {
+    /// Open or create a database at `path`, keeping buckets of a minute for 30 days
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Keep the history in memory only, for example in tests
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, Error> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection,
+            resolution: Duration::from_secs(60),
+            retention: Duration::from_secs(30 * 24 * 60 * 60),
+            last_bucket: None,
+        })
+    }
+
+    /// Length of the buckets samples are downsampled into, at least a millisecond. Buckets already recorded keep their length
+    pub fn resolution(mut self, resolution: Duration) -> Self {
+        self.resolution = resolution.max(Duration::from_millis(1));
+        self
+    }
+
+    /// How long buckets are kept
+    pub fn retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Record a sample of pressure on a resource taken now, system-wide or within a cgroup
+    pub fn record(
+        &mut self,
+        resource: Resource,
+        cgroup: Option<&Path>,
+        stats: &PsiStats,
+    ) -> Result<(), Error> {
+        self.record_at(resource, cgroup, stats, SystemTime::now())
+    }
+
+    /// Record a sample of pressure on a resource taken at `timestamp`
+    pub fn record_at(
+        &mut self,
+        resource: Resource,
+        cgroup: Option<&Path>,
+        stats: &PsiStats,
+        timestamp: SystemTime,
+    ) -> Result<(), Error> {
+        let resolution = self.resolution.as_millis() as i64;
+        let bucket = millis(timestamp).div_euclid(resolution) * resolution;
+        self.connection.execute(
+            "INSERT INTO buckets VALUES (?1, ?2, ?3, 1, ?4, ?4, ?5, ?6, ?6, ?7)
+            ON CONFLICT DO UPDATE SET
+                samples = samples + 1,
+                some_avg10_sum = some_avg10_sum + excluded.some_avg10_sum,
+                some_avg10_max = max(some_avg10_max, excluded.some_avg10_max),
+                some_total_us = excluded.some_total_us,
+                full_avg10_sum = full_avg10_sum + excluded.full_avg10_sum,
+                full_avg10_max = max(full_avg10_max, excluded.full_avg10_max),
+                full_total_us = excluded.full_total_us",
+            params![
+                resource.to_string(),
+                cgroup_key(cgroup),
+                bucket,
+                stats.some.avg10,
+                stats.some.total.as_micros() as i64,
+                stats.full.avg10,
+                stats.full.total.as_micros() as i64,
+            ],
+        )?;
+        if self.last_bucket != Some(bucket) {
+            self.last_bucket = Some(bucket);
+            self.prune()?;
+        }
+        Ok(())
+    }
+
+    /// Record every sample taken by `sampler`, which samples `resource` system-wide or within `cgroup`. Only returns if sampling or recording fails
+    pub fn run(
+        &mut self,
+        sampler: Sampler,
+        resource: Resource,
+        cgroup: Option<&Path>,
+    ) -> Result<(), Error> {
+        for stats in sampler {
+            self.record(resource, cgroup, &stats?)?;
+        }
+        Ok(())
+    }
+
+    /// Buckets of a resource, system-wide or within a cgroup, that start within `range`, oldest first
+    pub fn range(
+        &self,
+        resource: Resource,
+        cgroup: Option<&Path>,
+        range: impl RangeBounds<SystemTime>,
+    ) -> Result<Vec<Bucket>, Error> {
+        let (from, to) = bounds(range);
+        let mut statement = self.connection.prepare_cached(
+            "SELECT start_ms, samples, some_avg10_sum, some_avg10_max, some_total_us, full_avg10_sum, full_avg10_max, full_total_us
+            FROM buckets WHERE resource = ?1 AND cgroup = ?2 AND start_ms >= ?3 AND start_ms <= ?4 ORDER BY start_ms",
+        )?;
+        let buckets = statement
+            .query_map(
+                params![resource.to_string(), cgroup_key(cgroup), from, to],
+                |row| {
+                    let samples: u32 = row.get(1)?;
+                    let line = |sum: f64, max: f32, total: i64| BucketLine {
+                        avg10_mean: (sum / f64::from(samples.max(1))) as f32,
+                        avg10_max: max,
+                        total: Duration::from_micros(total.max(0) as u64),
+                    };
+                    Ok(Bucket {
+                        start: UNIX_EPOCH
+                            + Duration::from_millis(row.get::<_, i64>(0)?.max(0) as u64),
+                        samples,
+                        some: line(row.get(2)?, row.get(3)?, row.get(4)?),
+                        full: line(row.get(5)?, row.get(6)?, row.get(7)?),
+                    })
+                },
+            )?
+            .collect::<Result<_, _>>()?;
+        Ok(buckets)
+    }
+
+    /// Aggregate the buckets of a resource that start within `range`, or [None] if there are none
+    pub fn summary(
+        &self,
+        resource: Resource,
+        cgroup: Option<&Path>,
+        range: impl RangeBounds<SystemTime>,
+    ) -> Result<Option<Summary>, Error> {
+        let buckets = self.range(resource, cgroup, range)?;
+        if buckets.is_empty() {
+            return Ok(None);
+        }
+        let samples = buckets.iter().map(|bucket| u64::from(bucket.samples)).sum();
+        let line = |kind| {
+            let mean = buckets
+                .iter()
+                .map(|bucket| f64::from(bucket.line(kind).avg10_mean) * f64::from(bucket.samples))
+                .sum::<f64>()
+                / samples as f64;
+            SummaryLine {
+                avg10_mean: mean as f32,
+                avg10_max: buckets
+                    .iter()
+                    .map(|bucket| bucket.line(kind).avg10_max)
+                    .fold(0.0, f32::max),
+                stalled: buckets
+                    .windows(2)
+                    .map(|pair| {
+                        pair[1]
+                            .line(kind)
+                            .total
+                            .saturating_sub(pair[0].line(kind).total)
+                    })
+                    .sum(),
+            }
+        };
+        Ok(Some(Summary {
+            samples,
+            some: line(StallKind::Some),
+            full: line(StallKind::Full),
+        }))
+    }
+
+    /// Delete buckets older than the retention period, returning how many were deleted
+    pub fn prune(&mut self) -> Result<usize, Error> {
+        let cutoff = millis(SystemTime::now()).saturating_sub(self.retention.as_millis() as i64);
+        Ok(self
+            .connection
+            .execute("DELETE FROM buckets WHERE start_ms < ?1", params![cutoff])?)
+    }
+}
+
+/// Milliseconds since the Unix epoch, negative before it
+fn millis(timestamp: SystemTime) -> i64 {
+    match timestamp.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    }
+}
+
+/// System-wide pressure is stored with an empty cgroup, as NULL would make every row distinct in the primary key
+fn cgroup_key(cgroup: Option<&Path>) -> String {
+    cgroup.map_or(String::new(), |cgroup| {
+        cgroup.to_string_lossy().into_owned()
+    })
+}
+
+/// Inclusive bounds of a range of timestamps in milliseconds
+fn bounds(range: impl RangeBounds<SystemTime>) -> (i64, i64) {
+    let from = match range.start_bound() {
+        Bound::Included(start) => millis(*start),
+        Bound::Excluded(start) => millis(*start).saturating_add(1),
+        Bound::Unbounded => i64::MIN,
+    };
+    let to = match range.end_bound() {
+        Bound::Included(end) => millis(*end),
+        Bound::Excluded(end) => millis(*end).saturating_sub(1),
+        Bound::Unbounded => i64::MAX,
+    };
+    (from, to)
+}
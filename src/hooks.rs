@@ -0,0 +1,207 @@
+use std::{
+    process::Command,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{CancellationToken, Error, Event, PressureMonitor, Severity, WaitOutcome};
+
+type Callback = dyn FnMut(&Event, Severity) + Send;
+type ErrorCallback = dyn FnMut(&Error) + Send;
+
+enum Action {
+    Callback(Box<Callback>),
+    Command(Command),
+}
+
+/// A reaction to pressure events, registered with a [HookRegistry]
+pub struct Hook {
+    action: Action,
+    min_severity: Severity,
+    cooldown: Duration,
+    timeout: Option<Duration>,
+    last_run: Option<Instant>,
+}
+
+impl Hook {
+    /// Minimum time between two runs of this hook, events during the cooldown are skipped
+    pub fn cooldown(&mut self, cooldown: Duration) -> &mut Self {
+        self.cooldown = cooldown;
+        self
+    }
+    /// How long a command may run before it is killed, by default it may run indefinitely. Callbacks always run to completion
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn run(&mut self, event: &Event, severity: Severity) -> Result<(), Error> {
+        if severity < self.min_severity
+            || self
+                .last_run
+                .is_some_and(|last_run| last_run.elapsed() < self.cooldown)
+        {
+            return Ok(());
+        }
+        self.last_run = Some(Instant::now());
+        let command = match &mut self.action {
+            Action::Callback(callback) => {
+                callback(event, severity);
+                return Ok(());
+            }
+            Action::Command(command) => command,
+        };
+        command
+            .env("PRESSURE_RESOURCE", event.resource.to_string())
+            .env("PRESSURE_SEVERITY", severity.to_string());
+        match &event.cgroup {
+            Some(cgroup) => command.env("PRESSURE_CGROUP", cgroup),
+            None => command.env_remove("PRESSURE_CGROUP"),
+        };
+        let mut child = command.spawn()?;
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                child.kill()?;
+                child.wait()?;
+                return Err(Error::HookTimedOut(self.timeout.unwrap_or_default()));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        if !status.success() {
+            return Err(Error::HookFailed(status));
+        }
+        Ok(())
+    }
+}
+
+/// Closures and external commands that react to pressure events, each only running from a minimum severity and at most once per cooldown
+///
+/// Commands receive the event through the PRESSURE_RESOURCE, PRESSURE_SEVERITY and, for cgroups, PRESSURE_CGROUP environment variables
+///
+/// ```no_run
+/// use pressure::{HookRegistry, PressureMonitor, Severity};
+/// use std::{process::Command, time::Duration};
+///
+/// let mut hooks = HookRegistry::new();
+/// hooks.on_event(Severity::Medium, |event, severity| eprintln!("{severity} pressure on {}", event.resource));
+/// hooks
+///     .on_event_exec(Severity::Critical, Command::new("/usr/local/bin/drop-caches"))
+///     .cooldown(Duration::from_secs(60))
+///     .timeout(Duration::from_secs(10));
+/// let runner = hooks.spawn(PressureMonitor::new()?)?;
+/// // ...
+/// runner.stop()?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Default)]
+pub struct HookRegistry {
+    hooks: Vec<Hook>,
+    on_error: Option<Box<ErrorCallback>>,
+    #[cfg(all(feature = "systemd", unix))]
+    notifier: Option<crate::ServiceNotifier>,
+}
+
+impl HookRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Call `callback` for events at which pressure is at least `min` severe
+    pub fn on_event(
+        &mut self,
+        min: Severity,
+        callback: impl FnMut(&Event, Severity) + Send + 'static,
+    ) -> &mut Hook {
+        self.register(Action::Callback(Box::new(callback)), min)
+    }
+    /// Run `command` for events at which pressure is at least `min` severe, waiting for it to exit
+    pub fn on_event_exec(&mut self, min: Severity, command: Command) -> &mut Hook {
+        self.register(Action::Command(command), min)
+    }
+    /// Call `callback` when a command fails, exits unsuccessfully or times out, which is otherwise ignored so that other hooks still run
+    pub fn on_error(&mut self, callback: impl FnMut(&Error) + Send + 'static) -> &mut Self {
+        self.on_error = Some(Box::new(callback));
+        self
+    }
+    /// Wait for events through `notifier` when [spawned](Self::spawn), so that the worker pings systemd's watchdog and reports pressure in the unit's status
+    #[cfg(all(feature = "systemd", unix))]
+    pub fn notify_service(&mut self, notifier: crate::ServiceNotifier) -> &mut Self {
+        self.notifier = Some(notifier);
+        self
+    }
+    fn register(&mut self, action: Action, min_severity: Severity) -> &mut Hook {
+        self.hooks.push(Hook {
+            action,
+            min_severity,
+            cooldown: Duration::ZERO,
+            timeout: None,
+            last_run: None,
+        });
+        self.hooks.last_mut().unwrap()
+    }
+    /// Run every hook matching the event's severity, in the order they were registered
+    pub fn dispatch(&mut self, event: &Event, severity: Severity) {
+        for hook in &mut self.hooks {
+            if let Err(e) = hook.run(event, severity)
+                && let Some(on_error) = &mut self.on_error
+            {
+                on_error(&e);
+            }
+        }
+    }
+    /// Wait for events on `monitor` on a worker thread, dispatching each of them to the hooks
+    pub fn spawn(mut self, mut monitor: PressureMonitor) -> Result<HookRunner, Error> {
+        let cancellation = monitor.cancellation_token()?;
+        let thread = std::thread::Builder::new()
+            .name("pressure-hooks".into())
+            .spawn(move || {
+                loop {
+                    #[cfg(all(feature = "systemd", unix))]
+                    let outcome = match &self.notifier {
+                        Some(notifier) => notifier.wait(&mut monitor)?,
+                        None => monitor.wait()?,
+                    };
+                    #[cfg(not(all(feature = "systemd", unix)))]
+                    let outcome = monitor.wait()?;
+                    match outcome {
+                        WaitOutcome::Triggered(event) => {
+                            let severity = monitor.severity()?;
+                            self.dispatch(&event, severity);
+                        }
+                        WaitOutcome::TimedOut | WaitOutcome::Cancelled => return Ok(()),
+                        WaitOutcome::Interrupted => {}
+                        WaitOutcome::Hangup => return Err(Error::Hangup),
+                        WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+                    }
+                }
+            })?;
+        Ok(HookRunner {
+            cancellation,
+            thread,
+        })
+    }
+}
+
+/// Worker thread dispatching events to a [HookRegistry], see [HookRegistry::spawn]
+pub struct HookRunner {
+    cancellation: CancellationToken,
+    thread: JoinHandle<Result<(), Error>>,
+}
+
+impl HookRunner {
+    /// Stop waiting for events once the hooks currently running have finished, returning the error that stopped the worker early, if any
+    pub fn stop(self) -> Result<(), Error> {
+        self.cancellation.cancel();
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+    /// Whether the worker stopped because waiting for events failed
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
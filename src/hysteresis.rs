@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use crate::PsiLine;
+
+/// Holds back events until pressure has been sustained, either for a number of consecutive firings or with avg10 staying above a threshold for a duration
+#[derive(Debug, Clone)]
+pub(crate) struct Hysteresis {
+    /// Firings required, and how long without a firing ends a streak
+    firings: Option<(u32, Duration)>,
+    /// Minimum avg10 and how long it must be held
+    avg10: Option<(f32, Duration)>,
+    streak: u32,
+    last_firing: Option<Instant>,
+    above_since: Option<Instant>,
+}
+
+impl Hysteresis {
+    pub(crate) fn new(
+        firings: Option<(u32, Duration)>,
+        avg10: Option<(f32, Duration)>,
+    ) -> Option<Self> {
+        (firings.is_some() || avg10.is_some()).then_some(Self {
+            firings,
+            avg10,
+            streak: 0,
+            last_firing: None,
+            above_since: None,
+        })
+    }
+
+    /// Whether [sustained](Self::sustained) needs the current pressure
+    pub(crate) fn reads_pressure(&self) -> bool {
+        self.avg10.is_some()
+    }
+
+    /// Record a firing, returning whether pressure has been sustained long enough for it to be surfaced
+    pub(crate) fn sustained(&mut self, pressure: Option<&PsiLine>) -> bool {
+        let now = Instant::now();
+        let mut sustained = true;
+        if let Some((required, gap)) = self.firings {
+            if self.last_firing.is_none_or(|last| now - last > gap) {
+                self.streak = 0;
+            }
+            self.streak = self.streak.saturating_add(1);
+            sustained &= self.streak >= required;
+        }
+        self.last_firing = Some(now);
+        if let (Some((threshold, duration)), Some(pressure)) = (self.avg10, pressure) {
+            if pressure.avg10 >= threshold {
+                let since = *self.above_since.get_or_insert(now);
+                sustained &= now - since >= duration;
+            } else {
+                self.above_since = None;
+                sustained = false;
+            }
+        }
+        sustained
+    }
+}
@@ -0,0 +1,119 @@
+use std::{ptr, time::Duration};
+
+use tikv_jemalloc_ctl::{background_thread, epoch, raw};
+
+use crate::{Error, HookRegistry, HookRunner, PressureMonitor, Severity};
+
+/// Purge the dirty pages of every jemalloc arena, returning them to the operating system. Returns how many bytes of dirty memory were released
+///
+/// This only affects the process' allocations if jemalloc is its global allocator, such as through the tikv-jemallocator crate
+pub fn jemalloc_purge_now() -> Result<usize, Error> {
+    let before = dirty_bytes()?;
+    // MALLCTL_ARENAS_ALL, the purge takes neither an input nor an output
+    let name = c"arena.4096.purge";
+    // SAFETY: the name is NUL-terminated, and purging reads and writes nothing through the pointers
+    let ret = unsafe {
+        tikv_jemalloc_sys::mallctl(
+            name.as_ptr(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::from_raw_os_error(ret).into());
+    }
+    Ok(before.saturating_sub(dirty_bytes()?))
+}
+
+/// Dirty memory across all arenas, which jemalloc keeps around to reuse instead of returning it to the operating system
+fn dirty_bytes() -> Result<usize, Error> {
+    // Statistics are only refreshed when the epoch is advanced
+    epoch::advance()?;
+    // SAFETY: both values are size_t
+    let (pages, page_size) = unsafe {
+        (
+            raw::read::<usize>(b"stats.arenas.4096.pdirty\0")?,
+            raw::read::<usize>(b"arenas.page\0")?,
+        )
+    };
+    Ok(pages * page_size)
+}
+
+/// Purges jemalloc's dirty pages on a worker thread whenever memory is under pressure, at most once per cooldown
+///
+/// ```no_run
+/// use pressure::{JemallocPurge, PressureMonitor, Severity};
+///
+/// let runner = JemallocPurge::new()
+///     .min_severity(Severity::Medium)
+///     .background_threads(true)
+///     .spawn_on(PressureMonitor::new()?, |released| match released {
+///         Ok(bytes) => eprintln!("returned {bytes} bytes to the system"),
+///         Err(e) => eprintln!("purging failed: {e}"),
+///     })?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct JemallocPurge {
+    cooldown: Duration,
+    min_severity: Severity,
+    background_threads: bool,
+}
+
+impl JemallocPurge {
+    pub fn new() -> Self {
+        Self {
+            cooldown: Duration::from_secs(10),
+            min_severity: Severity::Low,
+            background_threads: false,
+        }
+    }
+
+    /// Minimum time between two purges, defaults to 10 seconds
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Only purge for events at which pressure is at least this severe, defaults to [Severity::Low]
+    pub fn min_severity(mut self, min: Severity) -> Self {
+        self.min_severity = min;
+        self
+    }
+
+    /// Also enable jemalloc's background threads on the first event, so that dirty pages keep being purged asynchronously as they decay after pressure subsides
+    pub fn background_threads(mut self, enable: bool) -> Self {
+        self.background_threads = enable;
+        self
+    }
+
+    /// Purge on the events of `monitor`, passing how many bytes each purge released to `report`
+    pub fn spawn_on(
+        self,
+        monitor: PressureMonitor,
+        mut report: impl FnMut(Result<usize, Error>) + Send + 'static,
+    ) -> Result<HookRunner, Error> {
+        let mut enable_background_threads = self.background_threads;
+        let mut hooks = HookRegistry::new();
+        hooks
+            .on_event(self.min_severity, move |_, _| {
+                if enable_background_threads {
+                    enable_background_threads = false;
+                    if let Err(e) = background_thread::write(true) {
+                        report(Err(e.into()));
+                    }
+                }
+                report(jemalloc_purge_now());
+            })
+            .cooldown(self.cooldown);
+        hooks.spawn(monitor)
+    }
+}
+
+impl Default for JemallocPurge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,84 @@
+//! Resolving the cgroups kubelet places pods and their containers in, with either the systemd or cgroupfs cgroup driver
+//!
+//! ```no_run
+//! use pressure::{Resource, WaitOutcome, k8s};
+//!
+//! let mut monitor = k8s::pod_monitor("0f6a4bb4-1b34-4d4c-9b4f-5a1c4a3e8c7d", Resource::Memory)?;
+//! while let WaitOutcome::Triggered(event) = monitor.wait()? {
+//!     println!("memory pressure in {:?}", event.cgroup);
+//! }
+//! # Ok::<(), pressure::Error>(())
+//! ```
+use std::path::{Path, PathBuf};
+
+use crate::{Error, PressureMonitor, Resource, cgroup};
+
+/// Slices kubelet puts pods in with the systemd driver, the second one being used by kind and others running kubelet in its own slice
+const SYSTEMD_SLICES: [(&str, &str); 2] = [
+    ("kubepods.slice", "kubepods"),
+    ("kubelet.slice/kubelet-kubepods.slice", "kubelet-kubepods"),
+];
+
+/// Quality of service classes get their own cgroup below the pods' one, except for guaranteed pods which are placed directly in it
+const QOS_CLASSES: [Option<&str>; 3] = [None, Some("burstable"), Some("besteffort")];
+
+/// Resolve the cgroup of a pod from its UID, as found in `metadata.uid`
+pub fn pod_cgroup(pod_uid: &str) -> Result<PathBuf, Error> {
+    cgroup::check_id(pod_uid)?;
+    let root = cgroup::unified_root(None)?;
+    // The systemd driver escapes dashes in the UID, as they separate the levels of slices
+    let escaped = &pod_uid.replace('-', "_");
+    let systemd = SYSTEMD_SLICES.iter().flat_map(|(slice, prefix)| {
+        QOS_CLASSES.iter().map(move |qos| match qos {
+            Some(qos) => Path::new(slice)
+                .join(format!("{prefix}-{qos}.slice"))
+                .join(format!("{prefix}-{qos}-pod{escaped}.slice")),
+            None => Path::new(slice).join(format!("{prefix}-pod{escaped}.slice")),
+        })
+    });
+    let cgroupfs = QOS_CLASSES.iter().map(|qos| match qos {
+        Some(qos) => Path::new("kubepods")
+            .join(qos)
+            .join(format!("pod{pod_uid}")),
+        None => Path::new("kubepods").join(format!("pod{pod_uid}")),
+    });
+    systemd
+        .chain(cgroupfs)
+        .map(|path| root.join(path))
+        .find(|path| path.is_dir())
+        .ok_or_else(|| Error::CgroupNotFound(format!("pod{pod_uid}").into()))
+}
+
+/// Resolve the cgroup of a container in a pod, from the container's ID as found in `status.containerStatuses`, with or without the runtime's prefix such as `containerd://`
+pub fn container_cgroup(pod_uid: &str, container_id: &str) -> Result<PathBuf, Error> {
+    let pod = pod_cgroup(pod_uid)?;
+    let container_id = container_id
+        .split_once("://")
+        .map_or(container_id, |(_, id)| id);
+    cgroup::check_id(container_id)?;
+    // Scopes are named after the container runtime with the systemd driver
+    [
+        container_id.to_string(),
+        format!("cri-containerd-{container_id}.scope"),
+        format!("crio-{container_id}.scope"),
+        format!("docker-{container_id}.scope"),
+    ]
+    .into_iter()
+    .map(|name| pod.join(name))
+    .find(|path| path.is_dir())
+    .ok_or_else(|| Error::CgroupNotFound(pod.join(container_id)))
+}
+
+/// Create a monitor for a resource within a pod's cgroup, see [pod_cgroup]
+pub fn pod_monitor(pod_uid: &str, resource: Resource) -> Result<PressureMonitor, Error> {
+    PressureMonitor::for_cgroup(pod_cgroup(pod_uid)?, resource)
+}
+
+/// Create a monitor for a resource within a container's cgroup, see [container_cgroup]
+pub fn container_monitor(
+    pod_uid: &str,
+    container_id: &str,
+    resource: Resource,
+) -> Result<PressureMonitor, Error> {
+    PressureMonitor::for_cgroup(container_cgroup(pod_uid, container_id)?, resource)
+}
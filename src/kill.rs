@@ -0,0 +1,551 @@
+use std::{
+    io::ErrorKind,
+    os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    libc,
+    poll::{PollFd, PollFlags},
+    sys::signal::Signal,
+};
+
+use crate::{
+    CancellationToken, Error, PsiLine, PsiStats, Resource, StallKind, poll_timeout, procfs_root,
+};
+
+/// oom_score_adj of processes the OOM killer must never kill
+const OOM_SCORE_ADJ_MIN: i32 = -1000;
+
+/// A process or cgroup that can be killed by [KillPolicy]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Victim {
+    Process(i32),
+    Cgroup(PathBuf),
+}
+
+/// A victim considered by [KillPolicy], along with what it is ranked by. Processes with an oom_score_adj of -1000, and cgroups with such a process anywhere in them, are never considered
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Candidate {
+    pub victim: Victim,
+    /// Resident memory of a process, or memory.current of a cgroup, in bytes
+    pub memory: u64,
+    /// The process' oom_score_adj, or the highest one among the processes of a cgroup and its descendants
+    pub oom_score_adj: i32,
+}
+
+/// How [KillPolicy] picks a victim among the candidates
+pub enum VictimSelection {
+    /// The candidate using the most memory
+    LargestMemory,
+    /// The candidate with the highest oom_score_adj, using the most memory among those with the same value
+    OomScoreAdj,
+    /// A ranking callback, returning the index of the candidate to kill, or [None] to kill nothing
+    Custom(Box<RankFn>),
+}
+
+type RankFn = dyn FnMut(&[Candidate]) -> Option<usize> + Send;
+
+impl VictimSelection {
+    fn select(&mut self, candidates: &[Candidate]) -> Option<usize> {
+        let enumerated = candidates.iter().enumerate();
+        match self {
+            VictimSelection::LargestMemory => enumerated
+                .max_by_key(|(_, candidate)| candidate.memory)
+                .map(|(index, _)| index),
+            VictimSelection::OomScoreAdj => enumerated
+                .max_by_key(|(_, candidate)| (candidate.oom_score_adj, candidate.memory))
+                .map(|(index, _)| index),
+            VictimSelection::Custom(rank) => {
+                rank(candidates).filter(|&index| index < candidates.len())
+            }
+        }
+    }
+}
+
+/// What [KillPolicy] did once pressure was sustained
+#[derive(Debug, Clone, PartialEq)]
+pub struct KillReport {
+    pub victim: Candidate,
+    /// The pressure that caused the kill
+    pub pressure: PsiLine,
+    /// How long pressure stayed above the threshold
+    pub sustained: Duration,
+    /// Whether the victim was only selected, without being killed
+    pub dry_run: bool,
+    pub timestamp: Instant,
+}
+
+enum Scope {
+    /// Processes, optionally limited to a cgroup
+    Processes(Option<PathBuf>),
+    /// Child cgroups of a parent cgroup
+    Cgroups(PathBuf),
+}
+
+/// A userspace OOM killer in the spirit of oomd, which kills a process or cgroup once memory pressure stays above a threshold for a while,
+/// before the system thrashes for long enough for the kernel's OOM killer to step in
+///
+/// By default it acts on `some` memory pressure of at least 60% over 10 seconds, sustained for 30 seconds, and kills the process or cgroup using the most memory
+///
+/// ```no_run
+/// use pressure::{KillPolicy, VictimSelection};
+/// use std::time::Duration;
+///
+/// let mut policy = KillPolicy::cgroups("/sys/fs/cgroup/user.slice")
+///     .select(VictimSelection::OomScoreAdj)
+///     .dry_run(true);
+/// policy.run(Duration::from_secs(1), |report| eprintln!("would kill {:?}", report.victim))?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct KillPolicy {
+    scope: Scope,
+    kind: StallKind,
+    threshold: f32,
+    duration: Duration,
+    cooldown: Duration,
+    selection: VictimSelection,
+    dry_run: bool,
+    above_since: Option<Instant>,
+    last_kill: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    /// Where the processes that are ranked are described
+    procfs_root: PathBuf,
+}
+
+impl KillPolicy {
+    /// Act on system-wide memory pressure, killing a process
+    pub fn processes() -> Self {
+        Self::with_scope(Scope::Processes(None))
+    }
+    /// Act on memory pressure within a cgroup v2 hierarchy, killing one of its processes
+    pub fn processes_in(cgroup: impl AsRef<Path>) -> Self {
+        Self::with_scope(Scope::Processes(Some(cgroup.as_ref().to_path_buf())))
+    }
+    /// Act on memory pressure within a cgroup v2 hierarchy, killing one of its child cgroups
+    pub fn cgroups(parent: impl AsRef<Path>) -> Self {
+        Self::with_scope(Scope::Cgroups(parent.as_ref().to_path_buf()))
+    }
+    fn with_scope(scope: Scope) -> Self {
+        Self {
+            scope,
+            kind: StallKind::Some,
+            threshold: 60.0,
+            duration: Duration::from_secs(30),
+            cooldown: Duration::from_secs(15),
+            selection: VictimSelection::LargestMemory,
+            dry_run: false,
+            above_since: None,
+            last_kill: None,
+            cancellation: None,
+            procfs_root: procfs_root(),
+        }
+    }
+    /// Percentage of time stalled over the last 10 seconds at which pressure counts as too high
+    pub fn threshold(mut self, kind: StallKind, avg10: f32) -> Self {
+        self.kind = kind;
+        self.threshold = avg10;
+        self
+    }
+    /// How long pressure has to stay above the threshold before killing
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+    /// How long to wait after a kill before killing again, giving pressure time to drop once memory was freed
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+    pub fn select(mut self, selection: VictimSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+    /// Report the victim that would be killed, without killing it
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    /// Get a token that can be used to stop [run](Self::run) from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// List the victims that could currently be killed
+    pub fn candidates(&self) -> Result<Vec<Candidate>, Error> {
+        Ok(self
+            .scan()?
+            .into_iter()
+            .map(|(candidate, _)| candidate)
+            .collect())
+    }
+    /// List the victims that could currently be killed, along with the start time of processes, which tells them apart from later processes reusing their pid
+    fn scan(&self) -> Result<Vec<(Candidate, Option<u64>)>, Error> {
+        match &self.scope {
+            Scope::Processes(None) => {
+                let pids = std::fs::read_dir(&self.procfs_root)?
+                    .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok());
+                Ok(processes(&self.procfs_root, pids))
+            }
+            Scope::Processes(Some(cgroup)) => {
+                let pids = subtree_procs(cgroup)?.unwrap_or_default();
+                Ok(processes(&self.procfs_root, pids.into_iter()))
+            }
+            Scope::Cgroups(parent) => {
+                let mut candidates = Vec::new();
+                for entry in std::fs::read_dir(parent)? {
+                    let path = entry?.path();
+                    // Only directories with the memory controller enabled are child cgroups that can be ranked
+                    let Ok(memory) = std::fs::read_to_string(path.join("memory.current")) else {
+                        continue;
+                    };
+                    // Cgroups removed while scanning can't be killed anymore
+                    let Some(pids) = subtree_procs(&path)? else {
+                        continue;
+                    };
+                    let oom_score_adjs: Vec<i32> = pids
+                        .into_iter()
+                        .filter_map(|pid| oom_score_adj(&self.procfs_root, pid))
+                        .collect();
+                    // Killing the cgroup would kill its protected processes along with it
+                    if oom_score_adjs.contains(&OOM_SCORE_ADJ_MIN) {
+                        continue;
+                    }
+                    let candidate = Candidate {
+                        victim: Victim::Cgroup(path),
+                        memory: memory.trim().parse().unwrap_or_default(),
+                        oom_score_adj: oom_score_adjs.into_iter().max().unwrap_or_default(),
+                    };
+                    candidates.push((candidate, None));
+                }
+                Ok(candidates)
+            }
+        }
+    }
+    /// Check pressure once, killing a victim if it has stayed above the threshold for long enough
+    pub fn check(&mut self) -> Result<Option<KillReport>, Error> {
+        let path = match &self.scope {
+            Scope::Processes(None) => Resource::Memory.proc_path(),
+            Scope::Processes(Some(cgroup)) | Scope::Cgroups(cgroup) => {
+                cgroup.join(Resource::Memory.cgroup_file())
+            }
+        };
+        let pressure = *PsiStats::read_path(path)?.line(self.kind);
+        let now = Instant::now();
+        if pressure.avg10 < self.threshold {
+            self.above_since = None;
+            return Ok(None);
+        }
+        let above_since = *self.above_since.get_or_insert(now);
+        let cooling_down = self
+            .last_kill
+            .is_some_and(|last_kill| now.saturating_duration_since(last_kill) < self.cooldown);
+        if now - above_since < self.duration || cooling_down {
+            return Ok(None);
+        }
+        let (mut candidates, mut start_times): (Vec<_>, Vec<_>) = self.scan()?.into_iter().unzip();
+        let Some(index) = self.selection.select(&candidates) else {
+            return Ok(None);
+        };
+        let victim = candidates.swap_remove(index);
+        if !self.dry_run {
+            kill(
+                &victim.victim,
+                start_times.swap_remove(index),
+                &self.procfs_root,
+            )?;
+        }
+        self.last_kill = Some(now);
+        self.above_since = None;
+        Ok(Some(KillReport {
+            victim,
+            pressure,
+            sustained: now - above_since,
+            dry_run: self.dry_run,
+            timestamp: now,
+        }))
+    }
+    /// Check pressure every `interval`, passing the report of every kill to `report`, until cancelled through [cancellation_token](Self::cancellation_token)
+    pub fn run(
+        &mut self,
+        interval: Duration,
+        mut report: impl FnMut(&KillReport),
+    ) -> Result<(), Error> {
+        loop {
+            if let Some(cancellation) = &self.cancellation {
+                let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+                if nix::poll::poll(&mut fds, poll_timeout(interval))? > 0 {
+                    return Ok(());
+                }
+            } else {
+                std::thread::sleep(interval);
+            }
+            if let Some(kill) = self.check()? {
+                report(&kill);
+            }
+        }
+    }
+}
+
+/// Describe processes that can be killed, leaving out init, this process, processes protected from the OOM killer and kernel threads, which have no resident memory
+fn processes(procfs_root: &Path, pids: impl Iterator<Item = i32>) -> Vec<(Candidate, Option<u64>)> {
+    let own = std::process::id() as i32;
+    pids.filter(|&pid| pid != 1 && pid != own)
+        .filter_map(|pid| {
+            let candidate = Candidate {
+                victim: Victim::Process(pid),
+                memory: resident_memory(procfs_root, pid)?,
+                oom_score_adj: oom_score_adj(procfs_root, pid)
+                    .filter(|&adj| adj != OOM_SCORE_ADJ_MIN)?,
+            };
+            Some((candidate, Some(start_time(procfs_root, pid)?)))
+        })
+        .collect()
+}
+
+/// Pids of the processes in a cgroup and its descendants, or [None] if the cgroup was removed
+fn subtree_procs(cgroup: &Path) -> Result<Option<Vec<i32>>, Error> {
+    let mut pids = Vec::new();
+    let mut cgroups = vec![cgroup.to_path_buf()];
+    while let Some(current) = cgroups.pop() {
+        let procs = match std::fs::read_to_string(current.join("cgroup.procs")) {
+            Ok(procs) => procs,
+            Err(e) if vanished(&e) && current == cgroup => return Ok(None),
+            // A descendant removed while walking the cgroup took its processes with it
+            Err(e) if vanished(&e) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        pids.extend(procs.lines().filter_map(|pid| pid.parse::<i32>().ok()));
+        let entries = match std::fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) if vanished(&e) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                cgroups.push(entry.path());
+            }
+        }
+    }
+    Ok(Some(pids))
+}
+
+/// Whether reading a cgroup's files failed because it was removed, which reports ENODEV for files that were already open
+fn vanished(error: &std::io::Error) -> bool {
+    error.kind() == ErrorKind::NotFound || error.raw_os_error() == Some(libc::ENODEV)
+}
+
+fn resident_memory(procfs_root: &Path, pid: i32) -> Option<u64> {
+    let status = std::fs::read_to_string(procfs_root.join(format!("{pid}/status"))).ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+fn oom_score_adj(procfs_root: &Path, pid: i32) -> Option<i32> {
+    let value = std::fs::read_to_string(procfs_root.join(format!("{pid}/oom_score_adj"))).ok()?;
+    value.trim().parse().ok()
+}
+
+/// When a process started, in clock ticks since boot
+fn start_time(procfs_root: &Path, pid: i32) -> Option<u64> {
+    let stat = std::fs::read_to_string(procfs_root.join(format!("{pid}/stat"))).ok()?;
+    // The command name before the other fields is parenthesized, and can itself contain spaces and parentheses
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+fn kill(victim: &Victim, start_time: Option<u64>, procfs_root: &Path) -> Result<(), Error> {
+    match victim {
+        Victim::Process(pid) => kill_process(*pid, start_time, procfs_root),
+        Victim::Cgroup(path) => match std::fs::write(path.join("cgroup.kill"), "1") {
+            Ok(()) => Ok(()),
+            // The cgroup was removed since it was ranked
+            Err(e) if vanished(&e) && !path.exists() => Ok(()),
+            // cgroup.kill was added in Linux 5.14, before which processes are killed one by one
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                for pid in subtree_procs(path)?.unwrap_or_default() {
+                    if oom_score_adj(procfs_root, pid) != Some(OOM_SCORE_ADJ_MIN) {
+                        kill_process(pid, None, procfs_root)?;
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        },
+    }
+}
+
+/// Kill a process through a pidfd, so that the signal can't reach another process that reused its pid.
+/// Processes that exited, or whose pid was reused since `start_time` was read, are left alone
+fn kill_process(pid: i32, start_time: Option<u64>, procfs_root: &Path) -> Result<(), Error> {
+    // SAFETY: pidfd_open takes no pointers
+    let pidfd = match Errno::result(unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) }) {
+        Ok(pidfd) => pidfd,
+        // The process exited since it was ranked
+        Err(Errno::ESRCH) => return Ok(()),
+        // pidfd_open was added in Linux 5.3, before which processes are signalled by pid
+        Err(Errno::ENOSYS) => {
+            return match nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), Signal::SIGKILL) {
+                Ok(()) | Err(Errno::ESRCH) => Ok(()),
+                Err(e) => Err(e.into()),
+            };
+        }
+        Err(e) => return Err(e.into()),
+    };
+    // SAFETY: pidfd_open returned a new fd, owned by nothing else
+    let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd as i32) };
+    // The pidfd keeps referring to the process it was opened for, which is the ranked one unless the pid was reused since, giving it another start time
+    if start_time.is_some() && self::start_time(procfs_root, pid) != start_time {
+        return Ok(());
+    }
+    // SAFETY: pidfd_send_signal is passed a pidfd that stays open for the call and no siginfo
+    let sent = Errno::result(unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd.as_raw_fd(),
+            Signal::SIGKILL as libc::c_int,
+            std::ptr::null::<libc::siginfo_t>(),
+            0,
+        )
+    });
+    match sent {
+        // The process exited since the pidfd was opened
+        Ok(_) | Err(Errno::ESRCH) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of fixtures, with the given files written
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("pressure-kill-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (path, contents) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    fn policy(root: &Path) -> KillPolicy {
+        let mut policy = KillPolicy::cgroups(root.join("cgroup"));
+        policy.procfs_root = root.join("proc");
+        policy
+    }
+
+    #[test]
+    fn killing_an_exited_process_succeeds() {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id() as i32;
+        child.wait().unwrap();
+        kill(&Victim::Process(pid), None, Path::new("/proc")).unwrap();
+    }
+
+    #[test]
+    fn leaves_processes_that_reused_the_pid_alone() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+        let started = start_time(Path::new("/proc"), pid).unwrap();
+        kill(&Victim::Process(pid), Some(started + 1), Path::new("/proc")).unwrap();
+        assert_eq!(child.try_wait().unwrap(), None);
+        kill(&Victim::Process(pid), Some(started), Path::new("/proc")).unwrap();
+        assert!(!child.wait().unwrap().success());
+    }
+
+    #[test]
+    fn skips_processes_protected_from_the_oom_killer() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+        let protected = std::fs::write(format!("/proc/{pid}/oom_score_adj"), "-1000").is_ok();
+        let candidates = processes(Path::new("/proc"), std::iter::once(pid));
+        child.kill().unwrap();
+        child.wait().unwrap();
+        // Lowering oom_score_adj needs CAP_SYS_RESOURCE
+        if protected {
+            assert!(candidates.is_empty());
+        } else {
+            assert_eq!(candidates.len(), 1);
+        }
+    }
+
+    #[test]
+    fn skips_cgroups_with_protected_processes_among_their_descendants() {
+        let root = fixture(
+            "mixed",
+            &[
+                ("cgroup/mixed/memory.current", "4096"),
+                ("cgroup/mixed/cgroup.procs", ""),
+                ("cgroup/mixed/leaf/cgroup.procs", "100\n101\n"),
+                ("cgroup/nested/memory.current", "1024"),
+                ("cgroup/nested/cgroup.procs", ""),
+                ("cgroup/nested/leaf/cgroup.procs", "102\n"),
+                ("proc/100/oom_score_adj", "0"),
+                ("proc/101/oom_score_adj", "-1000"),
+                ("proc/102/oom_score_adj", "300"),
+            ],
+        );
+        let candidates = policy(&root).candidates().unwrap();
+        assert_eq!(
+            candidates,
+            [Candidate {
+                victim: Victim::Cgroup(root.join("cgroup/nested")),
+                memory: 1024,
+                oom_score_adj: 300,
+            }]
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn skips_cgroups_removed_while_scanning() {
+        // memory.current was read before the cgroup was removed, along with its cgroup.procs
+        let root = fixture(
+            "vanished",
+            &[
+                ("cgroup/gone/memory.current", "4096"),
+                ("cgroup/alive/memory.current", "1024"),
+                ("cgroup/alive/cgroup.procs", "100\n"),
+                ("proc/100/oom_score_adj", "0"),
+            ],
+        );
+        let candidates = policy(&root).candidates().unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].victim,
+            Victim::Cgroup(root.join("cgroup/alive"))
+        );
+        // Killing a cgroup that has since been removed has nothing left to do
+        kill(
+            &Victim::Cgroup(root.join("cgroup/removed")),
+            None,
+            &root.join("proc"),
+        )
+        .unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    sync::atomic::{AtomicU8, Ordering},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{CancellationToken, Error, PressureMonitor, Severity, WaitOutcome};
+
+/// How often a [LevelTracker] re-reads the severity between events
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+static LEVEL: AtomicU8 = AtomicU8::new(Severity::Low as u8);
+
+/// The latest severity published by a [LevelTracker] or [publish_level], or [Severity::Low] if none was published yet
+///
+/// This is a single relaxed atomic load, cheap enough for hot paths such as allocation or cache insertion
+pub fn current_level() -> Severity {
+    match LEVEL.load(Ordering::Relaxed) {
+        level if level == Severity::Critical as u8 => Severity::Critical,
+        level if level == Severity::Medium as u8 => Severity::Medium,
+        _ => Severity::Low,
+    }
+}
+
+/// Set the severity returned by [current_level], for applications that already classify pressure in their own event loop
+pub fn publish_level(severity: Severity) {
+    LEVEL.store(severity as u8, Ordering::Relaxed);
+}
+
+/// Keeps the process-global [current_level] up to date from a monitor on a worker thread
+///
+/// The severity is re-read on every event and every second, so it also drops once pressure subsides
+///
+/// ```no_run
+/// use pressure::{LevelTracker, PressureMonitor, Severity};
+///
+/// let tracker = LevelTracker::spawn(PressureMonitor::new()?)?;
+/// // On a hot path
+/// if pressure::current_level() >= Severity::Medium {
+///     // Skip caching the result
+/// }
+/// tracker.stop()?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct LevelTracker {
+    cancellation: CancellationToken,
+    thread: JoinHandle<Result<(), Error>>,
+}
+
+impl LevelTracker {
+    /// Publish the severity of `monitor` until stopped
+    pub fn spawn(mut monitor: PressureMonitor) -> Result<Self, Error> {
+        let cancellation = monitor.cancellation_token()?;
+        publish_level(monitor.severity()?);
+        let thread = std::thread::Builder::new()
+            .name("pressure-level".into())
+            .spawn(move || {
+                loop {
+                    match monitor.wait_timeout(REFRESH_INTERVAL)? {
+                        WaitOutcome::Triggered(_)
+                        | WaitOutcome::TimedOut
+                        | WaitOutcome::Interrupted => {}
+                        WaitOutcome::Cancelled => return Ok(()),
+                        WaitOutcome::Hangup => return Err(Error::Hangup),
+                        WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+                    }
+                    publish_level(monitor.severity()?);
+                }
+            })?;
+        Ok(Self {
+            cancellation,
+            thread,
+        })
+    }
+    /// Stop updating the level, which keeps its last value, returning the error that stopped the worker early, if any
+    pub fn stop(self) -> Result<(), Error> {
+        self.cancellation.cancel();
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+    /// Whether the worker stopped because waiting for events or reading the severity failed
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
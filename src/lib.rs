@@ -1,4 +1,4 @@
-//! Pressure monitoring library, using Pressure Stall Information (PSI) On Linux.
+//! Pressure monitoring library, using Pressure Stall Information (PSI) On Linux, the memory pressure dispatch source on macOS, free memory thresholds on FreeBSD and memory resource notifications on Windows.
 //! # Example:
 //! ```
 //! use pressure::PressureMonitor;
@@ -12,123 +12,1212 @@
 //!     });
 //! }
 //! ```
-#[cfg(not(target_os = "linux"))]
-compile_error!("pressure is only supported on Linux-based operating systems");
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd",
+    windows
+)))]
+compile_error!(
+    "pressure is only supported on Linux-based operating systems, macOS, FreeBSD and Windows"
+);
 
+#[cfg(unix)]
+mod activation;
+#[cfg(unix)]
+mod adaptive;
+pub mod aggregate;
+mod allocator;
+#[cfg(target_os = "android")]
+pub mod android;
+#[cfg(all(feature = "async-io", unix))]
+pub mod async_io;
+#[cfg(unix)]
+mod backend;
+#[cfg(all(feature = "test-util", any(target_os = "linux", target_os = "android")))]
+mod balloon;
+mod budget;
+#[cfg(unix)]
+mod builder;
+mod bus;
+#[cfg(all(feature = "calloop", unix))]
+pub mod calloop;
+mod cancel;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod capabilities;
+#[cfg(all(feature = "capi", unix))]
+mod capi;
+#[cfg(unix)]
+mod cgroup;
+#[cfg(unix)]
+mod clearing;
+mod clock;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod cold;
+mod compression;
+#[cfg(unix)]
+mod concurrency;
+#[cfg(all(feature = "containers", target_os = "linux"))]
+pub mod container;
+#[cfg(unix)]
+mod control;
+#[cfg(all(feature = "dbus", unix))]
+mod dbus;
+#[cfg(unix)]
+mod debounce;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod drop_caches;
+#[cfg(unix)]
+mod encoding;
+#[cfg(unix)]
+mod env;
+mod event;
+#[cfg(unix)]
+mod event_log;
+#[cfg(unix)]
+mod export;
+#[cfg(unix)]
+mod forward;
+#[cfg(target_os = "freebsd")]
+mod freebsd;
+#[cfg(unix)]
+mod gate;
+#[cfg(all(feature = "glib", unix))]
+pub mod glib;
+mod history;
+#[cfg(all(feature = "sqlite", unix))]
+mod history_store;
+mod hooks;
+#[cfg(unix)]
+mod hysteresis;
+#[cfg(all(feature = "jemalloc", unix))]
+mod jemalloc;
+#[cfg(all(feature = "k8s", target_os = "linux"))]
+pub mod k8s;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod kill;
+mod level;
+#[cfg(feature = "lru")]
+mod lru;
+#[cfg(target_os = "macos")]
+mod macos;
+mod meminfo;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod memory_events;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mimalloc")]
+mod mimalloc;
+#[cfg(all(feature = "mio", unix))]
+mod mio;
+#[cfg(all(feature = "test-util", unix))]
+mod mock;
+#[cfg(all(feature = "otel", unix))]
+mod otel;
+#[cfg(unix)]
+mod poll_set;
+#[cfg(unix)]
+mod polling;
+#[cfg(unix)]
+mod producer;
+#[cfg(all(feature = "prometheus", unix))]
+mod prometheus;
+mod proportional;
+#[cfg(all(feature = "python", unix))]
+mod python;
+mod rate;
+#[cfg(all(feature = "async", unix))]
+mod reactor;
+#[cfg(all(any(feature = "async", feature = "calloop"), unix))]
+mod readiness;
+mod reaper;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod reclaim;
+#[cfg(unix)]
+mod reconnect;
+#[cfg(unix)]
+mod report;
+#[cfg(unix)]
+mod rotation;
+#[cfg(unix)]
+mod sampler;
+#[cfg(unix)]
+mod semaphore;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod set;
+mod severity;
+mod shared;
+pub mod shrink;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod squeeze;
+mod stats;
+#[cfg(unix)]
+mod supervisor;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod system;
+#[cfg(all(feature = "systemd", unix))]
+mod systemd;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod thrashing;
+#[cfg(unix)]
+mod throttle;
+#[cfg(all(feature = "tokio", unix))]
+pub mod tokio;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod tree;
+mod trigger;
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod trim;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+mod uring;
+#[cfg(windows)]
+mod windows;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod working_set;
+
+use std::{env::VarError, path::PathBuf, time::Duration};
+#[cfg(unix)]
 use std::{
-    env::VarError,
     io::Write,
     os::{
-        fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
+        fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
         unix::{fs::FileTypeExt, net::UnixStream},
     },
+    path::Path,
+    time::Instant,
 };
 
-use base64::Engine;
+#[cfg(unix)]
+use adaptive::Adaptive;
+#[cfg(unix)]
 use nix::{
     errno::Errno,
-    poll::{PollFd, PollFlags, PollTimeout},
+    fcntl::{FcntlArg, OFlag},
+    poll::{PollFlags, PollTimeout},
+    sys::stat::SFlag,
 };
-use thiserror::Error;
+#[cfg(unix)]
+use poll_set::PollSet;
+#[cfg(unix)]
+use polling::{Paused, Poller};
+#[cfg(unix)]
+use reconnect::Reconnect;
+
+pub use allocator::PressureAllocator;
+#[cfg(unix)]
+pub use backend::PressureBackend;
+#[cfg(all(feature = "test-util", any(target_os = "linux", target_os = "android")))]
+pub use balloon::{MemoryBalloon, MemoryBalloonBuilder};
+pub use budget::{BudgetReport, ReclaimBudget};
+#[cfg(unix)]
+pub use builder::{ConfigSource, PressureMonitorBuilder};
+pub use bus::PressureBus;
+pub use cancel::CancellationToken;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use capabilities::{Capabilities, capabilities};
+pub use clock::ClockTimestamp;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use cold::{Advice, ColdRegions, RegionId};
+pub use compression::{CompressionContext, CompressionPressure, ZramStats, ZswapStats};
+#[cfg(unix)]
+pub use concurrency::{ConcurrencyController, ConcurrencyTarget};
+#[cfg(unix)]
+pub use control::ControlSocket;
+#[cfg(all(feature = "dbus", unix))]
+pub use dbus::LowMemoryWarningEmitter;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use drop_caches::{Caches, DropCaches};
+#[cfg(unix)]
+use event::Target;
+pub use event::{Event, Transition};
+#[cfg(unix)]
+pub use event_log::EventLogger;
+#[cfg(unix)]
+pub use export::{ExportFormat, SampleExporter};
+#[cfg(unix)]
+pub use forward::PressureForwarder;
+#[cfg(unix)]
+pub use gate::{PressureGate, WaitOpen};
+pub use history::History;
+#[cfg(all(feature = "sqlite", unix))]
+pub use history_store::{Bucket, BucketLine, HistoryStore, Summary, SummaryLine};
+pub use hooks::{Hook, HookRegistry, HookRunner};
+#[cfg(all(feature = "jemalloc", unix))]
+pub use jemalloc::{JemallocPurge, jemalloc_purge_now};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use kill::{Candidate, KillPolicy, KillReport, Victim, VictimSelection};
+pub use level::{LevelTracker, current_level, publish_level};
+#[cfg(feature = "lru")]
+pub use lru::PressureLru;
+pub use meminfo::MemoryContext;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use memory_events::{MemoryEvent, MemoryEventCounters, MemoryEventKind, MemoryEventsWatcher};
+#[cfg(feature = "mimalloc")]
+pub use mimalloc::{MimallocCollect, mimalloc_collect_now};
+#[cfg(all(feature = "test-util", unix))]
+pub use mock::{MockHandle, MockPressureMonitor};
+#[cfg(all(feature = "otel", unix))]
+pub use otel::PressureInstruments;
+#[cfg(unix)]
+pub use producer::WatchEndpoint;
+#[cfg(all(feature = "prometheus", unix))]
+pub use prometheus::{MetricsSampler, PressureMetrics};
+pub use proportional::ReclaimController;
+pub use rate::EventRate;
+#[cfg(all(feature = "async", unix))]
+pub use reactor::{AsyncPressureMonitor, AsyncPressureStream, Reactor, Wait};
+pub use reaper::{DirCacheReaper, ReapReport};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use reclaim::{ReclaimDriver, ReclaimOutcome};
+#[cfg(unix)]
+pub use report::{HostPressureReport, ReportEntry};
+#[cfg(unix)]
+pub use sampler::Sampler;
+#[cfg(unix)]
+pub use semaphore::{PressurePermit, PressureSemaphore};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use set::{MonitorKey, MonitorMut, MonitorSet};
+pub use severity::{Severity, SeverityThresholds};
+pub use shared::SharedPressureMonitor;
+pub use shrink::Shrinkable;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use squeeze::{Mappings, SqueezePolicy, SqueezeReport, Squeezer};
+pub use stats::{PsiDelta, PsiLine, PsiStats, parse_psi};
+#[cfg(unix)]
+pub use supervisor::PressureSupervisor;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use system::{Resources, SystemPressureMonitor};
+#[cfg(all(feature = "systemd", unix))]
+pub use systemd::ServiceNotifier;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use thrashing::{Thrashing, ThrashingDetector};
+#[cfg(unix)]
+pub use throttle::{IoThrottle, ThrottledWriter};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use tree::CgroupTreeMonitor;
+pub use trigger::{StallKind, Trigger};
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+pub use trim::{AutoTrim, malloc_trim_now};
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+pub use uring::UringMonitorSet;
+#[cfg(windows)]
+pub use windows::PressureMonitor;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use working_set::WorkingSetEstimator;
 
-#[derive(Error, Debug)]
+#[derive(Debug)]
+#[cfg_attr(feature = "thiserror", derive(thiserror::Error))]
 pub enum Error {
-    #[error("nix error: {0}")]
-    Nix(#[from] nix::Error),
-    #[error("io error: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("{0}")]
-    VarError(#[from] VarError),
-    #[error("expected regular file, fifo or socket, got something else")]
+    #[cfg(unix)]
+    #[cfg_attr(feature = "thiserror", error("nix error: {0}"))]
+    Nix(#[cfg_attr(feature = "thiserror", from)] nix::Error),
+    #[cfg_attr(feature = "thiserror", error("io error: {0}"))]
+    Io(#[cfg_attr(feature = "thiserror", from)] std::io::Error),
+    #[cfg_attr(feature = "thiserror", error("{0}"))]
+    VarError(#[cfg_attr(feature = "thiserror", from)] VarError),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("expected regular file, fifo or socket, got something else")
+    )]
     UnexpectedFileType,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("{0} pressure information is not available on this system")
+    )]
+    ResourceUnavailable(Resource),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("trigger window must be between 500ms and 10s, got {0:?}")
+    )]
+    InvalidWindow(Duration),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("stall threshold must be non-zero and no longer than the window, got {0:?}")
+    )]
+    InvalidThreshold(Duration),
+    #[cfg_attr(feature = "thiserror", error("{0} pressure does not track {1} stalls"))]
+    UnsupportedStallKind(Resource, StallKind),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("process is not part of a cgroup v2 hierarchy")
+    )]
+    NoUnifiedCgroup,
+    #[cfg_attr(feature = "thiserror", error("malformed pressure stall information"))]
+    MalformedStats,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("malformed memory.events, expected `<counter> <value>` lines")
+    )]
+    MalformedMemoryEvents,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("malformed /proc/meminfo or /proc/vmstat")
+    )]
+    MalformedMemoryInfo,
+    #[cfg_attr(feature = "thiserror", error("hook command did not exit within {0:?}"))]
+    HookTimedOut(Duration),
+    #[cfg_attr(feature = "thiserror", error("hook command failed: {0}"))]
+    HookFailed(std::process::ExitStatus),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("malformed memory limit or usage, expected a byte count or `max`")
+    )]
+    MalformedMemoryValue,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("malformed trigger, expected `<some|full> <threshold in us> <window in us>`")
+    )]
+    MalformedTrigger,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("environment variable {0} is not valid unicode")
+    )]
+    NonUtf8Env(&'static str),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("MEMORY_PRESSURE_WATCH must be an absolute path, got {0:?}")
+    )]
+    InvalidPath(PathBuf),
+    #[cfg_attr(feature = "thiserror", error("invalid MEMORY_PRESSURE_WRITE: {0}"))]
+    InvalidWrite(String),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("monitor has no file descriptor that signals pressure events")
+    )]
+    NoFileDescriptor,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("monitor was created from a file descriptor and has no path to reopen")
+    )]
+    NoPath,
+    #[cfg_attr(feature = "thiserror", error("monitor is paused"))]
+    Paused,
+    #[cfg_attr(feature = "thiserror", error("shared monitor stopped: {0}"))]
+    MonitorStopped(String),
+    #[cfg_attr(feature = "thiserror", error("unknown command {0:?}"))]
+    UnknownCommand(String),
+    #[cfg_attr(feature = "thiserror", error("monitor's file descriptor was hung up"))]
+    Hangup,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("poll reported an error condition on the monitor's file descriptor, revents {0:#x}")
+    )]
+    PollError(i16),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("pressure stall information is disabled, boot with psi=1 to enable it")
+    )]
+    PsiDisabled,
+    #[cfg_attr(
+        feature = "thiserror",
+        error(
+            "permission denied opening {path:?}, registering triggers requires CAP_SYS_RESOURCE before Linux 6.5"
+        )
+    )]
+    PermissionDenied { path: PathBuf },
+    #[cfg_attr(
+        feature = "thiserror",
+        error(
+            "trigger `{trigger}` was rejected, unprivileged triggers require a window that is a multiple of 2s"
+        )
+    )]
+    TriggerRejected { trigger: Trigger },
+    #[cfg_attr(feature = "thiserror", error("cgroup {0:?} does not exist"))]
+    CgroupNotFound(PathBuf),
+    #[cfg_attr(feature = "thiserror", error("invalid pod or container ID {0:?}"))]
+    InvalidId(String),
+    #[cfg_attr(
+        feature = "thiserror",
+        error("container ID {0:?} matches more than one container")
+    )]
+    AmbiguousId(String),
+    #[cfg_attr(feature = "thiserror", error("dropping caches was not enabled"))]
+    DropCachesDisabled,
+    #[cfg_attr(
+        feature = "thiserror",
+        error("dropping caches requires running as root")
+    )]
+    NotRoot,
+    #[cfg(feature = "dbus")]
+    #[cfg_attr(feature = "thiserror", error("D-Bus error: {0}"))]
+    DBus(#[cfg_attr(feature = "thiserror", from)] zbus::Error),
+    #[cfg(all(feature = "jemalloc", unix))]
+    #[cfg_attr(feature = "thiserror", error("jemalloc error: {0}"))]
+    Jemalloc(#[cfg_attr(feature = "thiserror", from)] tikv_jemalloc_ctl::Error),
+    #[cfg(feature = "prometheus")]
+    #[cfg_attr(feature = "thiserror", error("prometheus error: {0}"))]
+    Prometheus(#[cfg_attr(feature = "thiserror", from)] ::prometheus::Error),
+    #[cfg(all(feature = "tonic", unix))]
+    #[cfg_attr(feature = "thiserror", error("gRPC transport error: {0}"))]
+    Grpc(#[cfg_attr(feature = "thiserror", from)] tonic::transport::Error),
+    #[cfg(all(feature = "sqlite", unix))]
+    #[cfg_attr(feature = "thiserror", error("SQLite error: {0}"))]
+    Sqlite(#[cfg_attr(feature = "thiserror", from)] rusqlite::Error),
+}
+
+#[cfg(not(feature = "thiserror"))]
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(not(feature = "thiserror"))]
+impl std::error::Error for Error {}
+
+#[cfg(all(not(feature = "thiserror"), unix))]
+impl From<nix::Error> for Error {
+    fn from(e: nix::Error) -> Self {
+        Error::Nix(e)
+    }
+}
+
+#[cfg(not(feature = "thiserror"))]
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(not(feature = "thiserror"))]
+impl From<VarError> for Error {
+    fn from(e: VarError) -> Self {
+        Error::VarError(e)
+    }
+}
+
+/// A resource tracked by pressure stall information
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Resource {
+    Memory,
+    Cpu,
+    Io,
+    /// Time spent servicing interrupts, only available on Linux 6.1 and later with CONFIG_IRQ_TIME_ACCOUNTING enabled
+    Irq,
+}
+
+impl Resource {
+    /// Path to the system-wide pressure file for this resource, see [procfs_root]
+    pub(crate) fn proc_path(self) -> PathBuf {
+        self.proc_path_in(&procfs_root())
+    }
+
+    /// Path to the system-wide pressure file for this resource within procfs mounted at `procfs_root`
+    pub(crate) fn proc_path_in(self, procfs_root: &std::path::Path) -> PathBuf {
+        procfs_root.join("pressure").join(self.to_string())
+    }
+
+    /// Name of the pressure file for this resource within a cgroup
+    #[cfg(unix)]
+    pub(crate) fn cgroup_file(self) -> &'static str {
+        match self {
+            Resource::Memory => "memory.pressure",
+            Resource::Cpu => "cpu.pressure",
+            Resource::Io => "io.pressure",
+            Resource::Irq => "irq.pressure",
+        }
+    }
+
+    /// Check whether the running system reports pressure for this resource
+    pub fn is_available(self) -> bool {
+        #[cfg(any(target_os = "macos", target_os = "freebsd", windows))]
+        return self == Resource::Memory;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        return self.proc_path().exists();
+    }
+}
+
+/// Where procfs is mounted, `/proc` unless overridden with the `PRESSURE_PROCFS_ROOT` environment variable, for example in containers mounting it elsewhere
+pub(crate) fn procfs_root() -> PathBuf {
+    std::env::var_os("PRESSURE_PROCFS_ROOT")
+        .filter(|root| !root.is_empty())
+        .map_or_else(|| PathBuf::from("/proc"), PathBuf::from)
 }
 
+impl std::fmt::Display for Resource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Resource::Memory => "memory",
+            Resource::Cpu => "cpu",
+            Resource::Io => "io",
+            Resource::Irq => "irq",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(unix)]
 /// Represents a pressure monitor that can be used to wait for memory pressure events
 pub struct PressureMonitor {
-    pressure_file: MonitorType,
+    source: Source,
+    target: Target,
+    adaptive: Option<Adaptive>,
+    /// Reopens the monitor's path once its fd hung up, see [PressureMonitorBuilder::reconnect]
+    reconnect: Option<Reconnect>,
+    /// Triggers registered in addition to the main one, see [PressureMonitorBuilder::additional_trigger]
+    additional: Vec<(Trigger, OwnedFd)>,
+    /// Kernel pressure file additional triggers are registered with, which is read while paused, or [None] without a path to reopen
+    pressure_path: Option<PathBuf>,
+    cancellation: Option<CancellationToken>,
+    /// Whether signals end waits with [WaitOutcome::Interrupted], see [PressureMonitorBuilder::interruptible]
+    interruptible: bool,
+    poll_set: PollSet,
 }
 
+#[cfg(unix)]
 impl PressureMonitor {
+    /// Create a monitor for memory pressure, honoring systemd's memory pressure interface if present
     pub fn new() -> Result<Self, Error> {
-        let pressure_file = init_monitor()?;
-        Ok(Self { pressure_file })
+        Self::with_resource(Resource::Memory)
+    }
+    /// Create a monitor for the given resource
+    pub fn with_resource(resource: Resource) -> Result<Self, Error> {
+        Self::builder().resource(resource).build()
+    }
+    /// Create a monitor for a resource within a cgroup v2 hierarchy, for example `/sys/fs/cgroup/system.slice`
+    pub fn for_cgroup(cgroup: impl AsRef<Path>, resource: Resource) -> Result<Self, Error> {
+        Self::builder()
+            .resource(resource)
+            .cgroup(cgroup.as_ref())
+            .build()
+    }
+    /// Create a monitor for a resource within the cgroup the calling process belongs to
+    pub fn for_current_cgroup(resource: Resource) -> Result<Self, Error> {
+        Self::builder().resource(resource).current_cgroup().build()
+    }
+    /// Create a monitor for an explicit pressure file, fifo or socket, such as a bind-mounted pressure file or one used in tests.
+    /// Events are described as memory pressure, use [PressureMonitorBuilder::path] to monitor other resources
+    pub fn from_path(path: impl AsRef<Path>, trigger: Trigger) -> Result<Self, Error> {
+        Self::builder().path(path.as_ref()).trigger(trigger).build()
+    }
+    /// Create a monitor from an already opened pressure file, fifo or connected socket, for example one handed over by a supervisor.
+    /// No trigger is written, so it must already have been registered. Events are described as memory pressure
+    pub fn from_fd(fd: OwnedFd) -> Result<Self, Error> {
+        Ok(Self {
+            source: Source::Trigger(monitor_from_fd(fd)?),
+            target: Target::for_fd(),
+            adaptive: None,
+            reconnect: None,
+            additional: Vec::new(),
+            pressure_path: None,
+            cancellation: None,
+            interruptible: false,
+            poll_set: PollSet::default(),
+        })
+    }
+    /// Create a monitor waiting on a custom source of pressure events, with events describing the given resource
+    pub fn from_backend(backend: impl PressureBackend + 'static, resource: Resource) -> Self {
+        Self {
+            source: Source::Backend(Box::new(backend)),
+            target: Target::for_backend(resource),
+            adaptive: None,
+            reconnect: None,
+            additional: Vec::new(),
+            pressure_path: None,
+            cancellation: None,
+            interruptible: false,
+            poll_set: PollSet::default(),
+        }
+    }
+    /// Create a monitor for system-wide memory pressure as reported by low-memory-monitor's LowMemoryWarning signal on the system bus,
+    /// for processes that can't access pressure files. Every warning is an event, and [severity](Self::severity) reflects the level of the last one
+    #[cfg(feature = "dbus")]
+    pub fn low_memory_monitor() -> Result<Self, Error> {
+        Ok(Self::from_backend(
+            dbus::LowMemoryMonitorBackend::new()?,
+            Resource::Memory,
+        ))
+    }
+    /// Create a monitor from a file descriptor passed through socket activation, identified by the FileDescriptorName= of the socket unit.
+    /// Returns [None] if no file descriptor with that name was passed to this process
+    pub fn from_listen_fd(name: &str) -> Result<Option<Self>, Error> {
+        activation::listen_fd(name)?.map(Self::from_fd).transpose()
+    }
+    /// Create a [Sampler] that periodically reads pressure for a resource instead of waiting for trigger events
+    pub fn sampler(resource: Resource, interval: Duration) -> Result<Sampler, Error> {
+        Sampler::new(resource, interval)
+    }
+    /// Configure a monitor, see [PressureMonitorBuilder]
+    pub fn builder() -> PressureMonitorBuilder {
+        PressureMonitorBuilder::new()
+    }
+    pub fn resource(&self) -> Resource {
+        self.target.resource
+    }
+    /// The cgroup being monitored, or [None] for system-wide pressure
+    pub fn cgroup(&self) -> Option<&Path> {
+        self.target.cgroup()
+    }
+    /// Whether the monitor was configured explicitly or through systemd's memory pressure interface, see [PressureMonitorBuilder::prefer_environment]
+    pub fn config_source(&self) -> ConfigSource {
+        self.target.config
     }
-    /// Wait for a single pressure event to occur.
+    /// The trigger the monitor is currently armed with, which changes over time in adaptive mode, see [PressureMonitorBuilder::adaptive]
+    pub fn trigger(&self) -> Option<Trigger> {
+        self.target.trigger
+    }
+    /// Triggers registered in addition to the main one, including those to register again once paused, see [PressureMonitorBuilder::additional_trigger]
+    pub fn additional_triggers(&self) -> impl Iterator<Item = Trigger> {
+        let paused = match &self.source {
+            Source::Paused(paused) => paused.additional.as_slice(),
+            _ => &[],
+        };
+        self.additional
+            .iter()
+            .map(|(trigger, _)| *trigger)
+            .chain(paused.iter().copied())
+    }
+    /// Replace the monitor's trigger, reopening the pressure file or reconnecting to the service manager's socket.
+    /// In adaptive mode the new trigger becomes the base threshold. Monitors that fell back to polling or use a custom backend have no trigger to replace
+    pub fn set_trigger(&mut self, trigger: Trigger) -> Result<(), Error> {
+        match self.source {
+            Source::Trigger(_) => {}
+            Source::Polling(_) | Source::Backend(_) => return Err(Error::NoFileDescriptor),
+            Source::Paused(_) => return Err(Error::Paused),
+        }
+        if let Some(adaptive) = &mut self.adaptive {
+            adaptive.rebase(trigger)?;
+        }
+        self.source = Source::Trigger(self.target.reopen(trigger)?);
+        Ok(())
+    }
+    /// Close the monitor's fds, unregistering its triggers from the kernel until [resume](Self::resume) registers them again.
+    /// While paused, waits only return once they time out or are cancelled, and stats are read from the kernel's pressure file.
+    /// Pausing and resuming replace the monitor's fd, so event loops it was registered with must register it again afterwards,
+    /// see `MonitorSet::get_mut` for monitors in a set. While paused its fd never becomes ready.
+    /// Monitors that fell back to polling or use a custom backend have no trigger to unregister, and those created from a file descriptor couldn't be resumed
+    pub fn pause(&mut self) -> Result<(), Error> {
+        match self.source {
+            Source::Trigger(_) => {}
+            Source::Polling(_) | Source::Backend(_) => return Err(Error::NoFileDescriptor),
+            Source::Paused(_) => return Ok(()),
+        }
+        self.target.path()?;
+        let pressure_path = self.pressure_path.as_deref().ok_or(Error::NoPath)?;
+        let additional = self.additional_triggers().collect();
+        self.source = Source::Paused(Paused::new(pressure_path, additional)?);
+        self.additional.clear();
+        // A pending reconnect is made by resuming instead
+        if let Some(reconnect) = &mut self.reconnect {
+            reconnect.succeeded();
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(path = ?self.target.path, "paused pressure monitor");
+        Ok(())
+    }
+    /// Register the triggers of a paused monitor again, reopening its pressure file or reconnecting to the service manager's socket.
+    /// Events that would have occurred while paused aren't delivered. The monitor stays paused if reopening fails
+    pub fn resume(&mut self) -> Result<(), Error> {
+        let Source::Paused(paused) = &self.source else {
+            return Ok(());
+        };
+        let pressure_file = self.target.reconnect()?;
+        let pressure_path = self.pressure_path.as_deref().ok_or(Error::NoPath)?;
+        self.additional = paused
+            .additional
+            .iter()
+            .map(|&trigger| Ok((trigger, open_additional(pressure_path, trigger)?)))
+            .collect::<Result<_, Error>>()?;
+        self.source = Source::Trigger(pressure_file);
+        #[cfg(feature = "tracing")]
+        tracing::info!(path = ?self.target.path, "resumed pressure monitor");
+        Ok(())
+    }
+    pub fn is_paused(&self) -> bool {
+        matches!(self.source, Source::Paused(_))
+    }
+    /// Read the current pressure stall information for the monitored resource
+    pub fn stats(&self) -> Result<PsiStats, Error> {
+        self.source.backend().stats(self.target.resource)
+    }
+    /// Classify the current pressure on the monitored resource, see [PressureMonitorBuilder::severity_thresholds]
+    pub fn severity(&self) -> Result<Severity, Error> {
+        match self.source.backend().severity() {
+            Some(severity) => Ok(severity),
+            None => Ok(self.target.classify(&self.stats()?)),
+        }
+    }
+    /// Map current memory pressure onto an Android trim level, see [TrimLevel::from_stats](android::TrimLevel::from_stats)
+    #[cfg(target_os = "android")]
+    pub fn trim_level(&self) -> Result<Option<android::TrimLevel>, Error> {
+        Ok(android::TrimLevel::from_stats(&self.stats()?))
+    }
+    /// Whether the monitor fell back to polling because a trigger couldn't be registered, see [PressureMonitorBuilder::fallback_to_polling]
+    pub fn is_polling(&self) -> bool {
+        matches!(self.source, Source::Polling(_))
+    }
+    /// How often the monitor delivered events
+    pub fn event_rate(&self) -> EventRate {
+        self.target.events.rate()
+    }
+    /// Get a token that can be used to cancel waits on this monitor from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Wait for a single pressure event to occur, returning either [WaitOutcome::Triggered] with the [Event] or [WaitOutcome::Cancelled],
+    /// or [WaitOutcome::Hangup] or [WaitOutcome::Error] once the monitor's fd died and no more events can be received.
     /// It is safe to call this function in a busy loop, as even if memory pressure persists the kernel limits the amount of events sent
-    pub fn wait(&mut self) -> Result<(), Error> {
-        let (pollflag, needs_read) = match &self.pressure_file {
-            MonitorType::File(_) => (PollFlags::POLLPRI, false),
-            MonitorType::Fifo(_) | MonitorType::Socket(_) => (PollFlags::POLLIN, true),
+    pub fn wait(&mut self) -> Result<WaitOutcome, Error> {
+        self.wait_until(None)
+    }
+    /// Wait for a single pressure event to occur, giving up once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<WaitOutcome, Error> {
+        self.wait_until(Some(timeout))
+    }
+    /// Wait for a pressure event at which pressure is at least `min` severe, returning [None] if the wait was cancelled or interrupted
+    pub fn wait_for_severity(&mut self, min: Severity) -> Result<Option<(Event, Severity)>, Error> {
+        loop {
+            let event = match self.wait()? {
+                WaitOutcome::Triggered(event) => event,
+                WaitOutcome::TimedOut | WaitOutcome::Cancelled | WaitOutcome::Interrupted => {
+                    return Ok(None);
+                }
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            };
+            let severity = self.severity()?;
+            if severity >= min {
+                return Ok(Some((event, severity)));
+            }
+        }
+    }
+    /// Check whether a pressure event is pending without blocking, consuming and returning it if so
+    pub fn try_wait(&mut self) -> Result<Option<Event>, Error> {
+        if self.is_paused() {
+            return Ok(None);
+        }
+        // Triggers go through the full wait, which also handles adaptive mode and additional triggers
+        if !matches!(self.source, Source::Trigger(_)) {
+            if !self.source.backend_mut().try_wait()? {
+                return self.woken();
+            }
+            let event = self.target.event();
+            return self.fired(event);
+        }
+        match self.wait_until(Some(Duration::ZERO))? {
+            WaitOutcome::Triggered(event) => Ok(Some(event)),
+            WaitOutcome::TimedOut | WaitOutcome::Cancelled | WaitOutcome::Interrupted => Ok(None),
+            WaitOutcome::Hangup => Err(Error::Hangup),
+            WaitOutcome::Error(revents) => Err(Error::PollError(revents)),
+        }
+    }
+    /// Readiness the monitor's fd signals pressure events with when registering it with an external event loop, or [None] if it fell back to polling.
+    /// Once the fd is reported ready, call [acknowledge](Self::acknowledge) before waiting again
+    pub fn poll_interest(&self) -> Option<PollInterest> {
+        self.source.backend().poll_interest()
+    }
+    /// Take ownership of the monitor's fd, for use with an external event loop. Additional triggers are unregistered.
+    /// Monitors that fell back to polling have no fd that signals events
+    pub fn into_fd(self) -> Result<OwnedFd, Error> {
+        match self.source {
+            Source::Trigger(pressure_file) => Ok(pressure_file.into()),
+            Source::Polling(_) | Source::Backend(_) => Err(Error::NoFileDescriptor),
+            Source::Paused(_) => Err(Error::Paused),
+        }
+    }
+    /// Consume a pressure event after the monitor's fd was reported ready by an external event loop,
+    /// returning how many notifications queued on a service manager's socket or fifo were coalesced into it, see [Event::coalesced]
+    pub fn acknowledge(&mut self) -> Result<u32, Error> {
+        match &mut self.source {
+            Source::Trigger(pressure_file) => pressure_file.drain(),
+            source => source.backend_mut().acknowledge().map(|()| 1),
+        }
+    }
+    fn wait_until(&mut self, timeout: Option<Duration>) -> Result<WaitOutcome, Error> {
+        // Timeouts too long to represent wait forever
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        loop {
+            if let Source::Paused(paused) = &mut self.source {
+                // Nothing is held back or re-armed while paused, so only the timeout ends the wait
+                let remaining =
+                    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                let cancellation = self.cancellation.as_ref().map(AsFd::as_fd);
+                match paused.wait(remaining, cancellation) {
+                    Err(e) if is_interrupted(&e) && !self.interruptible => continue,
+                    Err(e) if is_interrupted(&e) => return Ok(WaitOutcome::Interrupted),
+                    outcome => return Ok(outcome?.map(|()| self.target.event())),
+                }
+            }
+            let cooldown = self.adaptive.as_ref().and_then(Adaptive::cooldown_deadline);
+            let wake = [deadline, cooldown, self.target.wake_deadline()]
+                .into_iter()
+                .flatten()
+                .min();
+            let remaining = wake.map(|wake| wake.saturating_duration_since(Instant::now()));
+            let outcome = match self.reconnect.as_ref().and_then(Reconnect::retry_at) {
+                Some(retry_at) => self.reconnect_once(retry_at, remaining),
+                None => self.poll_once(remaining),
+            };
+            let outcome = match outcome {
+                Ok(WaitOutcome::Triggered(event))
+                    if event.transition == Transition::Reconnected =>
+                {
+                    return Ok(WaitOutcome::Triggered(event));
+                }
+                Ok(outcome) => outcome,
+                Err(e) if is_interrupted(&e) => {
+                    if self.interruptible {
+                        return Ok(WaitOutcome::Interrupted);
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(resource = %self.target.resource, error = %e, "waiting for pressure failed");
+                    return Err(e);
+                }
+            };
+            if let Some(adaptive) = &mut self.adaptive {
+                let rearmed = match outcome {
+                    WaitOutcome::Triggered(_) => adaptive.escalate(self.target.path()?)?,
+                    WaitOutcome::TimedOut
+                        if cooldown.is_some_and(|cooldown| cooldown <= Instant::now()) =>
+                    {
+                        adaptive.deescalate(self.target.path()?)?
+                    }
+                    WaitOutcome::TimedOut
+                    | WaitOutcome::Cancelled
+                    | WaitOutcome::Interrupted
+                    | WaitOutcome::Hangup
+                    | WaitOutcome::Error(_) => None,
+                };
+                if let Some(pressure_file) = rearmed {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(trigger = ?adaptive.armed(), "re-armed adaptive trigger");
+                    self.source = Source::Trigger(pressure_file);
+                    self.target.trigger = Some(adaptive.armed());
+                }
+            }
+            match outcome {
+                WaitOutcome::Triggered(event) => {
+                    if let Some(event) = self.fired(event)? {
+                        return Ok(WaitOutcome::Triggered(event));
+                    }
+                }
+                WaitOutcome::TimedOut => {
+                    if let Some(event) = self.woken()? {
+                        return Ok(WaitOutcome::Triggered(event));
+                    }
+                    if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                        return Ok(outcome);
+                    }
+                }
+                WaitOutcome::Hangup => match &mut self.reconnect {
+                    Some(reconnect) => reconnect.hung_up(),
+                    None => return Ok(outcome),
+                },
+                WaitOutcome::Cancelled | WaitOutcome::Interrupted | WaitOutcome::Error(_) => {
+                    return Ok(outcome);
+                }
+            }
+        }
+    }
+    /// Wait until `retry_at` or `timeout` for the next attempt at reopening the monitor's path after its fd hung up,
+    /// returning a [Transition::Reconnected] event once it succeeded
+    fn reconnect_once(
+        &mut self,
+        retry_at: Instant,
+        timeout: Option<Duration>,
+    ) -> Result<WaitOutcome, Error> {
+        let until_retry = retry_at.saturating_duration_since(Instant::now());
+        let cancellation = self.cancellation.as_ref().map(AsFd::as_fd);
+        if polling::sleep(
+            timeout.map_or(until_retry, |t| t.min(until_retry)),
+            cancellation,
+        )? {
+            return Ok(WaitOutcome::Cancelled);
+        }
+        let Some(reconnect) = self
+            .reconnect
+            .as_mut()
+            .filter(|_| retry_at <= Instant::now())
+        else {
+            return Ok(WaitOutcome::TimedOut);
         };
-        nix::poll::poll(
-            &mut [PollFd::new(self.pressure_file.as_fd(), pollflag)],
-            PollTimeout::NONE,
-        )
-        .unwrap();
-        if needs_read {
-            let mut buf = [0; 1024];
-            match nix::unistd::read(self.pressure_file.as_fd(), &mut buf) {
-                Ok(_) => {}
-                Err(Errno::EWOULDBLOCK) => {}
-                Err(e) => Err(e)?,
+        match self.target.reconnect() {
+            Ok(pressure_file) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!(path = ?self.target.path, "reconnected pressure monitor");
+                reconnect.succeeded();
+                self.source = Source::Trigger(pressure_file);
+                self.target.events.record();
+                Ok(WaitOutcome::Triggered(Event {
+                    transition: Transition::Reconnected,
+                    ..self.target.event()
+                }))
+            }
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(path = ?self.target.path, error = %_e, "reconnecting pressure monitor failed");
+                reconnect.failed();
+                Ok(WaitOutcome::TimedOut)
             }
         }
-        Ok(())
+    }
+    /// Handle an event received from the source, returning it if it should be delivered now
+    fn fired(&mut self, event: Event) -> Result<Option<Event>, Error> {
+        let stats = self
+            .target
+            .reads_pressure()
+            .then(|| self.stats())
+            .transpose()?;
+        self.target
+            .fired(event, stats.as_ref())
+            .map(|event| self.target.enrich(event))
+            .transpose()
+    }
+    /// Handle waking up without an event, returning events held back or pressure clearing
+    fn woken(&mut self) -> Result<Option<Event>, Error> {
+        let stats = self.target.clear_due().then(|| self.stats()).transpose()?;
+        self.target
+            .woken(stats.as_ref())
+            .map(|event| self.target.enrich(event))
+            .transpose()
+    }
+    fn poll_once(&mut self, timeout: Option<Duration>) -> Result<WaitOutcome, Error> {
+        let cancellation = self.cancellation.as_ref().map(AsFd::as_fd);
+        let pressure_file = match &mut self.source {
+            Source::Trigger(pressure_file) => pressure_file,
+            source => {
+                let outcome = source.backend_mut().wait(timeout, cancellation)?;
+                return Ok(outcome.map(|()| self.target.event()));
+            }
+        };
+        let fds = std::iter::once((pressure_file.as_fd(), pressure_file.poll_flags()))
+            .chain(
+                self.additional
+                    .iter()
+                    .map(|(_, fd)| (fd.as_fd(), PollFlags::POLLPRI)),
+            )
+            .chain(cancellation.map(|cancellation| (cancellation, PollFlags::POLLIN)));
+        if self.poll_set.poll(fds, timeout)? == 0 {
+            return Ok(WaitOutcome::TimedOut);
+        }
+        let triggers = 1 + self.additional.len();
+        if self.poll_set.is_ready(triggers) {
+            return Ok(WaitOutcome::Cancelled);
+        }
+        if let Some(outcome) = (0..triggers).find_map(|i| fd_died(self.poll_set.revents(i))) {
+            return Ok(outcome);
+        }
+        // If several triggers fire at once, report the one added last
+        let additional = (1..triggers)
+            .rev()
+            .find(|&i| self.poll_set.is_ready(i))
+            .and_then(|i| self.additional.get(i - 1))
+            .map(|(trigger, _)| *trigger);
+        let coalesced = if self.poll_set.is_ready(0) {
+            pressure_file.drain()?
+        } else {
+            1
+        };
+        let event = match additional {
+            Some(trigger) => self.target.event_for(trigger),
+            None => self.target.event(),
+        };
+        Ok(WaitOutcome::Triggered(Event { coalesced, ..event }))
+    }
+}
+
+#[cfg(unix)]
+/// Whether a wait failed because a signal was delivered to the waiting thread
+pub(crate) fn is_interrupted(error: &Error) -> bool {
+    errno(error) == Some(Errno::EINTR)
+}
+
+#[cfg(unix)]
+/// The errno behind a failed syscall, if the error is one
+pub(crate) fn errno(error: &Error) -> Option<Errno> {
+    match error {
+        Error::Nix(errno) => Some(*errno),
+        Error::Io(e) => e.raw_os_error().map(Errno::from_raw),
+        _ => None,
     }
 }
 
-#[cfg(feature = "tokio")]
-pub mod tokio {
-    //! Asynchronous pressure monitoring using Tokio's event loop
-    use std::os::fd::AsFd;
+#[cfg(unix)]
+/// Size of the stack buffers pressure files and notifications are read into
+pub(crate) const READ_BUFFER_SIZE: usize = 512;
 
-    use nix::errno::Errno;
-    use tokio::io::{Interest, unix::AsyncFd};
+#[cfg(unix)]
+/// Convert a timeout for use with poll(2), rounding up so that sub-millisecond timeouts don't turn into a non-blocking poll
+pub(crate) fn poll_timeout(timeout: Duration) -> PollTimeout {
+    PollTimeout::try_from(timeout.as_micros().div_ceil(1000)).unwrap_or(PollTimeout::MAX)
+}
 
-    use crate::{Error, MonitorType, init_monitor};
+/// Result of waiting for a pressure event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitOutcome<T = Event> {
+    /// A pressure event occurred
+    Triggered(T),
+    /// The timeout elapsed before a pressure event occurred
+    TimedOut,
+    /// The wait was interrupted through a [CancellationToken]
+    Cancelled,
+    /// A signal was delivered to the waiting thread. This is only returned by monitors built with [PressureMonitorBuilder::interruptible], others keep waiting
+    Interrupted,
+    /// The monitor's fd was hung up, for example because the service manager closed its end of the socket or fifo.
+    /// No more events will be received on it
+    Hangup,
+    /// poll(2) reported an error condition on the monitor's fd, such as a pressure file whose cgroup was removed, with the raw revents
+    Error(i16),
+}
 
-    /// Asynchronous equivalent to [PressureMonitor](`super::PressureMonitor`)
-    pub struct PressureMonitor {
-        pressure_file: AsyncFd<MonitorType>,
+impl<T> WaitOutcome<T> {
+    /// Convert the event of a triggered wait, leaving other outcomes as they are
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> WaitOutcome<U> {
+        match self {
+            WaitOutcome::Triggered(event) => WaitOutcome::Triggered(f(event)),
+            WaitOutcome::TimedOut => WaitOutcome::TimedOut,
+            WaitOutcome::Cancelled => WaitOutcome::Cancelled,
+            WaitOutcome::Interrupted => WaitOutcome::Interrupted,
+            WaitOutcome::Hangup => WaitOutcome::Hangup,
+            WaitOutcome::Error(revents) => WaitOutcome::Error(revents),
+        }
     }
+}
+
+#[cfg(unix)]
+/// The outcome of a wait in which poll(2) reported `revents` for a monitor's fd, if they mean the fd died rather than an event occurring
+pub(crate) fn fd_died<T>(revents: Option<PollFlags>) -> Option<WaitOutcome<T>> {
+    let revents = revents?;
+    // A peer closing a socket without reading the trigger resets the connection, which is still a hangup
+    if revents.contains(PollFlags::POLLHUP) {
+        Some(WaitOutcome::Hangup)
+    } else if revents.intersects(PollFlags::POLLERR | PollFlags::POLLNVAL) {
+        Some(WaitOutcome::Error(revents.bits()))
+    } else {
+        None
+    }
+}
+
+/// Readiness a monitor's fd signals pressure events with, see [PressureMonitor::poll_interest]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PollInterest {
+    /// POLLPRI or EPOLLPRI, used by kernel pressure files, which always report themselves as readable
+    Priority,
+    /// POLLIN or EPOLLIN, used for notifications from a service manager's fifo or socket
+    Readable,
+}
 
-    impl PressureMonitor {
-        pub fn new() -> Result<Self, Error> {
-            let pressure_file = init_monitor()?;
-            Ok(Self {
-                pressure_file: AsyncFd::new(pressure_file)?,
-            })
+#[cfg(unix)]
+/// Where pressure events come from
+pub(crate) enum Source {
+    Trigger(MonitorType),
+    Polling(Poller),
+    /// A custom backend, see [PressureMonitor::from_backend]
+    Backend(Box<dyn PressureBackend>),
+    /// The monitor's triggers were closed, see [PressureMonitor::pause]
+    Paused(Paused),
+}
+
+#[cfg(unix)]
+impl Source {
+    fn backend(&self) -> &dyn PressureBackend {
+        match self {
+            Source::Trigger(pressure_file) => pressure_file,
+            Source::Polling(poller) => poller,
+            Source::Backend(backend) => backend.as_ref(),
+            Source::Paused(paused) => paused,
         }
+    }
 
-        /// Wait for a single pressure event to occur.
-        /// It is safe to call this function in a busy loop, as even if memory pressure persists the kernel limits the amount of events sent
-        pub async fn wait(&mut self) -> Result<(), Error> {
-            let (pollflag, needs_read) = match self.pressure_file.get_ref() {
-                MonitorType::File(_) => (Interest::PRIORITY, false),
-                MonitorType::Fifo(_) | MonitorType::Socket(_) => (Interest::READABLE, true),
-            };
-            self.pressure_file.ready(pollflag).await?.clear_ready();
-            if needs_read {
-                let mut buf = [0; 512];
-                match nix::unistd::read(self.pressure_file.get_ref().as_fd(), &mut buf) {
-                    Ok(_) => {}
-                    Err(Errno::EWOULDBLOCK) => {}
-                    Err(e) => Err(e)?,
-                }
-            }
-            Ok(())
+    fn backend_mut(&mut self) -> &mut dyn PressureBackend {
+        match self {
+            Source::Trigger(pressure_file) => pressure_file,
+            Source::Polling(poller) => poller,
+            Source::Backend(backend) => backend.as_mut(),
+            Source::Paused(paused) => paused,
         }
     }
 }
 
+#[cfg(unix)]
 pub(crate) enum MonitorType {
     File(OwnedFd),
     Fifo(OwnedFd),
     Socket(OwnedFd),
 }
 
+#[cfg(unix)]
+impl MonitorType {
+    pub(crate) fn poll_interest(&self) -> PollInterest {
+        match self {
+            MonitorType::File(_) => PollInterest::Priority,
+            MonitorType::Fifo(_) | MonitorType::Socket(_) => PollInterest::Readable,
+        }
+    }
+
+    fn stats(&self, resource: Resource) -> Result<PsiStats, Error> {
+        match self {
+            MonitorType::File(fd) => PsiStats::read_fd(fd),
+            // Notifications from a fifo or socket come from a service manager watching on our behalf, so fall back to system-wide pressure
+            MonitorType::Fifo(_) | MonitorType::Socket(_) => PsiStats::read(resource),
+        }
+    }
+
+    /// Fail if the fd hung up or reported an error, for event loops that only report readiness
+    #[cfg(feature = "async")]
+    pub(crate) fn check_alive(&self) -> Result<(), Error> {
+        let mut fds = [nix::poll::PollFd::new(self.as_fd(), self.poll_flags())];
+        nix::poll::poll(&mut fds, PollTimeout::ZERO)?;
+        match fd_died::<()>(fds[0].revents()) {
+            Some(WaitOutcome::Error(revents)) => Err(Error::PollError(revents)),
+            Some(_) => Err(Error::Hangup),
+            None => Ok(()),
+        }
+    }
+    /// Readiness to poll the fd for, see [poll_interest](Self::poll_interest)
+    pub(crate) fn poll_flags(&self) -> PollFlags {
+        match self.poll_interest() {
+            PollInterest::Priority => PollFlags::POLLPRI,
+            PollInterest::Readable => PollFlags::POLLIN,
+        }
+    }
+    /// Consume the pending event, returning how many notifications were coalesced into it
+    pub(crate) fn drain(&self) -> Result<u32, Error> {
+        Ok(self.receive()?.unwrap_or(1))
+    }
+    /// Consume the pending event, returning how many notifications were coalesced into it or [None] if no notification was queued
+    pub(crate) fn receive(&self) -> Result<Option<u32>, Error> {
+        match self {
+            MonitorType::Fifo(_) | MonitorType::Socket(_) => self.read_notifications(),
+            // Polling a pressure file already consumes the event
+            MonitorType::File(_) => Ok(Some(1)),
+        }
+    }
+    /// Read every notification queued on a fifo or socket, which the service manager writes a byte to on each event, returning how many were read
+    fn read_notifications(&self) -> Result<Option<u32>, Error> {
+        let mut buf = [0; READ_BUFFER_SIZE];
+        let mut read = 0;
+        let closed = loop {
+            match nix::unistd::read(self.as_fd(), &mut buf) {
+                // The other end was closed, which the next wait reports
+                Ok(0) => break true,
+                Ok(bytes) => read += bytes,
+                Err(Errno::EWOULDBLOCK) => break false,
+                Err(e) => Err(e)?,
+            }
+        };
+        #[cfg(feature = "tracing")]
+        tracing::trace!(bytes = read, "drained notifications");
+        Ok((read > 0 || closed).then(|| u32::try_from(read).unwrap_or(u32::MAX).max(1)))
+    }
+}
+
+#[cfg(unix)]
 impl AsFd for MonitorType {
-    fn as_fd(&self) -> BorrowedFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
         match self {
             MonitorType::File(owned_fd) => owned_fd.as_fd(),
             MonitorType::Fifo(owned_fd) => owned_fd.as_fd(),
@@ -137,6 +1226,16 @@ impl AsFd for MonitorType {
     }
 }
 
+#[cfg(unix)]
+impl From<MonitorType> for OwnedFd {
+    fn from(pressure_file: MonitorType) -> Self {
+        match pressure_file {
+            MonitorType::File(fd) | MonitorType::Fifo(fd) | MonitorType::Socket(fd) => fd,
+        }
+    }
+}
+
+#[cfg(unix)]
 impl AsRawFd for MonitorType {
     fn as_raw_fd(&self) -> std::os::unix::prelude::RawFd {
         match self {
@@ -147,49 +1246,119 @@ impl AsRawFd for MonitorType {
     }
 }
 
-const DEFAULT_PRESSURE: &[u8; 19] = b"some 20000 2000000\x00";
+#[cfg(unix)]
+/// The fd pressure events are signalled on, with the readiness given by [PressureMonitor::poll_interest].
+/// For monitors that fell back to polling this is the pressure file being sampled, which reports an error condition right away and mustn't be registered.
+/// While [paused](PressureMonitor::pause) it is a placeholder that never becomes ready
+impl AsFd for PressureMonitor {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.source.backend().as_fd()
+    }
+}
 
-fn init_monitor() -> Result<MonitorType, Error> {
-    let source = std::env::var("MEMORY_PRESSURE_WATCH");
-    let (path, write) = match source.as_deref() {
-        // Systemd sets MEMORY_PRESSURE_WATCH to /dev/null to indicate memory pressure monitoring is disabled for this service/unit
-        // Instead of disabling memory pressure handling entirely we instead default to /proc/pressure/memory
-        Ok("/dev/null") | Err(VarError::NotPresent) => {
-            ("/proc/pressure/memory".into(), DEFAULT_PRESSURE.into())
-        }
-        Ok(path) => match std::env::var("MEMORY_PRESSURE_WRITE") {
-            Ok(write) => {
-                let write = base64::prelude::BASE64_STANDARD.decode(&write).unwrap();
-                (path, write)
-            }
-            Err(_) => (path, Vec::new()),
-        },
-        Err(e) => Err(e.clone())?,
-    };
+#[cfg(unix)]
+impl AsRawFd for PressureMonitor {
+    fn as_raw_fd(&self) -> std::os::unix::prelude::RawFd {
+        self.as_fd().as_raw_fd()
+    }
+}
+
+#[cfg(unix)]
+/// Determine what kind of monitor an already opened file descriptor is
+fn monitor_from_fd(fd: OwnedFd) -> Result<MonitorType, Error> {
+    let file_type = SFlag::from_bits_truncate(nix::sys::stat::fstat(&fd)?.st_mode) & SFlag::S_IFMT;
+    let flags = OFlag::from_bits_retain(nix::fcntl::fcntl(&fd, FcntlArg::F_GETFL)?);
+    // Notifications are read until they would block, which would otherwise hang on a blocking fd
+    nix::fcntl::fcntl(&fd, FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK))?;
+    match file_type {
+        SFlag::S_IFREG => Ok(MonitorType::File(fd)),
+        SFlag::S_IFIFO => Ok(MonitorType::Fifo(fd)),
+        SFlag::S_IFSOCK => Ok(MonitorType::Socket(fd)),
+        _ => Err(Error::UnexpectedFileType),
+    }
+}
+
+/// Create a pipe whose ends are non-blocking and closed on exec, without relying on pipe2(2) which not every platform has
+#[cfg(unix)]
+pub(crate) fn nonblocking_pipe() -> Result<(OwnedFd, OwnedFd), Error> {
+    let (read, write) = nix::unistd::pipe()?;
+    for fd in [&read, &write] {
+        nix::fcntl::fcntl(fd, FcntlArg::F_SETFD(nix::fcntl::FdFlag::FD_CLOEXEC))?;
+        nix::fcntl::fcntl(fd, FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+    }
+    Ok((read, write))
+}
 
-    let file_type = std::fs::metadata(&path)?.file_type();
+#[cfg(unix)]
+/// Open a pressure file, fifo or socket and register a trigger with it, if any
+fn open_monitor(path: &Path, trigger: Option<Trigger>) -> Result<MonitorType, Error> {
+    let write = trigger.map(Trigger::to_bytes).unwrap_or_default();
+    let file_type = std::fs::metadata(path)?.file_type();
 
     if file_type.is_file() || file_type.is_fifo() {
         let fd = nix::fcntl::open(
-            &path[..],
+            path,
             nix::fcntl::OFlag::O_RDWR
                 | nix::fcntl::OFlag::O_CLOEXEC
                 | nix::fcntl::OFlag::O_NONBLOCK,
             nix::sys::stat::Mode::empty(),
-        )?;
-        nix::unistd::write(&fd, &write)?;
+        )
+        .map_err(|e| open_error(path, None, e.into()))?;
+        nix::unistd::write(&fd, &write).map_err(|e| open_error(path, trigger, e.into()))?;
         if file_type.is_file() {
             Ok(MonitorType::File(fd))
         } else {
             Ok(MonitorType::Fifo(fd))
         }
     } else if file_type.is_socket() {
-        let mut stream = UnixStream::connect(&path[..])?;
+        let mut stream = match inherited_socket(path)? {
+            Some(stream) => stream,
+            None => UnixStream::connect(path).map_err(|e| open_error(path, None, e.into()))?,
+        };
         stream.set_nonblocking(true)?;
-        stream.write_all(&write)?;
+        stream
+            .write_all(&write)
+            .map_err(|e| open_error(path, trigger, e.into()))?;
         let fd: OwnedFd = stream.into();
         Ok(MonitorType::Socket(fd))
     } else {
         Err(Error::UnexpectedFileType)
     }
 }
+
+#[cfg(unix)]
+/// Register an additional trigger with a kernel pressure file
+fn open_additional(path: &Path, trigger: Trigger) -> Result<OwnedFd, Error> {
+    match open_monitor(path, Some(trigger))? {
+        MonitorType::File(fd) => Ok(fd),
+        MonitorType::Fifo(_) | MonitorType::Socket(_) => Err(Error::UnexpectedFileType),
+    }
+}
+
+#[cfg(unix)]
+/// Duplicate a connected socket inherited from a supervisor, which passes it as `/dev/fd/N` since it can't be connected to by path
+fn inherited_socket(path: &Path) -> Result<Option<UnixStream>, Error> {
+    let Some(fd) = ["/dev/fd/", "/proc/self/fd/"]
+        .iter()
+        .find_map(|prefix| path.to_str()?.strip_prefix(prefix)?.parse::<RawFd>().ok())
+    else {
+        return Ok(None);
+    };
+    // SAFETY: the path was just resolved, so the descriptor is open in this process
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    Ok(Some(fd.try_clone_to_owned()?.into()))
+}
+
+#[cfg(unix)]
+/// Describe why opening a monitor's path, or registering `trigger` on it, failed
+fn open_error(path: &Path, trigger: Option<Trigger>, error: Error) -> Error {
+    match (errno(&error), trigger) {
+        (Some(Errno::EACCES | Errno::EPERM), _) => Error::PermissionDenied {
+            path: path.to_path_buf(),
+        },
+        // Pressure files exist but refuse triggers when PSI was disabled at boot
+        (Some(Errno::EOPNOTSUPP), _) => Error::PsiDisabled,
+        (Some(Errno::EINVAL), Some(trigger)) => Error::TriggerRejected { trigger },
+        _ => error,
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use ::lru::LruCache;
+
+use crate::{Severity, Shrinkable};
+
+/// A thread-safe LRU cache that evicts its least recently used entries when memory is under pressure
+///
+/// Caches are added to the [shrink](crate::shrink) registry when created, so they are shrunk along with everything else in it by [shrink::spawn](crate::shrink::spawn).
+/// On each event a fraction of the entries is evicted, twice that fraction once pressure is critical
+///
+/// ```no_run
+/// use pressure::{PressureLru, PressureMonitor, Severity};
+/// use std::num::NonZeroUsize;
+///
+/// let cache = PressureLru::new(NonZeroUsize::new(10_000).unwrap(), 0.25);
+/// let _runner = pressure::shrink::spawn(PressureMonitor::new()?, Severity::Medium, |_, _| {})?;
+/// cache.put("key".to_owned(), vec![0u8; 4096]);
+/// assert!(cache.get("key").is_some());
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct PressureLru<K, V> {
+    cache: Mutex<LruCache<K, V>>,
+    evict_fraction: f32,
+}
+
+impl<K: Hash + Eq + Send + 'static, V: Send + 'static> PressureLru<K, V> {
+    /// Create a cache holding up to `capacity` entries, evicting `evict_fraction` (between 0 and 1) of them on each pressure event
+    pub fn new(capacity: NonZeroUsize, evict_fraction: f32) -> Arc<Self> {
+        let cache = Arc::new(Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+            evict_fraction: evict_fraction.clamp(0.0, 1.0),
+        });
+        crate::shrink::register(&cache);
+        cache
+    }
+}
+
+impl<K: Hash + Eq, V> PressureLru<K, V> {
+    /// Get a copy of the value of `key`, marking it as most recently used
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.lock().get(key).cloned()
+    }
+
+    /// Insert a value, returning the one previously stored for `key`
+    pub fn put(&self, key: K, value: V) -> Option<V> {
+        self.lock().put(key, value)
+    }
+
+    /// Remove the value of `key`
+    pub fn pop<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().pop(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Lock the underlying cache, for operations not provided here
+    pub fn lock(&self) -> MutexGuard<'_, LruCache<K, V>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<K: Hash + Eq + Send, V: Send> Shrinkable for PressureLru<K, V> {
+    /// Evict the least recently used entries, returning their size as estimated from the size of their key and value, not counting memory they own on the heap
+    fn shrink(&self, level: Severity) -> usize {
+        let fraction = match level {
+            Severity::Critical => (self.evict_fraction * 2.0).min(1.0),
+            Severity::Low | Severity::Medium => self.evict_fraction,
+        };
+        let mut cache = self.lock();
+        let evict = (cache.len() as f32 * fraction).ceil() as usize;
+        (0..evict)
+            .map_while(|_| cache.pop_lru())
+            .map(|_| std::mem::size_of::<(K, V)>())
+            .sum()
+    }
+
+    /// Evict the least recently used entries until their estimated size reaches `bytes`
+    fn shrink_bytes(&self, bytes: usize) -> usize {
+        let entry = std::mem::size_of::<(K, V)>();
+        let mut cache = self.lock();
+        (0..bytes.div_ceil(entry.max(1)))
+            .map_while(|_| cache.pop_lru())
+            .map(|_| entry)
+            .sum()
+    }
+}
@@ -0,0 +1,228 @@
+//! Memory pressure on macOS, which has no pressure stall information but reports pressure levels through a dispatch source
+
+use std::{
+    ffi::{c_ulong, c_void},
+    os::fd::{AsFd, BorrowedFd, OwnedFd},
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+    time::Duration,
+};
+
+use nix::{
+    errno::Errno,
+    poll::{PollFd, PollFlags, PollTimeout},
+};
+
+use crate::{
+    Error, PollInterest, PressureBackend, PsiStats, Resource, Severity, Source, WaitOutcome,
+    event::Target, poll_timeout,
+};
+
+// See <dispatch/source.h>
+const DISPATCH_MEMORYPRESSURE_NORMAL: c_ulong = 0x01;
+const DISPATCH_MEMORYPRESSURE_WARN: c_ulong = 0x02;
+const DISPATCH_MEMORYPRESSURE_CRITICAL: c_ulong = 0x04;
+const DISPATCH_QUEUE_PRIORITY_DEFAULT: isize = 0;
+
+#[repr(C)]
+struct DispatchSourceType {
+    _private: [u8; 0],
+}
+
+type DispatchObject = *mut c_void;
+
+// libdispatch is part of libSystem, which every binary links against
+unsafe extern "C" {
+    static _dispatch_source_type_memorypressure: DispatchSourceType;
+    fn dispatch_get_global_queue(identifier: isize, flags: usize) -> DispatchObject;
+    fn dispatch_source_create(
+        kind: *const DispatchSourceType,
+        handle: usize,
+        mask: c_ulong,
+        queue: DispatchObject,
+    ) -> DispatchObject;
+    fn dispatch_set_context(object: DispatchObject, context: *mut c_void);
+    fn dispatch_source_set_event_handler_f(
+        source: DispatchObject,
+        handler: unsafe extern "C" fn(*mut c_void),
+    );
+    fn dispatch_source_set_cancel_handler_f(
+        source: DispatchObject,
+        handler: unsafe extern "C" fn(*mut c_void),
+    );
+    fn dispatch_source_get_data(source: DispatchObject) -> c_ulong;
+    fn dispatch_resume(object: DispatchObject);
+    fn dispatch_source_cancel(source: DispatchObject);
+    fn dispatch_release(object: DispatchObject);
+}
+
+/// State shared with the event handler, which runs on a dispatch queue
+struct Context {
+    source: DispatchObject,
+    level: Arc<AtomicU8>,
+    /// Write end of the pipe the backend waits on
+    notify: OwnedFd,
+}
+
+/// Backend receiving memory pressure level changes from a DISPATCH_SOURCE_TYPE_MEMORYPRESSURE dispatch source
+///
+/// The event handler forwards warn and critical levels through a pipe, so the backend has an fd that can be waited on like a pressure file
+pub(crate) struct DispatchBackend {
+    source: DispatchObject,
+    level: Arc<AtomicU8>,
+    notifications: OwnedFd,
+}
+
+// SAFETY: dispatch objects may be used from any thread
+unsafe impl Send for DispatchBackend {}
+
+impl DispatchBackend {
+    pub(crate) fn new() -> Result<Self, Error> {
+        let (notifications, notify) = crate::nonblocking_pipe()?;
+        let level = Arc::new(AtomicU8::new(Severity::Low as u8));
+        let mask = DISPATCH_MEMORYPRESSURE_NORMAL
+            | DISPATCH_MEMORYPRESSURE_WARN
+            | DISPATCH_MEMORYPRESSURE_CRITICAL;
+        // SAFETY: the source is created suspended, so the context is set before any handler can run, and it is only freed by the cancel handler
+        let source = unsafe {
+            let queue = dispatch_get_global_queue(DISPATCH_QUEUE_PRIORITY_DEFAULT, 0);
+            let source = dispatch_source_create(
+                &raw const _dispatch_source_type_memorypressure,
+                0,
+                mask,
+                queue,
+            );
+            if source.is_null() {
+                return Err(Error::ResourceUnavailable(Resource::Memory));
+            }
+            let context = Box::new(Context {
+                source,
+                level: level.clone(),
+                notify,
+            });
+            dispatch_set_context(source, Box::into_raw(context).cast());
+            dispatch_source_set_event_handler_f(source, handle_event);
+            dispatch_source_set_cancel_handler_f(source, free_context);
+            dispatch_resume(source);
+            source
+        };
+        Ok(Self {
+            source,
+            level,
+            notifications,
+        })
+    }
+
+    /// Drain pending notifications from the event handler, returning whether there were any
+    fn read_notifications(&self) -> Result<bool, Error> {
+        let mut buf = [0; 64];
+        let mut received = false;
+        loop {
+            match nix::unistd::read(&self.notifications, &mut buf) {
+                Ok(0) | Err(Errno::EWOULDBLOCK) => return Ok(received),
+                Ok(_) => received = true,
+                Err(e) => return Err(e)?,
+            }
+        }
+    }
+}
+
+impl PressureBackend for DispatchBackend {
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error> {
+        let mut fds = vec![PollFd::new(self.notifications.as_fd(), PollFlags::POLLIN)];
+        if let Some(cancellation) = cancellation {
+            fds.push(PollFd::new(cancellation, PollFlags::POLLIN));
+        }
+        let ready = nix::poll::poll(&mut fds, timeout.map_or(PollTimeout::NONE, poll_timeout))?;
+        if ready == 0 {
+            return Ok(WaitOutcome::TimedOut);
+        }
+        if fds.get(1).is_some_and(|fd| fd.any() == Some(true)) {
+            return Ok(WaitOutcome::Cancelled);
+        }
+        self.read_notifications()?;
+        Ok(WaitOutcome::Triggered(()))
+    }
+
+    fn try_wait(&mut self) -> Result<bool, Error> {
+        self.read_notifications()
+    }
+
+    /// macOS doesn't expose stall times, use [PressureMonitor::severity](crate::PressureMonitor::severity) instead
+    fn stats(&self, resource: Resource) -> Result<PsiStats, Error> {
+        Err(Error::ResourceUnavailable(resource))
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        Some(match self.level.load(Ordering::Relaxed) {
+            level if level == Severity::Critical as u8 => Severity::Critical,
+            level if level == Severity::Medium as u8 => Severity::Medium,
+            _ => Severity::Low,
+        })
+    }
+
+    fn poll_interest(&self) -> Option<PollInterest> {
+        Some(PollInterest::Readable)
+    }
+
+    fn acknowledge(&mut self) -> Result<(), Error> {
+        self.read_notifications().map(drop)
+    }
+}
+
+impl AsFd for DispatchBackend {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.notifications.as_fd()
+    }
+}
+
+impl Drop for DispatchBackend {
+    fn drop(&mut self) {
+        // SAFETY: the source is owned by the backend, and cancelling it makes the cancel handler free the context
+        unsafe {
+            dispatch_source_cancel(self.source);
+            dispatch_release(self.source);
+        }
+    }
+}
+
+unsafe extern "C" fn handle_event(context: *mut c_void) {
+    // SAFETY: the context is only freed by the cancel handler, which never runs concurrently with the event handler
+    let context = unsafe { &*context.cast::<Context>() };
+    let data = unsafe { dispatch_source_get_data(context.source) };
+    let severity = if data & DISPATCH_MEMORYPRESSURE_CRITICAL != 0 {
+        Severity::Critical
+    } else if data & DISPATCH_MEMORYPRESSURE_WARN != 0 {
+        Severity::Medium
+    } else {
+        Severity::Low
+    };
+    context.level.store(severity as u8, Ordering::Relaxed);
+    // Returning to normal isn't a pressure event
+    if severity > Severity::Low {
+        // A full pipe already has an event pending
+        let _ = nix::unistd::write(&context.notify, &[severity as u8]);
+    }
+}
+
+unsafe extern "C" fn free_context(context: *mut c_void) {
+    // SAFETY: the context was leaked from a box when the source was created, and the cancel handler runs once
+    drop(unsafe { Box::from_raw(context.cast::<Context>()) });
+}
+
+/// Open system-wide memory pressure notifications, the only resource macOS reports pressure for
+pub(crate) fn open(resource: Resource) -> Result<(Source, Target), Error> {
+    if resource != Resource::Memory {
+        return Err(Error::ResourceUnavailable(resource));
+    }
+    Ok((
+        Source::Backend(Box::new(DispatchBackend::new()?)),
+        Target::for_backend(resource),
+    ))
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use crate::Error;
+
+/// A snapshot of selected system-wide memory statistics from `/proc/meminfo` and `/proc/vmstat`, telling whether reclaim, swap or dirty writeback drives memory pressure
+///
+/// Attached to memory pressure events with [PressureMonitorBuilder::attach_memory_context](crate::PressureMonitorBuilder::attach_memory_context).
+/// The counters only ever grow, so compare two snapshots to get the activity between them
+///
+/// ```no_run
+/// use pressure::MemoryContext;
+///
+/// let before = MemoryContext::read()?;
+/// std::thread::sleep(std::time::Duration::from_secs(1));
+/// let after = MemoryContext::read()?;
+/// println!(
+///     "{} MiB available, scanned {} and stole {} pages, swapped in {} pages",
+///     after.mem_available >> 20,
+///     after.pgscan - before.pgscan,
+///     after.pgsteal - before.pgsteal,
+///     after.pswpin - before.pswpin,
+/// );
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryContext {
+    /// Estimate of the bytes available to start new applications without swapping
+    pub mem_available: u64,
+    pub swap_free: u64,
+    /// Bytes waiting to be written back to disk
+    pub dirty: u64,
+    /// Bytes being written back to disk
+    pub writeback: u64,
+    /// Pages scanned by kswapd, direct reclaim and khugepaged since boot
+    pub pgscan: u64,
+    /// Pages reclaimed by kswapd, direct reclaim and khugepaged since boot
+    pub pgsteal: u64,
+    /// Pages swapped in since boot
+    pub pswpin: u64,
+    /// Pages swapped out since boot
+    pub pswpout: u64,
+    /// Pages read back in since boot shortly after being evicted, a sign of the working set not fitting in memory
+    pub refaults: u64,
+}
+
+impl MemoryContext {
+    /// Read the statistics from procfs, mounted at `/proc` unless overridden with the `PRESSURE_PROCFS_ROOT` environment variable
+    pub fn read() -> Result<Self, Error> {
+        Self::read_in(&crate::procfs_root())
+    }
+
+    /// Read the statistics from procfs mounted at `procfs_root`
+    pub fn read_in(procfs_root: &Path) -> Result<Self, Error> {
+        let mut context = Self::default();
+        let meminfo = std::fs::read_to_string(procfs_root.join("meminfo"))?;
+        let mut available = false;
+        for line in meminfo.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let field = match key {
+                "MemAvailable" => {
+                    available = true;
+                    &mut context.mem_available
+                }
+                "SwapFree" => &mut context.swap_free,
+                "Dirty" => &mut context.dirty,
+                "Writeback" => &mut context.writeback,
+                _ => continue,
+            };
+            let kib = value.trim().trim_end_matches("kB").trim_end();
+            *field = kib.parse::<u64>().map_err(|_| Error::MalformedMemoryInfo)? * 1024;
+        }
+        if !available {
+            return Err(Error::MalformedMemoryInfo);
+        }
+        // Counters added by newer kernels, such as those of khugepaged, are left at zero on older ones
+        let vmstat = std::fs::read_to_string(procfs_root.join("vmstat"))?;
+        for line in vmstat.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let field = match key {
+                "pgscan_kswapd" | "pgscan_direct" | "pgscan_khugepaged" => &mut context.pgscan,
+                "pgsteal_kswapd" | "pgsteal_direct" | "pgsteal_khugepaged" => &mut context.pgsteal,
+                "pswpin" => &mut context.pswpin,
+                "pswpout" => &mut context.pswpout,
+                // Split into anonymous and file pages since Linux 5.9
+                "workingset_refault" | "workingset_refault_anon" | "workingset_refault_file" => {
+                    &mut context.refaults
+                }
+                _ => continue,
+            };
+            *field += value
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| Error::MalformedMemoryInfo)?;
+        }
+        Ok(context)
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    poll::{PollFd, PollFlags, PollTimeout},
+    sys::inotify::{AddWatchFlags, InitFlags, Inotify},
+};
+
+use crate::{CancellationToken, Error, WaitOutcome, cgroup, poll_timeout};
+
+/// Counters from a cgroup's memory.events file, each counting how often the event occurred since the cgroup was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MemoryEventCounters {
+    /// Reclaim below memory.low because of high memory pressure
+    pub low: u64,
+    /// Usage exceeding memory.high, after which processes are throttled and forced into reclaim
+    pub high: u64,
+    /// Usage about to exceed memory.max, after which the OOM killer is invoked if reclaim fails
+    pub max: u64,
+    /// The OOM killer being invoked, also counting invocations that didn't kill anything
+    pub oom: u64,
+    /// Processes killed by the OOM killer
+    pub oom_kill: u64,
+    /// The whole cgroup being killed by the OOM killer, only counted with memory.oom.group enabled
+    pub oom_group_kill: u64,
+}
+
+impl MemoryEventCounters {
+    /// Read the counters of a cgroup v2 hierarchy
+    pub fn read(cgroup: impl AsRef<Path>) -> Result<Self, Error> {
+        std::fs::read_to_string(cgroup.as_ref().join("memory.events"))?.parse()
+    }
+
+    /// Read the counters from an already opened memory.events file, without changing its offset
+    fn read_fd(fd: impl AsFd) -> Result<Self, Error> {
+        let mut buf = [0; crate::READ_BUFFER_SIZE];
+        let len = nix::sys::uio::pread(fd, &mut buf, 0)?;
+        std::str::from_utf8(&buf[..len])
+            .map_err(|_| Error::MalformedMemoryEvents)?
+            .parse()
+    }
+
+    /// Events that occurred between `previous` and these counters
+    fn events_since(&self, previous: &Self) -> impl Iterator<Item = (MemoryEventKind, u64)> {
+        [
+            (MemoryEventKind::OomKill, self.oom_kill, previous.oom_kill),
+            (MemoryEventKind::MaxBreach, self.max, previous.max),
+            (MemoryEventKind::HighBreach, self.high, previous.high),
+        ]
+        .into_iter()
+        .filter_map(|(kind, current, previous)| {
+            // Counters only decrease if the cgroup was removed and recreated under the same path
+            let count = current.checked_sub(previous).unwrap_or(current);
+            (count > 0).then_some((kind, count))
+        })
+    }
+}
+
+impl FromStr for MemoryEventCounters {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut counters = MemoryEventCounters::default();
+        for line in s.lines().filter(|line| !line.is_empty()) {
+            let (name, value) = line.split_once(' ').ok_or(Error::MalformedMemoryEvents)?;
+            let value = value.parse().map_err(|_| Error::MalformedMemoryEvents)?;
+            match name {
+                "low" => counters.low = value,
+                "high" => counters.high = value,
+                "max" => counters.max = value,
+                "oom" => counters.oom = value,
+                "oom_kill" => counters.oom_kill = value,
+                "oom_group_kill" => counters.oom_group_kill = value,
+                // Newer kernels may add counters, such as sock_throttled
+                _ => {}
+            }
+        }
+        Ok(counters)
+    }
+}
+
+/// Kind of memory event reported by [MemoryEventsWatcher]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryEventKind {
+    /// A process in the cgroup was killed by the OOM killer
+    OomKill,
+    /// Usage exceeded memory.high, and the cgroup was throttled
+    HighBreach,
+    /// Usage reached memory.max
+    MaxBreach,
+}
+
+/// An event received by [MemoryEventsWatcher]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemoryEvent {
+    pub kind: MemoryEventKind,
+    /// How often the event occurred since the previous check, as the kernel may count several occurrences before the watcher reads the counters
+    pub count: u64,
+    /// The cgroup the event occurred in
+    pub cgroup: PathBuf,
+    /// When the event was received
+    pub timestamp: Instant,
+}
+
+/// Watches a cgroup's memory.events file, reporting OOM kills and breaches of memory.high and memory.max that pressure stall information doesn't reveal
+///
+/// ```no_run
+/// use pressure::{MemoryEventKind, MemoryEventsWatcher, WaitOutcome};
+///
+/// let mut watcher = MemoryEventsWatcher::for_current_cgroup()?;
+/// while let WaitOutcome::Triggered(event) = watcher.wait()? {
+///     if event.kind == MemoryEventKind::OomKill {
+///         eprintln!("{} processes were OOM killed", event.count);
+///     }
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct MemoryEventsWatcher {
+    cgroup: PathBuf,
+    inotify: Inotify,
+    file: File,
+    counters: MemoryEventCounters,
+    pending: VecDeque<MemoryEvent>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl MemoryEventsWatcher {
+    /// Watch a cgroup v2 hierarchy, reporting events that occur after the watcher was created
+    pub fn new(cgroup: impl AsRef<Path>) -> Result<Self, Error> {
+        let cgroup = cgroup.as_ref().to_path_buf();
+        let path = cgroup.join("memory.events");
+        let inotify = Inotify::init(InitFlags::IN_CLOEXEC | InitFlags::IN_NONBLOCK)?;
+        // The kernel generates a modification event whenever one of the counters changes
+        inotify.add_watch(&path, AddWatchFlags::IN_MODIFY)?;
+        let file = File::open(&path)?;
+        let counters = MemoryEventCounters::read_fd(&file)?;
+        Ok(Self {
+            cgroup,
+            inotify,
+            file,
+            counters,
+            pending: VecDeque::new(),
+            cancellation: None,
+        })
+    }
+    /// Watch the cgroup the calling process belongs to
+    pub fn for_current_cgroup() -> Result<Self, Error> {
+        Self::new(cgroup::current_cgroup()?)
+    }
+    pub fn cgroup(&self) -> &Path {
+        &self.cgroup
+    }
+    /// The counters as of the last check
+    pub fn counters(&self) -> MemoryEventCounters {
+        self.counters
+    }
+    /// Get a token that can be used to cancel waits on this watcher from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Wait for a single memory event, returning either [WaitOutcome::Triggered] with the [MemoryEvent] or [WaitOutcome::Cancelled].
+    /// If several kinds of events occurred at once they are returned by subsequent calls, OOM kills first
+    pub fn wait(&mut self) -> Result<WaitOutcome<MemoryEvent>, Error> {
+        self.wait_until(None)
+    }
+    /// Wait for a single memory event, giving up once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<WaitOutcome<MemoryEvent>, Error> {
+        self.wait_until(Some(timeout))
+    }
+    /// Check whether a memory event is pending without blocking, consuming and returning it if so
+    pub fn try_wait(&mut self) -> Result<Option<MemoryEvent>, Error> {
+        if self.pending.is_empty() && self.drain()? {
+            self.check()?;
+        }
+        Ok(self.pending.pop_front())
+    }
+    fn wait_until(&mut self, timeout: Option<Duration>) -> Result<WaitOutcome<MemoryEvent>, Error> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(event) = self.try_wait()? {
+                return Ok(WaitOutcome::Triggered(event));
+            }
+            let mut fds = vec![PollFd::new(self.inotify.as_fd(), PollFlags::POLLIN)];
+            if let Some(cancellation) = &self.cancellation {
+                fds.push(PollFd::new(cancellation.as_fd(), PollFlags::POLLIN));
+            }
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let ready =
+                nix::poll::poll(&mut fds, remaining.map_or(PollTimeout::NONE, poll_timeout))?;
+            if ready == 0 {
+                return Ok(WaitOutcome::TimedOut);
+            }
+            if fds.get(1).is_some_and(|fd| fd.any() == Some(true)) {
+                return Ok(WaitOutcome::Cancelled);
+            }
+        }
+    }
+    /// Consume pending inotify events, returning whether there were any
+    fn drain(&self) -> Result<bool, Error> {
+        let mut received = false;
+        loop {
+            match self.inotify.read_events() {
+                Ok(events) if !events.is_empty() => received = true,
+                Ok(_) | Err(Errno::EAGAIN) => return Ok(received),
+                Err(e) => return Err(e)?,
+            }
+        }
+    }
+    /// Compare the counters against the previous check, queueing an event for each one that increased
+    fn check(&mut self) -> Result<(), Error> {
+        let counters = MemoryEventCounters::read_fd(&self.file)?;
+        let timestamp = Instant::now();
+        self.pending
+            .extend(
+                counters
+                    .events_since(&self.counters)
+                    .map(|(kind, count)| MemoryEvent {
+                        kind,
+                        count,
+                        cgroup: self.cgroup.clone(),
+                        timestamp,
+                    }),
+            );
+        self.counters = counters;
+        Ok(())
+    }
+}
+
+/// The inotify instance, which becomes readable once memory.events was modified
+impl AsFd for MemoryEventsWatcher {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inotify.as_fd()
+    }
+}
+
+impl AsRawFd for MemoryEventsWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_fd().as_raw_fd()
+    }
+}
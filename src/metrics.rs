@@ -0,0 +1,68 @@
+//! Recording pressure stall information and events through the [metrics] facade, leaving the choice of exporter to the application
+//!
+//! Every metric is labelled by `resource` and, for pressure within a cgroup, `cgroup`:
+//!
+//! - [STALL_PERCENT], a gauge of the averages additionally labelled by `kind` (`some` or `full`) and `window` (`10s`, `60s` or `300s`)
+//! - [STALL_MICROSECONDS], a counter of the total stall time labelled by `kind`
+//! - [EVENTS], a counter of pressure events labelled by the `kind` of stall the monitor watched
+//!
+//! ```no_run
+//! use pressure::{PressureMonitor, Resource, WaitOutcome};
+//! use std::time::Duration;
+//!
+//! let mut monitor = PressureMonitor::new()?;
+//! loop {
+//!     pressure::metrics::record_sample(Resource::Memory, None, &monitor.stats()?);
+//!     if let WaitOutcome::Triggered(event) = monitor.wait_timeout(Duration::from_secs(10))? {
+//!         pressure::metrics::record_event(&event);
+//!     }
+//! }
+//! # Ok::<(), pressure::Error>(())
+//! ```
+use std::path::Path;
+
+use ::metrics::{Label, counter, gauge};
+
+use crate::{Event, PsiStats, Resource};
+
+/// Name of the gauge of average stall percentages
+pub const STALL_PERCENT: &str = "pressure_stall_percent";
+/// Name of the counter of total stall time, in microseconds as reported by the kernel
+pub const STALL_MICROSECONDS: &str = "pressure_stall_microseconds_total";
+/// Name of the counter of pressure events
+pub const EVENTS: &str = "pressure_events_total";
+
+/// Record a sample of pressure on a resource, system-wide or within a cgroup
+pub fn record_sample(resource: Resource, cgroup: Option<&Path>, stats: &PsiStats) {
+    for (kind, line) in [("some", &stats.some), ("full", &stats.full)] {
+        for (window, average) in [
+            ("10s", line.avg10),
+            ("60s", line.avg60),
+            ("300s", line.avg300),
+        ] {
+            let mut labels = labels(resource, cgroup);
+            labels.push(Label::new("kind", kind));
+            labels.push(Label::new("window", window));
+            gauge!(STALL_PERCENT, labels).set(f64::from(average));
+        }
+        let mut labels = labels(resource, cgroup);
+        labels.push(Label::new("kind", kind));
+        // The kernel's totals are already cumulative, so they replace the counter's value instead of incrementing it
+        counter!(STALL_MICROSECONDS, labels).absolute(line.total.as_micros() as u64);
+    }
+}
+
+/// Count a pressure event
+pub fn record_event(event: &Event) {
+    let mut labels = labels(event.resource, event.cgroup.as_deref());
+    labels.push(Label::new("kind", event.kind.to_string()));
+    counter!(EVENTS, labels).increment(1);
+}
+
+fn labels(resource: Resource, cgroup: Option<&Path>) -> Vec<Label> {
+    let mut labels = vec![Label::new("resource", resource.to_string())];
+    if let Some(cgroup) = cgroup {
+        labels.push(Label::new("cgroup", cgroup.to_string_lossy().into_owned()));
+    }
+    labels
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use crate::{Error, HookRegistry, HookRunner, PressureMonitor, Severity};
+
+/// Force mimalloc to free the memory it retains for reuse, returning it to the operating system
+///
+/// This only affects the process' allocations if mimalloc is its global allocator, such as through the mimalloc crate.
+/// mimalloc's statistics don't reflect purged pages outside of Windows, so unlike `jemalloc_purge_now` this can't report how much was released
+pub fn mimalloc_collect_now() {
+    // SAFETY: mi_collect has no preconditions
+    unsafe { libmimalloc_sys::mi_collect(true) };
+}
+
+/// Calls mimalloc's `mi_collect(true)` on a worker thread whenever memory is under pressure, at most once per cooldown
+///
+/// ```no_run
+/// use pressure::{MimallocCollect, PressureMonitor, Severity};
+///
+/// let runner = MimallocCollect::spawn()?;
+/// // Or only once pressure is medium
+/// let runner = MimallocCollect::new()
+///     .min_severity(Severity::Medium)
+///     .spawn_on(PressureMonitor::new()?)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct MimallocCollect {
+    cooldown: Duration,
+    min_severity: Severity,
+}
+
+impl MimallocCollect {
+    pub fn new() -> Self {
+        Self {
+            cooldown: Duration::from_secs(10),
+            min_severity: Severity::Low,
+        }
+    }
+
+    /// Collect on system-wide memory pressure with the default configuration
+    pub fn spawn() -> Result<HookRunner, Error> {
+        Self::new().spawn_on(PressureMonitor::new()?)
+    }
+
+    /// Minimum time between two collections, defaults to 10 seconds
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Only collect for events at which pressure is at least this severe, defaults to [Severity::Low]
+    pub fn min_severity(mut self, min: Severity) -> Self {
+        self.min_severity = min;
+        self
+    }
+
+    /// Collect on the events of `monitor`
+    pub fn spawn_on(self, monitor: PressureMonitor) -> Result<HookRunner, Error> {
+        let mut hooks = HookRegistry::new();
+        hooks
+            .on_event(self.min_severity, |_, _| mimalloc_collect_now())
+            .cooldown(self.cooldown);
+        hooks.spawn(monitor)
+    }
+}
+
+impl Default for MimallocCollect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,44 @@
+use std::{io, os::fd::AsRawFd};
+
+use mio::{Interest, Registry, Token, event::Source, unix::SourceFd};
+
+use crate::{PollInterest, PressureMonitor};
+
+/// Registers the monitor's fd with a mio [Registry](mio::Registry)
+///
+/// The requested interests are ignored in favor of the readiness the monitor signals events with, which is priority readiness for pressure files and readability for notifications from a service manager.
+/// Once the monitor is reported as ready, call [PressureMonitor::acknowledge] before polling again. Monitors that fell back to polling, or whose backend doesn't signal events on its fd, can't be registered
+impl Source for PressureMonitor {
+    fn register(&mut self, registry: &Registry, token: Token, _: Interest) -> io::Result<()> {
+        let (fd, interest) = self.mio_source()?;
+        SourceFd(&fd).register(registry, token, interest)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: Token, _: Interest) -> io::Result<()> {
+        let (fd, interest) = self.mio_source()?;
+        SourceFd(&fd).reregister(registry, token, interest)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        let (fd, _) = self.mio_source()?;
+        SourceFd(&fd).deregister(registry)
+    }
+}
+
+impl PressureMonitor {
+    fn mio_source(&self) -> io::Result<(std::os::fd::RawFd, Interest)> {
+        let interest = match self.poll_interest() {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Some(PollInterest::Priority) => Interest::PRIORITY,
+            Some(PollInterest::Readable) => Interest::READABLE,
+            // Priority readiness is only used by pressure files, which other platforms don't have
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "monitors without an fd signalling events can't be registered with mio",
+                ));
+            }
+        };
+        Ok((self.as_raw_fd(), interest))
+    }
+}
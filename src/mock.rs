@@ -0,0 +1,323 @@
+use std::{
+    collections::VecDeque,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    poll::{PollFd, PollFlags, PollTimeout},
+};
+
+use crate::{
+    CancellationToken, ClockTimestamp, Error, Event, EventRate, PollInterest, PressureBackend,
+    PsiStats, Resource, Severity, StallKind, Transition, WaitOutcome, poll_timeout,
+    rate::EventCounter,
+};
+
+/// A stand-in for [PressureMonitor](crate::PressureMonitor) receiving the events fired through its [MockHandle], for testing code that reacts to pressure without relying on real pressure stall information
+///
+/// ```
+/// use pressure::{MockPressureMonitor, Resource, WaitOutcome};
+/// use std::time::Duration;
+///
+/// let mut monitor = MockPressureMonitor::new(Resource::Memory)?;
+/// let handle = monitor.handle();
+/// std::thread::spawn(move || handle.fire_pressure());
+/// let WaitOutcome::Triggered(event) = monitor.wait()? else { panic!() };
+/// assert_eq!(event.resource, Resource::Memory);
+/// assert_eq!(monitor.wait_timeout(Duration::from_millis(10))?, WaitOutcome::TimedOut);
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct MockPressureMonitor {
+    shared: Arc<Shared>,
+    /// Read end of the pipe, which is readable while events are queued
+    notifications: OwnedFd,
+    cancellation: Option<CancellationToken>,
+    events: EventCounter,
+}
+
+/// Fires events at a [MockPressureMonitor] and sets the stall information it reports, from any thread
+#[derive(Clone)]
+pub struct MockHandle {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    resource: Resource,
+    cgroup: Option<PathBuf>,
+    /// Events fired and not received yet. The pipe is written to while holding the lock, so it is readable exactly while events are queued
+    events: Mutex<VecDeque<Event>>,
+    notify: OwnedFd,
+    stats: Mutex<PsiStats>,
+}
+
+impl MockPressureMonitor {
+    /// Create a mock monitor for system-wide pressure on a resource
+    pub fn new(resource: Resource) -> Result<Self, Error> {
+        Self::open(resource, None)
+    }
+
+    /// Create a mock monitor for pressure on a resource within a cgroup, which doesn't need to exist
+    pub fn for_cgroup(cgroup: impl AsRef<Path>, resource: Resource) -> Result<Self, Error> {
+        Self::open(resource, Some(cgroup.as_ref().to_path_buf()))
+    }
+
+    fn open(resource: Resource, cgroup: Option<PathBuf>) -> Result<Self, Error> {
+        let (notifications, notify) = crate::nonblocking_pipe()?;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                resource,
+                cgroup,
+                events: Mutex::new(VecDeque::new()),
+                notify,
+                stats: Mutex::new(PsiStats::default()),
+            }),
+            notifications,
+            cancellation: None,
+            events: EventCounter::default(),
+        })
+    }
+
+    /// Get a handle firing events at this monitor
+    pub fn handle(&self) -> MockHandle {
+        MockHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    pub fn resource(&self) -> Resource {
+        self.shared.resource
+    }
+
+    /// The cgroup the monitor pretends to monitor, or [None] for system-wide pressure
+    pub fn cgroup(&self) -> Option<&Path> {
+        self.shared.cgroup.as_deref()
+    }
+
+    /// The stall information last set through [MockHandle::set_stats], which is all zeroes until then
+    pub fn stats(&self) -> Result<PsiStats, Error> {
+        Ok(*lock(&self.shared.stats))
+    }
+
+    /// Classify the stall information last set through [MockHandle::set_stats] using the default [SeverityThresholds](crate::SeverityThresholds)
+    pub fn severity(&self) -> Result<Severity, Error> {
+        Ok(Severity::classify(&self.stats()?.some))
+    }
+
+    /// How often the monitor delivered events, see [PressureMonitor::event_rate](crate::PressureMonitor::event_rate)
+    ///
+    /// ```
+    /// use pressure::{MockPressureMonitor, Resource};
+    ///
+    /// let mut monitor = MockPressureMonitor::new(Resource::Memory)?;
+    /// monitor.handle().fire_pressure()?;
+    /// monitor.handle().fire_pressure()?;
+    /// while monitor.try_wait()?.is_some() {}
+    /// let rate = monitor.event_rate();
+    /// assert_eq!((rate.total, rate.last_minute, rate.last_hour), (2, 2, 2));
+    /// assert!(rate.since_last.is_some());
+    /// # Ok::<(), pressure::Error>(())
+    /// ```
+    pub fn event_rate(&self) -> EventRate {
+        self.events.rate()
+    }
+
+    /// Get a token that can be used to cancel waits on this monitor from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Wait until an event is fired, returning either [WaitOutcome::Triggered] with the [Event] or [WaitOutcome::Cancelled]
+    pub fn wait(&mut self) -> Result<WaitOutcome, Error> {
+        self.wait_until(None)
+    }
+
+    /// Wait until an event is fired, giving up once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<WaitOutcome, Error> {
+        // Timeouts too long to represent wait forever
+        self.wait_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Wait for an event fired while the stats last set through [MockHandle::set_stats] are at least `min` severe,
+    /// returning [None] if the wait was cancelled, see [PressureMonitor::wait_for_severity](crate::PressureMonitor::wait_for_severity)
+    ///
+    /// ```
+    /// use pressure::{MockPressureMonitor, PsiLine, PsiStats, Resource, Severity};
+    ///
+    /// let mut monitor = MockPressureMonitor::new(Resource::Memory)?;
+    /// let handle = monitor.handle();
+    /// std::thread::spawn(move || {
+    ///     handle.fire_pressure()?;
+    ///     let some = PsiLine { avg10: 80.0, ..Default::default() };
+    ///     handle.set_stats(PsiStats { some, ..Default::default() });
+    ///     handle.fire_pressure()
+    /// });
+    /// let (_, severity) = monitor.wait_for_severity(Severity::Critical)?.unwrap();
+    /// assert_eq!(severity, Severity::Critical);
+    /// # Ok::<(), pressure::Error>(())
+    /// ```
+    pub fn wait_for_severity(&mut self, min: Severity) -> Result<Option<(Event, Severity)>, Error> {
+        loop {
+            let WaitOutcome::Triggered(event) = self.wait()? else {
+                return Ok(None);
+            };
+            let severity = self.severity()?;
+            if severity >= min {
+                return Ok(Some((event, severity)));
+            }
+        }
+    }
+
+    /// Check whether an event was fired without blocking, consuming and returning it if so
+    pub fn try_wait(&mut self) -> Result<Option<Event>, Error> {
+        let mut events = lock(&self.shared.events);
+        let event = events.pop_front();
+        if events.is_empty() {
+            drain(&self.notifications)?;
+        }
+        if event.is_some() {
+            self.events.record();
+        }
+        Ok(event)
+    }
+
+    /// The monitor's fd is readable while fired events are queued, after which [try_wait](Self::try_wait) receives them
+    pub fn poll_interest(&self) -> Option<PollInterest> {
+        Some(PollInterest::Readable)
+    }
+
+    fn wait_until(&mut self, deadline: Option<Instant>) -> Result<WaitOutcome, Error> {
+        let cancellation = self.cancellation.clone();
+        self.poll_until(deadline, cancellation.as_ref().map(AsFd::as_fd))
+    }
+
+    /// Wait until an event is fired, `deadline` passes or `cancellation` becomes readable
+    fn poll_until(
+        &mut self,
+        deadline: Option<Instant>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome, Error> {
+        loop {
+            if let Some(event) = self.try_wait()? {
+                return Ok(WaitOutcome::Triggered(event));
+            }
+            let mut fds = vec![PollFd::new(self.notifications.as_fd(), PollFlags::POLLIN)];
+            fds.extend(
+                cancellation.map(|cancellation| PollFd::new(cancellation, PollFlags::POLLIN)),
+            );
+            let timeout = deadline.map_or(PollTimeout::NONE, |deadline| {
+                poll_timeout(deadline.saturating_duration_since(Instant::now()))
+            });
+            if nix::poll::poll(&mut fds, timeout)? == 0 {
+                return Ok(WaitOutcome::TimedOut);
+            }
+            if fds.get(1).is_some_and(|fd| fd.any() == Some(true)) {
+                return Ok(WaitOutcome::Cancelled);
+            }
+        }
+    }
+}
+
+impl MockHandle {
+    /// Queue an event for the monitor, waking up a thread or task waiting on it
+    pub fn fire(&self, event: Event) -> Result<(), Error> {
+        let mut events = lock(&self.shared.events);
+        if events.is_empty() {
+            nix::unistd::write(&self.shared.notify, &[0])?;
+        }
+        events.push_back(event);
+        Ok(())
+    }
+
+    /// Fire an event describing pressure on the monitor's resource, as a trigger firing would
+    pub fn fire_pressure(&self) -> Result<(), Error> {
+        self.fire(Event {
+            resource: self.shared.resource,
+            kind: StallKind::Some,
+            trigger: None,
+            cgroup: self.shared.cgroup.clone(),
+            timestamp: Instant::now(),
+            clock: ClockTimestamp::now(),
+            transition: Transition::Pressure,
+            coalesced: 1,
+            suppressed: 0,
+            stats: None,
+            memory: None,
+            compression: None,
+        })
+    }
+
+    /// Set the stall information the monitor reports from now on, which also determines its severity
+    pub fn set_stats(&self, stats: PsiStats) {
+        *lock(&self.shared.stats) = stats;
+    }
+}
+
+/// Drives a [PressureMonitor](crate::PressureMonitor) created with [from_backend](crate::PressureMonitor::from_backend), so that events fired through the handle
+/// go through the monitor's own handling. Only that events were fired is passed on, the monitor describes them itself
+impl PressureBackend for MockPressureMonitor {
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error> {
+        // Timeouts too long to represent wait forever
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        Ok(self.poll_until(deadline, cancellation)?.map(drop))
+    }
+
+    fn try_wait(&mut self) -> Result<bool, Error> {
+        Ok(MockPressureMonitor::try_wait(self)?.is_some())
+    }
+
+    fn stats(&self, _: Resource) -> Result<PsiStats, Error> {
+        MockPressureMonitor::stats(self)
+    }
+
+    fn poll_interest(&self) -> Option<PollInterest> {
+        MockPressureMonitor::poll_interest(self)
+    }
+
+    fn acknowledge(&mut self) -> Result<(), Error> {
+        MockPressureMonitor::try_wait(self).map(drop)
+    }
+}
+
+impl AsFd for MockPressureMonitor {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.notifications.as_fd()
+    }
+}
+
+impl AsRawFd for MockPressureMonitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_fd().as_raw_fd()
+    }
+}
+
+/// Lock state shared with handles, which stays consistent even if a thread panicked while holding the lock
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Read everything written to the pipe, so that it is no longer readable
+fn drain(notifications: &OwnedFd) -> Result<(), Error> {
+    let mut buf = [0; 64];
+    loop {
+        match nix::unistd::read(notifications, &mut buf) {
+            Ok(0) | Err(Errno::EAGAIN) => return Ok(()),
+            Ok(_) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Recording pressure stall information and events as OpenTelemetry instruments
+use std::path::{Path, PathBuf};
+
+use opentelemetry::{
+    KeyValue,
+    metrics::{Counter, Meter, ObservableCounter, ObservableGauge},
+};
+
+use crate::{Event, PsiStats, Resource};
+
+/// OpenTelemetry instruments describing pressure on resources, with `cgroup` attributes for pressure within cgroups:
+///
+/// - `pressure.<resource>`, an observable gauge per resource of the averages in percent, with `kind` (`some` or `full`) and `window` (`10s`, `60s` or `300s`) attributes
+/// - `pressure.<resource>.stall_time`, an observable counter per resource of the total stall time in seconds, with a `kind` attribute
+/// - `pressure.events`, a counter of the events recorded through [record_event](Self::record_event), with a `resource` attribute
+///
+/// Pressure is read whenever the meter provider collects the observable instruments, so no separate sampling is needed
+///
+/// ```no_run
+/// use pressure::{PressureInstruments, Resource};
+///
+/// let meter = opentelemetry::global::meter("pressure");
+/// let _instruments = PressureInstruments::new(&meter, vec![(Resource::Memory, None), (Resource::Cpu, None)]);
+/// ```
+pub struct PressureInstruments {
+    events: Counter<u64>,
+    _averages: Vec<ObservableGauge<f64>>,
+    _totals: Vec<ObservableCounter<f64>>,
+}
+
+impl PressureInstruments {
+    /// Create instruments observing each resource, system-wide for a cgroup of [None] or within the given cgroup.
+    /// Resources that can't be read when collected are skipped
+    pub fn new(meter: &Meter, targets: Vec<(Resource, Option<PathBuf>)>) -> Self {
+        let mut averages = Vec::new();
+        let mut totals = Vec::new();
+        for resource in [Resource::Memory, Resource::Cpu, Resource::Io, Resource::Irq] {
+            let cgroups: Vec<Option<PathBuf>> = targets
+                .iter()
+                .filter(|(target, _)| *target == resource)
+                .map(|(_, cgroup)| cgroup.clone())
+                .collect();
+            if cgroups.is_empty() {
+                continue;
+            }
+            let observed = cgroups.clone();
+            averages.push(
+                meter
+                    .f64_observable_gauge(format!("pressure.{resource}"))
+                    .with_description("Percentage of time tasks were stalled on a resource")
+                    .with_unit("%")
+                    .with_callback(move |observer| {
+                        for (stats, attributes) in sample(resource, &observed) {
+                            for (kind, line) in [("some", &stats.some), ("full", &stats.full)] {
+                                for (window, average) in [
+                                    ("10s", line.avg10),
+                                    ("60s", line.avg60),
+                                    ("300s", line.avg300),
+                                ] {
+                                    let mut attributes = attributes.clone();
+                                    attributes.push(KeyValue::new("kind", kind));
+                                    attributes.push(KeyValue::new("window", window));
+                                    observer.observe(f64::from(average), &attributes);
+                                }
+                            }
+                        }
+                    })
+                    .build(),
+            );
+            totals.push(
+                meter
+                    .f64_observable_counter(format!("pressure.{resource}.stall_time"))
+                    .with_description("Total time tasks were stalled on a resource")
+                    .with_unit("s")
+                    .with_callback(move |observer| {
+                        for (stats, attributes) in sample(resource, &cgroups) {
+                            for (kind, line) in [("some", &stats.some), ("full", &stats.full)] {
+                                let mut attributes = attributes.clone();
+                                attributes.push(KeyValue::new("kind", kind));
+                                observer.observe(line.total.as_secs_f64(), &attributes);
+                            }
+                        }
+                    })
+                    .build(),
+            );
+        }
+        Self {
+            events: meter
+                .u64_counter("pressure.events")
+                .with_description("Pressure events received")
+                .build(),
+            _averages: averages,
+            _totals: totals,
+        }
+    }
+    /// Count a pressure event
+    pub fn record_event(&self, event: &Event) {
+        let mut attributes = vec![KeyValue::new("resource", event.resource.to_string())];
+        attributes.extend(cgroup_attribute(event.cgroup.as_deref()));
+        self.events.add(1, &attributes);
+    }
+}
+
+/// Read pressure on a resource system-wide or within each cgroup, along with the attributes describing where it was read
+fn sample(resource: Resource, cgroups: &[Option<PathBuf>]) -> Vec<(PsiStats, Vec<KeyValue>)> {
+    cgroups
+        .iter()
+        .filter_map(|cgroup| {
+            let stats = match cgroup {
+                Some(cgroup) => PsiStats::read_path(cgroup.join(resource.cgroup_file())),
+                None => PsiStats::read(resource),
+            };
+            Some((stats.ok()?, cgroup_attribute(cgroup.as_deref()).collect()))
+        })
+        .collect()
+}
+
+fn cgroup_attribute(cgroup: Option<&Path>) -> impl Iterator<Item = KeyValue> {
+    cgroup
+        .map(|cgroup| KeyValue::new("cgroup", cgroup.to_string_lossy().into_owned()))
+        .into_iter()
+}
@@ -0,0 +1,57 @@
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::time::Duration;
+
+use nix::errno::Errno;
+use nix::libc;
+use nix::poll::PollFlags;
+
+use crate::{Error, poll_timeout};
+
+/// The fds a monitor polls while waiting, kept between waits so waiting doesn't allocate once the set has grown to fit
+#[derive(Debug, Default)]
+pub(crate) struct PollSet {
+    fds: Vec<libc::pollfd>,
+}
+
+impl PollSet {
+    /// Poll `fds` with the given interest until one is ready or `timeout` elapses, returning how many are ready
+    pub(crate) fn poll<'fd>(
+        &mut self,
+        fds: impl IntoIterator<Item = (BorrowedFd<'fd>, PollFlags)>,
+        timeout: Option<Duration>,
+    ) -> Result<usize, Error> {
+        self.fds.clear();
+        self.fds
+            .extend(fds.into_iter().map(|(fd, interest)| libc::pollfd {
+                fd: fd.as_raw_fd(),
+                events: interest.bits(),
+                revents: 0,
+            }));
+        let timeout = timeout.map_or(-1, |timeout| {
+            let millis = poll_timeout(timeout).as_millis().unwrap_or(u32::MAX);
+            i32::try_from(millis).unwrap_or(i32::MAX)
+        });
+        // SAFETY: the pointer and length describe the initialized entries of `fds`, and the fds are borrowed for the duration of the call
+        let ready = Errno::result(unsafe {
+            libc::poll(
+                self.fds.as_mut_ptr(),
+                self.fds.len() as libc::nfds_t,
+                timeout,
+            )
+        })?;
+        Ok(ready as usize)
+    }
+
+    /// Events reported for the fd at `index` by the last [poll](Self::poll), or [None] if it wasn't polled
+    pub(crate) fn revents(&self, index: usize) -> Option<PollFlags> {
+        self.fds
+            .get(index)
+            .map(|fd| PollFlags::from_bits_retain(fd.revents))
+    }
+
+    /// Whether the last [poll](Self::poll) reported any events for the fd at `index`
+    pub(crate) fn is_ready(&self, index: usize) -> bool {
+        self.revents(index)
+            .is_some_and(|revents| !revents.is_empty())
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    os::fd::{AsFd, BorrowedFd, OwnedFd},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use nix::poll::{PollFd, PollFlags, PollTimeout};
+
+use crate::{
+    Error, PollInterest, PressureBackend, PsiStats, Resource, Sampler, StallKind, Trigger,
+    WaitOutcome, nonblocking_pipe, poll_timeout,
+};
+
+/// Fallback used in place of a trigger when the kernel doesn't allow registering one, periodically checking avg10 against a threshold instead
+pub(crate) struct Poller {
+    sampler: Sampler,
+    kind: StallKind,
+    threshold: f32,
+    last_check: Option<Instant>,
+}
+
+impl Poller {
+    pub(crate) fn new(
+        path: &Path,
+        kind: StallKind,
+        threshold: f32,
+        interval: Duration,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            sampler: Sampler::from_path(path, interval)?,
+            kind,
+            threshold,
+            last_check: None,
+        })
+    }
+
+    pub(crate) fn interval(&self) -> Duration {
+        self.sampler.interval()
+    }
+
+    pub(crate) fn stats(&self) -> Result<PsiStats, Error> {
+        self.sampler.sample()
+    }
+
+    /// Check whether pressure is currently above the threshold
+    pub(crate) fn is_pressured(&self) -> Result<bool, Error> {
+        Ok(self.stats()?.line(self.kind).avg10 >= self.threshold)
+    }
+}
+
+impl PressureBackend for Poller {
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error> {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        loop {
+            let next_check = Instant::now() + self.interval();
+            if let Some(deadline) = deadline.filter(|deadline| next_check > *deadline) {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if sleep(remaining, cancellation)? {
+                    return Ok(WaitOutcome::Cancelled);
+                }
+                return Ok(WaitOutcome::TimedOut);
+            }
+            if sleep(self.interval(), cancellation)? {
+                return Ok(WaitOutcome::Cancelled);
+            }
+            self.last_check = Some(Instant::now());
+            if self.is_pressured()? {
+                return Ok(WaitOutcome::Triggered(()));
+            }
+        }
+    }
+
+    /// Check for pressure if at least one interval has passed since the last check, so that persisting pressure doesn't produce an event on every call
+    fn try_wait(&mut self) -> Result<bool, Error> {
+        let now = Instant::now();
+        if self
+            .last_check
+            .is_some_and(|last_check| now < last_check + self.interval())
+        {
+            return Ok(false);
+        }
+        self.last_check = Some(now);
+        self.is_pressured()
+    }
+
+    fn stats(&self, _: Resource) -> Result<PsiStats, Error> {
+        Poller::stats(self)
+    }
+}
+
+impl AsFd for Poller {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.sampler.as_fd()
+    }
+}
+
+/// Stands in for the fds of a paused monitor. Its fd is the read end of a pipe whose write end is held open, so that it never signals anything to event loops
+/// it is registered with, which the kernel's pressure files would by reporting an error without a trigger. Stats are read from the pressure file by its path
+pub(crate) struct Paused {
+    path: PathBuf,
+    placeholder: (OwnedFd, OwnedFd),
+    /// Additional triggers to register again once the monitor is resumed
+    pub(crate) additional: Vec<Trigger>,
+}
+
+impl Paused {
+    pub(crate) fn new(path: &Path, additional: Vec<Trigger>) -> Result<Self, Error> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            placeholder: nonblocking_pipe()?,
+            additional,
+        })
+    }
+}
+
+impl PressureBackend for Paused {
+    fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+        cancellation: Option<BorrowedFd<'_>>,
+    ) -> Result<WaitOutcome<()>, Error> {
+        // Timeouts too long to represent wait forever
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let placeholder = self.placeholder.0.as_fd();
+        // The placeholder never becomes ready, so polling it waits until cancelled. Signals fail the poll with EINTR, for interruptible monitors to return
+        let mut fds = [
+            PollFd::new(placeholder, PollFlags::POLLIN),
+            PollFd::new(cancellation.unwrap_or(placeholder), PollFlags::POLLIN),
+        ];
+        loop {
+            let timeout = deadline.map_or(PollTimeout::NONE, |deadline| {
+                poll_timeout(deadline.saturating_duration_since(Instant::now()))
+            });
+            if nix::poll::poll(&mut fds, timeout)? > 0 {
+                return Ok(WaitOutcome::Cancelled);
+            }
+            // Timeouts longer than poll(2) can wait for take several polls
+            if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                return Ok(WaitOutcome::TimedOut);
+            }
+        }
+    }
+
+    fn stats(&self, _: Resource) -> Result<PsiStats, Error> {
+        PsiStats::read_path(&self.path)
+    }
+
+    fn poll_interest(&self) -> Option<PollInterest> {
+        Some(PollInterest::Readable)
+    }
+}
+
+impl AsFd for Paused {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.placeholder.0.as_fd()
+    }
+}
+
+/// Sleep for `duration`, returning early with `true` if cancelled
+pub(crate) fn sleep(duration: Duration, cancellation: Option<BorrowedFd>) -> Result<bool, Error> {
+    let Some(cancellation) = cancellation else {
+        std::thread::sleep(duration);
+        return Ok(false);
+    };
+    let ready = nix::poll::poll(
+        &mut [PollFd::new(cancellation, PollFlags::POLLIN)],
+        poll_timeout(duration),
+    )?;
+    Ok(ready > 0)
+}
+
+/// Check whether an error opening a monitor means triggers can't be used, rather than a configuration error
+pub(crate) fn is_trigger_unsupported(error: &Error) -> bool {
+    use nix::errno::Errno;
+    matches!(error, Error::PermissionDenied { .. } | Error::PsiDisabled)
+        || matches!(
+            crate::errno(error),
+            Some(Errno::EPERM | Errno::EACCES | Errno::EROFS | Errno::EOPNOTSUPP)
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_fd_never_becomes_ready() {
+        let path = Path::new("/proc/pressure/memory");
+        if !path.exists() {
+            return;
+        }
+        let paused = Paused::new(path, Vec::new()).unwrap();
+        let ready = nix::poll::poll(
+            &mut [PollFd::new(
+                paused.as_fd(),
+                PollFlags::POLLIN | PollFlags::POLLPRI,
+            )],
+            nix::poll::PollTimeout::ZERO,
+        )
+        .unwrap();
+        assert_eq!(ready, 0);
+        paused.stats(Resource::Memory).unwrap();
+    }
+
+    #[test]
+    fn paused_waits_without_timeout_end_on_cancellation_or_signals() {
+        use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal, sigaction};
+        use std::os::unix::thread::JoinHandleExt;
+
+        let path = Path::new("/proc/pressure/memory");
+        let mut paused = Paused::new(path, Vec::new()).unwrap();
+        let token = crate::CancellationToken::new().unwrap();
+        let waiting = std::thread::spawn({
+            let token = token.clone();
+            move || paused.wait(None, Some(token.as_fd())).unwrap()
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        token.cancel();
+        assert_eq!(waiting.join().unwrap(), WaitOutcome::Cancelled);
+
+        // Without SA_RESTART, the signal interrupts the poll
+        extern "C" fn ignore(_: nix::libc::c_int) {}
+        let action = SigAction::new(
+            SigHandler::Handler(ignore),
+            SaFlags::empty(),
+            SigSet::empty(),
+        );
+        // SAFETY: the handler does nothing
+        unsafe { sigaction(Signal::SIGUSR2, &action) }.unwrap();
+        let mut paused = Paused::new(path, Vec::new()).unwrap();
+        let waiting = std::thread::spawn(move || paused.wait(None, None));
+        std::thread::sleep(Duration::from_millis(50));
+        // SAFETY: the thread is still running, as it is only joined afterwards
+        let sent =
+            unsafe { nix::libc::pthread_kill(waiting.as_pthread_t(), Signal::SIGUSR2 as i32) };
+        assert_eq!(sent, 0);
+        assert!(crate::is_interrupted(&waiting.join().unwrap().unwrap_err()));
+    }
+}
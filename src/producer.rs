@@ -0,0 +1,204 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{ErrorKind, Read, Write},
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use nix::{fcntl::OFlag, sys::stat::Mode};
+
+use crate::{Error, Trigger};
+
+/// A process watching through a [WatchEndpoint] socket
+struct Client {
+    stream: UnixStream,
+    /// Bytes of the trigger received so far, until its terminating NUL
+    received: Vec<u8>,
+    trigger: Option<Trigger>,
+}
+
+enum Endpoint {
+    Fifo(File),
+    Socket {
+        listener: UnixListener,
+        clients: Vec<Client>,
+    },
+}
+
+/// The service manager's half of systemd's memory pressure protocol, for supervisors and process managers notifying children that use this crate or sd-event
+///
+/// The endpoint is a socket or fifo that children find through MEMORY_PRESSURE_WATCH. Clients of a socket write the trigger they were passed through
+/// MEMORY_PRESSURE_WRITE once connected, and each [notify](Self::notify) writes a byte to every client, which they receive as an event.
+/// A fifo can only notify a single child and carries no trigger, since the child would read its own write back. The path is removed once the endpoint is dropped
+///
+/// ```no_run
+/// use pressure::{Trigger, WatchEndpoint};
+/// use std::process::Command;
+///
+/// let mut endpoint = WatchEndpoint::socket("/run/my-supervisor/memory-pressure")?;
+/// let trigger: Trigger = "some 150000 2000000".parse()?;
+/// let mut worker = Command::new("/usr/bin/worker");
+/// endpoint.set_env(&mut worker, Some(trigger));
+/// let _child = worker.spawn()?;
+/// // Whenever the supervisor detects pressure
+/// endpoint.notify()?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct WatchEndpoint {
+    path: PathBuf,
+    endpoint: Endpoint,
+}
+
+impl WatchEndpoint {
+    /// Listen on a unix socket at `path`, replacing a stale socket left behind by a previous run
+    pub fn socket(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        if std::fs::symlink_metadata(&path)
+            .is_ok_and(|metadata| std::os::unix::fs::FileTypeExt::is_socket(&metadata.file_type()))
+        {
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            path,
+            endpoint: Endpoint::Socket {
+                listener,
+                clients: Vec::new(),
+            },
+        })
+    }
+
+    /// Create a fifo at `path`, kept open so that the child doesn't see it hang up between notifications
+    pub fn fifo(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        nix::unistd::mkfifo(&path, Mode::from_bits_truncate(0o600))?;
+        let fd = nix::fcntl::open(
+            &path,
+            OFlag::O_RDWR | OFlag::O_CLOEXEC | OFlag::O_NONBLOCK,
+            Mode::empty(),
+        )?;
+        Ok(Self {
+            path,
+            endpoint: Endpoint::Fifo(fd.into()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The MEMORY_PRESSURE_WATCH and MEMORY_PRESSURE_WRITE variables telling a child to watch this endpoint with `trigger`.
+    /// MEMORY_PRESSURE_WRITE is left out without a trigger and for fifos
+    pub fn env(&self, trigger: Option<Trigger>) -> Vec<(&'static str, OsString)> {
+        let mut env = vec![("MEMORY_PRESSURE_WATCH", self.path.clone().into())];
+        if let (Some(trigger), Endpoint::Socket { .. }) = (trigger, &self.endpoint) {
+            env.push((
+                "MEMORY_PRESSURE_WRITE",
+                trigger.to_memory_pressure_write().into(),
+            ));
+        }
+        env
+    }
+
+    /// Set the variables of [env](Self::env) on `command`, removing MEMORY_PRESSURE_WRITE if it was inherited but isn't set
+    pub fn set_env(&self, command: &mut Command, trigger: Option<Trigger>) {
+        command.env_remove("MEMORY_PRESSURE_WRITE");
+        command.envs(self.env(trigger));
+    }
+
+    /// Accept pending clients and read the triggers they wrote, dropping those that disconnected. Returns the number of connected clients
+    pub fn accept(&mut self) -> Result<usize, Error> {
+        let Endpoint::Socket { listener, clients } = &mut self.endpoint else {
+            return Ok(1);
+        };
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    clients.push(Client {
+                        stream,
+                        received: Vec::new(),
+                        trigger: None,
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        clients.retain_mut(Client::receive);
+        Ok(clients.len())
+    }
+
+    /// Triggers written by the connected clients, as of the last [accept](Self::accept)
+    pub fn triggers(&self) -> Vec<Trigger> {
+        match &self.endpoint {
+            Endpoint::Socket { clients, .. } => {
+                clients.iter().filter_map(|client| client.trigger).collect()
+            }
+            Endpoint::Fifo(_) => Vec::new(),
+        }
+    }
+
+    /// Notify every client of an event, first accepting pending ones. Returns the number of clients notified
+    pub fn notify(&mut self) -> Result<usize, Error> {
+        self.accept()?;
+        match &mut self.endpoint {
+            Endpoint::Fifo(fifo) => match fifo.write(&[1]) {
+                Ok(_) => Ok(1),
+                // The child hasn't read earlier notifications yet, which it receives as one event anyway
+                Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(1),
+                Err(e) => Err(e.into()),
+            },
+            Endpoint::Socket { clients, .. } => {
+                clients.retain_mut(|client| match client.stream.write(&[1]) {
+                    Ok(_) => true,
+                    Err(e) => e.kind() == ErrorKind::WouldBlock,
+                });
+                Ok(clients.len())
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Read what the client wrote, returning false once it disconnected
+    fn receive(&mut self) -> bool {
+        let mut buf = [0; 64];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return false,
+                Ok(read) => self.received.extend_from_slice(&buf[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => return false,
+            }
+        }
+        if let Some(end) = self.received.iter().position(|&byte| byte == 0) {
+            // Clients without a trigger write nothing, malformed triggers are ignored like an absent one
+            self.trigger = Trigger::from_bytes(&self.received[..end]).ok();
+            self.received.drain(..=end);
+        }
+        true
+    }
+}
+
+impl AsFd for WatchEndpoint {
+    /// The listening socket, readable when clients are waiting to be accepted, or the fifo
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        match &self.endpoint {
+            Endpoint::Fifo(fifo) => fifo.as_fd(),
+            Endpoint::Socket { listener, .. } => listener.as_fd(),
+        }
+    }
+}
+
+impl Drop for WatchEndpoint {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,207 @@
+//! Exporting pressure stall information and events as Prometheus metrics
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    os::fd::AsFd,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use nix::poll::{PollFd, PollFlags};
+use prometheus::{CounterVec, Encoder, GaugeVec, IntCounterVec, Opts, Registry, TextEncoder};
+
+use crate::{CancellationToken, Error, Event, PsiLine, PsiStats, Resource, poll_timeout};
+
+/// Last total stall time observed for a resource, cgroup and kind of stall, as Prometheus counters can only be incremented
+type Totals = HashMap<(Resource, Option<PathBuf>, &'static str), Duration>;
+
+/// Prometheus metrics describing pressure on resources, labelled by `resource` and `cgroup` (empty for system-wide pressure):
+///
+/// - `pressure_stall_percent`, a gauge of the averages additionally labelled by `kind` (`some` or `full`) and `window` (`10s`, `60s` or `300s`)
+/// - `pressure_stall_seconds_total`, a counter of the total stall time labelled by `kind`
+/// - `pressure_events_total`, a counter of the events recorded through [record_event](Self::record_event)
+///
+/// ```no_run
+/// use pressure::{PressureMetrics, Resource};
+/// use std::time::Duration;
+///
+/// let metrics = PressureMetrics::new()?;
+/// let _sampler = metrics.spawn_sampler(vec![(Resource::Memory, None), (Resource::Io, None)], Duration::from_secs(5))?;
+/// metrics.serve("127.0.0.1:9101")?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct PressureMetrics {
+    registry: Registry,
+    averages: GaugeVec,
+    totals: CounterVec,
+    events: IntCounterVec,
+    observed: Arc<Mutex<Totals>>,
+}
+
+impl PressureMetrics {
+    /// Create the metrics in a registry of their own
+    pub fn new() -> Result<Self, Error> {
+        Self::register(&Registry::new())
+    }
+    /// Create the metrics and add them to an existing registry
+    pub fn register(registry: &Registry) -> Result<Self, Error> {
+        let averages = GaugeVec::new(
+            Opts::new(
+                "pressure_stall_percent",
+                "Percentage of time tasks were stalled on a resource",
+            ),
+            &["resource", "cgroup", "kind", "window"],
+        )?;
+        let totals = CounterVec::new(
+            Opts::new(
+                "pressure_stall_seconds_total",
+                "Total time tasks were stalled on a resource",
+            ),
+            &["resource", "cgroup", "kind"],
+        )?;
+        let events = IntCounterVec::new(
+            Opts::new("pressure_events_total", "Pressure events received"),
+            &["resource", "cgroup"],
+        )?;
+        registry.register(Box::new(averages.clone()))?;
+        registry.register(Box::new(totals.clone()))?;
+        registry.register(Box::new(events.clone()))?;
+        Ok(Self {
+            registry: registry.clone(),
+            averages,
+            totals,
+            events,
+            observed: Arc::default(),
+        })
+    }
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+    /// Update the metrics of a resource, system-wide or within a cgroup, with a sample of its pressure
+    pub fn observe(&self, resource: Resource, cgroup: Option<&Path>, stats: &PsiStats) {
+        let name = resource.to_string();
+        let cgroup_label = cgroup.map(Path::to_string_lossy).unwrap_or_default();
+        let mut observed = self.observed.lock().unwrap_or_else(|e| e.into_inner());
+        for (kind, line) in [("some", &stats.some), ("full", &stats.full)] {
+            for (window, average) in windows(line) {
+                self.averages
+                    .with_label_values(&[&name, &*cgroup_label, kind, window])
+                    .set(f64::from(average));
+            }
+            let previous = observed
+                .insert((resource, cgroup.map(Path::to_path_buf), kind), line.total)
+                .unwrap_or_default();
+            self.totals
+                .with_label_values(&[&name, &*cgroup_label, kind])
+                .inc_by(line.total.saturating_sub(previous).as_secs_f64());
+        }
+    }
+    /// Count a pressure event
+    pub fn record_event(&self, event: &Event) {
+        let cgroup = event.cgroup.as_deref().map(Path::to_string_lossy);
+        self.events
+            .with_label_values(&[&*event.resource.to_string(), &*cgroup.unwrap_or_default()])
+            .inc();
+    }
+    /// The metrics in Prometheus' text exposition format
+    pub fn encode(&self) -> Result<String, Error> {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+    /// Sample each resource, system-wide for a cgroup of [None] or within the given cgroup, every `interval` on a separate thread.
+    /// Resources that can't be read are skipped
+    pub fn spawn_sampler(
+        &self,
+        targets: Vec<(Resource, Option<PathBuf>)>,
+        interval: Duration,
+    ) -> Result<MetricsSampler, Error> {
+        let cancellation = CancellationToken::new()?;
+        let token = cancellation.clone();
+        let metrics = self.clone();
+        let thread = std::thread::Builder::new()
+            .name("pressure-metrics".into())
+            .spawn(move || {
+                loop {
+                    for (resource, cgroup) in &targets {
+                        let stats = match cgroup {
+                            Some(cgroup) => {
+                                PsiStats::read_path(cgroup.join(resource.cgroup_file()))
+                            }
+                            None => PsiStats::read(*resource),
+                        };
+                        if let Ok(stats) = stats {
+                            metrics.observe(*resource, cgroup.as_deref(), &stats);
+                        }
+                    }
+                    let mut fds = [PollFd::new(token.as_fd(), PollFlags::POLLIN)];
+                    if nix::poll::poll(&mut fds, poll_timeout(interval))
+                        .is_ok_and(|ready| ready > 0)
+                    {
+                        return;
+                    }
+                }
+            })?;
+        Ok(MetricsSampler {
+            cancellation,
+            thread,
+        })
+    }
+    /// Serve the metrics over HTTP at `/metrics` on a separate thread, which runs for as long as the process does. Returns the address it listens on
+    pub fn serve(&self, addr: impl ToSocketAddrs) -> Result<SocketAddr, Error> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let metrics = self.clone();
+        std::thread::Builder::new()
+            .name("pressure-metrics-http".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // A misbehaving client only affects its own request
+                    let _ = metrics.respond(stream);
+                }
+            })?;
+        Ok(local_addr)
+    }
+    fn respond(&self, mut stream: TcpStream) -> Result<(), Error> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut request = String::new();
+        BufReader::new(&stream).read_line(&mut request)?;
+        let encoder = TextEncoder::new();
+        let (status, content_type, body) = match request.split_whitespace().nth(1) {
+            Some("/metrics") => ("200 OK", encoder.format_type(), self.encode()?),
+            _ => ("404 Not Found", "text/plain", "not found\n".to_owned()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        Ok(())
+    }
+}
+
+fn windows(line: &PsiLine) -> [(&'static str, f32); 3] {
+    [
+        ("10s", line.avg10),
+        ("60s", line.avg60),
+        ("300s", line.avg300),
+    ]
+}
+
+/// Thread updating [PressureMetrics] from samples, see [PressureMetrics::spawn_sampler]
+pub struct MetricsSampler {
+    cancellation: CancellationToken,
+    thread: JoinHandle<()>,
+}
+
+impl MetricsSampler {
+    /// Stop sampling, waiting for the thread to exit
+    pub fn stop(self) {
+        self.cancellation.cancel();
+        let _ = self.thread.join();
+    }
+}
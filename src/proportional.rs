@@ -0,0 +1,74 @@
+/// Computes how much memory to release each cycle from how far pressure exceeds a target, so that the response scales with pressure instead of being all-or-nothing
+///
+/// The amount is proportional to the excess avg10, plus an integral term that grows while pressure stays above the target so that a persisting excess
+/// is released eventually. The integral is reset as soon as pressure drops to the target, and the amount is capped at a maximum per cycle
+///
+/// ```
+/// use pressure::ReclaimController;
+///
+/// // Release 4MiB per percentage point above 10% some avg10, and at most 64MiB per cycle
+/// let mut controller = ReclaimController::new(10.0, 4 << 20).max_bytes(64 << 20);
+/// assert_eq!(controller.update(5.0), 0);
+/// assert_eq!(controller.update(12.5), 10 << 20);
+/// assert_eq!(controller.update(40.0), 64 << 20);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReclaimController {
+    target: f32,
+    proportional: f64,
+    integral: f64,
+    max_bytes: u64,
+    /// Excess accumulated over the cycles pressure stayed above the target, in percentage points
+    accumulated: f64,
+}
+
+impl ReclaimController {
+    /// Aim for `target` avg10, releasing `bytes_per_percent` for every percentage point above it. There is no integral term or cap by default
+    pub fn new(target: f32, bytes_per_percent: u64) -> Self {
+        Self {
+            target,
+            proportional: bytes_per_percent as f64,
+            integral: 0.0,
+            max_bytes: u64::MAX,
+            accumulated: 0.0,
+        }
+    }
+
+    /// Additionally release `bytes_per_percent` for every percentage point accumulated above the target over the cycles pressure stayed above it
+    pub fn integral(mut self, bytes_per_percent: u64) -> Self {
+        self.integral = bytes_per_percent as f64;
+        self
+    }
+
+    /// Most bytes to release in one cycle
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// Compute how many bytes to release this cycle given the current avg10
+    pub fn update(&mut self, avg10: f32) -> u64 {
+        let excess = f64::from(avg10 - self.target);
+        if excess <= 0.0 {
+            self.accumulated = 0.0;
+            return 0;
+        }
+        self.accumulated += excess;
+        let bytes = self.proportional * excess + self.integral * self.accumulated;
+        if bytes >= self.max_bytes as f64 {
+            // Stop accumulating once capped, so that the integral doesn't keep the output capped long after pressure eased
+            self.accumulated -= excess;
+            return self.max_bytes;
+        }
+        bytes as u64
+    }
+
+    /// Forget the excess accumulated so far
+    pub fn reset(&mut self) {
+        self.accumulated = 0.0;
+    }
+}
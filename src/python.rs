@@ -0,0 +1,338 @@
+//! Python module, built with `maturin build --features python` and imported as `pressure`
+//!
+//! ```python
+//! import asyncio, pressure
+//!
+//! async def main():
+//!     monitor = pressure.PressureMonitor("memory")
+//!     while event := await monitor.wait_async():
+//!         print(event.resource, "pressure, severity", monitor.severity())
+//!
+//! asyncio.run(main())
+//! ```
+use std::{
+    ffi::CStr,
+    os::fd::AsRawFd,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+    sync::PyOnceLock,
+};
+
+use crate::{CancellationToken, Error, Resource, WaitOutcome};
+
+create_exception!(
+    pressure,
+    PressureError,
+    PyOSError,
+    "Raised for failures that aren't a failed syscall, such as pressure stall information being disabled"
+);
+
+impl From<Error> for PyErr {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(e) => e.into(),
+            Error::Nix(errno) => std::io::Error::from(errno).into(),
+            e => PressureError::new_err(e.to_string()),
+        }
+    }
+}
+
+fn parse_resource(resource: &str) -> PyResult<Resource> {
+    match resource {
+        "memory" => Ok(Resource::Memory),
+        "cpu" => Ok(Resource::Cpu),
+        "io" => Ok(Resource::Io),
+        "irq" => Ok(Resource::Irq),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown resource {resource:?}, expected memory, cpu, io or irq"
+        ))),
+    }
+}
+
+/// Lock state a panicking thread may have left behind, which stays consistent as it is only replaced whole
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Stall information for a single kind of stall, with the total in seconds
+#[pyclass(name = "PsiLine", frozen, get_all, skip_from_py_object)]
+#[derive(Clone)]
+struct PyPsiLine {
+    avg10: f32,
+    avg60: f32,
+    avg300: f32,
+    total: f64,
+}
+
+/// Current pressure stall information for a resource
+#[pyclass(name = "PsiStats", frozen, get_all)]
+struct PyPsiStats {
+    some: PyPsiLine,
+    full: PyPsiLine,
+}
+
+impl From<crate::PsiLine> for PyPsiLine {
+    fn from(line: crate::PsiLine) -> Self {
+        Self {
+            avg10: line.avg10,
+            avg60: line.avg60,
+            avg300: line.avg300,
+            total: line.total.as_secs_f64(),
+        }
+    }
+}
+
+impl From<crate::PsiStats> for PyPsiStats {
+    fn from(stats: crate::PsiStats) -> Self {
+        Self {
+            some: stats.some.into(),
+            full: stats.full.into(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyPsiStats {
+    /// Read system-wide pressure stall information for a resource
+    #[staticmethod]
+    #[pyo3(signature = (resource = "memory"))]
+    fn read(resource: &str) -> PyResult<Self> {
+        Ok(crate::PsiStats::read(parse_resource(resource)?)?.into())
+    }
+
+    /// Read pressure stall information from a pressure file
+    #[staticmethod]
+    fn read_path(path: PathBuf) -> PyResult<Self> {
+        Ok(crate::PsiStats::read_path(path)?.into())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PsiStats(some.avg10={}, full.avg10={})",
+            self.some.avg10, self.full.avg10
+        )
+    }
+}
+
+/// A pressure event, describing the monitor it was received on
+#[pyclass(name = "Event", frozen, get_all)]
+struct PyEvent {
+    resource: String,
+    kind: String,
+    /// The trigger that fired formatted as written to the pressure file, or None
+    trigger: Option<String>,
+    cgroup: Option<PathBuf>,
+    /// "pressure", "cleared" or "reconnected"
+    transition: &'static str,
+    coalesced: u32,
+    suppressed: u32,
+}
+
+impl From<crate::Event> for PyEvent {
+    fn from(event: crate::Event) -> Self {
+        Self {
+            resource: event.resource.to_string(),
+            kind: event.kind.to_string(),
+            trigger: event.trigger.map(|trigger| trigger.to_string()),
+            cgroup: event.cgroup,
+            transition: match event.transition {
+                crate::Transition::Pressure => "pressure",
+                crate::Transition::Cleared => "cleared",
+                crate::Transition::Reconnected => "reconnected",
+            },
+            coalesced: event.coalesced,
+            suppressed: event.suppressed,
+        }
+    }
+}
+
+#[pymethods]
+impl PyEvent {
+    fn __repr__(&self) -> String {
+        format!(
+            "Event(resource={:?}, kind={:?}, cgroup={:?}, transition={:?})",
+            self.resource, self.kind, self.cgroup, self.transition
+        )
+    }
+}
+
+/// Coroutine waiting on a monitor in asyncio's executor, written in Python as coroutines can't be implemented with stable PyO3
+const WAIT_ASYNC_SOURCE: &CStr = cr#"
+import asyncio
+
+async def wait_async(monitor):
+    try:
+        return await asyncio.get_running_loop().run_in_executor(None, monitor.wait)
+    except asyncio.CancelledError:
+        monitor.cancel()
+        raise
+"#;
+
+static WAIT_ASYNC: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+
+/// Waits for pressure events on a resource, honoring systemd's memory pressure interface if present
+#[pyclass(name = "PressureMonitor", frozen)]
+struct PyPressureMonitor {
+    resource: Resource,
+    cgroup: Option<PathBuf>,
+    monitor: Mutex<crate::PressureMonitor>,
+    /// Token interrupting the wait in progress, replaced once it was used as tokens stay cancelled
+    cancellation: Mutex<CancellationToken>,
+}
+
+#[pymethods]
+impl PyPressureMonitor {
+    /// Create a monitor for a resource, within a cgroup v2 hierarchy if one is given
+    #[new]
+    #[pyo3(signature = (resource = "memory", cgroup = None))]
+    fn new(resource: &str, cgroup: Option<PathBuf>) -> PyResult<Self> {
+        let resource = parse_resource(resource)?;
+        let mut monitor = match cgroup {
+            Some(cgroup) => crate::PressureMonitor::for_cgroup(cgroup, resource)?,
+            None => crate::PressureMonitor::with_resource(resource)?,
+        };
+        let cancellation = monitor.cancellation_token()?;
+        Ok(Self {
+            resource,
+            cgroup: monitor.cgroup().map(PathBuf::from),
+            monitor: Mutex::new(monitor),
+            cancellation: Mutex::new(cancellation),
+        })
+    }
+
+    #[getter]
+    fn resource(&self) -> String {
+        self.resource.to_string()
+    }
+
+    #[getter]
+    fn cgroup(&self) -> Option<PathBuf> {
+        self.cgroup.clone()
+    }
+
+    /// Read the current pressure stall information for the monitored resource, once a wait in progress on another thread returned
+    fn stats(&self, py: Python<'_>) -> PyResult<PyPsiStats> {
+        Ok(py.detach(|| lock(&self.monitor).stats())?.into())
+    }
+
+    /// Classify the current pressure as "low", "medium" or "critical", once a wait in progress on another thread returned
+    fn severity(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(py.detach(|| lock(&self.monitor).severity())?.to_string())
+    }
+
+    /// Block until a pressure event occurs, returning None once `timeout` seconds elapsed or the wait was cancelled.
+    /// The GIL is released while waiting
+    #[pyo3(signature = (timeout = None))]
+    fn wait(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Option<PyEvent>> {
+        let timeout = timeout
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let outcome = py.detach(|| {
+            let mut monitor = lock(&self.monitor);
+            let outcome = match timeout {
+                Some(timeout) => monitor.wait_timeout(timeout),
+                None => monitor.wait(),
+            };
+            if let Ok(WaitOutcome::Cancelled) = outcome {
+                monitor.cancellation = None;
+                *lock(&self.cancellation) = monitor.cancellation_token()?;
+            }
+            outcome
+        })?;
+        match outcome {
+            WaitOutcome::Triggered(event) => Ok(Some(event.into())),
+            WaitOutcome::TimedOut | WaitOutcome::Cancelled | WaitOutcome::Interrupted => Ok(None),
+            WaitOutcome::Hangup => Err(Error::Hangup.into()),
+            WaitOutcome::Error(revents) => Err(Error::PollError(revents).into()),
+        }
+    }
+
+    /// Wait for a pressure event from asyncio, in the event loop's default executor, returning a coroutine.
+    /// Cancelling the awaiting task cancels the wait, so that the executor's thread is freed
+    fn wait_async<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let py = slf.py();
+        WAIT_ASYNC
+            .get_or_try_init(py, || {
+                let module = PyModule::from_code(
+                    py,
+                    WAIT_ASYNC_SOURCE,
+                    c"pressure_wait_async.py",
+                    c"pressure_wait_async",
+                )?;
+                PyResult::Ok(module.getattr("wait_async")?.unbind())
+            })?
+            .bind(py)
+            .call1((slf,))
+    }
+
+    /// Interrupt the wait in progress from another thread, making it return None
+    fn cancel(&self) {
+        lock(&self.cancellation).cancel();
+    }
+
+    /// The monitor's fd. Pressure files signal events with POLLPRI, while systemd's sockets and fifos become readable
+    fn fileno(&self, py: Python<'_>) -> i32 {
+        py.detach(|| lock(&self.monitor).as_raw_fd())
+    }
+}
+
+/// Periodically reads pressure stall information without registering a trigger, iterating over a PsiStats per interval
+#[pyclass(name = "Sampler", frozen)]
+struct PySampler {
+    sampler: Mutex<crate::Sampler>,
+}
+
+#[pymethods]
+impl PySampler {
+    /// Sample a resource every `interval` seconds, within a cgroup v2 hierarchy if one is given
+    #[new]
+    #[pyo3(signature = (resource = "memory", interval = 1.0, cgroup = None))]
+    fn new(resource: &str, interval: f64, cgroup: Option<PathBuf>) -> PyResult<Self> {
+        let resource = parse_resource(resource)?;
+        let interval = Duration::try_from_secs_f64(interval)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let sampler = match cgroup {
+            Some(cgroup) => crate::Sampler::for_cgroup(cgroup, resource, interval)?,
+            None => crate::Sampler::new(resource, interval)?,
+        };
+        Ok(Self {
+            sampler: Mutex::new(sampler),
+        })
+    }
+
+    /// Take a sample immediately, without waiting for the next interval
+    fn sample(&self) -> PyResult<PyPsiStats> {
+        Ok(lock(&self.sampler).sample()?.into())
+    }
+
+    fn __iter__(slf: Bound<'_, Self>) -> Bound<'_, Self> {
+        slf
+    }
+
+    /// Wait for the next interval and sample, releasing the GIL while waiting
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<PyPsiStats>> {
+        let sample = py.detach(|| lock(&self.sampler).next());
+        Ok(sample.transpose()?.map(PyPsiStats::from))
+    }
+}
+
+#[pymodule(name = "pressure")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyPressureMonitor>()?;
+    module.add_class::<PySampler>()?;
+    module.add_class::<PyPsiStats>()?;
+    module.add_class::<PyPsiLine>()?;
+    module.add_class::<PyEvent>()?;
+    module.add("PressureError", module.py().get_type::<PressureError>())?;
+    Ok(())
+}
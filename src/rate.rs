@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+/// How often a monitor delivered events, to check that it is still working or to base policies on how often pressure occurs
+///
+/// ```no_run
+/// use pressure::PressureMonitor;
+///
+/// let monitor = PressureMonitor::new()?;
+/// // ...
+/// let rate = monitor.event_rate();
+/// if rate.last_hour > 30 {
+///     // Pressure keeps recurring, shrink for good instead of waiting for the next event
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventRate {
+    /// Events delivered since the monitor was created
+    pub total: u64,
+    /// Events delivered within the last minute, counted per whole second
+    pub last_minute: u64,
+    /// Events delivered within the last hour, counted per whole minute
+    pub last_hour: u64,
+    /// Time since the last event was delivered, or [None] if there was none yet
+    pub since_last: Option<Duration>,
+    /// Longest time without events since the monitor was created, including the time before the first event and since the last one
+    pub longest_quiet: Duration,
+}
+
+/// Counts the events a monitor delivered
+#[derive(Debug, Clone)]
+pub(crate) struct EventCounter {
+    created: Instant,
+    total: u64,
+    last: Option<Instant>,
+    /// Events delivered in each of the last 60 seconds and minutes since the monitor was created, indexed modulo 60 so that counting doesn't allocate
+    seconds: [u32; 60],
+    minutes: [u32; 60],
+    /// Longest time between events, not counting the time since the last one
+    longest_quiet: Duration,
+}
+
+impl Default for EventCounter {
+    fn default() -> Self {
+        Self {
+            created: Instant::now(),
+            total: 0,
+            last: None,
+            seconds: [0; 60],
+            minutes: [0; 60],
+            longest_quiet: Duration::ZERO,
+        }
+    }
+}
+
+impl EventCounter {
+    /// Count an event delivered now
+    pub(crate) fn record(&mut self) {
+        let now = Instant::now();
+        let quiet = now.saturating_duration_since(self.last.unwrap_or(self.created));
+        self.longest_quiet = self.longest_quiet.max(quiet);
+        let latest = self.elapsed_secs(self.last);
+        let elapsed = self.elapsed_secs(Some(now));
+        advance(&mut self.seconds, latest, elapsed);
+        advance(&mut self.minutes, latest / 60, elapsed / 60);
+        self.total += 1;
+        self.last = Some(now);
+    }
+
+    pub(crate) fn rate(&self) -> EventRate {
+        let now = Instant::now();
+        let latest = self.elapsed_secs(self.last);
+        let elapsed = self.elapsed_secs(Some(now));
+        let since_last = self.last.map(|last| now.saturating_duration_since(last));
+        let quiet = since_last.unwrap_or_else(|| now.saturating_duration_since(self.created));
+        EventRate {
+            total: self.total,
+            last_minute: within(&self.seconds, latest, elapsed),
+            last_hour: within(&self.minutes, latest / 60, elapsed / 60),
+            since_last,
+            longest_quiet: self.longest_quiet.max(quiet),
+        }
+    }
+
+    /// Whole seconds between the monitor's creation and `at`, or 0 without an instant
+    fn elapsed_secs(&self, at: Option<Instant>) -> u64 {
+        at.map_or(0, |at| at.saturating_duration_since(self.created).as_secs())
+    }
+}
+
+/// Count an event in the bucket of unit `now`, clearing the buckets of the units passed since `latest`, the unit of the previous event
+fn advance(buckets: &mut [u32; 60], latest: u64, now: u64) {
+    for unit in latest + 1..=now.min(latest + 60) {
+        buckets[(unit % 60) as usize] = 0;
+    }
+    buckets[(now % 60) as usize] += 1;
+}
+
+/// Events counted in the 60 units up to `now`, given that the buckets were last advanced to unit `latest`
+fn within(buckets: &[u32; 60], latest: u64, now: u64) -> u64 {
+    (now.saturating_sub(59)..=latest)
+        .map(|unit| u64::from(buckets[(unit % 60) as usize]))
+        .sum()
+}
@@ -0,0 +1,454 @@
+//! Runtime-agnostic asynchronous monitoring, which the tokio and async-io modules adapt to their event loops
+use std::{
+    future::{Future, IntoFuture},
+    io,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Instant,
+};
+
+use futures_core::Stream;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use nix::sys::epoll::{Epoll, EpollEvent, EpollTimeout};
+
+use crate::{
+    ConfigSource, Error, Event, EventRate, PollInterest, PressureBackend, PressureMonitorBuilder,
+    PsiStats, Resource, Severity, Source, Trigger, event::Target, monitor_from_fd, polling::Poller,
+    readiness::ReadableMonitor,
+};
+
+/// An event loop asynchronous monitors wait on, which only needs to report when an fd becomes readable and when a deadline passes.
+/// Implemented by [tokio::Tokio](crate::tokio::Tokio) and [async_io::AsyncIo](crate::async_io::AsyncIo), and implementable for other runtimes
+///
+/// Pressure files signal events through priority readiness, so monitors wrap them in an epoll instance that becomes readable instead
+pub trait Reactor {
+    /// An fd registered with the event loop for readability
+    type Registration: Send + Unpin;
+    /// A future completing once a deadline passed
+    type Timer: Future + Send + Unpin;
+
+    /// Register an fd for readability, which is only ever read from by the monitor
+    ///
+    /// # Safety
+    ///
+    /// `fd` must stay open until the registration is dropped
+    unsafe fn register(fd: RawFd) -> io::Result<Self::Registration>;
+
+    /// Check whether the registered fd is readable, waking the task once it becomes readable otherwise
+    fn poll_readable(
+        registration: &Self::Registration,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>>;
+
+    /// Forget the readiness reported by [poll_readable](Self::poll_readable), right before the monitor consumes what the fd signalled,
+    /// so that polling again waits until the fd becomes readable again
+    fn clear_readiness(registration: &Self::Registration, cx: &mut Context<'_>);
+
+    /// Create a timer completing at `deadline`
+    fn timer(deadline: Instant) -> Self::Timer;
+}
+
+/// Asynchronous equivalent to [PressureMonitor](crate::PressureMonitor), waiting on the event loop of any [Reactor]
+///
+/// ```no_run
+/// use pressure::{AsyncPressureMonitor, tokio::Tokio};
+///
+/// # async fn example() -> Result<(), pressure::Error> {
+/// let mut monitor = AsyncPressureMonitor::<Tokio>::new()?;
+/// loop {
+///     let event = monitor.wait().await?;
+///     // Drop caches
+/// }
+/// # }
+/// ```
+pub struct AsyncPressureMonitor<R: Reactor> {
+    source: AsyncSource<R>,
+    target: Target,
+    /// Timer waking the monitor at the target's wake deadline, along with that deadline
+    wake: Option<(Instant, R::Timer)>,
+    state: WaitState,
+}
+
+/// Progress of a wait, kept in the monitor so that dropping the future returned by [wait](AsyncPressureMonitor::wait) doesn't lose it
+enum WaitState {
+    /// Waiting for the source to signal an event
+    Waiting,
+    /// An event was consumed from the source, but reading the stats needed to decide whether to deliver it failed
+    Received(Box<Event>),
+}
+
+/// Fields are dropped in order, so registrations are dropped before the fds they refer to are closed
+enum AsyncSource<R: Reactor> {
+    Trigger(R::Registration, ReadableMonitor),
+    /// The timer completes once the poller should check pressure again
+    Polling(Option<R::Timer>, Poller),
+    /// An epoll instance makes backends signalling priority readiness readable
+    Backend(
+        R::Registration,
+        Option<PriorityEpoll>,
+        Box<dyn PressureBackend>,
+    ),
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+type PriorityEpoll = Epoll;
+// Priority readiness is only used by pressure files, which other platforms don't have
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+type PriorityEpoll = std::convert::Infallible;
+
+impl<R: Reactor> AsyncPressureMonitor<R> {
+    pub fn new() -> Result<Self, Error> {
+        Self::with_resource(Resource::Memory)
+    }
+
+    pub fn with_resource(resource: Resource) -> Result<Self, Error> {
+        PressureMonitorBuilder::new()
+            .resource(resource)
+            .build_async()
+    }
+
+    pub fn for_cgroup(cgroup: impl AsRef<Path>, resource: Resource) -> Result<Self, Error> {
+        PressureMonitorBuilder::new()
+            .resource(resource)
+            .cgroup(cgroup.as_ref())
+            .build_async()
+    }
+
+    pub fn for_current_cgroup(resource: Resource) -> Result<Self, Error> {
+        PressureMonitorBuilder::new()
+            .resource(resource)
+            .current_cgroup()
+            .build_async()
+    }
+
+    pub fn from_path(path: impl AsRef<Path>, trigger: Trigger) -> Result<Self, Error> {
+        PressureMonitorBuilder::new()
+            .path(path.as_ref())
+            .trigger(trigger)
+            .build_async()
+    }
+
+    /// Create a monitor from an already opened pressure file, fifo or connected socket, see [PressureMonitor::from_fd](crate::PressureMonitor::from_fd)
+    pub fn from_fd(fd: OwnedFd) -> Result<Self, Error> {
+        Self::from_source(Source::Trigger(monitor_from_fd(fd)?), Target::for_fd())
+    }
+
+    /// Create a monitor from a file descriptor passed through socket activation, see [PressureMonitor::from_listen_fd](crate::PressureMonitor::from_listen_fd)
+    pub fn from_listen_fd(name: &str) -> Result<Option<Self>, Error> {
+        crate::activation::listen_fd(name)?
+            .map(Self::from_fd)
+            .transpose()
+    }
+
+    /// Create a monitor waiting on a custom source of pressure events, see [PressureMonitor::from_backend](crate::PressureMonitor::from_backend).
+    /// The backend must signal events on its fd
+    pub fn from_backend(
+        backend: impl PressureBackend + 'static,
+        resource: Resource,
+    ) -> Result<Self, Error> {
+        Self::from_source(
+            Source::Backend(Box::new(backend)),
+            Target::for_backend(resource),
+        )
+    }
+
+    pub(crate) fn from_source(source: Source, target: Target) -> Result<Self, Error> {
+        let source = match source {
+            Source::Trigger(pressure_file) => trigger(pressure_file)?,
+            Source::Polling(poller) => AsyncSource::Polling(None, poller),
+            Source::Paused(_) => return Err(Error::Paused),
+            Source::Backend(backend) => {
+                let epoll = match backend.poll_interest().ok_or(Error::NoFileDescriptor)? {
+                    PollInterest::Readable => None,
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    PollInterest::Priority => Some(crate::readiness::priority_epoll(&backend)?),
+                    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                    PollInterest::Priority => return Err(Error::UnexpectedFileType),
+                };
+                let fd = match &epoll {
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    Some(epoll) => epoll.0.as_raw_fd(),
+                    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                    Some(never) => match *never {},
+                    None => backend.as_fd().as_raw_fd(),
+                };
+                // SAFETY: the registration is dropped before the epoll instance and the backend
+                let registration = unsafe { R::register(fd)? };
+                AsyncSource::Backend(registration, epoll, backend)
+            }
+        };
+        Ok(Self {
+            source,
+            target,
+            wake: None,
+            state: WaitState::Waiting,
+        })
+    }
+
+    /// Replace the monitor's trigger, see [PressureMonitor::set_trigger](crate::PressureMonitor::set_trigger)
+    pub fn set_trigger(&mut self, trigger: Trigger) -> Result<(), Error> {
+        if let AsyncSource::Polling(..) | AsyncSource::Backend(..) = self.source {
+            return Err(Error::NoFileDescriptor);
+        }
+        self.source = self::trigger(self.target.reopen(trigger)?)?;
+        Ok(())
+    }
+
+    /// Read the current pressure stall information for the monitored resource
+    pub fn stats(&self) -> Result<PsiStats, Error> {
+        match &self.source {
+            AsyncSource::Trigger(_, monitor) => monitor.pressure_file().stats(self.target.resource),
+            AsyncSource::Polling(_, poller) => poller.stats(),
+            AsyncSource::Backend(.., backend) => backend.stats(self.target.resource),
+        }
+    }
+
+    pub fn resource(&self) -> Resource {
+        self.target.resource
+    }
+
+    /// The cgroup being monitored, or [None] for system-wide pressure
+    pub fn cgroup(&self) -> Option<&Path> {
+        self.target.cgroup()
+    }
+
+    /// Classify the current pressure on the monitored resource, see [PressureMonitorBuilder::severity_thresholds]
+    pub fn severity(&self) -> Result<Severity, Error> {
+        if let AsyncSource::Backend(.., backend) = &self.source
+            && let Some(severity) = backend.severity()
+        {
+            return Ok(severity);
+        }
+        Ok(self.target.classify(&self.stats()?))
+    }
+
+    /// Whether the monitor was configured explicitly or through systemd's memory pressure interface, see [PressureMonitorBuilder::prefer_environment]
+    pub fn config_source(&self) -> ConfigSource {
+        self.target.config
+    }
+
+    /// Whether the monitor fell back to polling because a trigger couldn't be registered, see [PressureMonitorBuilder::fallback_to_polling]
+    pub fn is_polling(&self) -> bool {
+        matches!(self.source, AsyncSource::Polling(..))
+    }
+
+    /// How often the monitor delivered events
+    pub fn event_rate(&self) -> EventRate {
+        self.target.events.rate()
+    }
+
+    /// Take ownership of the monitor's fd, deregistering it from the event loop, see [PressureMonitor::into_fd](crate::PressureMonitor::into_fd)
+    pub fn into_fd(self) -> Result<OwnedFd, Error> {
+        match self.source {
+            AsyncSource::Trigger(registration, monitor) => {
+                drop(registration);
+                Ok(monitor.into_inner().into())
+            }
+            AsyncSource::Polling(..) | AsyncSource::Backend(..) => Err(Error::NoFileDescriptor),
+        }
+    }
+
+    /// Wait for a single pressure event to occur.
+    /// It is safe to call this function in a busy loop, as even if memory pressure persists the kernel limits the amount of events sent
+    ///
+    /// The returned future is cancel-safe, see [poll_wait](Self::poll_wait)
+    pub fn wait(&mut self) -> Wait<'_, R> {
+        Wait { monitor: self }
+    }
+
+    /// Wait for a pressure event at which pressure is at least `min` severe
+    pub async fn wait_for_severity(&mut self, min: Severity) -> Result<(Event, Severity), Error> {
+        loop {
+            let event = self.wait().await?;
+            let severity = self.severity()?;
+            if severity >= min {
+                return Ok((event, severity));
+            }
+        }
+    }
+
+    /// Turn the monitor into a [Stream] of pressure events, for use with stream combinators
+    pub fn into_stream(self) -> AsyncPressureStream<R> {
+        AsyncPressureStream { monitor: self }
+    }
+
+    /// Poll for a pressure event, registering the task to be woken once one may be available.
+    ///
+    /// Progress is kept in the monitor rather than in a future: readiness is only cleared right before the event it signalled is consumed,
+    /// and an event consumed from the source stays in the monitor until it is returned. So a [wait](Self::wait) dropped at any await point,
+    /// for example by losing a `tokio::select!` race, neither misses an event nor reports one twice on the next call
+    pub fn poll_wait(&mut self, cx: &mut Context<'_>) -> Poll<Result<Event, Error>> {
+        loop {
+            let event = match std::mem::replace(&mut self.state, WaitState::Waiting) {
+                WaitState::Received(event) => *event,
+                WaitState::Waiting => match self.poll_next_event(cx) {
+                    Poll::Ready(event) => event?,
+                    Poll::Pending => {
+                        if let Some(event) = ready!(self.poll_wake(cx))? {
+                            return Poll::Ready(Ok(event));
+                        }
+                        continue;
+                    }
+                },
+            };
+            let stats = match self.target.reads_pressure().then(|| self.stats()) {
+                Some(Err(e)) => {
+                    // Delivered by the next call instead of being lost
+                    self.state = WaitState::Received(Box::new(event));
+                    return Poll::Ready(Err(e));
+                }
+                stats => stats.transpose()?,
+            };
+            if let Some(event) = self.target.fired(event, stats.as_ref()) {
+                return Poll::Ready(self.target.enrich(event));
+            }
+        }
+    }
+
+    /// Wait for the target's wake deadline, returning events held back or pressure clearing once it passed
+    fn poll_wake(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Event>, Error>> {
+        let Some(deadline) = self.target.wake_deadline() else {
+            self.wake = None;
+            return Poll::Pending;
+        };
+        let (_, timer) = match &mut self.wake {
+            Some(wake) if wake.0 == deadline => wake,
+            wake => wake.insert((deadline, R::timer(deadline))),
+        };
+        ready!(Pin::new(timer).poll(cx));
+        self.wake = None;
+        Poll::Ready(self.woken())
+    }
+
+    /// Handle waking up without an event, returning events held back or pressure clearing
+    fn woken(&mut self) -> Result<Option<Event>, Error> {
+        let stats = self.target.clear_due().then(|| self.stats()).transpose()?;
+        self.target
+            .woken(stats.as_ref())
+            .map(|event| self.target.enrich(event))
+            .transpose()
+    }
+
+    fn poll_next_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<Event, Error>> {
+        let coalesced = match &mut self.source {
+            AsyncSource::Trigger(registration, monitor) => loop {
+                ready!(R::poll_readable(registration, cx))?;
+                // Readiness is kept on failure, so that waiting again fails right away
+                monitor.pressure_file().check_alive()?;
+                R::clear_readiness(registration, cx);
+                // Readiness can outlive the notifications it signalled, when an earlier wait already read them
+                if let Some(coalesced) = monitor.receive()? {
+                    break coalesced;
+                }
+            },
+            AsyncSource::Polling(timer, poller) => loop {
+                let next_check =
+                    timer.get_or_insert_with(|| R::timer(Instant::now() + poller.interval()));
+                ready!(Pin::new(next_check).poll(cx));
+                *timer = None;
+                if poller.is_pressured()? {
+                    break 1;
+                }
+            },
+            AsyncSource::Backend(registration, epoll, backend) => {
+                ready!(R::poll_readable(registration, cx))?;
+                R::clear_readiness(registration, cx);
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                if let Some(epoll) = epoll {
+                    epoll.wait(&mut [EpollEvent::empty()], EpollTimeout::ZERO)?;
+                }
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                let _ = epoll;
+                backend.acknowledge()?;
+                1
+            }
+        };
+        Poll::Ready(Ok(Event {
+            coalesced,
+            ..self.target.event()
+        }))
+    }
+}
+
+/// The fd pressure events are signalled on, see [PressureMonitor::poll_interest](crate::PressureMonitor::poll_interest)
+impl<R: Reactor> AsFd for AsyncPressureMonitor<R> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        match &self.source {
+            AsyncSource::Trigger(_, monitor) => monitor.pressure_file().as_fd(),
+            AsyncSource::Polling(_, poller) => poller.as_fd(),
+            AsyncSource::Backend(.., backend) => backend.as_fd(),
+        }
+    }
+}
+
+impl<R: Reactor> AsRawFd for AsyncPressureMonitor<R> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_fd().as_raw_fd()
+    }
+}
+
+/// Register a pressure file, fifo or socket, waiting on pressure files through an epoll instance
+fn trigger<R: Reactor>(pressure_file: crate::MonitorType) -> Result<AsyncSource<R>, Error> {
+    let monitor = ReadableMonitor::new(pressure_file)?;
+    // SAFETY: the registration is dropped before the monitor
+    let registration = unsafe { R::register(monitor.as_fd().as_raw_fd())? };
+    Ok(AsyncSource::Trigger(registration, monitor))
+}
+
+/// Stream of pressure events produced by [AsyncPressureMonitor::into_stream]
+pub struct AsyncPressureStream<R: Reactor> {
+    monitor: AsyncPressureMonitor<R>,
+}
+
+impl<R: Reactor> AsyncPressureStream<R> {
+    /// Get the monitor back, for example to read its stats between events
+    pub fn into_inner(self) -> AsyncPressureMonitor<R> {
+        self.monitor
+    }
+}
+
+impl<R: Reactor> Stream for AsyncPressureStream<R> {
+    type Item = Result<Event, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.monitor.poll_wait(cx).map(Some)
+    }
+}
+
+/// Future returned by [AsyncPressureMonitor::wait], which can also be awaited through `&mut monitor`
+///
+/// ```no_run
+/// use pressure::tokio::PressureMonitor;
+///
+/// # async fn example(mut shutdown: tokio::sync::oneshot::Receiver<()>) -> Result<(), pressure::Error> {
+/// let mut monitor = PressureMonitor::new()?;
+/// loop {
+///     tokio::select! {
+///         event = &mut monitor => println!("{:?} pressure", event?.resource),
+///         _ = &mut shutdown => return Ok(()),
+///     }
+/// }
+/// # }
+/// ```
+pub struct Wait<'a, R: Reactor> {
+    monitor: &'a mut AsyncPressureMonitor<R>,
+}
+
+impl<R: Reactor> Future for Wait<'_, R> {
+    type Output = Result<Event, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.monitor.poll_wait(cx)
+    }
+}
+
+impl<'a, R: Reactor> IntoFuture for &'a mut AsyncPressureMonitor<R> {
+    type Output = Result<Event, Error>;
+    type IntoFuture = Wait<'a, R>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.wait()
+    }
+}
@@ -0,0 +1,77 @@
+use std::os::fd::{AsFd, BorrowedFd};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
+
+use crate::{Error, MonitorType};
+
+/// Exposes pressure events as plain readability, for event loops that can't wait for priority readiness
+///
+/// Pressure files always report themselves as readable and signal events through POLLPRI, so they are registered with an inner epoll instance which only becomes readable once an event occurs
+pub(crate) struct ReadableMonitor {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    epoll: Option<Epoll>,
+    pressure_file: MonitorType,
+}
+
+impl ReadableMonitor {
+    pub(crate) fn new(pressure_file: MonitorType) -> Result<Self, Error> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let epoll = match pressure_file {
+            MonitorType::File(_) => Some(priority_epoll(&pressure_file)?),
+            MonitorType::Fifo(_) | MonitorType::Socket(_) => None,
+        };
+        // Pressure files only exist on Linux
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        if let MonitorType::File(_) = pressure_file {
+            return Err(Error::UnexpectedFileType);
+        }
+        Ok(Self {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            epoll,
+            pressure_file,
+        })
+    }
+
+    pub(crate) fn pressure_file(&self) -> &MonitorType {
+        &self.pressure_file
+    }
+
+    pub(crate) fn into_inner(self) -> MonitorType {
+        self.pressure_file
+    }
+
+    /// Consume the pending event after the fd became readable, returning how many notifications were coalesced into it
+    #[cfg(feature = "calloop")]
+    pub(crate) fn clear(&self) -> Result<u32, Error> {
+        Ok(self.receive()?.unwrap_or(1))
+    }
+
+    /// Consume the pending event, returning how many notifications were coalesced into it or [None] if readiness was stale
+    pub(crate) fn receive(&self) -> Result<Option<u32>, Error> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(epoll) = &self.epoll {
+            epoll.wait(&mut [EpollEvent::empty()], EpollTimeout::ZERO)?;
+            return Ok(Some(1));
+        }
+        self.pressure_file.receive()
+    }
+}
+
+/// Create an epoll instance which becomes readable once `fd` signals priority readiness
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn priority_epoll(fd: impl AsFd) -> Result<Epoll, Error> {
+    let epoll = Epoll::new(EpollCreateFlags::EPOLL_CLOEXEC)?;
+    epoll.add(fd, EpollEvent::new(EpollFlags::EPOLLPRI, 0))?;
+    Ok(epoll)
+}
+
+impl AsFd for ReadableMonitor {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(epoll) = &self.epoll {
+            return epoll.0.as_fd();
+        }
+        self.pressure_file.as_fd()
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{Error, PressureMonitor, WaitOutcome};
+
+/// Files a [DirCacheReaper] deleted, or would have deleted in a dry run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ReapReport {
+    /// Total size of the cache directories before reaping
+    pub size_before: u64,
+    /// Bytes freed by deleting the files
+    pub freed: u64,
+    /// Deleted files, least recently used first
+    pub deleted: Vec<PathBuf>,
+    /// Whether nothing was actually deleted, see [DirCacheReaper::dry_run]
+    pub dry_run: bool,
+}
+
+/// Deletes files from on-disk cache directories on pressure events, least recently used first, until they fit within a target size.
+/// Applications with disk caches can thereby relieve IO pressure and, as cached files also occupy the page cache, memory pressure
+///
+/// Files are ordered by the later of their access and modification times, since filesystems mounted with `relatime` or `noatime` update access times
+/// rarely or never. Reaps are rate limited, and can be limited to a number of files each. Files that vanish or can't be deleted, and directories that can't be read, are skipped
+///
+/// ```no_run
+/// use pressure::{DirCacheReaper, PressureMonitor, Resource};
+/// use std::time::Duration;
+///
+/// let mut reaper = DirCacheReaper::new(512 << 20)
+///     .dir("/var/cache/my-app/thumbnails")
+///     .dir("/var/cache/my-app/downloads")
+///     .min_interval(Duration::from_secs(30));
+/// reaper.run_on_events(&mut PressureMonitor::with_resource(Resource::Io)?)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct DirCacheReaper {
+    dirs: Vec<PathBuf>,
+    target: u64,
+    use_atime: bool,
+    dry_run: bool,
+    min_interval: Duration,
+    max_files: usize,
+    last_reap: Option<Instant>,
+}
+
+impl DirCacheReaper {
+    /// Reap down to `target` bytes across all directories, at most every 10s
+    pub fn new(target: u64) -> Self {
+        Self {
+            dirs: Vec::new(),
+            target,
+            use_atime: true,
+            dry_run: false,
+            min_interval: Duration::from_secs(10),
+            max_files: usize::MAX,
+            last_reap: None,
+        }
+    }
+    /// Add a cache directory, which is searched recursively without following symlinks. Directories that don't exist are treated as empty
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push(dir.into());
+        self
+    }
+    /// Whether access times count as use, otherwise files are ordered by modification time only
+    pub fn use_atime(mut self, use_atime: bool) -> Self {
+        self.use_atime = use_atime;
+        self
+    }
+    /// Report the files that would be deleted without deleting them
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    /// Minimum time between two reaps, events in between are skipped
+    pub fn min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+    /// Most files to delete per reap, so that a single reap doesn't cause a burst of IO
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Delete the least recently used files until the directories fit within the target size, or return [None] if the last reap was too recent
+    pub fn reap(&mut self) -> Result<Option<ReapReport>, Error> {
+        let now = Instant::now();
+        if self
+            .last_reap
+            .is_some_and(|last| now.saturating_duration_since(last) < self.min_interval)
+        {
+            return Ok(None);
+        }
+        self.last_reap = Some(now);
+        let mut files = Vec::new();
+        for dir in &self.dirs {
+            self.collect(dir, &mut files)?;
+        }
+        files.sort_unstable_by_key(|(used, _, _)| *used);
+        let size_before: u64 = files.iter().map(|(_, size, _)| size).sum();
+        let mut report = ReapReport {
+            size_before,
+            dry_run: self.dry_run,
+            ..ReapReport::default()
+        };
+        let mut size = size_before;
+        for (_, file_size, path) in files {
+            if size <= self.target || report.deleted.len() >= self.max_files {
+                break;
+            }
+            if !self.dry_run {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => {}
+                    // Someone else deleted it first, which frees its space all the same
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        size -= file_size;
+                        continue;
+                    }
+                    Err(_) => continue,
+                }
+            }
+            size -= file_size;
+            report.freed += file_size;
+            report.deleted.push(path);
+        }
+        Ok(Some(report))
+    }
+
+    /// Reap on every event received by `monitor`, until its wait is cancelled
+    pub fn run_on_events(&mut self, monitor: &mut PressureMonitor) -> Result<(), Error> {
+        loop {
+            match monitor.wait()? {
+                WaitOutcome::Triggered(_) => {
+                    self.reap()?;
+                }
+                WaitOutcome::TimedOut | WaitOutcome::Cancelled => return Ok(()),
+                WaitOutcome::Interrupted => {}
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            };
+        }
+    }
+
+    /// Add the regular files below `dir` with when they were last used and their size
+    fn collect(
+        &self,
+        dir: &Path,
+        files: &mut Vec<(SystemTime, u64, PathBuf)>,
+    ) -> Result<(), Error> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            // The cache may not have been created yet, a subdirectory was deleted concurrently, or it belongs to another user
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::PermissionDenied) => {
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            // Not following symlinks keeps the reaper within the cache directories
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                self.collect(&entry.path(), files)?;
+            } else if metadata.is_file() {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                let used = match metadata.accessed() {
+                    Ok(accessed) if self.use_atime => accessed.max(modified),
+                    _ => modified,
+                };
+                files.push((used, metadata.len(), entry.path()));
+            }
+        }
+        Ok(())
+    }
+}
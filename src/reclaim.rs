@@ -0,0 +1,191 @@
+use std::{
+    fs::{File, OpenOptions},
+    os::fd::AsFd,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    poll::{PollFd, PollFlags},
+};
+
+use crate::{
+    CancellationToken, Error, PressureMonitor, PsiStats, ReclaimController, Resource, WaitOutcome,
+    cgroup, poll_timeout,
+};
+
+/// Result of a single reclaim attempt by [ReclaimDriver]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReclaimOutcome {
+    /// The full step was reclaimed
+    Reclaimed(u64),
+    /// The kernel couldn't reclaim the full step, usually because few cold pages are left
+    Incomplete,
+    /// Reclaim was skipped because full pressure was too high, or still is within the backoff period
+    BackedOff,
+}
+
+/// Proactively reclaims memory from a cgroup by writing to its memory.reclaim file, pushing cold pages out before they cause pressure
+///
+/// As reclaiming pages that are still in use stalls the cgroup, reclaim backs off whenever full memory pressure reaches a threshold
+///
+/// ```no_run
+/// use pressure::ReclaimDriver;
+/// use std::time::Duration;
+///
+/// let mut driver = ReclaimDriver::for_current_cgroup()?
+///     .step(32 << 20)
+///     .full_threshold(0.5);
+/// driver.run_every(Duration::from_secs(6))?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct ReclaimDriver {
+    cgroup: PathBuf,
+    reclaim: File,
+    pressure: File,
+    step: u64,
+    full_threshold: f32,
+    backoff: Duration,
+    backed_off_at: Option<Instant>,
+    reclaimed: u64,
+    cancellation: Option<CancellationToken>,
+}
+
+impl ReclaimDriver {
+    /// Drive reclaim for a cgroup v2 hierarchy, by default reclaiming 16MiB per step and backing off for 10s once full pressure reaches 1%
+    pub fn new(cgroup: impl AsRef<Path>) -> Result<Self, Error> {
+        let cgroup = cgroup.as_ref().to_path_buf();
+        Ok(Self {
+            reclaim: OpenOptions::new()
+                .write(true)
+                .open(cgroup.join("memory.reclaim"))?,
+            pressure: File::open(cgroup.join(Resource::Memory.cgroup_file()))?,
+            cgroup,
+            step: 16 << 20,
+            full_threshold: 1.0,
+            backoff: Duration::from_secs(10),
+            backed_off_at: None,
+            reclaimed: 0,
+            cancellation: None,
+        })
+    }
+    /// Drive reclaim for the cgroup the calling process belongs to
+    pub fn for_current_cgroup() -> Result<Self, Error> {
+        Self::new(cgroup::current_cgroup()?)
+    }
+    /// Amount of memory in bytes to reclaim per step
+    pub fn step(mut self, bytes: u64) -> Self {
+        self.step = bytes;
+        self
+    }
+    /// Percentage of time all tasks were stalled on memory over the last 10 seconds at which reclaim backs off
+    pub fn full_threshold(mut self, avg10: f32) -> Self {
+        self.full_threshold = avg10;
+        self
+    }
+    /// How long to skip reclaim after full pressure reached the threshold
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    pub fn cgroup(&self) -> &Path {
+        &self.cgroup
+    }
+    /// Total amount of memory in bytes reclaimed by completed steps
+    pub fn reclaimed(&self) -> u64 {
+        self.reclaimed
+    }
+    /// Get a token that can be used to stop [run_every](Self::run_every) from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Reclaim a single step, unless full pressure is too high
+    pub fn reclaim(&mut self) -> Result<ReclaimOutcome, Error> {
+        self.reclaim_bytes(self.step)
+    }
+    /// Reclaim `bytes` instead of a step, unless full pressure is too high
+    pub fn reclaim_bytes(&mut self, bytes: u64) -> Result<ReclaimOutcome, Error> {
+        let now = Instant::now();
+        if self
+            .backed_off_at
+            .is_some_and(|at| now.saturating_duration_since(at) < self.backoff)
+        {
+            return Ok(ReclaimOutcome::BackedOff);
+        }
+        if PsiStats::read_fd(&self.pressure)?.full.avg10 >= self.full_threshold {
+            self.backed_off_at = Some(now);
+            return Ok(ReclaimOutcome::BackedOff);
+        }
+        match nix::sys::uio::pwrite(&self.reclaim, bytes.to_string().as_bytes(), 0) {
+            Ok(_) => {
+                self.reclaimed += bytes;
+                Ok(ReclaimOutcome::Reclaimed(bytes))
+            }
+            // The kernel gives up with EAGAIN if it couldn't reclaim the requested amount
+            Err(Errno::EAGAIN) => Ok(ReclaimOutcome::Incomplete),
+            Err(e) => Err(e)?,
+        }
+    }
+    /// Reclaim a step on every event received by `monitor`, until its wait is cancelled.
+    /// The monitor should use a low threshold, so that reclaim starts well before pressure becomes noticeable
+    pub fn run_on_events(&mut self, monitor: &mut PressureMonitor) -> Result<(), Error> {
+        loop {
+            match monitor.wait()? {
+                WaitOutcome::Triggered(_) => {
+                    self.reclaim()?;
+                }
+                WaitOutcome::TimedOut | WaitOutcome::Cancelled => return Ok(()),
+                WaitOutcome::Interrupted => {}
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            };
+        }
+    }
+    /// On every event received by `monitor`, reclaim the amount `controller` computes from the avg10 of the monitored kind of stall, until its wait is cancelled.
+    /// Unlike [run_on_events](Self::run_on_events) the amount scales with pressure, so `monitor` usually watches pressure elsewhere, such as system-wide,
+    /// that reclaiming from this cgroup relieves
+    pub fn run_controlled(
+        &mut self,
+        monitor: &mut PressureMonitor,
+        controller: &mut ReclaimController,
+    ) -> Result<(), Error> {
+        loop {
+            match monitor.wait()? {
+                WaitOutcome::Triggered(event) => {
+                    let stats = match event.stats {
+                        Some(stats) => stats,
+                        None => monitor.stats()?,
+                    };
+                    let bytes = controller.update(stats.line(event.kind).avg10);
+                    if bytes > 0 {
+                        self.reclaim_bytes(bytes)?;
+                    }
+                }
+                WaitOutcome::TimedOut | WaitOutcome::Cancelled => return Ok(()),
+                WaitOutcome::Interrupted => {}
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            };
+        }
+    }
+    /// Reclaim a step every `interval`, until cancelled through [cancellation_token](Self::cancellation_token)
+    pub fn run_every(&mut self, interval: Duration) -> Result<(), Error> {
+        loop {
+            if let Some(cancellation) = &self.cancellation {
+                let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+                if nix::poll::poll(&mut fds, poll_timeout(interval))? > 0 {
+                    return Ok(());
+                }
+            } else {
+                std::thread::sleep(interval);
+            }
+            self.reclaim()?;
+        }
+    }
+}
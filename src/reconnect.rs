@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+/// Schedules attempts to reopen a monitor's path after its fd hung up, doubling the delay after each failed attempt
+pub(crate) struct Reconnect {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    backoff: Duration,
+    /// When to try reopening the path next, or [None] while the fd is alive
+    retry_at: Option<Instant>,
+}
+
+impl Reconnect {
+    pub(crate) fn new(initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            initial_backoff,
+            max_backoff,
+            backoff: initial_backoff,
+            retry_at: None,
+        }
+    }
+
+    pub(crate) fn retry_at(&self) -> Option<Instant> {
+        self.retry_at
+    }
+
+    /// Start reconnecting right away after the fd hung up
+    pub(crate) fn hung_up(&mut self) {
+        self.backoff = self.initial_backoff;
+        self.retry_at = Some(Instant::now());
+    }
+
+    pub(crate) fn failed(&mut self) {
+        self.retry_at = Some(Instant::now() + self.backoff);
+        self.backoff = (self.backoff * 2).min(self.max_backoff);
+    }
+
+    pub(crate) fn succeeded(&mut self) {
+        self.retry_at = None;
+    }
+}
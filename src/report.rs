@@ -0,0 +1,137 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{Error, PsiLine, PsiStats, Resource, Severity, SeverityThresholds, StallKind, cgroup};
+
+const RESOURCES: [Resource; 4] = [Resource::Memory, Resource::Cpu, Resource::Io, Resource::Irq];
+
+/// Pressure on a resource, system-wide or within a cgroup, as part of a [HostPressureReport]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportEntry {
+    pub resource: Resource,
+    /// The cgroup the stall information was read from, or [None] for system-wide pressure
+    pub cgroup: Option<PathBuf>,
+    pub stats: PsiStats,
+    /// Severity of the kind of stall monitors default to for the resource, `some` except for IRQ, which only reports `full` stalls
+    pub severity: Severity,
+}
+
+/// A snapshot of pressure on every resource, system-wide and within selected cgroups, for health endpoints and periodic snapshots without setting up monitors
+///
+/// Resources the kernel doesn't report pressure for, such as IRQ before Linux 6.1, are left out
+///
+/// ```no_run
+/// use pressure::{HostPressureReport, Severity, SeverityThresholds};
+///
+/// let report = HostPressureReport::collect_with(["system.slice", "user.slice"], SeverityThresholds::default())?;
+/// if report.severity() == Severity::Critical {
+///     let worst = report.worst().unwrap();
+///     println!("{} pressure in {:?}", worst.resource, worst.cgroup);
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HostPressureReport {
+    /// When the stall information was read
+    pub timestamp: SystemTime,
+    /// System-wide entries first, followed by those of each cgroup in the order they were given, each in the order memory, CPU, IO and IRQ
+    pub entries: Vec<ReportEntry>,
+}
+
+impl HostPressureReport {
+    /// Read system-wide pressure on every resource, classified with the default [SeverityThresholds]
+    pub fn collect() -> Result<Self, Error> {
+        Self::collect_with(std::iter::empty::<&Path>(), SeverityThresholds::default())
+    }
+
+    /// Read system-wide pressure on every resource, along with pressure within each of `cgroups`, where relative paths are within the unified hierarchy
+    pub fn collect_with<P: AsRef<Path>>(
+        cgroups: impl IntoIterator<Item = P>,
+        thresholds: SeverityThresholds,
+    ) -> Result<Self, Error> {
+        let timestamp = SystemTime::now();
+        let mut entries = Vec::new();
+        for resource in RESOURCES {
+            let stats = match PsiStats::read(resource) {
+                Err(Error::ResourceUnavailable(_)) => continue,
+                stats => stats?,
+            };
+            entries.push(ReportEntry::new(resource, None, stats, &thresholds));
+        }
+        for cgroup in cgroups {
+            let cgroup = cgroup::resolve(cgroup.as_ref(), None)?;
+            if !cgroup.is_dir() {
+                return Err(Error::CgroupNotFound(cgroup));
+            }
+            for resource in RESOURCES {
+                let path = cgroup.join(resource.cgroup_file());
+                if !path.exists() {
+                    continue;
+                }
+                let stats = PsiStats::read_path(path)?;
+                entries.push(ReportEntry::new(
+                    resource,
+                    Some(cgroup.clone()),
+                    stats,
+                    &thresholds,
+                ));
+            }
+        }
+        Ok(Self { timestamp, entries })
+    }
+
+    /// Highest severity among the entries, or [Severity::Low] without entries
+    pub fn severity(&self) -> Severity {
+        self.entries
+            .iter()
+            .map(|entry| entry.severity)
+            .max()
+            .unwrap_or(Severity::Low)
+    }
+
+    /// The entry with the highest severity, preferring the one stalled the most over the last 10 seconds among equally severe entries
+    pub fn worst(&self) -> Option<&ReportEntry> {
+        self.entries.iter().max_by(|a, b| {
+            a.severity
+                .cmp(&b.severity)
+                .then(a.line().avg10.total_cmp(&b.line().avg10))
+        })
+    }
+
+    /// Entries for pressure within `cgroup`, as it was given resolved within the unified hierarchy, or system-wide entries with [None]
+    pub fn entries_for<'a>(
+        &'a self,
+        cgroup: Option<&'a Path>,
+    ) -> impl Iterator<Item = &'a ReportEntry> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.cgroup.as_deref() == cgroup)
+    }
+}
+
+impl ReportEntry {
+    fn new(
+        resource: Resource,
+        cgroup: Option<PathBuf>,
+        stats: PsiStats,
+        thresholds: &SeverityThresholds,
+    ) -> Self {
+        let mut entry = Self {
+            resource,
+            cgroup,
+            stats,
+            severity: Severity::Low,
+        };
+        entry.severity = thresholds.classify(entry.line());
+        entry
+    }
+
+    /// The line the severity was classified from
+    fn line(&self) -> &PsiLine {
+        self.stats.line(StallKind::default_for(self.resource))
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use crate::Error;
+
+/// Where exporters and loggers append their rows, rotating files once they grow too large or too old
+pub(crate) enum Output {
+    Writer(Box<dyn Write + Send>),
+    File {
+        path: PathBuf,
+        file: File,
+        written: u64,
+        /// When the current file was created, or opened if the filesystem doesn't record creation times
+        created: SystemTime,
+        rotation: Rotation,
+    },
+}
+
+/// When to rotate a file, and how many rotated files to keep
+#[derive(Default)]
+pub(crate) struct Rotation {
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    keep: usize,
+}
+
+impl Output {
+    pub(crate) fn writer(writer: impl Write + Send + 'static) -> Self {
+        Output::Writer(Box::new(writer))
+    }
+
+    /// Append to a file, creating it if it doesn't exist
+    pub(crate) fn open(path: PathBuf) -> Result<Self, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        Ok(Output::File {
+            path,
+            file,
+            written: metadata.len(),
+            created: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            rotation: Rotation::default(),
+        })
+    }
+
+    /// Whether nothing was written to the output yet, which is never known for writers
+    pub(crate) fn is_empty(&self) -> bool {
+        matches!(self, Output::File { written: 0, .. })
+    }
+
+    /// Rotate once the file would grow beyond `max_bytes`, has no effect on writers
+    pub(crate) fn rotate_at_size(&mut self, max_bytes: u64, keep: usize) {
+        if let Output::File { rotation, .. } = self {
+            rotation.max_bytes = Some(max_bytes);
+            rotation.keep = keep;
+        }
+    }
+
+    /// Rotate once the file is older than `max_age`, has no effect on writers
+    pub(crate) fn rotate_at_age(&mut self, max_age: Duration, keep: usize) {
+        if let Output::File { rotation, .. } = self {
+            rotation.max_age = Some(max_age);
+            rotation.keep = keep;
+        }
+    }
+
+    /// Rotate the file if writing `additional` bytes would make it too large, or if it is too old, returning whether it was rotated.
+    /// Empty files are never rotated
+    pub(crate) fn rotate_if_due(&mut self, additional: u64) -> Result<bool, Error> {
+        let Output::File {
+            path,
+            file,
+            written,
+            created,
+            rotation,
+        } = self
+        else {
+            return Ok(false);
+        };
+        let too_large = rotation
+            .max_bytes
+            .is_some_and(|max_bytes| *written + additional > max_bytes);
+        let too_old = rotation
+            .max_age
+            .is_some_and(|max_age| created.elapsed().is_ok_and(|elapsed| elapsed >= max_age));
+        if *written == 0 || !(too_large || too_old) {
+            return Ok(false);
+        }
+        let rotated = |n: usize| {
+            let mut name = OsString::from(path.as_os_str());
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        if rotation.keep == 0 {
+            std::fs::remove_file(&*path)?;
+        } else {
+            for n in (1..rotation.keep).rev() {
+                match std::fs::rename(rotated(n), rotated(n + 1)) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            std::fs::rename(&*path, rotated(1))?;
+        }
+        *file = OpenOptions::new().create(true).append(true).open(&*path)?;
+        *written = 0;
+        *created = SystemTime::now();
+        Ok(true)
+    }
+
+    /// Append a row, flushing writers after it
+    pub(crate) fn write(&mut self, row: &[u8]) -> Result<(), Error> {
+        match self {
+            Output::Writer(writer) => {
+                writer.write_all(row)?;
+                writer.flush()?;
+            }
+            Output::File { file, written, .. } => {
+                file.write_all(row)?;
+                *written += row.len() as u64;
+            }
+        }
+        Ok(())
+    }
+}
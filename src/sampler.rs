@@ -0,0 +1,86 @@
+use std::{
+    fs::File,
+    os::fd::{AsFd, BorrowedFd},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{ClockTimestamp, Error, PsiStats, Resource};
+
+/// Periodically reads pressure stall information, without registering a trigger
+///
+/// Unlike [PressureMonitor](crate::PressureMonitor) this doesn't need write access to the pressure file, and doesn't consume one of the kernel's trigger slots.
+/// The first sample is taken immediately, after which [next](Iterator::next) blocks until the next interval has elapsed
+pub struct Sampler {
+    file: File,
+    interval: Duration,
+    next_sample: Option<Instant>,
+}
+
+impl Sampler {
+    /// Sample system-wide pressure for a resource
+    pub fn new(resource: Resource, interval: Duration) -> Result<Self, Error> {
+        if !resource.is_available() {
+            return Err(Error::ResourceUnavailable(resource));
+        }
+        Self::from_path(resource.proc_path(), interval)
+    }
+
+    /// Sample pressure for a resource within a cgroup v2 hierarchy
+    pub fn for_cgroup(
+        cgroup: impl AsRef<Path>,
+        resource: Resource,
+        interval: Duration,
+    ) -> Result<Self, Error> {
+        Self::from_path(cgroup.as_ref().join(resource.cgroup_file()), interval)
+    }
+
+    pub(crate) fn from_path(path: impl AsRef<Path>, interval: Duration) -> Result<Self, Error> {
+        Ok(Self {
+            file: File::open(path)?,
+            interval,
+            next_sample: None,
+        })
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Take a sample immediately, without waiting for the next interval
+    pub fn sample(&self) -> Result<PsiStats, Error> {
+        PsiStats::read_fd(&self.file)
+    }
+
+    /// Take a sample immediately, along with the system's clocks read right after the pressure file was
+    pub fn sample_timestamped(&self) -> Result<(PsiStats, ClockTimestamp), Error> {
+        let stats = self.sample()?;
+        Ok((stats, ClockTimestamp::now()))
+    }
+
+    /// Block until the next interval has elapsed like [next](Iterator::next), returning the sample along with the system's clocks read right after it was taken
+    pub fn next_timestamped(&mut self) -> Result<(PsiStats, ClockTimestamp), Error> {
+        let now = Instant::now();
+        if let Some(next_sample) = self.next_sample {
+            std::thread::sleep(next_sample.saturating_duration_since(now));
+        }
+        // If sampling fell behind skip the missed intervals instead of sampling in a burst to catch up
+        let deadline = self.next_sample.unwrap_or(now) + self.interval;
+        self.next_sample = Some(deadline.max(Instant::now()));
+        self.sample_timestamped()
+    }
+}
+
+impl AsFd for Sampler {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl Iterator for Sampler {
+    type Item = Result<PsiStats, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_timestamped().map(|(stats, _)| stats))
+    }
+}
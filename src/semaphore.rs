@@ -0,0 +1,259 @@
+use std::{
+    os::fd::AsFd,
+    sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+    time::{Duration, Instant},
+};
+
+use nix::poll::{PollFd, PollFlags};
+
+use crate::{
+    CancellationToken, Error, PsiLine, Sampler, Severity, SeverityThresholds, StallKind,
+    poll_timeout,
+};
+
+struct State {
+    min: usize,
+    max: usize,
+    limit: usize,
+    in_use: usize,
+    thresholds: SeverityThresholds,
+    recovery_step: usize,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    released: Condvar,
+    /// Stops the threads started by [PressureSemaphore::watch] once the semaphore is dropped
+    cancellation: Mutex<Option<CancellationToken>>,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if let Some(cancellation) = self
+            .cancellation
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+        {
+            cancellation.cancel();
+        }
+    }
+}
+
+/// A counting semaphore whose number of permits shrinks as pressure rises and recovers once it clears, for admission control driven by actual stall data
+///
+/// The limit is the maximum number of permits while pressure is [Severity::Low], halfway between the minimum and maximum while it is [Severity::Medium],
+/// and the minimum once it is [Severity::Critical]. It drops as soon as pressure rises, and grows back by a few permits per sample once it subsides so that load
+/// doesn't come back all at once. Permits already handed out aren't revoked when the limit drops, new ones are only handed out once enough were released.
+/// Clones share the same permits
+///
+/// ```no_run
+/// use pressure::{PressureSemaphore, Resource, Sampler, StallKind};
+/// use std::time::Duration;
+///
+/// let semaphore = PressureSemaphore::new(64).min_permits(4);
+/// semaphore.watch(
+///     [
+///         Sampler::new(Resource::Memory, Duration::from_secs(1))?,
+///         Sampler::new(Resource::Cpu, Duration::from_secs(1))?,
+///     ],
+///     StallKind::Some,
+/// )?;
+/// // For each request
+/// match semaphore.try_acquire() {
+///     Some(_permit) => { /* handle the request while holding the permit */ }
+///     None => { /* reject it, the system is overloaded */ }
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct PressureSemaphore {
+    shared: Arc<Shared>,
+}
+
+impl PressureSemaphore {
+    /// A semaphore handing out at most `max_permits` permits while there is no pressure
+    pub fn new(max_permits: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    min: max_permits.min(1),
+                    max: max_permits,
+                    limit: max_permits,
+                    in_use: 0,
+                    thresholds: SeverityThresholds::default(),
+                    recovery_step: max_permits.div_ceil(10).max(1),
+                }),
+                released: Condvar::new(),
+                cancellation: Mutex::new(None),
+            }),
+        }
+    }
+    /// Number of permits left once pressure is critical, defaults to 1. Setting it to 0 stops admitting anything under critical pressure
+    pub fn min_permits(self, min: usize) -> Self {
+        {
+            let mut state = self.lock();
+            state.min = min.min(state.max);
+        }
+        self
+    }
+    /// Thresholds at which pressure is medium or critical
+    pub fn thresholds(self, thresholds: SeverityThresholds) -> Self {
+        self.lock().thresholds = thresholds;
+        self
+    }
+    /// How many permits are added back per sample once pressure subsides, defaults to a tenth of the maximum
+    pub fn recovery_step(self, permits: usize) -> Self {
+        self.lock().recovery_step = permits.max(1);
+        self
+    }
+
+    /// Adjust the limit on a worker thread for as long as the semaphore exists, following the most severe pressure across `samplers`.
+    /// Samples are taken at the shortest interval of the samplers, and failed samples are skipped
+    pub fn watch(
+        &self,
+        samplers: impl IntoIterator<Item = Sampler>,
+        kind: StallKind,
+    ) -> Result<(), Error> {
+        let samplers: Vec<Sampler> = samplers.into_iter().collect();
+        let Some(interval) = samplers.iter().map(Sampler::interval).min() else {
+            return Ok(());
+        };
+        let cancellation = {
+            let mut cancellation = self
+                .shared
+                .cancellation
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            match &*cancellation {
+                Some(token) => token.clone(),
+                None => cancellation.insert(CancellationToken::new()?).clone(),
+            }
+        };
+        let shared = Arc::downgrade(&self.shared);
+        std::thread::Builder::new()
+            .name("pressure-semaphore".into())
+            .spawn(move || watch(shared, samplers, kind, interval, cancellation))?;
+        Ok(())
+    }
+
+    /// Adjust the limit to the most severe of `pressure`, such as the lines of the resources a service depends on
+    pub fn update(&self, pressure: &[PsiLine]) {
+        let mut state = self.lock();
+        let Some(severity) = pressure
+            .iter()
+            .map(|line| state.thresholds.classify(line))
+            .max()
+        else {
+            return;
+        };
+        let target = match severity {
+            Severity::Low => state.max,
+            Severity::Medium => state.min + (state.max - state.min) / 2,
+            Severity::Critical => state.min,
+        };
+        let grew = target > state.limit;
+        state.limit = if grew {
+            (state.limit + state.recovery_step).min(target)
+        } else {
+            target
+        };
+        drop(state);
+        if grew {
+            self.shared.released.notify_all();
+        }
+    }
+
+    /// Block until a permit is available
+    pub fn acquire(&self) -> PressurePermit {
+        let mut state = self.lock();
+        while state.in_use >= state.limit {
+            state = self
+                .shared
+                .released
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        self.grant(state)
+    }
+    /// Take a permit if one is available right away
+    pub fn try_acquire(&self) -> Option<PressurePermit> {
+        let state = self.lock();
+        (state.in_use < state.limit).then(|| self.grant(state))
+    }
+    /// Wait up to `timeout` for a permit
+    pub fn acquire_timeout(&self, timeout: Duration) -> Option<PressurePermit> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        while state.in_use >= state.limit {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            state = self
+                .shared
+                .released
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        Some(self.grant(state))
+    }
+
+    /// Number of permits currently handed out at most
+    pub fn limit(&self) -> usize {
+        self.lock().limit
+    }
+    /// Number of permits that can be acquired right away
+    pub fn available(&self) -> usize {
+        let state = self.lock();
+        state.limit.saturating_sub(state.in_use)
+    }
+
+    fn grant(&self, mut state: MutexGuard<'_, State>) -> PressurePermit {
+        state.in_use += 1;
+        PressurePermit {
+            shared: self.shared.clone(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A permit of a [PressureSemaphore], released when dropped
+pub struct PressurePermit {
+    shared: Arc<Shared>,
+}
+
+impl Drop for PressurePermit {
+    fn drop(&mut self) {
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .in_use -= 1;
+        self.shared.released.notify_one();
+    }
+}
+
+fn watch(
+    shared: Weak<Shared>,
+    samplers: Vec<Sampler>,
+    kind: StallKind,
+    interval: Duration,
+    cancellation: CancellationToken,
+) {
+    loop {
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        let pressure: Vec<PsiLine> = samplers
+            .iter()
+            .filter_map(|sampler| sampler.sample().ok())
+            .map(|stats| *stats.line(kind))
+            .collect();
+        PressureSemaphore { shared }.update(&pressure);
+        let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+        if !matches!(nix::poll::poll(&mut fds, poll_timeout(interval)), Ok(0)) {
+            return;
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout},
+};
+
+use crate::{Error, Event, PollInterest, PressureMonitor, Source, WaitOutcome, poll_timeout};
+
+/// Most events harvested from epoll per syscall. Waits keep harvesting while batches come back full
+const BATCH: usize = 256;
+
+/// Identifies a monitor in a [MonitorSet]. Keys of removed monitors are reused by monitors inserted later
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MonitorKey(usize);
+
+impl MonitorKey {
+    /// Position of the monitor's slot in the set, which is below the number of monitors the set ever held at once
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Waits on multiple monitors at once, reporting which of them received a pressure event
+///
+/// Monitors are registered with a single epoll instance, edge-triggered for pressure files, fifos and sockets so that each wait only costs as much as the
+/// monitors that fired, however many are watched. They are kept in a slab indexed by [MonitorKey], so that node agents can add and remove monitors as
+/// cgroups come and go. [wait_into](Self::wait_into) harvests events in batches into a reused buffer, and reports monitors whose fd died without failing the others
+///
+/// ```no_run
+/// use pressure::{MonitorSet, PressureMonitor, Resource};
+/// let mut set = MonitorSet::new().unwrap();
+/// set.insert(PressureMonitor::with_resource(Resource::Memory).unwrap()).unwrap();
+/// set.insert(PressureMonitor::for_cgroup("/sys/fs/cgroup/system.slice", Resource::Cpu).unwrap()).unwrap();
+/// loop {
+///     for event in set.wait().unwrap() {
+///         println!("{} pressure in {:?}", event.resource, event.cgroup);
+///     }
+/// }
+/// ```
+pub struct MonitorSet {
+    epoll: Epoll,
+    slots: Vec<Option<PressureMonitor>>,
+    /// Slots of removed monitors, reused before the slab grows
+    free: Vec<usize>,
+    len: usize,
+    /// Outcomes to report with the next wait, for monitors that couldn't be registered again after being changed through [get_mut](Self::get_mut),
+    /// and monitors whose fd died while [wait](Self::wait) returned events of others
+    pending: Vec<(MonitorKey, WaitOutcome)>,
+    /// Buffer epoll reports events into
+    events: Vec<EpollEvent>,
+}
+
+impl MonitorSet {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            epoll: Epoll::new(EpollCreateFlags::EPOLL_CLOEXEC)?,
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            pending: Vec::new(),
+            events: vec![EpollEvent::empty(); BATCH],
+        })
+    }
+
+    /// Add a monitor to the set. Monitors that fell back to polling, or whose backend doesn't signal events on its fd, can't be added
+    pub fn insert(&mut self, monitor: PressureMonitor) -> Result<MonitorKey, Error> {
+        // Slots of removed monitors are only taken once the new one was registered
+        let slot = self.free.last().copied().unwrap_or(self.slots.len());
+        register(&self.epoll, &monitor, slot)?;
+        if slot < self.slots.len() {
+            self.free.pop();
+        }
+        match self.slots.get_mut(slot) {
+            Some(free) => *free = Some(monitor),
+            None => self.slots.push(Some(monitor)),
+        }
+        self.len += 1;
+        Ok(MonitorKey(slot))
+    }
+
+    /// Remove a monitor from the set, returning it
+    pub fn remove(&mut self, key: MonitorKey) -> Option<PressureMonitor> {
+        let monitor = self.slots.get_mut(key.0)?.take()?;
+        deregister(&self.epoll, &monitor);
+        self.pending.retain(|&(pending, _)| pending != key);
+        self.free.push(key.0);
+        self.len -= 1;
+        Some(monitor)
+    }
+
+    pub fn get(&self, key: MonitorKey) -> Option<&PressureMonitor> {
+        self.slots.get(key.0)?.as_ref()
+    }
+
+    /// Change a monitor in the set, such as with [set_trigger](PressureMonitor::set_trigger) or [pause](PressureMonitor::pause), which replace its fds.
+    /// Its fds are waited on again once the returned guard is dropped, and a monitor that can't be waited on anymore is reported as a
+    /// [WaitOutcome::Error] by the next wait
+    pub fn get_mut(&mut self, key: MonitorKey) -> Option<MonitorMut<'_>> {
+        let monitor = self.slots.get_mut(key.0)?.as_mut()?;
+        deregister(&self.epoll, monitor);
+        Some(MonitorMut {
+            epoll: &self.epoll,
+            monitor,
+            key,
+            pending: &mut self.pending,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over the monitors in the set, in the order of their keys
+    pub fn iter(&self) -> impl Iterator<Item = &PressureMonitor> {
+        self.slots.iter().flatten()
+    }
+
+    /// Iterate over the keys and monitors in the set, in the order of their keys
+    pub fn entries(&self) -> impl Iterator<Item = (MonitorKey, &PressureMonitor)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, monitor)| Some((MonitorKey(slot), monitor.as_ref()?)))
+    }
+
+    /// Wait until at least one monitor receives a pressure event, returning an event for every trigger that fired.
+    /// Fails with [Error::Hangup] or [Error::PollError] if a monitor's fd died, once the events received along with it were returned.
+    /// Events received along with an error acknowledging another monitor's event are lost, use [wait_into](Self::wait_into) to keep them
+    pub fn wait(&mut self) -> Result<Vec<Event>, Error> {
+        self.wait_events(None)
+    }
+
+    /// Wait until at least one monitor receives a pressure event, returning an empty list once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<Vec<Event>, Error> {
+        // Timeouts too long to represent wait forever
+        self.wait_events(Instant::now().checked_add(timeout))
+    }
+
+    /// Wait until at least one monitor receives a pressure event or `timeout` elapses, appending what happened to each monitor that was ready to `outcomes`
+    /// and returning how many were appended. Reusing `outcomes` across waits avoids allocating while thousands of monitors are watched.
+    ///
+    /// Each entry is [WaitOutcome::Triggered] for a trigger that fired, or [WaitOutcome::Hangup] or [WaitOutcome::Error] for a monitor whose fd died,
+    /// such as that of a removed cgroup. Dead monitors stop being waited on, but stay in the set until [removed](Self::remove).
+    /// If acknowledging an event fails, the error is returned once every ready monitor was handled, with their outcomes still appended
+    pub fn wait_into(
+        &mut self,
+        outcomes: &mut Vec<(MonitorKey, WaitOutcome)>,
+        timeout: Option<Duration>,
+    ) -> Result<usize, Error> {
+        // Timeouts too long to represent wait forever
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        self.harvest(outcomes, deadline)
+    }
+
+    fn wait_events(&mut self, deadline: Option<Instant>) -> Result<Vec<Event>, Error> {
+        let mut outcomes = Vec::new();
+        self.harvest(&mut outcomes, deadline)?;
+        let mut events = Vec::with_capacity(outcomes.len());
+        for (key, outcome) in outcomes {
+            match outcome {
+                WaitOutcome::Triggered(event) => events.push(event),
+                // Monitors that died are reported by the next wait, as they are edge-triggered and the events of the others wouldn't be reported again
+                died => self.pending.push((key, died)),
+            }
+        }
+        // Without events to return, one dead monitor is reported now and the others by the waits after, which don't block while any are left
+        if events.is_empty() && !self.pending.is_empty() {
+            return Err(match self.pending.remove(0).1 {
+                WaitOutcome::Error(revents) => Error::PollError(revents),
+                _ => Error::Hangup,
+            });
+        }
+        Ok(events)
+    }
+
+    fn harvest(
+        &mut self,
+        outcomes: &mut Vec<(MonitorKey, WaitOutcome)>,
+        deadline: Option<Instant>,
+    ) -> Result<usize, Error> {
+        let before = outcomes.len();
+        outcomes.append(&mut self.pending);
+        let mut timeout = if outcomes.len() > before {
+            EpollTimeout::ZERO
+        } else {
+            deadline.map_or(EpollTimeout::NONE, |deadline| {
+                poll_timeout(deadline.saturating_duration_since(Instant::now()))
+            })
+        };
+        let mut draining = false;
+        // Every ready fd is handled before reporting an error, as edge-triggered fds aren't reported again
+        let mut error = None;
+        loop {
+            let ready = match self.epoll.wait(&mut self.events, timeout) {
+                Err(Errno::EINTR) if draining => break,
+                // Resume waiting after a signal was delivered to the thread
+                Err(Errno::EINTR) => {
+                    timeout = deadline.map_or(EpollTimeout::NONE, |deadline| {
+                        poll_timeout(deadline.saturating_duration_since(Instant::now()))
+                    });
+                    continue;
+                }
+                ready => ready?,
+            };
+            for i in 0..ready {
+                let event = self.events[i];
+                let key = MonitorKey((event.data() >> 32) as usize);
+                match self.outcome(key, event) {
+                    Ok(Some(outcome)) => outcomes.push((key, outcome)),
+                    Ok(None) => {}
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                }
+            }
+            // A full batch means more fds may be ready, which are collected without waiting again
+            if ready < self.events.len() {
+                break;
+            }
+            timeout = EpollTimeout::ZERO;
+            draining = true;
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(outcomes.len() - before),
+        }
+    }
+
+    /// What an fd being reported ready means for its monitor
+    fn outcome(
+        &mut self,
+        key: MonitorKey,
+        event: EpollEvent,
+    ) -> Result<Option<WaitOutcome>, Error> {
+        let Some(Some(monitor)) = self.slots.get_mut(key.0) else {
+            return Ok(None);
+        };
+        let died = if event.events().contains(EpollFlags::EPOLLHUP) {
+            Some(WaitOutcome::Hangup)
+        } else if event.events().contains(EpollFlags::EPOLLERR) {
+            Some(WaitOutcome::Error(event.events().bits() as i16))
+        } else {
+            None
+        };
+        if let Some(died) = died {
+            deregister(&self.epoll, monitor);
+            return Ok(Some(died));
+        }
+        let additional = (event.data() as u32)
+            .checked_sub(1)
+            .and_then(|i| monitor.additional.get(i as usize));
+        let event = match additional {
+            Some((trigger, _)) => monitor.target.event_for(*trigger),
+            None => Event {
+                coalesced: monitor.acknowledge()?,
+                ..monitor.target.event()
+            },
+        };
+        Ok(Some(WaitOutcome::Triggered(event)))
+    }
+}
+
+/// Mutable access to a monitor in a [MonitorSet], which waits on its fds again once dropped, see [MonitorSet::get_mut]
+pub struct MonitorMut<'a> {
+    epoll: &'a Epoll,
+    monitor: &'a mut PressureMonitor,
+    key: MonitorKey,
+    pending: &'a mut Vec<(MonitorKey, WaitOutcome)>,
+}
+
+impl Deref for MonitorMut<'_> {
+    type Target = PressureMonitor;
+
+    fn deref(&self) -> &PressureMonitor {
+        self.monitor
+    }
+}
+
+impl DerefMut for MonitorMut<'_> {
+    fn deref_mut(&mut self) -> &mut PressureMonitor {
+        self.monitor
+    }
+}
+
+impl Drop for MonitorMut<'_> {
+    fn drop(&mut self) {
+        if register(self.epoll, self.monitor, self.key.0).is_err() {
+            let error = WaitOutcome::Error(EpollFlags::EPOLLERR.bits() as i16);
+            self.pending.push((self.key, error));
+        }
+    }
+}
+
+/// Wait on a monitor's fds, identifying them by the monitor's slot
+fn register(epoll: &Epoll, monitor: &PressureMonitor, slot: usize) -> Result<(), Error> {
+    let mut flags = match monitor.poll_interest() {
+        Some(PollInterest::Priority) => EpollFlags::EPOLLPRI,
+        Some(PollInterest::Readable) => EpollFlags::EPOLLIN,
+        None => return Err(Error::NoFileDescriptor),
+    };
+    // Acknowledging consumes everything pending on the crate's own fds, while backends may leave some behind and stay level-triggered
+    if matches!(monitor.source, Source::Trigger(_)) {
+        flags |= EpollFlags::EPOLLET;
+    }
+    // Each fd is identified by the monitor's slot in the upper half, and which of its triggers it belongs to in the lower half
+    let index = (slot as u64) << 32;
+    epoll.add(monitor, EpollEvent::new(flags, index))?;
+    for (i, (_, fd)) in monitor.additional.iter().enumerate() {
+        let added = epoll.add(
+            fd,
+            EpollEvent::new(
+                EpollFlags::EPOLLPRI | EpollFlags::EPOLLET,
+                index | (i as u64 + 1),
+            ),
+        );
+        if let Err(e) = added {
+            deregister(epoll, monitor);
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// Stop waiting on a monitor's fds, which fails harmlessly for fds that were already deregistered
+fn deregister(epoll: &Epoll, monitor: &PressureMonitor) {
+    let _ = epoll.delete(monitor);
+    for (_, fd) in &monitor.additional {
+        let _ = epoll.delete(fd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        os::unix::net::UnixStream,
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+    };
+
+    use super::*;
+    use crate::{Resource, StallKind, Trigger};
+
+    #[test]
+    fn events_received_along_with_a_hangup_are_returned() {
+        let mut set = MonitorSet::new().unwrap();
+        let mut peers = Vec::new();
+        for _ in 0..2 {
+            let (monitor, peer) = UnixStream::pair().unwrap();
+            set.insert(PressureMonitor::from_fd(monitor.into()).unwrap())
+                .unwrap();
+            peers.push(peer);
+        }
+        drop(peers.remove(0));
+        peers[0].write_all(b"1").unwrap();
+
+        let events = set.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            set.wait_timeout(Duration::from_secs(5)),
+            Err(Error::Hangup)
+        ));
+        assert!(
+            set.wait_timeout(Duration::from_millis(50))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn monitors_changed_in_the_set_are_still_waited_on() {
+        let trigger =
+            |threshold| Trigger::new(StallKind::Some, threshold, Duration::from_secs(2)).unwrap();
+        let Ok(monitor) = PressureMonitor::builder()
+            .resource(Resource::Cpu)
+            .trigger(trigger(Duration::from_millis(1500)))
+            .build()
+        else {
+            // The kernel doesn't report CPU pressure
+            return;
+        };
+        let mut set = MonitorSet::new().unwrap();
+        let key = set.insert(monitor).unwrap();
+        // Replacing the trigger closes the fd the set was waiting on
+        set.get_mut(key)
+            .unwrap()
+            .set_trigger(trigger(Duration::from_micros(1)))
+            .unwrap();
+
+        // Keep more threads runnable than there are CPUs, so they stall on each other
+        let stop = AtomicBool::new(false);
+        let events = thread::scope(|scope| {
+            let threads = thread::available_parallelism().map_or(1, usize::from) + 1;
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    while !stop.load(Ordering::Relaxed) {
+                        std::hint::spin_loop();
+                    }
+                });
+            }
+            let events = set.wait_timeout(Duration::from_secs(10));
+            stop.store(true, Ordering::Relaxed);
+            events
+        });
+        let events = events.unwrap();
+        assert!(!events.is_empty());
+        assert_eq!(
+            events[0].trigger.unwrap().threshold(),
+            Duration::from_micros(1)
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::PsiLine;
+
+/// How severe pressure on a resource currently is, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Low,
+    Medium,
+    Critical,
+}
+
+impl Severity {
+    /// Classify a line of pressure stall information using the default [SeverityThresholds]
+    pub fn classify(line: &PsiLine) -> Self {
+        SeverityThresholds::default().classify(line)
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Percentages of time stalled at which pressure is classified as [Severity::Medium] or [Severity::Critical], defaulting to 10% and 40%
+///
+/// ```
+/// use pressure::{PsiLine, Severity, SeverityThresholds};
+/// let line = PsiLine { avg10: 25.0, ..Default::default() };
+/// assert_eq!(SeverityThresholds::default().classify(&line), Severity::Medium);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeverityThresholds {
+    pub medium: f32,
+    pub critical: f32,
+}
+
+impl SeverityThresholds {
+    /// Pressure is critical once avg10 reaches the critical threshold.
+    /// It is medium once avg10 reaches the medium threshold, or while avg60 stays above it after a burst of pressure has subsided
+    pub fn classify(&self, line: &PsiLine) -> Severity {
+        if line.avg10 >= self.critical {
+            Severity::Critical
+        } else if line.avg10 >= self.medium || line.avg60 >= self.medium {
+            Severity::Medium
+        } else {
+            Severity::Low
+        }
+    }
+}
+
+impl Default for SeverityThresholds {
+    fn default() -> Self {
+        Self {
+            medium: 10.0,
+            critical: 40.0,
+        }
+    }
+}
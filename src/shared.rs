@@ -0,0 +1,283 @@
+use std::{
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard, Weak,
+        mpsc::{self, Receiver, Sender},
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use crate::Trigger;
+use crate::{
+    CancellationToken, Error, Event, EventRate, PressureMonitor, PsiStats, Resource, Severity,
+    WaitOutcome,
+};
+
+/// Work to be done on the worker's monitor, which sends its own reply
+type Request = Box<dyn FnOnce(&mut PressureMonitor) + Send>;
+
+#[derive(Default)]
+struct State {
+    /// Number of events published so far, which waiters compare against to notice new ones
+    generation: u64,
+    last: Option<(Event, Severity)>,
+    subscribers: Vec<Sender<(Event, Severity)>>,
+    /// Events received while paused are dropped instead of published
+    paused: bool,
+    requests: Vec<Request>,
+    /// Why the worker stopped, after which every wait fails
+    stopped: Option<String>,
+}
+
+struct Shared {
+    resource: Resource,
+    state: Mutex<State>,
+    events: Condvar,
+    /// Interrupts the worker's wait, replaced whenever it was used to hand over requests as tokens stay cancelled
+    cancellation: Mutex<CancellationToken>,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        self.cancellation
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .cancel();
+    }
+}
+
+/// A monitor that can be cloned freely and waited on from many threads at once, waiting for events on an internal worker thread
+///
+/// Every thread blocked in [wait](Self::wait) is woken by the next event, and every receiver returned by [subscribe](Self::subscribe) receives all events from then on.
+/// The worker stops once every clone is dropped
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, SharedPressureMonitor};
+///
+/// let monitor = SharedPressureMonitor::new(PressureMonitor::new()?)?;
+/// let cache_monitor = monitor.clone();
+/// std::thread::spawn(move || {
+///     while let Ok((event, severity)) = cache_monitor.wait() {
+///         // Shrink caches
+///     }
+/// });
+/// for (event, severity) in monitor.subscribe() {
+///     // Pause background work
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct SharedPressureMonitor {
+    shared: Arc<Shared>,
+}
+
+impl SharedPressureMonitor {
+    /// Wait for events on `monitor` on a worker thread
+    pub fn new(mut monitor: PressureMonitor) -> Result<Self, Error> {
+        let shared = Arc::new(Shared {
+            resource: monitor.resource(),
+            state: Mutex::default(),
+            events: Condvar::new(),
+            cancellation: Mutex::new(monitor.cancellation_token()?),
+        });
+        let weak = Arc::downgrade(&shared);
+        std::thread::Builder::new()
+            .name("pressure-shared".into())
+            .spawn(move || {
+                let result = dispatch(&mut monitor, &weak);
+                if let (Err(e), Some(shared)) = (result, weak.upgrade()) {
+                    let mut state = shared.lock();
+                    state.stopped = Some(e.to_string());
+                    // Disconnect the receivers, and fail pending requests by dropping their senders
+                    state.subscribers.clear();
+                    state.requests.clear();
+                    drop(state);
+                    shared.events.notify_all();
+                }
+            })?;
+        Ok(Self { shared })
+    }
+    pub fn resource(&self) -> Resource {
+        self.shared.resource
+    }
+    /// The last event received along with the severity of pressure at the time, if any
+    pub fn last_event(&self) -> Option<(Event, Severity)> {
+        self.shared.lock().last.clone()
+    }
+    /// Block until the next event. If several events occur before this thread wakes up, the latest one is returned
+    pub fn wait(&self) -> Result<(Event, Severity), Error> {
+        let mut state = self.shared.lock();
+        let generation = state.generation;
+        loop {
+            if let Some(event) = next_event(&state, generation)? {
+                return Ok(event);
+            }
+            state = self
+                .shared
+                .events
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+    /// Block until the next event, giving up once `timeout` has elapsed
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<WaitOutcome<(Event, Severity)>, Error> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+        let generation = state.generation;
+        loop {
+            if let Some(event) = next_event(&state, generation)? {
+                return Ok(WaitOutcome::Triggered(event));
+            }
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                return Ok(WaitOutcome::TimedOut);
+            };
+            state = self
+                .shared
+                .events
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+    /// Receive every event from now on, along with the severity of pressure at the time.
+    /// The receiver is disconnected if the worker stops because waiting for events failed
+    pub fn subscribe(&self) -> Receiver<(Event, Severity)> {
+        let (sender, receiver) = mpsc::channel();
+        let mut state = self.shared.lock();
+        if state.stopped.is_none() {
+            state.subscribers.push(sender);
+        }
+        receiver
+    }
+    /// Read the current pressure stall information through the worker's monitor
+    pub fn stats(&self) -> Result<PsiStats, Error> {
+        self.request(|monitor| monitor.stats())
+    }
+    /// Classify the current pressure through the worker's monitor
+    pub fn severity(&self) -> Result<Severity, Error> {
+        self.request(|monitor| monitor.severity())
+    }
+    /// The main trigger the worker's monitor is armed with followed by its additional triggers
+    #[cfg(unix)]
+    pub fn triggers(&self) -> Result<Vec<Trigger>, Error> {
+        self.request(|monitor| {
+            Ok(monitor
+                .trigger()
+                .into_iter()
+                .chain(monitor.additional_triggers())
+                .collect())
+        })
+    }
+    /// Replace the trigger of the worker's monitor, see [PressureMonitor::set_trigger]
+    #[cfg(unix)]
+    pub fn set_trigger(&self, trigger: Trigger) -> Result<(), Error> {
+        self.request(move |monitor| monitor.set_trigger(trigger))
+    }
+    /// Stop publishing events until [resume](Self::resume) is called. Events received in the meantime are dropped
+    pub fn pause(&self) {
+        self.shared.lock().paused = true;
+    }
+    pub fn resume(&self) {
+        self.shared.lock().paused = false;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.shared.lock().paused
+    }
+    /// Number of events published since the monitor was created
+    pub fn event_count(&self) -> u64 {
+        self.shared.lock().generation
+    }
+    /// How often the worker's monitor delivered events, including those dropped while paused
+    pub fn event_rate(&self) -> Result<EventRate, Error> {
+        self.request(|monitor| Ok(monitor.event_rate()))
+    }
+    /// Run `f` on the worker's monitor, interrupting its wait, and block until it returns
+    fn request<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut PressureMonitor) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        let (sender, receiver) = mpsc::channel();
+        let mut state = self.shared.lock();
+        if let Some(e) = &state.stopped {
+            return Err(Error::MonitorStopped(e.clone()));
+        }
+        state.requests.push(Box::new(move |monitor| {
+            let _ = sender.send(f(monitor));
+        }));
+        // Cancelled while holding the state lock, so the worker can't replace the token before taking the request
+        self.shared.cancellation().cancel();
+        drop(state);
+        receiver.recv().unwrap_or_else(|_| {
+            let stopped = self.shared.lock().stopped.clone();
+            Err(Error::MonitorStopped(stopped.unwrap_or_default()))
+        })
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    fn cancellation(&self) -> MutexGuard<'_, CancellationToken> {
+        self.cancellation.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The event received after `generation`, if any
+fn next_event(state: &State, generation: u64) -> Result<Option<(Event, Severity)>, Error> {
+    if let Some(e) = &state.stopped {
+        return Err(Error::MonitorStopped(e.clone()));
+    }
+    Ok(state
+        .last
+        .clone()
+        .filter(|_| state.generation != generation))
+}
+
+/// Publish the events of `monitor` until every handle is dropped
+fn dispatch(monitor: &mut PressureMonitor, shared: &Weak<Shared>) -> Result<(), Error> {
+    loop {
+        let outcome = monitor.wait()?;
+        let Some(shared) = shared.upgrade() else {
+            return Ok(());
+        };
+        let event = match outcome {
+            WaitOutcome::Triggered(event) => event,
+            WaitOutcome::Cancelled => {
+                handle_requests(monitor, &shared)?;
+                continue;
+            }
+            WaitOutcome::TimedOut => return Ok(()),
+            WaitOutcome::Interrupted => continue,
+            WaitOutcome::Hangup => return Err(Error::Hangup),
+            WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+        };
+        let severity = monitor.severity()?;
+        let mut state = shared.lock();
+        if state.paused {
+            continue;
+        }
+        state.generation += 1;
+        state.last = Some((event.clone(), severity));
+        state
+            .subscribers
+            .retain(|subscriber| subscriber.send((event.clone(), severity)).is_ok());
+        drop(state);
+        shared.events.notify_all();
+    }
+}
+
+/// Arm the monitor with a fresh cancellation token and run the requests that cancelled its wait
+fn handle_requests(monitor: &mut PressureMonitor, shared: &Shared) -> Result<(), Error> {
+    monitor.cancellation = None;
+    let token = monitor.cancellation_token()?;
+    let requests = {
+        let mut state = shared.lock();
+        *shared.cancellation() = token;
+        std::mem::take(&mut state.requests)
+    };
+    for request in requests {
+        request(monitor);
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! A process-wide registry of caches and other memory that can be released under pressure
+//!
+//! ```no_run
+//! use pressure::{PressureMonitor, Severity, Shrinkable};
+//! use std::sync::{Arc, Mutex};
+//!
+//! struct Cache(Mutex<Vec<Vec<u8>>>);
+//!
+//! impl Shrinkable for Cache {
+//!     fn shrink(&self, level: Severity) -> usize {
+//!         let mut entries = self.0.lock().unwrap();
+//!         // Drop half of the entries under medium pressure, and all of them once it's critical
+//!         let keep = if level == Severity::Critical { 0 } else { entries.len() / 2 };
+//!         entries.drain(keep..).map(|entry| entry.len()).sum()
+//!     }
+//! }
+//!
+//! let cache = Arc::new(Cache(Mutex::new(Vec::new())));
+//! pressure::shrink::register(&cache);
+//! let runner = pressure::shrink::spawn(PressureMonitor::new()?, Severity::Medium, |severity, reclaimed| {
+//!     eprintln!("released {reclaimed} bytes under {severity} pressure");
+//! })?;
+//! # Ok::<(), pressure::Error>(())
+//! ```
+use std::sync::{Arc, Mutex, Weak};
+
+#[cfg(unix)]
+use crate::ReclaimController;
+use crate::{Error, HookRegistry, HookRunner, PressureMonitor, Severity};
+
+/// Memory an application can release when the system is under pressure, such as a cache
+pub trait Shrinkable: Send + Sync {
+    /// Release memory in proportion to how severe pressure is, returning roughly how many bytes were released
+    fn shrink(&self, level: Severity) -> usize;
+
+    /// Release about `bytes`, returning roughly how many bytes were released. Shrinks at [Severity::Medium] unless implemented
+    fn shrink_bytes(&self, bytes: usize) -> usize {
+        let _ = bytes;
+        self.shrink(Severity::Medium)
+    }
+}
+
+static REGISTRY: Mutex<Vec<Weak<dyn Shrinkable>>> = Mutex::new(Vec::new());
+
+/// Add `shrinkable` to the registry for as long as it's alive, without keeping it alive
+pub fn register<T: Shrinkable + 'static>(shrinkable: &Arc<T>) {
+    let shrinkable: Arc<dyn Shrinkable> = shrinkable.clone();
+    registry().push(Arc::downgrade(&shrinkable));
+}
+
+/// Shrink everything in the registry, returning the total number of bytes released
+pub fn shrink_all(level: Severity) -> usize {
+    live()
+        .iter()
+        .map(|shrinkable| shrinkable.shrink(level))
+        .sum()
+}
+
+/// Release about `bytes` from the registry, asking each shrinkable in the order they were registered for what is left, and returning the number of bytes released
+pub fn shrink_bytes(bytes: usize) -> usize {
+    let mut released = 0;
+    for shrinkable in live() {
+        if released >= bytes {
+            break;
+        }
+        released += shrinkable.shrink_bytes(bytes - released);
+    }
+    released
+}
+
+/// Shrink the registry on each event of `monitor` at which pressure is at least `min` severe, on a worker thread.
+/// `report` receives the severity and the number of bytes released each time
+pub fn spawn(
+    monitor: PressureMonitor,
+    min: Severity,
+    mut report: impl FnMut(Severity, usize) + Send + 'static,
+) -> Result<HookRunner, Error> {
+    let mut hooks = HookRegistry::new();
+    hooks.on_event(min, move |_, severity| {
+        report(severity, shrink_all(severity))
+    });
+    hooks.spawn(monitor)
+}
+
+/// Release the amount `controller` computes from the avg10 of the monitored kind of stall on each event of `monitor`, on a worker thread.
+/// Events carry their stall information from then on, see [PressureMonitorBuilder::attach_stats](crate::PressureMonitorBuilder::attach_stats).
+/// `report` receives the number of bytes asked for and released each time
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, ReclaimController};
+///
+/// let controller = ReclaimController::new(10.0, 4 << 20).integral(1 << 20).max_bytes(256 << 20);
+/// let runner = pressure::shrink::spawn_controlled(PressureMonitor::new()?, controller, |asked, released| {
+///     eprintln!("released {released} of {asked} bytes");
+/// })?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[cfg(unix)]
+pub fn spawn_controlled(
+    mut monitor: PressureMonitor,
+    mut controller: ReclaimController,
+    mut report: impl FnMut(u64, usize) + Send + 'static,
+) -> Result<HookRunner, Error> {
+    monitor.target.attach_stats = true;
+    let mut hooks = HookRegistry::new();
+    hooks.on_event(Severity::Low, move |event, _| {
+        let Some(stats) = &event.stats else {
+            return;
+        };
+        let bytes = controller.update(stats.line(event.kind).avg10);
+        if bytes > 0 {
+            report(
+                bytes,
+                shrink_bytes(usize::try_from(bytes).unwrap_or(usize::MAX)),
+            );
+        }
+    });
+    hooks.spawn(monitor)
+}
+
+/// Every shrinkable still alive. They are shrunk outside of the lock, so that shrinkables can register others
+fn live() -> Vec<Arc<dyn Shrinkable>> {
+    let mut registry = registry();
+    registry.retain(|shrinkable| shrinkable.strong_count() > 0);
+    registry.iter().filter_map(Weak::upgrade).collect()
+}
+
+fn registry() -> std::sync::MutexGuard<'static, Vec<Weak<dyn Shrinkable>>> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,279 @@
+use std::{
+    os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+    path::{Path, PathBuf},
+    process::Child,
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    libc,
+    poll::{PollFd, PollFlags},
+};
+
+use crate::{CancellationToken, Error, PsiLine, PsiStats, Resource, StallKind, poll_timeout};
+
+/// Which of a process' mappings [Squeezer] pages out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mappings {
+    /// The heap, stacks and other anonymous memory, which is written to swap or zram
+    Anonymous,
+    /// Memory mapped files such as the process' libraries, which are dropped from the page cache and read back when needed
+    FileBacked,
+    All,
+}
+
+/// How a process registered with [Squeezer] is paged out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SqueezePolicy {
+    pub mappings: Mappings,
+    /// Percentage of time stalled over the last 10 seconds from which the process is paged out, so that less important processes can be squeezed earlier
+    pub threshold: f32,
+    /// Minimum time between two page-outs of the process
+    pub cooldown: Duration,
+}
+
+impl Default for SqueezePolicy {
+    /// Anonymous memory from 10% of time stalled, at most once a minute
+    fn default() -> Self {
+        Self {
+            mappings: Mappings::Anonymous,
+            threshold: 10.0,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A process [Squeezer] paged out
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqueezeReport {
+    pub pid: i32,
+    /// How many bytes of mappings were advised, of which only the resident pages were paged out
+    pub advised: usize,
+    /// The pressure that caused the page-out
+    pub pressure: PsiLine,
+    /// How long pressure stayed above the process' threshold
+    pub sustained: Duration,
+    pub timestamp: Instant,
+}
+
+struct Squeezed {
+    pid: i32,
+    pidfd: OwnedFd,
+    policy: SqueezePolicy,
+    above_since: Option<Instant>,
+    last_squeeze: Option<Instant>,
+}
+
+/// Pages out the memory of registered low-priority processes with process_madvise(2) once memory pressure is sustained, so a supervisor or session manager can squeeze background processes instead of killing them
+///
+/// Processes are referred to by pidfd, so a process that exited is never confused with one reusing its pid, and are forgotten once they exit.
+/// This requires Linux 5.10 or later, and CAP_SYS_NICE along with permission to ptrace the processes
+///
+/// ```no_run
+/// use pressure::{Mappings, SqueezePolicy, Squeezer};
+/// use std::{process::Command, time::Duration};
+///
+/// let mut squeezer = Squeezer::new().duration(Duration::from_secs(5));
+/// let indexer = Command::new("/usr/libexec/indexer").spawn()?;
+/// squeezer.add_child(&indexer, SqueezePolicy { threshold: 5.0, ..Default::default() })?;
+/// let thumbnailer = Command::new("/usr/libexec/thumbnailer").spawn()?;
+/// squeezer.add_child(&thumbnailer, SqueezePolicy { mappings: Mappings::All, ..Default::default() })?;
+/// squeezer.run(Duration::from_secs(1), |report| eprintln!("paged out {} bytes of {}", report.advised, report.pid))?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct Squeezer {
+    cgroup: Option<PathBuf>,
+    kind: StallKind,
+    duration: Duration,
+    processes: Vec<Squeezed>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Squeezer {
+    /// Act on system-wide memory pressure
+    pub fn new() -> Self {
+        Self {
+            cgroup: None,
+            kind: StallKind::Some,
+            duration: Duration::from_secs(10),
+            processes: Vec::new(),
+            cancellation: None,
+        }
+    }
+    /// Act on memory pressure within a cgroup v2 hierarchy
+    pub fn for_cgroup(cgroup: impl AsRef<Path>) -> Self {
+        Self {
+            cgroup: Some(cgroup.as_ref().to_path_buf()),
+            ..Self::new()
+        }
+    }
+    /// Kind of stall the processes' thresholds apply to, defaults to [StallKind::Some]
+    pub fn kind(mut self, kind: StallKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    /// How long pressure has to stay above a process' threshold before it is paged out
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+    /// Register a process to page out under pressure
+    pub fn add(&mut self, pid: i32, policy: SqueezePolicy) -> Result<(), Error> {
+        // SAFETY: pidfd_open takes no pointers
+        let pidfd = Errno::result(unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) })?;
+        // SAFETY: pidfd_open returned a new fd, owned by nothing else
+        let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd as i32) };
+        self.remove(pid);
+        self.processes.push(Squeezed {
+            pid,
+            pidfd,
+            policy,
+            above_since: None,
+            last_squeeze: None,
+        });
+        Ok(())
+    }
+    /// Register a child process to page out under pressure
+    pub fn add_child(&mut self, child: &Child, policy: SqueezePolicy) -> Result<(), Error> {
+        self.add(child.id() as i32, policy)
+    }
+    /// Stop paging out a process, returning whether it was registered
+    pub fn remove(&mut self, pid: i32) -> bool {
+        let len = self.processes.len();
+        self.processes.retain(|process| process.pid != pid);
+        self.processes.len() != len
+    }
+    /// Processes currently registered
+    pub fn pids(&self) -> impl Iterator<Item = i32> {
+        self.processes.iter().map(|process| process.pid)
+    }
+    /// Get a token that can be used to stop [run](Self::run) from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Check pressure once, paging out every process whose threshold it has stayed above for long enough
+    pub fn check(&mut self) -> Result<Vec<SqueezeReport>, Error> {
+        let path = match &self.cgroup {
+            Some(cgroup) => cgroup.join(Resource::Memory.cgroup_file()),
+            None => Resource::Memory.proc_path(),
+        };
+        let pressure = *PsiStats::read_path(path)?.line(self.kind);
+        let now = Instant::now();
+        let mut reports = Vec::new();
+        let mut exited = Vec::new();
+        for process in &mut self.processes {
+            if pressure.avg10 < process.policy.threshold {
+                process.above_since = None;
+                continue;
+            }
+            let above_since = *process.above_since.get_or_insert(now);
+            let cooling_down = process.last_squeeze.is_some_and(|last_squeeze| {
+                now.saturating_duration_since(last_squeeze) < process.policy.cooldown
+            });
+            if now - above_since < self.duration || cooling_down {
+                continue;
+            }
+            let advised = match page_out(process) {
+                Ok(advised) => advised,
+                Err(Error::Nix(Errno::ESRCH)) => {
+                    exited.push(process.pid);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            process.last_squeeze = Some(now);
+            process.above_since = None;
+            reports.push(SqueezeReport {
+                pid: process.pid,
+                advised,
+                pressure,
+                sustained: now - above_since,
+                timestamp: now,
+            });
+        }
+        self.processes
+            .retain(|process| !exited.contains(&process.pid));
+        Ok(reports)
+    }
+    /// Check pressure every `interval`, passing the report of every page-out to `report`, until cancelled through [cancellation_token](Self::cancellation_token)
+    pub fn run(
+        &mut self,
+        interval: Duration,
+        mut report: impl FnMut(&SqueezeReport),
+    ) -> Result<(), Error> {
+        loop {
+            if let Some(cancellation) = &self.cancellation {
+                let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+                if nix::poll::poll(&mut fds, poll_timeout(interval))? > 0 {
+                    return Ok(());
+                }
+            } else {
+                std::thread::sleep(interval);
+            }
+            for squeeze in self.check()? {
+                report(&squeeze);
+            }
+        }
+    }
+}
+
+impl Default for Squeezer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Advise the process' mappings selected by its policy with MADV_PAGEOUT, returning how many bytes were advised
+fn page_out(process: &Squeezed) -> Result<usize, Error> {
+    let maps = match std::fs::read_to_string(format!("/proc/{}/maps", process.pid)) {
+        Ok(maps) => maps,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(Errno::ESRCH)?,
+        Err(e) => return Err(e)?,
+    };
+    let ranges: Vec<libc::iovec> = maps
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (start, end) = fields.next()?.split_once('-')?;
+            let path = fields.nth(4);
+            let selected = match path {
+                // Mappings shared with the kernel can't be paged out
+                Some("[vdso]" | "[vvar]" | "[vsyscall]") => false,
+                Some(path) if path.starts_with('/') => {
+                    process.policy.mappings != Mappings::Anonymous
+                }
+                // The heap, stacks, named and unnamed anonymous mappings
+                _ => process.policy.mappings != Mappings::FileBacked,
+            };
+            let start = usize::from_str_radix(start, 16).ok()?;
+            let end = usize::from_str_radix(end, 16).ok()?;
+            selected.then_some(libc::iovec {
+                iov_base: start as *mut _,
+                iov_len: end - start,
+            })
+        })
+        .collect();
+    let mut advised = 0;
+    // The kernel accepts at most UIO_MAXIOV ranges per call
+    for chunk in ranges.chunks(libc::UIO_MAXIOV as usize) {
+        // SAFETY: the ranges are only read by the kernel, and refer to the other process' address space
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_process_madvise,
+                process.pidfd.as_raw_fd(),
+                chunk.as_ptr(),
+                chunk.len(),
+                libc::MADV_PAGEOUT,
+                0,
+            )
+        };
+        advised += Errno::result(ret)? as usize;
+    }
+    Ok(advised)
+}
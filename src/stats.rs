@@ -0,0 +1,302 @@
+#[cfg(unix)]
+use std::os::fd::AsFd;
+use std::{path::Path, str::FromStr, time::Duration};
+
+use crate::{Error, Resource, StallKind};
+
+/// Stall information for a single kind of stall
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PsiLine {
+    /// Percentage of time stalled over the last 10 seconds
+    pub avg10: f32,
+    /// Percentage of time stalled over the last 60 seconds
+    pub avg60: f32,
+    /// Percentage of time stalled over the last 300 seconds
+    pub avg300: f32,
+    /// Total time stalled since boot
+    pub total: Duration,
+}
+
+/// Current pressure stall information for a resource, as reported by a pressure file
+///
+/// Lines the kernel doesn't report for a resource (such as `some` for irq, or `full` on kernels older than 5.13 for cpu) are left zeroed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PsiStats {
+    pub some: PsiLine,
+    pub full: PsiLine,
+}
+
+impl PsiStats {
+    /// Get the stall information for a kind of stall
+    pub fn line(&self, kind: StallKind) -> &PsiLine {
+        match kind {
+            StallKind::Some => &self.some,
+            StallKind::Full => &self.full,
+        }
+    }
+
+    /// Stall time accumulated between an `earlier` snapshot of the same pressure file and this one.
+    /// Totals only ever increase, so a total lower than the earlier one (such as after the pressure file was swapped for another) counts as no stall
+    ///
+    /// ```
+    /// use pressure::{PsiLine, PsiStats, StallKind};
+    /// use std::time::Duration;
+    /// let earlier = PsiStats::default();
+    /// let later = PsiStats { some: PsiLine { total: Duration::from_millis(250), ..Default::default() }, ..Default::default() };
+    /// let delta = later.delta(&earlier);
+    /// assert_eq!(delta.some, Duration::from_millis(250));
+    /// assert_eq!(delta.percent(StallKind::Some, Duration::from_secs(1)), 25.0);
+    /// ```
+    pub fn delta(&self, earlier: &PsiStats) -> PsiDelta {
+        PsiDelta {
+            some: self.some.total.saturating_sub(earlier.some.total),
+            full: self.full.total.saturating_sub(earlier.full.total),
+        }
+    }
+
+    /// Read system-wide pressure stall information for a resource
+    pub fn read(resource: Resource) -> Result<Self, Error> {
+        let path = resource.proc_path();
+        if !path.exists() {
+            // The kernel doesn't create /proc/pressure at all when booted with psi=0
+            if cfg!(any(target_os = "linux", target_os = "android"))
+                && path.parent().is_some_and(|parent| !parent.exists())
+            {
+                return Err(Error::PsiDisabled);
+            }
+            return Err(Error::ResourceUnavailable(resource));
+        }
+        Self::read_path(&path)
+    }
+
+    /// Read pressure stall information from a pressure file, such as `/sys/fs/cgroup/system.slice/memory.pressure`
+    pub fn read_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            // Pressure files of cgroups fail to read instead when PSI is disabled
+            #[cfg(unix)]
+            if e.raw_os_error() == Some(nix::errno::Errno::EOPNOTSUPP as i32) {
+                return Error::PsiDisabled;
+            }
+            Error::from(e)
+        })?;
+        contents.parse()
+    }
+
+    /// Read pressure stall information from an already opened pressure file, without changing its offset
+    #[cfg(unix)]
+    pub(crate) fn read_fd(fd: impl AsFd) -> Result<Self, Error> {
+        let mut buf = [0; crate::READ_BUFFER_SIZE];
+        let len = nix::sys::uio::pread(fd, &mut buf, 0)?;
+        parse_psi(&buf[..len])
+    }
+}
+
+/// Stall time accumulated between two snapshots of pressure stall information, see [PsiStats::delta]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PsiDelta {
+    pub some: Duration,
+    pub full: Duration,
+}
+
+impl PsiDelta {
+    /// Get the stall time for a kind of stall
+    pub fn stall(&self, kind: StallKind) -> Duration {
+        match kind {
+            StallKind::Some => self.some,
+            StallKind::Full => self.full,
+        }
+    }
+
+    /// Percentage of the wall-clock `interval` between the snapshots that tasks were stalled, or 0 for an empty interval
+    pub fn percent(&self, kind: StallKind, interval: Duration) -> f32 {
+        if interval.is_zero() {
+            return 0.0;
+        }
+        (self.stall(kind).as_secs_f32() / interval.as_secs_f32() * 100.0).min(100.0)
+    }
+}
+
+/// Parse the contents of a pressure file, formatted as `some avg10=0.00 avg60=0.00 avg300=0.00 total=0` and `full ...` lines
+///
+/// This doesn't allocate, so pressure can be read into a stack buffer and parsed in hot sampling loops. Malformed input, including lines missing a field and averages that are negative or not finite, is rejected with [Error::MalformedStats].
+/// The `full` line is optional, as kernels before 5.13 don't report it for CPU pressure
+///
+/// ```
+/// use pressure::parse_psi;
+///
+/// let stats = parse_psi(b"some avg10=1.50 avg60=0.80 avg300=0.20 total=123456\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=0\n")?;
+/// assert_eq!(stats.some.avg10, 1.5);
+/// assert!(parse_psi(b"some avg10=1.50 avg60=0.80 avg300=0.20 total=x").is_err());
+/// assert!(parse_psi(b"partial avg10=0.00").is_err());
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub fn parse_psi(input: &[u8]) -> Result<PsiStats, Error> {
+    let mut stats = PsiStats::default();
+    for line in input.split(|&b| b == b'\n').filter(|line| !line.is_empty()) {
+        let (kind, values) = split_once(line, b' ').ok_or(Error::MalformedStats)?;
+        let target = match kind {
+            b"some" => &mut stats.some,
+            b"full" => &mut stats.full,
+            _ => return Err(Error::MalformedStats),
+        };
+        *target = parse_line(values)?;
+    }
+    Ok(stats)
+}
+
+/// Parse the values of a line, formatted as `avg10=0.00 avg60=0.00 avg300=0.00 total=0`
+fn parse_line(values: &[u8]) -> Result<PsiLine, Error> {
+    let mut line = PsiLine::default();
+    // Which fields were seen, as a line cut short by a truncated read is missing some
+    let mut seen = 0u8;
+    let fields = values
+        .split(u8::is_ascii_whitespace)
+        .filter(|field| !field.is_empty());
+    for field in fields {
+        let (key, value) = split_once(field, b'=').ok_or(Error::MalformedStats)?;
+        seen |= match key {
+            b"avg10" => {
+                line.avg10 = parse_average(value)?;
+                1
+            }
+            b"avg60" => {
+                line.avg60 = parse_average(value)?;
+                1 << 1
+            }
+            b"avg300" => {
+                line.avg300 = parse_average(value)?;
+                1 << 2
+            }
+            b"total" => {
+                line.total = Duration::from_micros(parse_value(value)?);
+                1 << 3
+            }
+            _ => return Err(Error::MalformedStats),
+        };
+    }
+    if seen != 0b1111 {
+        return Err(Error::MalformedStats);
+    }
+    Ok(line)
+}
+
+fn split_once(s: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let i = s.iter().position(|&b| b == separator)?;
+    Some((&s[..i], &s[i + 1..]))
+}
+
+/// Parse a percentage of time stalled, which the kernel never reports as negative, infinite or NaN
+fn parse_average(value: &[u8]) -> Result<f32, Error> {
+    let average: f32 = parse_value(value)?;
+    if !average.is_finite() || average < 0.0 {
+        return Err(Error::MalformedStats);
+    }
+    Ok(average)
+}
+
+fn parse_value<T: FromStr>(value: &[u8]) -> Result<T, Error> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or(Error::MalformedStats)
+}
+
+impl FromStr for PsiStats {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_psi(s.as_bytes())
+    }
+}
+
+impl FromStr for PsiLine {
+    type Err = Error;
+
+    /// Parse the values of a line, formatted as `avg10=0.00 avg60=0.00 avg300=0.00 total=0`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_line(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WELL_FORMED: &[u8] = b"some avg10=1.50 avg60=0.80 avg300=0.20 total=123456\nfull avg10=0.25 avg60=0.10 avg300=0.05 total=7890\n";
+
+    #[test]
+    fn parses_well_formed_input() {
+        let stats = parse_psi(WELL_FORMED).unwrap();
+        assert_eq!(
+            stats.some,
+            PsiLine {
+                avg10: 1.5,
+                avg60: 0.8,
+                avg300: 0.2,
+                total: Duration::from_micros(123456),
+            }
+        );
+        assert_eq!(
+            stats.full,
+            PsiLine {
+                avg10: 0.25,
+                avg60: 0.1,
+                avg300: 0.05,
+                total: Duration::from_micros(7890),
+            }
+        );
+        // Without a trailing newline, and with the fields in any order
+        let reordered = b"some total=5 avg300=0.00 avg60=0.00 avg10=2.00";
+        assert_eq!(parse_psi(reordered).unwrap().some.avg10, 2.0);
+    }
+
+    #[test]
+    fn missing_full_line_is_zero() {
+        let stats = parse_psi(b"some avg10=1.50 avg60=0.80 avg300=0.20 total=123456\n").unwrap();
+        assert_eq!(stats.some.total, Duration::from_micros(123456));
+        assert_eq!(stats.full, PsiLine::default());
+    }
+
+    #[test]
+    fn rejects_truncated_lines() {
+        for input in [
+            &b"some avg10=1.50 avg60=0.80 avg300=0.20"[..],
+            b"some avg10=1.50 avg60=0.80 avg3",
+            b"some avg10=1.50 avg60=",
+            b"some avg10=1.50 avg60=0.80 avg300=0.20 total=123456\nfull avg10=0.25 avg",
+        ] {
+            assert!(
+                matches!(parse_psi(input), Err(Error::MalformedStats)),
+                "{:?}",
+                String::from_utf8_lossy(input)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for input in [
+            &b"partial avg10=0.00 avg60=0.00 avg300=0.00 total=0"[..],
+            b"some",
+            b"some avg10",
+            b"some avg10=abc avg60=0.00 avg300=0.00 total=0",
+            b"some avg10=0.00 avg60=0.00 avg300=0.00 total=-1",
+            b"some avg10=0.00 avg60=0.00 avg300=0.00 total=0 extra=1",
+            b"some avg10=0.00 avg60=0.00 avg300=0.00 total=\xff",
+            b"\x00\x01\x02",
+            b"some avg10=NaN avg60=0.00 avg300=0.00 total=0",
+            b"some avg10=0.00 avg60=inf avg300=0.00 total=0",
+            b"some avg10=0.00 avg60=0.00 avg300=-inf total=0",
+            b"some avg10=-1.50 avg60=0.00 avg300=0.00 total=0",
+        ] {
+            assert!(
+                matches!(parse_psi(input), Err(Error::MalformedStats)),
+                "{:?}",
+                String::from_utf8_lossy(input)
+            );
+        }
+    }
+}
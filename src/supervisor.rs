@@ -0,0 +1,196 @@
+use std::{
+    io::{Read, Write},
+    os::{
+        fd::{AsRawFd, BorrowedFd},
+        unix::{net::UnixStream, process::CommandExt},
+    },
+    process::{Child, Command},
+    sync::{
+        Arc,
+        atomic::{AtomicI32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use nix::fcntl::{FcntlArg, FdFlag};
+
+use crate::{CancellationToken, Error, PressureMonitor, WaitOutcome};
+
+/// How often children are checked for having exited while no events arrive
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A child process of a [PressureSupervisor]
+struct Managed {
+    command: Command,
+    child: Child,
+    /// The supervisor's end of the socket pair shared with the child
+    socket: UnixStream,
+    /// The child's end of the current socket pair, read by the hook making it inheritable
+    child_fd: Arc<AtomicI32>,
+    exited_at: Option<Instant>,
+}
+
+impl Managed {
+    /// Start `command` with a new socket pair, passing it as MEMORY_PRESSURE_WATCH
+    fn start(command: &mut Command, child_fd: &AtomicI32) -> Result<(Child, UnixStream), Error> {
+        let (socket, child_end) = UnixStream::pair()?;
+        socket.set_nonblocking(true)?;
+        child_fd.store(child_end.as_raw_fd(), Ordering::Relaxed);
+        command
+            .env(
+                "MEMORY_PRESSURE_WATCH",
+                format!("/dev/fd/{}", child_end.as_raw_fd()),
+            )
+            .env_remove("MEMORY_PRESSURE_WRITE");
+        // The child's end is closed here once the child has inherited it
+        Ok((command.spawn()?, socket))
+    }
+}
+
+/// Monitors pressure on behalf of children and forwards its events to them, so that one privileged process serves pressure events to many unprivileged ones
+///
+/// Each child gets its own socket pair, passed through MEMORY_PRESSURE_WATCH as `/dev/fd/N`, and receives a byte on every event of the supervisor's monitor
+/// using systemd's wire protocol. Children built on this crate pick it up with [PressureMonitor::new]. Children that exit are restarted with a new socket pair
+/// unless disabled, and keep running once the supervisor is dropped
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, PressureSupervisor};
+/// use std::process::Command;
+///
+/// let mut supervisor = PressureSupervisor::new(PressureMonitor::new()?);
+/// for worker in 0..4 {
+///     let mut command = Command::new("/usr/bin/worker");
+///     command.arg(worker.to_string());
+///     supervisor.spawn(command)?;
+/// }
+/// supervisor.run()?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct PressureSupervisor {
+    monitor: PressureMonitor,
+    children: Vec<Managed>,
+    restart: bool,
+    restart_delay: Duration,
+}
+
+impl PressureSupervisor {
+    /// Forward the events of `monitor`, restarting children one second after they exit
+    pub fn new(monitor: PressureMonitor) -> Self {
+        Self {
+            monitor,
+            children: Vec::new(),
+            restart: true,
+            restart_delay: Duration::from_secs(1),
+        }
+    }
+    /// Whether to restart children that exited, otherwise they are forgotten
+    pub fn restart(mut self, restart: bool) -> Self {
+        self.restart = restart;
+        self
+    }
+    /// Time to wait before restarting a child that exited
+    pub fn restart_delay(mut self, delay: Duration) -> Self {
+        self.restart_delay = delay;
+        self
+    }
+
+    /// Start `command` as a child receiving the supervisor's events, returning its pid
+    pub fn spawn(&mut self, mut command: Command) -> Result<u32, Error> {
+        let child_fd = Arc::new(AtomicI32::new(-1));
+        let inherit = child_fd.clone();
+        // SAFETY: the hook only calls fcntl(2), which is async-signal-safe
+        unsafe {
+            command.pre_exec(move || {
+                // SAFETY: the child's end stays open in the parent until the child was spawned
+                let fd = BorrowedFd::borrow_raw(inherit.load(Ordering::Relaxed));
+                nix::fcntl::fcntl(fd, FcntlArg::F_SETFD(FdFlag::empty()))?;
+                Ok(())
+            });
+        }
+        let (child, socket) = Managed::start(&mut command, &child_fd)?;
+        let pid = child.id();
+        self.children.push(Managed {
+            command,
+            child,
+            socket,
+            child_fd,
+            exited_at: None,
+        });
+        Ok(pid)
+    }
+
+    /// Pids of the children currently running
+    pub fn children(&self) -> Vec<u32> {
+        self.children
+            .iter()
+            .filter(|managed| managed.exited_at.is_none())
+            .map(|managed| managed.child.id())
+            .collect()
+    }
+
+    /// Get a token that can be used to stop [run](Self::run) from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        self.monitor.cancellation_token()
+    }
+
+    /// Forward an event to every running child, returning how many were notified
+    pub fn notify(&mut self) -> usize {
+        let mut notified = 0;
+        for managed in &mut self.children {
+            if managed.exited_at.is_none() && managed.socket.write(&[1]).is_ok() {
+                notified += 1;
+            }
+        }
+        notified
+    }
+
+    /// Reap children that exited and restart those whose delay elapsed, discarding the triggers running children wrote
+    pub fn supervise(&mut self) -> Result<(), Error> {
+        let mut buf = [0; 64];
+        for managed in &mut self.children {
+            while matches!(managed.socket.read(&mut buf), Ok(read) if read > 0) {}
+            if managed.exited_at.is_none() && managed.child.try_wait()?.is_some() {
+                managed.exited_at = Some(Instant::now());
+            }
+        }
+        if !self.restart {
+            self.children.retain(|managed| managed.exited_at.is_none());
+            return Ok(());
+        }
+        for managed in &mut self.children {
+            if managed
+                .exited_at
+                .is_some_and(|exited_at| exited_at.elapsed() >= self.restart_delay)
+            {
+                let (child, socket) = Managed::start(&mut managed.command, &managed.child_fd)?;
+                managed.child = child;
+                managed.socket = socket;
+                managed.exited_at = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forward events and restart children until cancelled through [cancellation_token](Self::cancellation_token)
+    pub fn run(&mut self) -> Result<(), Error> {
+        loop {
+            match self.monitor.wait_timeout(CHECK_INTERVAL)? {
+                WaitOutcome::Triggered(_) => {
+                    self.notify();
+                }
+                WaitOutcome::TimedOut | WaitOutcome::Interrupted => {}
+                WaitOutcome::Cancelled => return Ok(()),
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            }
+            self.supervise()?;
+        }
+    }
+}
+
+impl Drop for Managed {
+    fn drop(&mut self) {
+        // Reap the child if it already exited, so that it doesn't linger as a zombie
+        let _ = self.child.try_wait();
+    }
+}
@@ -0,0 +1,157 @@
+use std::{fmt, ops::BitOr, time::Duration};
+
+use crate::{Error, MonitorSet, PressureMonitor, PsiStats, Resource};
+
+/// A set of resources, reported by [SystemPressureMonitor::wait] for the resources whose trigger fired
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Resources(u8);
+
+impl Resources {
+    pub const MEMORY: Self = Self(1);
+    pub const CPU: Self = Self(1 << 1);
+    pub const IO: Self = Self(1 << 2);
+    pub const IRQ: Self = Self(1 << 3);
+    /// Memory, CPU and IO, which every kernel with pressure stall information reports
+    pub const SYSTEM: Self = Self(Self::MEMORY.0 | Self::CPU.0 | Self::IO.0);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, resource: Resource) -> bool {
+        self.0 & Self::from(resource).0 != 0
+    }
+
+    pub fn insert(&mut self, resource: Resource) {
+        self.0 |= Self::from(resource).0;
+    }
+
+    /// Iterate over the resources in the set, in the order memory, CPU, IO and IRQ
+    pub fn iter(self) -> impl Iterator<Item = Resource> {
+        [Resource::Memory, Resource::Cpu, Resource::Io, Resource::Irq]
+            .into_iter()
+            .filter(move |resource| self.contains(*resource))
+    }
+}
+
+impl From<Resource> for Resources {
+    fn from(resource: Resource) -> Self {
+        match resource {
+            Resource::Memory => Self::MEMORY,
+            Resource::Cpu => Self::CPU,
+            Resource::Io => Self::IO,
+            Resource::Irq => Self::IRQ,
+        }
+    }
+}
+
+impl BitOr for Resources {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl FromIterator<Resource> for Resources {
+    fn from_iter<I: IntoIterator<Item = Resource>>(iter: I) -> Self {
+        let mut resources = Self::empty();
+        for resource in iter {
+            resources.insert(resource);
+        }
+        resources
+    }
+}
+
+impl fmt::Debug for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Waits for system-wide pressure on several resources at once from a single thread, reporting which of them fired
+///
+/// ```no_run
+/// use pressure::{Resource, SystemPressureMonitor};
+///
+/// let mut monitor = SystemPressureMonitor::new()?;
+/// loop {
+///     let fired = monitor.wait()?;
+///     if fired.contains(Resource::Memory) {
+///         // Drop caches
+///     }
+///     if fired.contains(Resource::Io) {
+///         // Defer background writes
+///     }
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct SystemPressureMonitor {
+    set: MonitorSet,
+}
+
+impl SystemPressureMonitor {
+    /// Monitor memory, CPU and IO pressure, each with its default trigger
+    pub fn new() -> Result<Self, Error> {
+        Self::with_resources(Resources::SYSTEM)
+    }
+
+    /// Monitor the given resources, each with its default trigger, see [PressureMonitor::with_resource]
+    pub fn with_resources(resources: Resources) -> Result<Self, Error> {
+        resources
+            .iter()
+            .map(PressureMonitor::with_resource)
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(Self::from_monitors)
+    }
+
+    /// Monitor resources with monitors configured by the caller, for example through [PressureMonitorBuilder](crate::PressureMonitorBuilder).
+    /// Monitors that fell back to polling can't be used
+    pub fn from_monitors(
+        monitors: impl IntoIterator<Item = PressureMonitor>,
+    ) -> Result<Self, Error> {
+        let mut set = MonitorSet::new()?;
+        for monitor in monitors {
+            set.insert(monitor)?;
+        }
+        Ok(Self { set })
+    }
+
+    /// The resources being monitored
+    pub fn resources(&self) -> Resources {
+        self.set.iter().map(PressureMonitor::resource).collect()
+    }
+
+    /// Read the current pressure stall information for a monitored resource, or [None] if it isn't monitored
+    pub fn stats(&self, resource: Resource) -> Option<Result<PsiStats, Error>> {
+        self.set
+            .iter()
+            .find(|monitor| monitor.resource() == resource)
+            .map(PressureMonitor::stats)
+    }
+
+    /// Wait until at least one resource's trigger fires, returning every resource that fired.
+    /// Fails with [Error::Hangup] or [Error::PollError] if a monitor's fd died
+    pub fn wait(&mut self) -> Result<Resources, Error> {
+        Ok(self
+            .set
+            .wait()?
+            .iter()
+            .map(|event| event.resource)
+            .collect())
+    }
+
+    /// Wait until at least one resource's trigger fires, returning an empty set once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<Resources, Error> {
+        Ok(self
+            .set
+            .wait_timeout(timeout)?
+            .iter()
+            .map(|event| event.resource)
+            .collect())
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    ffi::OsStr,
+    io,
+    os::unix::{ffi::OsStrExt, net::UnixDatagram},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{ClockTimestamp, Error, PressureMonitor, WaitOutcome};
+
+/// Reports the state of a service to systemd through the protocol of sd_notify(3), so that the unit's status reflects the pressure
+/// and the service manager can supervise the threads handling it.
+/// Every method does nothing if the process wasn't started by a service manager listening for notifications
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, ServiceNotifier, WaitOutcome};
+///
+/// let notifier = ServiceNotifier::from_env()?;
+/// let mut monitor = PressureMonitor::new()?;
+/// notifier.ready()?;
+/// loop {
+///     // Pings the watchdog while waiting, and reports the pressure in the unit's status once an event arrives
+///     if let WaitOutcome::Triggered(event) = notifier.wait(&mut monitor)? {
+///         // Shed load
+///     }
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ServiceNotifier {
+    socket: Option<Arc<UnixDatagram>>,
+    watchdog: Option<Duration>,
+}
+
+impl ServiceNotifier {
+    /// Connect to the socket in `NOTIFY_SOCKET`, and enable the watchdog if `WATCHDOG_USEC` is set for this process
+    pub fn from_env() -> Result<Self, Error> {
+        let socket = std::env::var_os("NOTIFY_SOCKET")
+            .filter(|address| !address.is_empty())
+            .map(|address| connect(&address))
+            .transpose()?
+            .map(Arc::new);
+        // Environment variables are inherited by child processes, so the watchdog is only ours if WATCHDOG_PID is unset or matches
+        let is_ours = std::env::var("WATCHDOG_PID")
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok())
+            .is_none_or(|pid| pid == std::process::id());
+        let watchdog = std::env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse().ok())
+            .filter(|&usec| usec > 0 && is_ours)
+            .map(Duration::from_micros);
+        Ok(Self { socket, watchdog })
+    }
+
+    /// Whether a service manager listens for notifications
+    pub fn is_enabled(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    /// How often the watchdog should be pinged, half its timeout as systemd recommends, or [None] if it isn't enabled
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog
+            .filter(|_| self.is_enabled())
+            .map(|timeout| timeout / 2)
+    }
+
+    /// Send newline-separated variable assignments, see sd_notify(3)
+    pub fn notify(&self, state: &str) -> Result<(), Error> {
+        if let Some(socket) = &self.socket {
+            socket.send(state.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Tell the service manager startup finished, once the monitors' triggers are armed
+    pub fn ready(&self) -> Result<(), Error> {
+        self.notify("READY=1")
+    }
+
+    /// Tell the service manager the configuration is being reloaded, after which [ready](Self::ready) must be sent again
+    pub fn reloading(&self) -> Result<(), Error> {
+        let monotonic = ClockTimestamp::now().monotonic.as_micros();
+        self.notify(&format!("RELOADING=1\nMONOTONIC_USEC={monotonic}"))
+    }
+
+    /// Tell the service manager the service is shutting down
+    pub fn stopping(&self) -> Result<(), Error> {
+        self.notify("STOPPING=1")
+    }
+
+    /// Set the status shown by `systemctl status`, a single line of free-form text
+    pub fn status(&self, status: &str) -> Result<(), Error> {
+        self.notify(&format!("STATUS={}", status.replace('\n', " ")))
+    }
+
+    /// Ping the watchdog, proving the service is still alive
+    pub fn watchdog(&self) -> Result<(), Error> {
+        if self.watchdog.is_none() {
+            return Ok(());
+        }
+        self.notify("WATCHDOG=1")
+    }
+
+    /// Set the status to the current pressure on the monitored resource, such as `memory pressure medium (some avg10 12.50%)`
+    pub fn report(&self, monitor: &PressureMonitor) -> Result<(), Error> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let kind = monitor.target.kind;
+        let mut status = format!(
+            "{} pressure {} ({kind} avg10 {:.2}%)",
+            monitor.resource(),
+            monitor.severity()?,
+            monitor.stats()?.line(kind).avg10
+        );
+        if let Some(cgroup) = monitor.cgroup() {
+            status.push_str(&format!(" in {}", cgroup.display()));
+        }
+        self.status(&status)
+    }
+
+    /// Wait for an event like [PressureMonitor::wait], pinging the watchdog at its interval while waiting and [reporting](Self::report) the pressure once an event arrives
+    pub fn wait(&self, monitor: &mut PressureMonitor) -> Result<WaitOutcome, Error> {
+        loop {
+            let outcome = match self.watchdog_interval() {
+                Some(interval) => monitor.wait_timeout(interval)?,
+                None => monitor.wait()?,
+            };
+            self.watchdog()?;
+            match outcome {
+                WaitOutcome::TimedOut => {}
+                WaitOutcome::Triggered(event) => {
+                    self.report(monitor)?;
+                    return Ok(WaitOutcome::Triggered(event));
+                }
+                outcome => return Ok(outcome),
+            }
+        }
+    }
+}
+
+/// Connect to the notification socket, where a leading `@` denotes Linux's abstract namespace
+fn connect(address: &OsStr) -> io::Result<UnixDatagram> {
+    let socket = UnixDatagram::unbound()?;
+    match address.as_bytes().strip_prefix(b"@") {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Some(name) => {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+            socket.connect_addr(&std::os::unix::net::SocketAddr::from_abstract_name(name)?)?;
+        }
+        _ => socket.connect(address)?,
+    }
+    Ok(socket)
+}
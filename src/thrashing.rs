@@ -0,0 +1,187 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    os::fd::AsFd,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use nix::poll::{PollFd, PollFlags};
+
+use crate::{CancellationToken, Error, MemoryContext, PsiStats, Resource, poll_timeout};
+
+/// Detects thrashing by correlating `full` memory pressure with swap and refault rates over a sliding window
+///
+/// `some` pressure alone can't tell mild reclaim from a swap storm. Thrashing is detected once every non-idle task was stalled on memory for at least
+/// a share of the window while pages are swapped or refaulted, evicted and read right back, at a high rate.
+/// It is reported once when it starts, and again only after it stopped for at least one sample
+///
+/// ```no_run
+/// use pressure::ThrashingDetector;
+///
+/// let mut detector = ThrashingDetector::new()?;
+/// while let Some(thrashing) = detector.wait()? {
+///     println!(
+///         "thrashing: {:.1}% full stall, {:.0} pages swapped and {:.0} refaulted per second",
+///         thrashing.full,
+///         thrashing.swap_in + thrashing.swap_out,
+///         thrashing.refaults
+///     );
+/// }
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct ThrashingDetector {
+    procfs_root: PathBuf,
+    pressure: File,
+    interval: Duration,
+    window: Duration,
+    full_threshold: f64,
+    swap_threshold: f64,
+    refault_threshold: f64,
+    /// Samples within the window, oldest first, each holding the total `full` stall time and the memory counters
+    samples: VecDeque<(Instant, Duration, MemoryContext)>,
+    thrashing: bool,
+    cancellation: Option<CancellationToken>,
+}
+
+/// Reported by a [ThrashingDetector] once thrashing starts, with the rates measured over its window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thrashing {
+    /// Share of the window every non-idle task was stalled on memory, in percent
+    pub full: f32,
+    /// Pages swapped in per second
+    pub swap_in: f64,
+    /// Pages swapped out per second
+    pub swap_out: f64,
+    /// Pages refaulted per second
+    pub refaults: f64,
+    /// Time between the oldest and newest sample the rates were measured over
+    pub window: Duration,
+    pub timestamp: Instant,
+}
+
+impl ThrashingDetector {
+    /// Detect system-wide thrashing, see [new_in](Self::new_in)
+    pub fn new() -> Result<Self, Error> {
+        Self::new_in(crate::procfs_root())
+    }
+
+    /// Detect system-wide thrashing, reading procfs mounted at `procfs_root`
+    ///
+    /// By default the counters are sampled every second over a window of 10s, and thrashing is detected once tasks were fully stalled for 10% of it
+    /// while over 1000 pages per second were swapped in and out or refaulted
+    pub fn new_in(procfs_root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let procfs_root = procfs_root.into();
+        let pressure = File::open(Resource::Memory.proc_path_in(&procfs_root))?;
+        let full = PsiStats::read_fd(&pressure)?.full.total;
+        let memory = MemoryContext::read_in(&procfs_root)?;
+        Ok(Self {
+            procfs_root,
+            pressure,
+            interval: Duration::from_secs(1),
+            window: Duration::from_secs(10),
+            full_threshold: 10.0,
+            swap_threshold: 1000.0,
+            refault_threshold: 1000.0,
+            samples: VecDeque::from([(Instant::now(), full, memory)]),
+            thrashing: false,
+            cancellation: None,
+        })
+    }
+    /// How often [wait](Self::wait) samples the counters
+    pub fn sample_every(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// Length of the sliding window rates are measured over
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+    /// Percentage of the window every non-idle task must have been stalled on memory
+    pub fn full_threshold(mut self, percent: f64) -> Self {
+        self.full_threshold = percent;
+        self
+    }
+    /// Pages swapped in and out per second, at or above which full pressure counts as thrashing
+    pub fn swap_threshold(mut self, pages_per_second: f64) -> Self {
+        self.swap_threshold = pages_per_second;
+        self
+    }
+    /// Pages refaulted per second, at or above which full pressure counts as thrashing
+    pub fn refault_threshold(mut self, pages_per_second: f64) -> Self {
+        self.refault_threshold = pages_per_second;
+        self
+    }
+    /// Interval between samples, see [sample_every](Self::sample_every)
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+    /// Whether the last sample found the system thrashing
+    pub fn is_thrashing(&self) -> bool {
+        self.thrashing
+    }
+    /// Get a token that can be used to stop [wait](Self::wait) from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Sample the counters now, returning [Thrashing] if it just started. This should be called once per interval
+    pub fn sample(&mut self) -> Result<Option<Thrashing>, Error> {
+        let now = Instant::now();
+        let full = PsiStats::read_fd(&self.pressure)?.full.total;
+        let memory = MemoryContext::read_in(&self.procfs_root)?;
+        self.samples.push_back((now, full, memory));
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(at, _, _)| now.saturating_duration_since(*at) >= self.window)
+        {
+            self.samples.pop_front();
+        }
+        let (Some((from, from_full, from_memory)), Some((to, to_full, to_memory))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return Ok(None);
+        };
+        let window = to.saturating_duration_since(*from);
+        if window.is_zero() {
+            return Ok(None);
+        }
+        let rate = |to: u64, from: u64| to.saturating_sub(from) as f64 / window.as_secs_f64();
+        let thrashing = Thrashing {
+            full: (to_full.saturating_sub(*from_full).as_secs_f64() / window.as_secs_f64() * 100.0)
+                as f32,
+            swap_in: rate(to_memory.pswpin, from_memory.pswpin),
+            swap_out: rate(to_memory.pswpout, from_memory.pswpout),
+            refaults: rate(to_memory.refaults, from_memory.refaults),
+            window,
+            timestamp: now,
+        };
+        let was_thrashing = self.thrashing;
+        self.thrashing = f64::from(thrashing.full) >= self.full_threshold
+            && (thrashing.swap_in + thrashing.swap_out >= self.swap_threshold
+                || thrashing.refaults >= self.refault_threshold);
+        Ok(Some(thrashing).filter(|_| self.thrashing && !was_thrashing))
+    }
+    /// Sample the counters once per interval until thrashing starts, returning [None] once cancelled through [cancellation_token](Self::cancellation_token)
+    pub fn wait(&mut self) -> Result<Option<Thrashing>, Error> {
+        loop {
+            if let Some(cancellation) = &self.cancellation {
+                let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+                if nix::poll::poll(&mut fds, poll_timeout(self.interval))? > 0 {
+                    return Ok(None);
+                }
+            } else {
+                std::thread::sleep(self.interval);
+            }
+            if let Some(thrashing) = self.sample()? {
+                return Ok(Some(thrashing));
+            }
+        }
+    }
+}
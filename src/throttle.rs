@@ -0,0 +1,193 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{Error, PsiStats, Resource, SeverityThresholds, StallKind};
+
+/// How often pressure is read at most
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct State {
+    max_rate: f64,
+    min_rate: f64,
+    burst: f64,
+    kind: StallKind,
+    thresholds: SeverityThresholds,
+    /// Bytes that can be written right away, negative once writes were reserved ahead of the refill
+    tokens: f64,
+    /// Bytes per second the bucket currently refills at
+    rate: f64,
+    last_refill: Instant,
+    /// When pressure was last read, if it was read yet
+    last_read: Option<Instant>,
+}
+
+struct Shared {
+    pressure: File,
+    state: Mutex<State>,
+}
+
+/// A token bucket for voluntarily throttling background writes such as compaction, log shipping or backups, refilling slower as IO pressure rises
+///
+/// The bucket refills at the maximum rate while `some` IO pressure over the last 10 seconds stays below the medium [SeverityThresholds],
+/// slows down linearly to the minimum rate as it approaches the critical threshold, and speeds back up once it subsides.
+/// Pressure is read at most once per second, when bytes are reserved. Clones share the same bucket
+///
+/// ```no_run
+/// use pressure::IoThrottle;
+/// use std::io::Write;
+///
+/// // Up to 100 MiB/s while the system is idle, down to 5 MiB/s when it's IO-starved
+/// let throttle = IoThrottle::new(100 << 20)?.min_rate(5 << 20);
+/// let mut backup = throttle.writer(std::fs::File::create("/var/backups/db.tar")?);
+/// backup.write_all(b"...")?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct IoThrottle {
+    shared: Arc<Shared>,
+}
+
+impl IoThrottle {
+    /// Throttle to at most `max_rate` bytes per second following system-wide IO pressure
+    pub fn new(max_rate: u64) -> Result<Self, Error> {
+        if !Resource::Io.is_available() {
+            return Err(Error::ResourceUnavailable(Resource::Io));
+        }
+        Self::from_path(Resource::Io.proc_path(), max_rate)
+    }
+    /// Throttle to at most `max_rate` bytes per second following IO pressure within a cgroup v2 hierarchy
+    pub fn for_cgroup(cgroup: impl AsRef<Path>, max_rate: u64) -> Result<Self, Error> {
+        Self::from_path(cgroup.as_ref().join(Resource::Io.cgroup_file()), max_rate)
+    }
+
+    fn from_path(path: impl AsRef<Path>, max_rate: u64) -> Result<Self, Error> {
+        let max_rate = max_rate as f64;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                pressure: File::open(path)?,
+                state: Mutex::new(State {
+                    max_rate,
+                    min_rate: max_rate / 10.0,
+                    burst: max_rate,
+                    kind: StallKind::Some,
+                    thresholds: SeverityThresholds::default(),
+                    tokens: max_rate,
+                    rate: max_rate,
+                    last_refill: Instant::now(),
+                    last_read: None,
+                }),
+            }),
+        })
+    }
+
+    /// Bytes per second the bucket refills at once pressure is critical, defaults to a tenth of the maximum rate
+    pub fn min_rate(self, min_rate: u64) -> Self {
+        {
+            let mut state = self.lock();
+            state.min_rate = (min_rate as f64).min(state.max_rate);
+        }
+        self
+    }
+    /// Bytes that can be written at once after an idle period, defaults to one second at the maximum rate
+    pub fn burst(self, burst: u64) -> Self {
+        {
+            let mut state = self.lock();
+            state.burst = burst as f64;
+            state.tokens = state.tokens.min(state.burst);
+        }
+        self
+    }
+    /// Kind of IO stall to follow, defaults to [StallKind::Some]
+    pub fn kind(self, kind: StallKind) -> Self {
+        self.lock().kind = kind;
+        self
+    }
+    /// Thresholds between which the rate slows down from the maximum to the minimum rate
+    pub fn thresholds(self, thresholds: SeverityThresholds) -> Self {
+        self.lock().thresholds = thresholds;
+        self
+    }
+
+    /// Bytes per second the bucket currently refills at
+    pub fn rate(&self) -> u64 {
+        self.lock().rate as u64
+    }
+
+    /// Take `bytes` from the bucket, returning how long to wait before writing them so that the rate is respected.
+    /// This doesn't block, for use with asynchronous timers
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        let mut state = self.lock();
+        let now = Instant::now();
+        if state
+            .last_read
+            .is_none_or(|last_read| now - last_read >= REFRESH_INTERVAL)
+        {
+            state.last_read = Some(now);
+            state.rate = self.current_rate(&state);
+        }
+        let elapsed = (now - state.last_refill).as_secs_f64();
+        state.last_refill = now;
+        state.tokens = (state.tokens + elapsed * state.rate).min(state.burst);
+        state.tokens -= bytes as f64;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / state.rate.max(1.0))
+        }
+    }
+    /// Take `bytes` from the bucket, blocking until they may be written
+    pub fn throttle(&self, bytes: u64) {
+        std::thread::sleep(self.reserve(bytes));
+    }
+    /// Wrap a writer, throttling every write
+    pub fn writer<W: Write>(&self, inner: W) -> ThrottledWriter<W> {
+        ThrottledWriter {
+            inner,
+            throttle: self.clone(),
+        }
+    }
+
+    /// Rate for the current pressure, keeping the previous rate if it can't be read
+    fn current_rate(&self, state: &State) -> f64 {
+        let Ok(stats) = PsiStats::read_fd(&self.shared.pressure) else {
+            return state.rate;
+        };
+        let SeverityThresholds { medium, critical } = state.thresholds;
+        let avg10 = stats.line(state.kind).avg10;
+        let slowdown = ((avg10 - medium) / (critical - medium)).clamp(0.0, 1.0) as f64;
+        state.max_rate - (state.max_rate - state.min_rate) * slowdown
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A writer throttled by an [IoThrottle], see [IoThrottle::writer]
+pub struct ThrottledWriter<W> {
+    inner: W,
+    throttle: IoThrottle,
+}
+
+impl<W> ThrottledWriter<W> {
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ThrottledWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.throttle.throttle(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
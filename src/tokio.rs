@@ -0,0 +1,302 @@
+//! Asynchronous pressure monitoring using Tokio's event loop
+use std::{
+    io,
+    os::fd::RawFd,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::{Duration, Instant},
+};
+
+use futures_core::Stream;
+use tokio::{
+    io::{Interest, unix::AsyncFd},
+    sync::watch,
+    time::Sleep,
+};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::{MemoryEvent, MemoryEventCounters};
+
+use crate::{
+    AsyncPressureMonitor, AsyncPressureStream, Error, Event, PsiStats, Reactor, Resource, Severity,
+};
+
+/// Asynchronous equivalent to [PressureMonitor](`super::PressureMonitor`)
+pub type PressureMonitor = AsyncPressureMonitor<Tokio>;
+
+/// Stream of pressure events produced by [PressureMonitor::into_stream]
+pub type PressureStream = AsyncPressureStream<Tokio>;
+
+/// [Reactor] waiting on Tokio's event loop. Registering fds and creating timers must happen within a Tokio runtime
+pub struct Tokio;
+
+impl Reactor for Tokio {
+    type Registration = AsyncFd<RawFd>;
+    type Timer = Pin<Box<Sleep>>;
+
+    unsafe fn register(fd: RawFd) -> io::Result<Self::Registration> {
+        AsyncFd::with_interest(fd, Interest::READABLE)
+    }
+
+    fn poll_readable(
+        registration: &Self::Registration,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        // Dropping the guard keeps the readiness until it is cleared
+        registration.poll_read_ready(cx).map_ok(drop)
+    }
+
+    fn clear_readiness(registration: &Self::Registration, cx: &mut Context<'_>) {
+        if let Poll::Ready(Ok(mut ready)) = registration.poll_read_ready(cx) {
+            ready.clear_ready();
+        }
+    }
+
+    fn timer(deadline: Instant) -> Self::Timer {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
+
+impl AsyncPressureMonitor<Tokio> {
+    /// Hand the monitor to a task keeping the returned receiver up to date with the current severity, so that many components can check or await
+    /// changes in pressure without each owning a monitor. The severity is re-read on every event and every second, so it also drops once pressure subsides.
+    ///
+    /// The task stops once every receiver is dropped, or if waiting for events fails, after which [changed](watch::Receiver::changed) returns an error.
+    /// This must be called from within a Tokio runtime
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), pressure::Error> {
+    /// use pressure::{Severity, tokio::PressureMonitor};
+    ///
+    /// let mut severity = PressureMonitor::new()?.watch()?;
+    /// while severity.changed().await.is_ok() {
+    ///     if *severity.borrow_and_update() >= Severity::Medium {
+    ///         // Stop prefetching
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(mut self) -> Result<watch::Receiver<Severity>, Error> {
+        let (sender, receiver) = watch::channel(self.severity()?);
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                // Timing out only means the severity is re-read
+                if let Ok(Err(_)) =
+                    tokio::time::timeout(SEVERITY_REFRESH_INTERVAL, self.wait()).await
+                {
+                    return;
+                }
+                if let Ok(severity) = self.severity() {
+                    sender.send_if_modified(|current| {
+                        std::mem::replace(current, severity) != severity
+                    });
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+/// How often [PressureMonitor::watch] re-reads the severity between events
+const SEVERITY_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Asynchronous equivalent to [MemoryEventsWatcher](crate::MemoryEventsWatcher)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub struct MemoryEventsWatcher {
+    watcher: AsyncFd<crate::MemoryEventsWatcher>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl MemoryEventsWatcher {
+    pub fn new(cgroup: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self {
+            watcher: AsyncFd::new(crate::MemoryEventsWatcher::new(cgroup)?)?,
+        })
+    }
+
+    pub fn for_current_cgroup() -> Result<Self, Error> {
+        Ok(Self {
+            watcher: AsyncFd::new(crate::MemoryEventsWatcher::for_current_cgroup()?)?,
+        })
+    }
+
+    pub fn cgroup(&self) -> &Path {
+        self.watcher.get_ref().cgroup()
+    }
+
+    /// The counters as of the last check
+    pub fn counters(&self) -> MemoryEventCounters {
+        self.watcher.get_ref().counters()
+    }
+
+    /// Wait for a single memory event, see [MemoryEventsWatcher::wait](crate::MemoryEventsWatcher::wait)
+    pub async fn wait(&mut self) -> Result<MemoryEvent, Error> {
+        loop {
+            if let Some(event) = self.watcher.get_mut().try_wait()? {
+                return Ok(event);
+            }
+            self.watcher.readable().await?.clear_ready();
+        }
+    }
+
+    /// Turn the watcher into a [Stream] of memory events, for use with stream combinators
+    pub fn into_stream(self) -> MemoryEventsStream {
+        MemoryEventsStream::new(self)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+type MemoryEventsFuture =
+    Pin<Box<dyn Future<Output = (MemoryEventsWatcher, Result<MemoryEvent, Error>)> + Send>>;
+
+/// Stream of memory events produced by [MemoryEventsWatcher::into_stream]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub struct MemoryEventsStream {
+    wait: MemoryEventsFuture,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl MemoryEventsStream {
+    fn new(mut watcher: MemoryEventsWatcher) -> Self {
+        Self {
+            wait: Box::pin(async move {
+                let result = watcher.wait().await;
+                (watcher, result)
+            }),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Stream for MemoryEventsStream {
+    type Item = Result<MemoryEvent, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let (watcher, result) = ready!(self.wait.as_mut().poll(cx));
+        *self = MemoryEventsStream::new(watcher);
+        Poll::Ready(Some(result))
+    }
+}
+
+/// Asynchronous equivalent to [MockPressureMonitor](crate::MockPressureMonitor), for testing tasks and [Stream] consumers that react to pressure
+///
+/// ```
+/// use pressure::{Resource, tokio::MockPressureMonitor};
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build()?;
+/// runtime.block_on(async {
+///     let mut monitor = MockPressureMonitor::new(Resource::Memory)?;
+///     monitor.handle().fire_pressure()?;
+///     assert_eq!(monitor.wait().await?.resource, Resource::Memory);
+///     Ok::<(), pressure::Error>(())
+/// })?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[cfg(feature = "test-util")]
+pub struct MockPressureMonitor {
+    monitor: AsyncFd<crate::MockPressureMonitor>,
+}
+
+#[cfg(feature = "test-util")]
+impl MockPressureMonitor {
+    /// Create a mock monitor for system-wide pressure on a resource. This must be called from within a Tokio runtime
+    pub fn new(resource: Resource) -> Result<Self, Error> {
+        Self::from_monitor(crate::MockPressureMonitor::new(resource)?)
+    }
+
+    /// Create a mock monitor for pressure on a resource within a cgroup, which doesn't need to exist
+    pub fn for_cgroup(cgroup: impl AsRef<Path>, resource: Resource) -> Result<Self, Error> {
+        Self::from_monitor(crate::MockPressureMonitor::for_cgroup(cgroup, resource)?)
+    }
+
+    /// Register a blocking mock monitor with the runtime
+    pub fn from_monitor(monitor: crate::MockPressureMonitor) -> Result<Self, Error> {
+        Ok(Self {
+            monitor: AsyncFd::with_interest(monitor, Interest::READABLE)?,
+        })
+    }
+
+    /// Get a handle firing events at this monitor
+    pub fn handle(&self) -> crate::MockHandle {
+        self.monitor.get_ref().handle()
+    }
+
+    pub fn resource(&self) -> Resource {
+        self.monitor.get_ref().resource()
+    }
+
+    /// The cgroup the monitor pretends to monitor, or [None] for system-wide pressure
+    pub fn cgroup(&self) -> Option<&Path> {
+        self.monitor.get_ref().cgroup()
+    }
+
+    /// The stall information last set through [MockHandle::set_stats](crate::MockHandle::set_stats)
+    pub fn stats(&self) -> Result<PsiStats, Error> {
+        self.monitor.get_ref().stats()
+    }
+
+    /// Classify the stall information last set through [MockHandle::set_stats](crate::MockHandle::set_stats)
+    pub fn severity(&self) -> Result<Severity, Error> {
+        self.monitor.get_ref().severity()
+    }
+
+    /// Wait until an event is fired
+    pub async fn wait(&mut self) -> Result<Event, Error> {
+        loop {
+            if let Some(event) = self.monitor.get_mut().try_wait()? {
+                return Ok(event);
+            }
+            self.monitor.readable().await?.clear_ready();
+        }
+    }
+
+    /// Wait for an event fired while the stats last set through [MockHandle::set_stats](crate::MockHandle::set_stats) are at least `min` severe
+    pub async fn wait_for_severity(&mut self, min: Severity) -> Result<(Event, Severity), Error> {
+        loop {
+            let event = self.wait().await?;
+            let severity = self.severity()?;
+            if severity >= min {
+                return Ok((event, severity));
+            }
+        }
+    }
+
+    /// Turn the monitor into a [Stream] of the events fired at it
+    pub fn into_stream(self) -> MockPressureStream {
+        MockPressureStream::new(self)
+    }
+}
+
+#[cfg(feature = "test-util")]
+type MockFuture = Pin<Box<dyn Future<Output = (MockPressureMonitor, Result<Event, Error>)> + Send>>;
+
+/// Stream of events produced by [MockPressureMonitor::into_stream]
+#[cfg(feature = "test-util")]
+pub struct MockPressureStream {
+    wait: MockFuture,
+}
+
+#[cfg(feature = "test-util")]
+impl MockPressureStream {
+    fn new(mut monitor: MockPressureMonitor) -> Self {
+        Self {
+            wait: Box::pin(async move {
+                let result = monitor.wait().await;
+                (monitor, result)
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Stream for MockPressureStream {
+    type Item = Result<Event, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let (monitor, result) = ready!(self.wait.as_mut().poll(cx));
+        *self = MockPressureStream::new(monitor);
+        Poll::Ready(Some(result))
+    }
+}
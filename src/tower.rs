@@ -0,0 +1,262 @@
+//! Load shedding for Tower services such as axum and tonic servers, driven by pressure severity
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicU8, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{CancellationToken, Error, PressureMonitor, Resource, Severity, WaitOutcome};
+
+/// How often the severity is re-read between events, so that it also drops once pressure subsides
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Error type of [LoadShed] services, which is either an [Overloaded] error or an error of the inner service
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A request was rejected because pressure was too severe
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("service overloaded, {resource} pressure is {severity}")]
+pub struct Overloaded {
+    pub resource: Resource,
+    pub severity: Severity,
+}
+
+/// Severity shared between the monitor's worker thread and the services
+struct Shared {
+    resource: Resource,
+    severity: AtomicU8,
+    /// Tasks of services whose requests are queued, woken whenever the severity changes
+    wakers: Mutex<Vec<Waker>>,
+    cancellation: CancellationToken,
+}
+
+impl Shared {
+    fn severity(&self) -> Severity {
+        match self.severity.load(Ordering::Relaxed) {
+            level if level == Severity::Critical as u8 => Severity::Critical,
+            level if level == Severity::Medium as u8 => Severity::Medium,
+            _ => Severity::Low,
+        }
+    }
+
+    fn set_severity(&self, severity: Severity) {
+        if self.severity.swap(severity as u8, Ordering::Relaxed) != severity as u8 {
+            let wakers =
+                std::mem::take(&mut *self.wakers.lock().unwrap_or_else(|e| e.into_inner()));
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        self.cancellation.cancel();
+    }
+}
+
+/// Layer wrapping services in [LoadShed], sharing the severity reported by one monitor between all of them
+///
+/// By default requests are rejected with [Overloaded] while pressure is critical, and never queued.
+/// The monitor waits for events on a worker thread, which stops once the layer and all its services are dropped
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, Severity, tower::LoadShedLayer};
+///
+/// let layer = LoadShedLayer::new(PressureMonitor::new()?)?
+///     .queue_at(Some(Severity::Medium))
+///     .reject_at(Some(Severity::Critical));
+/// // Added to a router with `.layer(layer)`, with errors handled by downcasting them to `pressure::tower::Overloaded`
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct LoadShedLayer {
+    shared: Arc<Shared>,
+    queue_at: Option<Severity>,
+    reject_at: Option<Severity>,
+}
+
+impl LoadShedLayer {
+    pub fn new(mut monitor: PressureMonitor) -> Result<Self, Error> {
+        let shared = Arc::new(Shared {
+            resource: monitor.resource(),
+            severity: AtomicU8::new(monitor.severity()? as u8),
+            wakers: Mutex::default(),
+            cancellation: monitor.cancellation_token()?,
+        });
+        let weak = Arc::downgrade(&shared);
+        std::thread::Builder::new()
+            .name("pressure-load-shed".into())
+            .spawn(move || watch(monitor, weak))?;
+        Ok(Self {
+            shared,
+            queue_at: None,
+            reject_at: Some(Severity::Critical),
+        })
+    }
+
+    /// Hold requests back while pressure is at least this severe, by reporting the service as not ready until it subsides
+    pub fn queue_at(mut self, min: Option<Severity>) -> Self {
+        self.queue_at = min;
+        self
+    }
+
+    /// Reject requests with [Overloaded] while pressure is at least this severe, defaults to [Severity::Critical]
+    pub fn reject_at(mut self, min: Option<Severity>) -> Self {
+        self.reject_at = min;
+        self
+    }
+
+    /// Severity of the pressure currently reported by the monitor
+    pub fn severity(&self) -> Severity {
+        self.shared.severity()
+    }
+}
+
+impl<S> Layer<S> for LoadShedLayer {
+    type Service = LoadShed<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadShed {
+            inner,
+            shared: self.shared.clone(),
+            queue_at: self.queue_at,
+            reject_at: self.reject_at,
+            rejecting: None,
+        }
+    }
+}
+
+/// Service rejecting or holding back requests depending on pressure, see [LoadShedLayer]
+pub struct LoadShed<S> {
+    inner: S,
+    shared: Arc<Shared>,
+    queue_at: Option<Severity>,
+    reject_at: Option<Severity>,
+    /// Severity the next request is rejected at, in which case the inner service wasn't polled
+    rejecting: Option<Severity>,
+}
+
+impl<S: Clone> Clone for LoadShed<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            shared: self.shared.clone(),
+            queue_at: self.queue_at,
+            reject_at: self.reject_at,
+            rejecting: None,
+        }
+    }
+}
+
+impl<S, Request> Service<Request> for LoadShed<S>
+where
+    S: Service<Request>,
+    S::Error: Into<BoxError>,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let severity = self.shared.severity();
+        if self.reject_at.is_some_and(|min| severity >= min) {
+            self.rejecting = Some(severity);
+            return Poll::Ready(Ok(()));
+        }
+        self.rejecting = None;
+        if self.queue_at.is_some_and(|min| severity >= min) {
+            self.shared.register(cx.waker());
+            // The severity may have changed before the waker was registered
+            if self.shared.severity() != severity {
+                cx.waker().wake_by_ref();
+            }
+            return Poll::Pending;
+        }
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        match self.rejecting.take() {
+            Some(severity) => ResponseFuture {
+                state: State::Overloaded {
+                    error: Some(Overloaded {
+                        resource: self.shared.resource,
+                        severity,
+                    }),
+                },
+            },
+            None => ResponseFuture {
+                state: State::Inner {
+                    future: self.inner.call(request),
+                },
+            },
+        }
+    }
+}
+
+pin_project! {
+    /// Response of a [LoadShed] service
+    pub struct ResponseFuture<F> {
+        #[pin]
+        state: State<F>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<F> {
+        Inner { #[pin] future: F },
+        Overloaded { error: Option<Overloaded> },
+    }
+}
+
+impl<F, T, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<BoxError>,
+{
+    type Output = Result<T, BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            StateProj::Inner { future } => future.poll(cx).map_err(Into::into),
+            StateProj::Overloaded { error } => {
+                Poll::Ready(Err(error.take().expect("polled after completion").into()))
+            }
+        }
+    }
+}
+
+/// Keep the shared severity up to date until the layer and its services are dropped. Errors reading the severity keep the previous one
+fn watch(mut monitor: PressureMonitor, shared: Weak<Shared>) {
+    loop {
+        // The severity stays as it was once no more events can be received
+        if let Ok(WaitOutcome::Cancelled | WaitOutcome::Hangup | WaitOutcome::Error(_)) =
+            monitor.wait_timeout(REFRESH_INTERVAL)
+        {
+            return;
+        }
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        if let Ok(severity) = monitor.severity() {
+            shared.set_severity(severity);
+        }
+    }
+}
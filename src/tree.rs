@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    sys::{
+        epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout},
+        inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor},
+    },
+};
+
+use crate::{
+    Error, Event, PollInterest, PressureMonitor, PressureMonitorBuilder, cgroup, poll_timeout,
+};
+
+/// Identifies the inotify fd among the monitors' fds registered with epoll
+const CHANGES: u64 = u64::MAX;
+
+/// How many fds a single wait reports readiness for, the rest are reported by the next wait
+const MAX_EVENTS: usize = 64;
+
+/// Monitors pressure of every cgroup below a root cgroup, reporting which of them is under pressure through [Event::cgroup]
+///
+/// Each cgroup is monitored by a [PressureMonitor] built from a template builder with the cgroup set through [PressureMonitorBuilder::cgroup].
+/// Cgroups that don't report pressure for the template's resource are skipped
+///
+/// ```no_run
+/// use pressure::{CgroupTreeMonitor, PressureMonitor, Resource};
+/// let template = PressureMonitor::builder().resource(Resource::Memory);
+/// let mut tree = CgroupTreeMonitor::watching("/sys/fs/cgroup/system.slice", template).unwrap();
+/// loop {
+///     for event in tree.wait().unwrap() {
+///         println!("memory pressure in {:?}", event.cgroup);
+///     }
+/// }
+/// ```
+pub struct CgroupTreeMonitor {
+    root: PathBuf,
+    template: PressureMonitorBuilder,
+    epoll: Epoll,
+    /// Monitors and their cgroup by the token identifying their fds in epoll
+    monitors: HashMap<u32, (PathBuf, PressureMonitor)>,
+    tokens: HashMap<PathBuf, u32>,
+    next_token: u32,
+    /// Watches for cgroups being created and removed, with the cgroup each watch is on
+    changes: Option<(Inotify, HashMap<WatchDescriptor, PathBuf>)>,
+    events: Vec<EpollEvent>,
+}
+
+impl CgroupTreeMonitor {
+    /// Monitor every cgroup below `root` that currently exists, where a relative `root` is resolved within the unified hierarchy
+    pub fn new(root: impl AsRef<Path>, template: PressureMonitorBuilder) -> Result<Self, Error> {
+        Self::open(root.as_ref(), template, false)
+    }
+
+    /// Monitor every cgroup below `root` like [new](Self::new), along with cgroups created below it later.
+    /// Removed cgroups stop being monitored
+    pub fn watching(
+        root: impl AsRef<Path>,
+        template: PressureMonitorBuilder,
+    ) -> Result<Self, Error> {
+        Self::open(root.as_ref(), template, true)
+    }
+
+    fn open(root: &Path, template: PressureMonitorBuilder, watch: bool) -> Result<Self, Error> {
+        let root = cgroup::resolve(root, None)?;
+        if !root.is_dir() {
+            return Err(Error::CgroupNotFound(root));
+        }
+        let epoll = Epoll::new(EpollCreateFlags::EPOLL_CLOEXEC)?;
+        let changes = if watch {
+            let inotify = Inotify::init(InitFlags::IN_CLOEXEC | InitFlags::IN_NONBLOCK)?;
+            epoll.add(&inotify, EpollEvent::new(EpollFlags::EPOLLIN, CHANGES))?;
+            Some((inotify, HashMap::new()))
+        } else {
+            None
+        };
+        let mut tree = Self {
+            root: root.clone(),
+            template,
+            epoll,
+            monitors: HashMap::new(),
+            tokens: HashMap::new(),
+            next_token: 0,
+            changes,
+            events: vec![EpollEvent::empty(); MAX_EVENTS],
+        };
+        tree.watch(&root)?;
+        tree.add_children(&root)?;
+        Ok(tree)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Number of cgroups being monitored
+    pub fn len(&self) -> usize {
+        self.monitors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.monitors.is_empty()
+    }
+
+    /// Iterate over the cgroups being monitored, in no particular order
+    pub fn cgroups(&self) -> impl Iterator<Item = &Path> {
+        self.tokens.keys().map(PathBuf::as_path)
+    }
+
+    /// Wait until at least one cgroup is under pressure, returning an event for every trigger that fired.
+    /// Fails with [Error::Hangup] or [Error::PollError] if the fd of a monitor whose cgroup still exists died
+    pub fn wait(&mut self) -> Result<Vec<Event>, Error> {
+        self.wait_until(None)
+    }
+
+    /// Wait until at least one cgroup is under pressure, returning an empty list once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<Vec<Event>, Error> {
+        // Timeouts too long to represent wait forever
+        self.wait_until(Instant::now().checked_add(timeout))
+    }
+
+    fn wait_until(&mut self, deadline: Option<Instant>) -> Result<Vec<Event>, Error> {
+        loop {
+            let timeout = deadline.map_or(EpollTimeout::NONE, |deadline| {
+                poll_timeout(deadline.saturating_duration_since(Instant::now()))
+            });
+            let ready = match self.epoll.wait(&mut self.events, timeout) {
+                // Resume waiting after a signal was delivered to the thread
+                Err(Errno::EINTR) => continue,
+                ready => ready?,
+            };
+            let mut received = Vec::with_capacity(ready);
+            for i in 0..ready {
+                let event = self.events[i];
+                if event.data() == CHANGES {
+                    self.apply_changes()?;
+                } else if let Some(event) = self.received(event)? {
+                    received.push(event);
+                }
+            }
+            // Only cgroups being created or removed doesn't end the wait
+            if !received.is_empty() || deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                return Ok(received);
+            }
+        }
+    }
+
+    /// Handle readiness reported for a monitor's fd, returning the event it received
+    fn received(&mut self, event: EpollEvent) -> Result<Option<Event>, Error> {
+        // Monitors of cgroups removed earlier in the same wait are gone
+        let Some((cgroup, monitor)) = self.monitors.get_mut(&((event.data() >> 32) as u32)) else {
+            return Ok(None);
+        };
+        if event
+            .events()
+            .intersects(EpollFlags::EPOLLHUP | EpollFlags::EPOLLERR)
+        {
+            // Pressure files of a removed cgroup report an error, possibly before inotify reports the removal
+            if !cgroup.exists() {
+                let cgroup = cgroup.clone();
+                self.remove(&cgroup);
+                return Ok(None);
+            }
+            if event.events().contains(EpollFlags::EPOLLHUP) {
+                return Err(Error::Hangup);
+            }
+            return Err(Error::PollError(event.events().bits() as i16));
+        }
+        let additional = (event.data() as u32)
+            .checked_sub(1)
+            .and_then(|i| monitor.additional.get(i as usize));
+        Ok(Some(match additional {
+            Some((trigger, _)) => monitor.target.event_for(*trigger),
+            None => Event {
+                coalesced: monitor.acknowledge()?,
+                ..monitor.target.event()
+            },
+        }))
+    }
+
+    /// Start or stop monitoring the cgroups inotify reported being created or removed
+    fn apply_changes(&mut self) -> Result<(), Error> {
+        loop {
+            let Some((inotify, _)) = &self.changes else {
+                return Ok(());
+            };
+            let changes = match inotify.read_events() {
+                Ok(changes) if !changes.is_empty() => changes,
+                Ok(_) | Err(Errno::EAGAIN) => return Ok(()),
+                Err(e) => return Err(e)?,
+            };
+            for change in changes {
+                let Some((_, watches)) = &mut self.changes else {
+                    return Ok(());
+                };
+                if change.mask.contains(AddWatchFlags::IN_Q_OVERFLOW) {
+                    self.rescan()?;
+                    continue;
+                }
+                if change.mask.contains(AddWatchFlags::IN_IGNORED) {
+                    watches.remove(&change.wd);
+                    continue;
+                }
+                let (Some(parent), Some(name)) = (watches.get(&change.wd), change.name) else {
+                    continue;
+                };
+                if !change.mask.contains(AddWatchFlags::IN_ISDIR) {
+                    continue;
+                }
+                let cgroup = parent.join(name);
+                if change.mask.contains(AddWatchFlags::IN_CREATE) {
+                    self.add(cgroup)?;
+                } else if change.mask.contains(AddWatchFlags::IN_DELETE) {
+                    self.remove(&cgroup);
+                }
+            }
+        }
+    }
+
+    /// Catch up with changes after inotify dropped events, by walking the tree again
+    fn rescan(&mut self) -> Result<(), Error> {
+        let removed: Vec<_> = self
+            .tokens
+            .keys()
+            .filter(|cgroup| !cgroup.exists())
+            .cloned()
+            .collect();
+        for cgroup in removed {
+            self.remove(&cgroup);
+        }
+        let root = self.root.clone();
+        self.add_children(&root)
+    }
+
+    fn add_children(&mut self, parent: &Path) -> Result<(), Error> {
+        let children = match std::fs::read_dir(parent) {
+            Ok(children) => children,
+            // The cgroup was removed while walking the tree
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        for child in children {
+            let child = child?;
+            if child.file_type()?.is_dir() {
+                self.add(child.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Monitor a cgroup and the cgroups below it
+    fn add(&mut self, cgroup: PathBuf) -> Result<(), Error> {
+        // Watch before listing children, so none are missed
+        self.watch(&cgroup)?;
+        if !self.tokens.contains_key(&cgroup) {
+            self.insert(cgroup.clone())?;
+        }
+        self.add_children(&cgroup)
+    }
+
+    fn insert(&mut self, cgroup: PathBuf) -> Result<(), Error> {
+        let monitor = match self.template.clone().cgroup(&cgroup).build() {
+            Ok(monitor) => monitor,
+            Err(Error::ResourceUnavailable(_) | Error::CgroupNotFound(_)) => return Ok(()),
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let flags = match monitor.poll_interest() {
+            Some(PollInterest::Priority) => EpollFlags::EPOLLPRI,
+            Some(PollInterest::Readable) => EpollFlags::EPOLLIN,
+            None => return Err(Error::NoFileDescriptor),
+        };
+        let token = self.next_token;
+        self.next_token = self.next_token.wrapping_add(1);
+        // Each fd is identified by the monitor's token in the upper half, and which of its triggers it belongs to in the lower half
+        let data = u64::from(token) << 32;
+        self.epoll.add(&monitor, EpollEvent::new(flags, data))?;
+        for (i, (_, fd)) in monitor.additional.iter().enumerate() {
+            self.epoll.add(
+                fd,
+                EpollEvent::new(EpollFlags::EPOLLPRI, data | (i as u64 + 1)),
+            )?;
+        }
+        self.tokens.insert(cgroup.clone(), token);
+        self.monitors.insert(token, (cgroup, monitor));
+        Ok(())
+    }
+
+    /// Stop monitoring a cgroup and the cgroups below it
+    fn remove(&mut self, cgroup: &Path) {
+        self.tokens.retain(|monitored, token| {
+            let removed = monitored.starts_with(cgroup);
+            // Closing the monitor's fds removes them from epoll
+            if removed {
+                self.monitors.remove(token);
+            }
+            !removed
+        });
+    }
+
+    fn watch(&mut self, cgroup: &Path) -> Result<(), Error> {
+        let Some((inotify, watches)) = &mut self.changes else {
+            return Ok(());
+        };
+        let flags = AddWatchFlags::IN_CREATE | AddWatchFlags::IN_DELETE | AddWatchFlags::IN_ONLYDIR;
+        match inotify.add_watch(cgroup, flags) {
+            Ok(watch) => {
+                watches.insert(watch, cgroup.to_path_buf());
+                Ok(())
+            }
+            // The cgroup was removed before it could be watched
+            Err(Errno::ENOENT) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::{str::FromStr, time::Duration};
+
+use crate::{Error, Resource};
+
+/// Smallest tracking window accepted by the kernel
+pub(crate) const WINDOW_MIN: Duration = Duration::from_millis(500);
+/// Largest tracking window accepted by the kernel
+pub(crate) const WINDOW_MAX: Duration = Duration::from_secs(10);
+
+/// The kind of stall a trigger tracks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum StallKind {
+    /// At least one task is stalled on the resource
+    Some,
+    /// All non-idle tasks are stalled on the resource simultaneously
+    Full,
+}
+
+impl StallKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            StallKind::Some => "some",
+            StallKind::Full => "full",
+        }
+    }
+}
+
+impl std::fmt::Display for StallKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A PSI trigger, notifying when tasks are stalled for at least `threshold` within any `window`
+///
+/// ```
+/// use std::time::Duration;
+/// use pressure::{StallKind, Trigger};
+/// let trigger: Trigger = "some 150000 1000000".parse().unwrap();
+/// assert_eq!(trigger.kind(), StallKind::Some);
+/// assert_eq!(trigger.threshold(), Duration::from_millis(150));
+/// assert_eq!(trigger.to_string(), "some 150000 1000000");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Trigger {
+    kind: StallKind,
+    threshold: Duration,
+    window: Duration,
+}
+
+impl Trigger {
+    /// Create a trigger, validating that the window is within the range accepted by the kernel and the threshold fits within it.
+    /// Both are written to the kernel in microseconds, so they must be whole microseconds
+    pub fn new(kind: StallKind, threshold: Duration, window: Duration) -> Result<Self, Error> {
+        if !(WINDOW_MIN..=WINDOW_MAX).contains(&window) || !whole_micros(window) {
+            return Err(Error::InvalidWindow(window));
+        }
+        if threshold.as_micros() == 0 || !whole_micros(threshold) || threshold > window {
+            return Err(Error::InvalidThreshold(threshold));
+        }
+        Ok(Self {
+            kind,
+            threshold,
+            window,
+        })
+    }
+
+    pub fn kind(self) -> StallKind {
+        self.kind
+    }
+
+    pub fn threshold(self) -> Duration {
+        self.threshold
+    }
+
+    pub fn window(self) -> Duration {
+        self.window
+    }
+
+    /// Serialize the trigger into the format expected by the kernel, including the trailing NUL
+    #[cfg(unix)]
+    pub(crate) fn to_bytes(self) -> Vec<u8> {
+        format!("{self}\0").into_bytes()
+    }
+
+    /// Encode the trigger as the base64 value of MEMORY_PRESSURE_WRITE, for supervisors passing it to a child like systemd does
+    ///
+    /// ```
+    /// let trigger: pressure::Trigger = "some 150000 1000000".parse().unwrap();
+    /// assert_eq!(trigger.to_memory_pressure_write(), "c29tZSAxNTAwMDAgMTAwMDAwMAA=");
+    /// ```
+    #[cfg(unix)]
+    pub fn to_memory_pressure_write(self) -> String {
+        crate::encoding::encode_base64(&self.to_bytes())
+    }
+
+    /// Parse a trigger in the format expected by the kernel, such as the one provided by systemd through MEMORY_PRESSURE_WRITE
+    #[cfg(unix)]
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        std::str::from_utf8(bytes)
+            .map_err(|_| Error::MalformedTrigger)?
+            .parse()
+    }
+
+    /// Check that the kernel tracks this trigger's kind of stall for a resource
+    #[cfg(unix)]
+    pub(crate) fn check_supported(self, resource: Resource) -> Result<(), Error> {
+        if resource == Resource::Irq && self.kind == StallKind::Some {
+            return Err(Error::UnsupportedStallKind(resource, self.kind));
+        }
+        Ok(())
+    }
+
+    /// Default trigger for a resource, used when none is configured
+    pub(crate) fn default_for(resource: Resource) -> Self {
+        Self {
+            kind: StallKind::default_for(resource),
+            threshold: Duration::from_millis(20),
+            window: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Whether a duration can be written in microseconds without truncating it
+fn whole_micros(duration: Duration) -> bool {
+    duration.subsec_nanos().is_multiple_of(1000)
+}
+
+/// Formats the trigger the way the kernel expects it to be written, such as `some 150000 1000000`
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.kind,
+            self.threshold.as_micros(),
+            self.window.as_micros()
+        )
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = Error;
+
+    /// Parse a trigger formatted as `<some|full> <threshold in us> <window in us>`, optionally followed by the NUL the kernel expects
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.trim_end_matches('\0').split_whitespace();
+        let kind = fields.next().ok_or(Error::MalformedTrigger)?.parse()?;
+        let mut micros = || -> Result<Duration, Error> {
+            let micros = fields.next().ok_or(Error::MalformedTrigger)?;
+            Ok(Duration::from_micros(
+                micros.parse().map_err(|_| Error::MalformedTrigger)?,
+            ))
+        };
+        let threshold = micros()?;
+        let window = micros()?;
+        if fields.next().is_some() {
+            return Err(Error::MalformedTrigger);
+        }
+        Self::new(kind, threshold, window)
+    }
+}
+
+/// Triggers are serialized in the format expected by the kernel, such as `"some 150000 1000000"`, so that deserialized triggers are validated
+#[cfg(feature = "serde")]
+impl serde::Serialize for Trigger {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Trigger {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for StallKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "some" => Ok(StallKind::Some),
+            "full" => Ok(StallKind::Full),
+            _ => Err(Error::MalformedTrigger),
+        }
+    }
+}
+
+impl StallKind {
+    pub(crate) fn default_for(resource: Resource) -> Self {
+        match resource {
+            // The kernel only tracks full stalls for irq, and rejects "some" triggers
+            Resource::Irq => StallKind::Full,
+            _ => StallKind::Some,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_sub_microsecond_durations() {
+        let window = Duration::from_secs(1);
+        for threshold in [Duration::from_nanos(1), Duration::from_nanos(999)] {
+            assert!(matches!(
+                Trigger::new(StallKind::Some, threshold, window),
+                Err(Error::InvalidThreshold(_))
+            ));
+        }
+        assert!(matches!(
+            Trigger::new(StallKind::Some, Duration::from_nanos(150_000_500), window),
+            Err(Error::InvalidThreshold(_))
+        ));
+        assert!(matches!(
+            Trigger::new(
+                StallKind::Some,
+                Duration::from_millis(150),
+                Duration::from_nanos(1_000_000_001)
+            ),
+            Err(Error::InvalidWindow(_))
+        ));
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        let trigger = Trigger::new(
+            StallKind::Full,
+            Duration::from_micros(1),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(trigger.to_string().parse::<Trigger>().unwrap(), trigger);
+    }
+}
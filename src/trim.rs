@@ -0,0 +1,77 @@
+use std::{ffi::c_int, time::Duration};
+
+use crate::{Error, HookRegistry, HookRunner, PressureMonitor, Severity};
+
+unsafe extern "C" {
+    fn malloc_trim(pad: usize) -> c_int;
+}
+
+/// Return free memory at the top of the heap and in unused pages of glibc's arenas to the operating system, returning whether any was released
+pub fn malloc_trim_now() -> bool {
+    // SAFETY: malloc_trim has no preconditions and is thread-safe
+    unsafe { malloc_trim(0) == 1 }
+}
+
+/// Calls glibc's `malloc_trim(0)` on a worker thread whenever memory is under pressure, at most once per cooldown
+///
+/// ```no_run
+/// use pressure::{AutoTrim, PressureMonitor, Severity};
+/// use std::time::Duration;
+///
+/// // Trim on every memory pressure event, at most once every 10 seconds
+/// let runner = AutoTrim::spawn()?;
+/// // Or only once pressure is medium, at most once a minute
+/// let runner = AutoTrim::new()
+///     .min_severity(Severity::Medium)
+///     .cooldown(Duration::from_secs(60))
+///     .spawn_on(PressureMonitor::new()?)?;
+/// # Ok::<(), pressure::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AutoTrim {
+    cooldown: Duration,
+    min_severity: Severity,
+}
+
+impl AutoTrim {
+    pub fn new() -> Self {
+        Self {
+            cooldown: Duration::from_secs(10),
+            min_severity: Severity::Low,
+        }
+    }
+
+    /// Trim on system-wide memory pressure with the default configuration
+    pub fn spawn() -> Result<HookRunner, Error> {
+        Self::new().spawn_on(PressureMonitor::new()?)
+    }
+
+    /// Minimum time between two trims, defaults to 10 seconds. Trimming walks every arena, so it shouldn't run on every event of a sustained episode
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Only trim for events at which pressure is at least this severe, defaults to [Severity::Low]
+    pub fn min_severity(mut self, min: Severity) -> Self {
+        self.min_severity = min;
+        self
+    }
+
+    /// Trim on the events of `monitor`
+    pub fn spawn_on(self, monitor: PressureMonitor) -> Result<HookRunner, Error> {
+        let mut hooks = HookRegistry::new();
+        hooks
+            .on_event(self.min_severity, |_, _| {
+                malloc_trim_now();
+            })
+            .cooldown(self.cooldown);
+        hooks.spawn(monitor)
+    }
+}
+
+impl Default for AutoTrim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    io::ErrorKind,
+    os::fd::AsRawFd,
+    time::{Duration, Instant},
+};
+
+use io_uring::{IoUring, opcode, squeue, types};
+use nix::{errno::Errno, poll::PollFlags};
+
+use crate::{Error, Event, PollInterest, PressureMonitor, WaitOutcome, fd_died};
+
+/// Number of submission queue entries. Polls that don't fit are submitted in batches
+const RING_ENTRIES: u32 = 128;
+
+/// Waits on many monitors at once like [MonitorSet](crate::MonitorSet), with a poll for each of their fds submitted to a shared io_uring.
+/// Polls that completed are submitted again together with the next wait, so each wait is a single syscall however many monitors fired
+///
+/// Requires Linux 5.11 or newer
+///
+/// ```no_run
+/// use pressure::{PressureMonitor, Resource, UringMonitorSet};
+/// let mut set = UringMonitorSet::new().unwrap();
+/// for cgroup in ["/sys/fs/cgroup/system.slice", "/sys/fs/cgroup/user.slice"] {
+///     set.insert(PressureMonitor::for_cgroup(cgroup, Resource::Memory).unwrap()).unwrap();
+/// }
+/// loop {
+///     for event in set.wait().unwrap() {
+///         println!("{} pressure in {:?}", event.resource, event.cgroup);
+///     }
+/// }
+/// ```
+pub struct UringMonitorSet {
+    ring: IoUring,
+    monitors: Vec<PressureMonitor>,
+    /// Whether each monitor's fd died, after which its polls aren't submitted again
+    dead: Vec<bool>,
+    /// Polls to submit with the next wait, identified like their completions
+    unsubmitted: Vec<u64>,
+}
+
+impl UringMonitorSet {
+    pub fn new() -> Result<Self, Error> {
+        let ring = IoUring::new(RING_ENTRIES)?;
+        // Waiting with a timeout passes it to io_uring_enter, which needs IORING_FEAT_EXT_ARG
+        if !ring.params().is_feature_ext_arg() {
+            return Err(Errno::EOPNOTSUPP.into());
+        }
+        Ok(Self {
+            ring,
+            monitors: Vec::new(),
+            dead: Vec::new(),
+            unsubmitted: Vec::new(),
+        })
+    }
+
+    /// Add a monitor to the set. Monitors that fell back to polling, or whose backend doesn't signal events on its fd, can't be added
+    pub fn insert(&mut self, monitor: PressureMonitor) -> Result<(), Error> {
+        if monitor.poll_interest().is_none() {
+            return Err(Error::NoFileDescriptor);
+        }
+        // Each poll is identified by the monitor's index in the upper half, and which of its triggers it belongs to in the lower half
+        let index = (self.monitors.len() as u64) << 32;
+        self.unsubmitted
+            .extend((0..=monitor.additional.len() as u64).map(|i| index | i));
+        self.monitors.push(monitor);
+        self.dead.push(false);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.monitors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.monitors.is_empty()
+    }
+
+    /// Iterate over the monitors in the set, in the order they were inserted
+    pub fn iter(&self) -> impl Iterator<Item = &PressureMonitor> {
+        self.monitors.iter()
+    }
+
+    /// Wait until at least one monitor receives a pressure event, returning an event for every trigger that fired.
+    /// Fails with [Error::Hangup] or [Error::PollError] if a monitor's fd died, use [wait_into](Self::wait_into) to find out which
+    pub fn wait(&mut self) -> Result<Vec<Event>, Error> {
+        self.wait_events(None)
+    }
+
+    /// Wait until at least one monitor receives a pressure event, returning an empty list once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<Vec<Event>, Error> {
+        // Timeouts too long to represent wait forever
+        self.wait_events(Instant::now().checked_add(timeout))
+    }
+
+    /// Wait until at least one monitor receives a pressure event or `timeout` elapses, appending what happened to each monitor that was ready to `outcomes`
+    /// along with the monitor's position in [iter](Self::iter), and returning how many were appended.
+    ///
+    /// Each entry is [WaitOutcome::Triggered] for a trigger that fired, or [WaitOutcome::Hangup] or [WaitOutcome::Error] for a monitor whose fd died,
+    /// such as that of a removed cgroup. Dead monitors stop being waited on
+    pub fn wait_into(
+        &mut self,
+        outcomes: &mut Vec<(usize, WaitOutcome)>,
+        timeout: Option<Duration>,
+    ) -> Result<usize, Error> {
+        // Timeouts too long to represent wait forever
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        self.harvest(outcomes, deadline)
+    }
+
+    fn wait_events(&mut self, deadline: Option<Instant>) -> Result<Vec<Event>, Error> {
+        let mut outcomes = Vec::new();
+        self.harvest(&mut outcomes, deadline)?;
+        outcomes
+            .into_iter()
+            .map(|(_, outcome)| match outcome {
+                WaitOutcome::Triggered(event) => Ok(event),
+                WaitOutcome::Error(revents) => Err(Error::PollError(revents)),
+                _ => Err(Error::Hangup),
+            })
+            .collect()
+    }
+
+    fn harvest(
+        &mut self,
+        outcomes: &mut Vec<(usize, WaitOutcome)>,
+        deadline: Option<Instant>,
+    ) -> Result<usize, Error> {
+        loop {
+            self.push_polls()?;
+            let submitted = match deadline {
+                Some(deadline) => {
+                    let timeout =
+                        types::Timespec::from(deadline.saturating_duration_since(Instant::now()));
+                    let args = types::SubmitArgs::new().timespec(&timeout);
+                    self.ring.submitter().submit_with_args(1, &args)
+                }
+                None => self.ring.submit_and_wait(1),
+            };
+            match submitted {
+                Ok(_) => {}
+                // The timeout elapsed, or a signal was delivered to the thread, after which waiting resumes
+                Err(e) if e.raw_os_error() == Some(Errno::ETIME as i32) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+            // The kernel reports a successful submission when the wait after it ends early, so check for completions either way
+            if !self.ring.completion().is_empty()
+                || deadline.is_some_and(|deadline| deadline <= Instant::now())
+            {
+                break;
+            }
+        }
+        let completions: Vec<_> = self
+            .ring
+            .completion()
+            .map(|completion| (completion.user_data(), completion.result()))
+            .collect();
+        let before = outcomes.len();
+        // Every completion is handled before reporting an error, as they were already taken off the queue
+        let mut error = None;
+        for (user_data, result) in completions {
+            let index = (user_data >> 32) as usize;
+            if self.dead.get(index).is_none_or(|&dead| dead) {
+                continue;
+            }
+            // A failed poll leaves the fd unusable like an error condition on it
+            let revents = match result {
+                ..0 => PollFlags::POLLERR,
+                _ => PollFlags::from_bits_retain(result as i16),
+            };
+            if let Some(died) = fd_died(Some(revents)) {
+                self.dead[index] = true;
+                outcomes.push((index, died));
+                continue;
+            }
+            // Polls are one-shot, so every completed poll is submitted again with the next wait
+            self.unsubmitted.push(user_data);
+            let monitor = &mut self.monitors[index];
+            let additional = (user_data as u32)
+                .checked_sub(1)
+                .and_then(|i| monitor.additional.get(i as usize));
+            let received = match additional {
+                Some((trigger, _)) => monitor.target.event_for(*trigger),
+                None => match monitor.acknowledge() {
+                    Ok(coalesced) => Event {
+                        coalesced,
+                        ..monitor.target.event()
+                    },
+                    Err(e) => {
+                        error.get_or_insert(e);
+                        continue;
+                    }
+                },
+            };
+            outcomes.push((index, WaitOutcome::Triggered(received)));
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(outcomes.len() - before),
+        }
+    }
+
+    /// Queue the polls that aren't pending, submitting early if they don't fit in the submission queue
+    fn push_polls(&mut self) -> Result<(), Error> {
+        let unsubmitted = std::mem::take(&mut self.unsubmitted);
+        for (i, &user_data) in unsubmitted.iter().enumerate() {
+            let Some(entry) = self.poll(user_data) else {
+                continue;
+            };
+            // SAFETY: polls don't pass buffers to the kernel, and the kernel holds its own reference to the polled file
+            while unsafe { self.ring.submission().push(&entry) }.is_err() {
+                if let Err(e) = self.ring.submit() {
+                    // Polls that weren't queued yet are pushed again with the next wait
+                    self.unsubmitted.extend_from_slice(&unsubmitted[i..]);
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A poll for the fd identified by `user_data`
+    fn poll(&self, user_data: u64) -> Option<squeue::Entry> {
+        let index = (user_data >> 32) as usize;
+        if self.dead[index] {
+            return None;
+        }
+        let monitor = &self.monitors[index];
+        let (fd, interest) = match (user_data as u32).checked_sub(1) {
+            Some(i) => (
+                monitor.additional.get(i as usize)?.1.as_raw_fd(),
+                PollFlags::POLLPRI,
+            ),
+            None => {
+                let interest = match monitor.poll_interest()? {
+                    PollInterest::Priority => PollFlags::POLLPRI,
+                    PollInterest::Readable => PollFlags::POLLIN,
+                };
+                (monitor.as_raw_fd(), interest)
+            }
+        };
+        Some(
+            opcode::PollAdd::new(types::Fd(fd), interest.bits() as u32)
+                .build()
+                .user_data(user_data),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, os::unix::net::UnixStream};
+
+    use super::*;
+
+    #[test]
+    fn hung_up_monitors_are_reported_without_losing_other_events() {
+        let mut set = UringMonitorSet::new().unwrap();
+        let mut peers = Vec::new();
+        for _ in 0..3 {
+            let (monitor, peer) = UnixStream::pair().unwrap();
+            set.insert(PressureMonitor::from_fd(monitor.into()).unwrap())
+                .unwrap();
+            peers.push(peer);
+        }
+        // The hangup completes before the notification, which must still be received
+        drop(peers.remove(0));
+        peers[1].write_all(b"1").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let mut outcomes = Vec::new();
+        set.wait_into(&mut outcomes, Some(Duration::from_secs(5)))
+            .unwrap();
+        outcomes.sort_by_key(|(index, _)| *index);
+        assert!(matches!(
+            outcomes[..],
+            [(0, WaitOutcome::Hangup), (2, WaitOutcome::Triggered(_))]
+        ));
+
+        // The dead monitor isn't waited on again, while the others still are
+        peers[0].write_all(b"1").unwrap();
+        outcomes.clear();
+        set.wait_into(&mut outcomes, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(matches!(outcomes[..], [(1, WaitOutcome::Triggered(_))]));
+        outcomes.clear();
+        set.wait_into(&mut outcomes, Some(Duration::from_millis(50)))
+            .unwrap();
+        assert!(outcomes.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Memory pressure on Windows, which has no pressure stall information but signals low memory through memory resource notification objects
+
+use std::{
+    os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, FromRawHandle, OwnedHandle},
+    time::{Duration, Instant},
+};
+
+use windows_sys::Win32::{
+    Foundation::{HANDLE, WAIT_FAILED, WAIT_OBJECT_0, WAIT_TIMEOUT},
+    System::{
+        Memory::{
+            CreateMemoryResourceNotification, HighMemoryResourceNotification,
+            LowMemoryResourceNotification, MEMORY_RESOURCE_NOTIFICATION_TYPE,
+            QueryMemoryResourceNotification,
+        },
+        Threading::{INFINITE, WaitForMultipleObjects},
+    },
+};
+
+use crate::{
+    CancellationToken, ClockTimestamp, Error, Event, EventRate, PsiStats, Resource, Severity,
+    StallKind, Transition, Trigger, WaitOutcome, rate::EventCounter,
+};
+
+/// Represents a pressure monitor that can be used to wait for memory pressure events
+///
+/// Events are signalled by the system's low memory resource notification, which stays signalled for as long as available memory is low.
+/// Like a PSI trigger, persisting pressure is reported at most once per window of the default memory trigger
+pub struct PressureMonitor {
+    low: OwnedHandle,
+    high: OwnedHandle,
+    window: Duration,
+    last_event: Option<Instant>,
+    pub(crate) cancellation: Option<CancellationToken>,
+    events: EventCounter,
+}
+
+impl PressureMonitor {
+    /// Create a monitor for system-wide memory pressure
+    pub fn new() -> Result<Self, Error> {
+        Self::with_resource(Resource::Memory)
+    }
+    /// Create a monitor for the given resource. Windows only reports pressure for [Resource::Memory]
+    pub fn with_resource(resource: Resource) -> Result<Self, Error> {
+        if resource != Resource::Memory {
+            return Err(Error::ResourceUnavailable(resource));
+        }
+        Ok(Self {
+            low: notification(LowMemoryResourceNotification)?,
+            high: notification(HighMemoryResourceNotification)?,
+            window: Trigger::default_for(resource).window(),
+            last_event: None,
+            cancellation: None,
+            events: EventCounter::default(),
+        })
+    }
+    pub fn resource(&self) -> Resource {
+        Resource::Memory
+    }
+    /// Windows doesn't expose stall times, use [severity](Self::severity) instead
+    pub fn stats(&self) -> Result<PsiStats, Error> {
+        Err(Error::ResourceUnavailable(Resource::Memory))
+    }
+    /// Classify current memory pressure, which is [Severity::Critical] while available memory is low, [Severity::Low] while it is high and [Severity::Medium] in between
+    pub fn severity(&self) -> Result<Severity, Error> {
+        if is_signalled(&self.low)? {
+            Ok(Severity::Critical)
+        } else if is_signalled(&self.high)? {
+            Ok(Severity::Low)
+        } else {
+            Ok(Severity::Medium)
+        }
+    }
+    /// How often the monitor delivered events
+    pub fn event_rate(&self) -> EventRate {
+        self.events.rate()
+    }
+    /// Get a token that can be used to cancel waits on this monitor from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Wait for a single pressure event to occur, returning either [WaitOutcome::Triggered] with the [Event] or [WaitOutcome::Cancelled].
+    /// It is safe to call this function in a busy loop, as even if memory pressure persists events are limited to one per window
+    pub fn wait(&mut self) -> Result<WaitOutcome, Error> {
+        self.wait_until(None)
+    }
+    /// Wait for a single pressure event to occur, giving up once `timeout` has elapsed
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<WaitOutcome, Error> {
+        self.wait_until(Some(timeout))
+    }
+    /// Wait for a pressure event at which pressure is at least `min` severe, returning [None] if the wait was cancelled
+    pub fn wait_for_severity(&mut self, min: Severity) -> Result<Option<(Event, Severity)>, Error> {
+        loop {
+            let event = match self.wait()? {
+                WaitOutcome::Triggered(event) => event,
+                WaitOutcome::TimedOut | WaitOutcome::Cancelled | WaitOutcome::Interrupted => {
+                    return Ok(None);
+                }
+                WaitOutcome::Hangup => return Err(Error::Hangup),
+                WaitOutcome::Error(revents) => return Err(Error::PollError(revents)),
+            };
+            let severity = self.severity()?;
+            if severity >= min {
+                return Ok(Some((event, severity)));
+            }
+        }
+    }
+    /// Check whether a pressure event is pending without blocking, consuming and returning it if so
+    pub fn try_wait(&mut self) -> Result<Option<Event>, Error> {
+        match self.wait_until(Some(Duration::ZERO))? {
+            WaitOutcome::Triggered(event) => Ok(Some(event)),
+            WaitOutcome::TimedOut | WaitOutcome::Cancelled | WaitOutcome::Interrupted => Ok(None),
+            WaitOutcome::Hangup => Err(Error::Hangup),
+            WaitOutcome::Error(revents) => Err(Error::PollError(revents)),
+        }
+    }
+    fn wait_until(&mut self, timeout: Option<Duration>) -> Result<WaitOutcome, Error> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let cancellation = self
+            .cancellation
+            .as_ref()
+            .map(|token| token.as_handle().as_raw_handle());
+        // The notification stays signalled while memory is low, so wait out the rest of the window after an event
+        let rearm = self.last_event.map(|last_event| last_event + self.window);
+        if let Some(rearm) = rearm.filter(|rearm| *rearm > Instant::now()) {
+            let until = deadline.map_or(rearm, |deadline| deadline.min(rearm));
+            match wait_any(cancellation.as_slice(), Some(until))? {
+                Some(_) => return Ok(WaitOutcome::Cancelled),
+                None if until < rearm => return Ok(WaitOutcome::TimedOut),
+                None => {}
+            }
+        }
+        let handles: Vec<HANDLE> = cancellation
+            .into_iter()
+            .chain([self.low.as_raw_handle()])
+            .collect();
+        match wait_any(&handles, deadline)? {
+            None => Ok(WaitOutcome::TimedOut),
+            Some(0) if cancellation.is_some() => Ok(WaitOutcome::Cancelled),
+            Some(_) => {
+                self.last_event = Some(Instant::now());
+                self.events.record();
+                Ok(WaitOutcome::Triggered(Event {
+                    resource: Resource::Memory,
+                    kind: StallKind::default_for(Resource::Memory),
+                    trigger: None,
+                    cgroup: None,
+                    timestamp: Instant::now(),
+                    clock: ClockTimestamp::now(),
+                    transition: Transition::Pressure,
+                    coalesced: 1,
+                    suppressed: 0,
+                    stats: None,
+                    memory: None,
+                    compression: None,
+                }))
+            }
+        }
+    }
+}
+
+/// The low memory resource notification, which stays signalled for as long as available memory is low
+impl AsHandle for PressureMonitor {
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        self.low.as_handle()
+    }
+}
+
+fn notification(kind: MEMORY_RESOURCE_NOTIFICATION_TYPE) -> Result<OwnedHandle, Error> {
+    // SAFETY: the notification type is one of the constants defined by the API
+    let handle = unsafe { CreateMemoryResourceNotification(kind) };
+    if handle.is_null() {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    // SAFETY: the handle was just created and is owned by nothing else
+    Ok(unsafe { OwnedHandle::from_raw_handle(handle) })
+}
+
+fn is_signalled(notification: &OwnedHandle) -> Result<bool, Error> {
+    let mut state = 0;
+    // SAFETY: the handle is a memory resource notification for as long as the monitor exists
+    if unsafe { QueryMemoryResourceNotification(notification.as_raw_handle(), &mut state) } == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(state != 0)
+}
+
+/// Wait until one of `handles` is signalled, returning its index, or [None] once `deadline` has passed
+fn wait_any(handles: &[HANDLE], deadline: Option<Instant>) -> Result<Option<usize>, Error> {
+    let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+    if handles.is_empty() {
+        std::thread::sleep(timeout.unwrap_or(Duration::MAX));
+        return Ok(None);
+    }
+    // Round up, so that sub-millisecond timeouts don't turn into a non-blocking wait
+    let milliseconds = timeout.map_or(INFINITE, |timeout| {
+        u32::try_from(timeout.as_micros().div_ceil(1000)).unwrap_or(INFINITE - 1)
+    });
+    // SAFETY: the handles stay valid for the duration of the call
+    let result =
+        unsafe { WaitForMultipleObjects(handles.len() as u32, handles.as_ptr(), 0, milliseconds) };
+    match result {
+        WAIT_TIMEOUT => Ok(None),
+        WAIT_FAILED => Err(std::io::Error::last_os_error())?,
+        index => Ok(Some((index - WAIT_OBJECT_0) as usize)),
+    }
+}
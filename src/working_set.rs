@@ -0,0 +1,193 @@
+use std::{
+    fs::{File, OpenOptions},
+    os::fd::AsFd,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use nix::poll::{PollFd, PollFlags};
+
+use crate::{CancellationToken, Error, PsiStats, Resource, cgroup, poll_timeout};
+
+/// Estimates how much memory a cgroup actually needs, by slowly lowering its memory.high until memory pressure appears and backing off when it does, like Meta's senpai
+///
+/// Once the limit settles it approximates the working set: the memory the cgroup can't lose without stalling. Lowering memory.high makes the kernel reclaim
+/// down to it immediately, so this also keeps cold pages out of memory. memory.high is restored to its original value once the estimator is dropped
+///
+/// ```no_run
+/// use pressure::WorkingSetEstimator;
+/// use std::time::Duration;
+///
+/// let mut estimator = WorkingSetEstimator::new("/sys/fs/cgroup/system.slice/example.service")?
+///     .target(Duration::from_millis(10));
+/// for _ in 0..100 {
+///     std::thread::sleep(estimator.interval());
+///     estimator.adjust()?;
+/// }
+/// println!("working set is about {} bytes", estimator.working_set());
+/// # Ok::<(), pressure::Error>(())
+/// ```
+pub struct WorkingSetEstimator {
+    cgroup: PathBuf,
+    high: File,
+    current: File,
+    pressure: File,
+    original: String,
+    interval: Duration,
+    target: Duration,
+    max_probe: f64,
+    max_backoff: f64,
+    min_size: u64,
+    max_size: u64,
+    limit: u64,
+    last_total: Duration,
+    cancellation: Option<CancellationToken>,
+}
+
+impl WorkingSetEstimator {
+    /// Estimate the working set of a cgroup v2 hierarchy, starting from its current usage
+    ///
+    /// By default memory.high is adjusted every 6s, aiming for 10ms of `some` memory stall per interval, lowered by up to 1% and raised by up to 10% per interval
+    pub fn new(cgroup: impl AsRef<Path>) -> Result<Self, Error> {
+        let cgroup = cgroup.as_ref().to_path_buf();
+        let high = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(cgroup.join("memory.high"))?;
+        let current = File::open(cgroup.join("memory.current"))?;
+        let pressure = File::open(cgroup.join(Resource::Memory.cgroup_file()))?;
+        let original = read_value(&high)?;
+        let limit = parse_bytes(&read_value(&current)?)?;
+        let last_total = PsiStats::read_fd(&pressure)?.some.total;
+        Ok(Self {
+            cgroup,
+            max_size: parse_bytes(&original)?,
+            high,
+            current,
+            pressure,
+            original,
+            interval: Duration::from_secs(6),
+            target: Duration::from_millis(10),
+            max_probe: 0.01,
+            max_backoff: 0.1,
+            min_size: 0,
+            limit,
+            last_total,
+            cancellation: None,
+        })
+    }
+    /// Estimate the working set of the cgroup the calling process belongs to
+    pub fn for_current_cgroup() -> Result<Self, Error> {
+        Self::new(cgroup::current_cgroup()?)
+    }
+    /// How often [run](Self::run) adjusts memory.high
+    pub fn adjust_every(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// Time some tasks may be stalled on memory per interval before the limit backs off
+    pub fn target(mut self, target: Duration) -> Self {
+        self.target = target;
+        self
+    }
+    /// Largest fraction the limit is lowered by per interval, reached when there is no pressure at all
+    pub fn max_probe(mut self, fraction: f64) -> Self {
+        self.max_probe = fraction;
+        self
+    }
+    /// Largest fraction the limit is raised by per interval, reached at twice the target pressure
+    pub fn max_backoff(mut self, fraction: f64) -> Self {
+        self.max_backoff = fraction;
+        self
+    }
+    /// Bounds the limit is kept within, defaulting to no lower bound and the original memory.high
+    pub fn size_bounds(mut self, min: u64, max: u64) -> Self {
+        self.min_size = min;
+        self.max_size = max;
+        self
+    }
+    pub fn cgroup(&self) -> &Path {
+        &self.cgroup
+    }
+    /// Interval between adjustments, see [adjust_every](Self::adjust_every)
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+    /// The estimated working set size in bytes, which is the limit memory.high is currently set to
+    pub fn working_set(&self) -> u64 {
+        self.limit
+    }
+    /// Get a token that can be used to stop [run](Self::run) from another thread
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken, Error> {
+        if let Some(token) = &self.cancellation {
+            return Ok(token.clone());
+        }
+        let token = CancellationToken::new()?;
+        self.cancellation = Some(token.clone());
+        Ok(token)
+    }
+    /// Adjust memory.high based on the pressure since the last adjustment, returning the new limit.
+    /// This should be called once per interval, as the target is the stall time per interval
+    pub fn adjust(&mut self) -> Result<u64, Error> {
+        let total = PsiStats::read_fd(&self.pressure)?.some.total;
+        let stalled = total.saturating_sub(self.last_total);
+        self.last_total = total;
+        let ratio = stalled.as_secs_f64() / self.target.as_secs_f64();
+        let limit = if ratio < 1.0 {
+            // Probe from actual usage, as lowering a limit the cgroup doesn't reach has no effect
+            let usage = parse_bytes(&read_value(&self.current)?)?;
+            let base = self.limit.min(usage) as f64;
+            base * (1.0 - self.max_probe * (1.0 - ratio))
+        } else {
+            self.limit as f64 * (1.0 + self.max_backoff * (ratio - 1.0).min(1.0))
+        };
+        self.limit = (limit as u64).clamp(self.min_size, self.max_size.max(self.min_size));
+        let value = match self.limit {
+            u64::MAX => "max".to_owned(),
+            limit => limit.to_string(),
+        };
+        write_value(&self.high, &value)?;
+        Ok(self.limit)
+    }
+    /// Adjust memory.high once per interval, until cancelled through [cancellation_token](Self::cancellation_token)
+    pub fn run(&mut self) -> Result<(), Error> {
+        loop {
+            if let Some(cancellation) = &self.cancellation {
+                let mut fds = [PollFd::new(cancellation.as_fd(), PollFlags::POLLIN)];
+                if nix::poll::poll(&mut fds, poll_timeout(self.interval))? > 0 {
+                    return Ok(());
+                }
+            } else {
+                std::thread::sleep(self.interval);
+            }
+            self.adjust()?;
+        }
+    }
+}
+
+impl Drop for WorkingSetEstimator {
+    fn drop(&mut self) {
+        // Leaving the lowered limit in place would keep throttling the cgroup
+        let _ = write_value(&self.high, &self.original);
+    }
+}
+
+fn read_value(fd: impl AsFd) -> Result<String, Error> {
+    let mut buf = [0; 32];
+    let len = nix::sys::uio::pread(fd, &mut buf, 0)?;
+    let value = std::str::from_utf8(&buf[..len]).map_err(|_| Error::MalformedMemoryValue)?;
+    Ok(value.trim().to_owned())
+}
+
+fn write_value(fd: impl AsFd, value: &str) -> Result<(), Error> {
+    nix::sys::uio::pwrite(fd, value.as_bytes(), 0)?;
+    Ok(())
+}
+
+/// Parse a byte count from a cgroup memory file, where `max` means unlimited
+fn parse_bytes(value: &str) -> Result<u64, Error> {
+    match value {
+        "max" => Ok(u64::MAX),
+        value => value.parse().map_err(|_| Error::MalformedMemoryValue),
+    }
+}